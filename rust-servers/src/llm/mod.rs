@@ -530,8 +530,7 @@ impl ModuleHandler for LLMHandler {
         match msg.msg_type.as_str() {
             "stream_start" => {
                 // 解析配置
-                let config: StreamConfig = serde_json::from_value(msg.payload.clone())
-                    .map_err(|e| RouterError::ModuleError(format!("Invalid stream config: {}", e)))?;
+                let config: StreamConfig = msg.parse()?;
                 
                 // 开始流式请求
                 self.start_stream(config).await
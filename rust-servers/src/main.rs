@@ -9,7 +9,10 @@ pub mod voice;
 pub mod llm;
 pub mod utils;
 
-use server::{Server, ServerConfig};
+// 常用类型统一导出
+pub mod prelude;
+
+use prelude::{Server, ServerConfig};
 use std::env;
 
 const SERVER_VERSION: &str = match option_env!("SW_SERVER_VERSION") {
@@ -24,6 +27,12 @@ macro_rules! log_info {
     };
 }
 
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        eprintln!("[ERROR] {}", format!($($arg)*));
+    };
+}
+
 macro_rules! log_debug {
     ($($arg:tt)*) => {
         if cfg!(debug_assertions) {
@@ -81,7 +90,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 创建并启动服务器
     let server = Server::new(config);
-    let port = server.start().await?;
+    let port = match server.start().await {
+        Ok(port) => port,
+        Err(e) => {
+            log_error!("服务器启动失败: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // 保持主线程运行
     log_info!("Smart Workflow Server 已启动，监听端口: {}", port);
@@ -0,0 +1,7 @@
+// 常用类型统一导出
+// 调用方可直接 `use crate::prelude::*`，各模块原有的路径保持可用
+
+pub use crate::server::{Server, ServerConfig};
+pub use crate::voice::asr::{FallbackStrategy, RealtimeTranscriptionTask, TranscriptionResult};
+pub use crate::voice::audio::{AudioData, AudioRecorder, RecordingMode};
+pub use crate::voice::beep::BeepPlayer;
@@ -3,7 +3,8 @@
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use crate::server::WsSender;
+use crate::server::{ServerStats, WsSender};
+use std::sync::Arc;
 
 /// 日志宏
 macro_rules! log_info {
@@ -41,14 +42,20 @@ pub enum ModuleType {
     Llm,
     /// 工具模块
     Utils,
+    /// 服务器系统信息 (由路由器直接处理)
+    System,
 }
 
+/// 全部模块类型
+const ALL_MODULES: [ModuleType; 4] = [ModuleType::Voice, ModuleType::Llm, ModuleType::Utils, ModuleType::System];
+
 impl std::fmt::Display for ModuleType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ModuleType::Voice => write!(f, "voice"),
             ModuleType::Llm => write!(f, "llm"),
             ModuleType::Utils => write!(f, "utils"),
+            ModuleType::System => write!(f, "system"),
         }
     }
 }
@@ -75,9 +82,23 @@ impl ModuleMessage {
         &self.payload
     }
     
-    /// 获取负载中的字段值
-    pub fn get_field<T: serde::de::DeserializeOwned>(&self, field: &str) -> Option<T> {
-        self.payload.get(field).and_then(|v| serde_json::from_value(v.clone()).ok())
+    /// 将整个负载解析为类型化请求
+    /// 
+    /// 字段缺失或取值无效时返回 `ModuleError`，错误信息指明具体字段
+    pub fn parse<T: serde::de::DeserializeOwned>(&self) -> Result<T, RouterError> {
+        serde_json::from_value(self.payload.clone()).map_err(|e| {
+            RouterError::ModuleError(format!("无效的 {} 消息: {}", self.msg_type, e))
+        })
+    }
+    
+    /// 需要确认回执时返回客户端消息 ID
+    /// 
+    /// 仅当消息同时带有 `ack: true` 与字符串 `msg_id` 时返回，默认不回执以免增加流量
+    pub fn ack_id(&self) -> Option<&str> {
+        if self.payload.get("ack").and_then(|v| v.as_bool()) != Some(true) {
+            return None;
+        }
+        self.payload.get("msg_id").and_then(|v| v.as_str())
     }
 }
 
@@ -117,6 +138,17 @@ impl ServerResponse {
         }
     }
     
+    /// 创建确认回执 (`ref_id` 为客户端消息 ID)
+    pub fn ack(module: ModuleType, ref_id: &str) -> Self {
+        Self {
+            module,
+            msg_type: "ack".to_string(),
+            payload: serde_json::json!({
+                "ref_id": ref_id
+            }),
+        }
+    }
+    
     /// 转换为 JSON 字符串
     pub fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
@@ -183,15 +215,23 @@ pub struct MessageRouter {
     llm_handler: crate::llm::LLMHandler,
     // Utils 模块处理器
     utils_handler: crate::utils::UtilsHandler,
+    // 服务器运行状态 (所有连接共享)
+    stats: Arc<ServerStats>,
 }
 
 impl MessageRouter {
     /// 创建新的消息路由器
     pub fn new() -> Self {
+        Self::with_stats(Arc::new(ServerStats::new()))
+    }
+    
+    /// 使用共享的服务器状态创建消息路由器
+    pub fn with_stats(stats: Arc<ServerStats>) -> Self {
         Self {
-            voice_handler: crate::voice::VoiceHandler::new(),
+            voice_handler: crate::voice::VoiceHandler::with_stats(Arc::clone(&stats)),
             llm_handler: crate::llm::LLMHandler::new(),
             utils_handler: crate::utils::UtilsHandler::new(),
+            stats,
         }
     }
     
@@ -240,6 +280,7 @@ impl MessageRouter {
                     "voice" => Some(ModuleType::Voice),
                     "llm" => Some(ModuleType::Llm),
                     "utils" => Some(ModuleType::Utils),
+                    "system" => Some(ModuleType::System),
                     _ => None,
                 };
             }
@@ -270,6 +311,54 @@ impl MessageRouter {
                 log_debug!("Utils 模块消息: {}", msg.msg_type);
                 self.utils_handler.handle(&msg).await
             }
+            ModuleType::System => {
+                log_debug!("System 消息: {}", msg.msg_type);
+                self.handle_system(&msg).await
+            }
+        }
+    }
+    
+    /// 路由二进制帧
+    /// 
+    /// 二进制帧不携带 module 字段，目前仅 Voice 模块的客户端音频流使用
+    pub async fn route_binary(&self, data: &[u8]) -> Result<(), RouterError> {
+        log_debug!("二进制帧: {} bytes", data.len());
+        self.voice_handler.handle_binary(data).await
+    }
+    
+    /// 处理 System 消息
+    async fn handle_system(&self, msg: &ModuleMessage) -> Result<Option<ServerResponse>, RouterError> {
+        match msg.msg_type.as_str() {
+            "status" => Ok(Some(ServerResponse::new(
+                ModuleType::System,
+                "status",
+                serde_json::json!({
+                    "connected_clients": self.stats.connected_clients(),
+                    "active_realtime_tasks": self.stats.active_realtime_tasks(),
+                    "uptime_secs": self.stats.uptime_secs(),
+                }),
+            ))),
+            "config" => {
+                let modules: Vec<ModuleType> = ALL_MODULES
+                    .into_iter()
+                    .filter(|&module| self.is_module_implemented(module))
+                    .collect();
+                let local_addr = self.stats.local_addr();
+                Ok(Some(ServerResponse::new(
+                    ModuleType::System,
+                    "config",
+                    serde_json::json!({
+                        "bind_address": local_addr.map(|addr| addr.ip().to_string()),
+                        "port": local_addr.map(|addr| addr.port()),
+                        "modules": modules,
+                        "voice": self.voice_handler.effective_config().await,
+                    }),
+                )))
+            }
+            _ => Err(RouterError::ModuleError(format!(
+                "Unknown System message type: {}",
+                msg.msg_type
+            ))),
         }
     }
     
@@ -287,12 +376,12 @@ impl MessageRouter {
     }
     
     /// 检查模块是否已实现
-    #[allow(dead_code)]
     pub fn is_module_implemented(&self, module: ModuleType) -> bool {
         match module {
             ModuleType::Voice => true,  // Voice 模块已实现
             ModuleType::Llm => true,    // LLM 模块已实现
             ModuleType::Utils => true,  // Utils 模块已实现
+            ModuleType::System => true, // System 消息由路由器处理
         }
     }
 }
@@ -341,6 +430,28 @@ mod tests {
         assert_eq!(msg.msg_type, "detect_language");
     }
     
+    #[test]
+    fn test_parse_typed_payload_reports_field() {
+        #[derive(Debug, Deserialize)]
+        struct ResizeRequest {
+            cols: u16,
+            #[allow(dead_code)]
+            rows: u16,
+        }
+        
+        let router = MessageRouter::new();
+        let msg = router.parse_message(r#"{"module": "voice", "type": "resize", "cols": 120, "rows": 40}"#).unwrap();
+        assert_eq!(msg.parse::<ResizeRequest>().unwrap().cols, 120);
+        
+        let msg = router.parse_message(r#"{"module": "voice", "type": "resize", "cols": 120}"#).unwrap();
+        let err = msg.parse::<ResizeRequest>().unwrap_err().to_string();
+        assert!(err.contains("resize") && err.contains("missing field `rows`"), "{}", err);
+        
+        let msg = router.parse_message(r#"{"module": "voice", "type": "resize", "cols": "wide", "rows": 40}"#).unwrap();
+        let err = msg.parse::<ResizeRequest>().unwrap_err().to_string();
+        assert!(err.contains("invalid type"), "{}", err);
+    }
+    
     #[test]
     fn test_parse_invalid_module() {
         let router = MessageRouter::new();
@@ -366,6 +477,7 @@ mod tests {
         assert_eq!(router.try_parse_module(r#"{"module": "voice"}"#), Some(ModuleType::Voice));
         assert_eq!(router.try_parse_module(r#"{"module": "llm"}"#), Some(ModuleType::Llm));
         assert_eq!(router.try_parse_module(r#"{"module": "utils"}"#), Some(ModuleType::Utils));
+        assert_eq!(router.try_parse_module(r#"{"module": "system"}"#), Some(ModuleType::System));
     }
     
     #[test]
@@ -404,6 +516,24 @@ mod tests {
         assert_eq!(response.payload.get("key").unwrap().as_str().unwrap(), "value");
     }
     
+    #[test]
+    fn test_ack_is_opt_in() {
+        let router = MessageRouter::new();
+        
+        let msg = router.parse_message(r#"{"module": "llm", "type": "stream_cancel", "msg_id": "m-1", "ack": true}"#).unwrap();
+        assert_eq!(msg.ack_id(), Some("m-1"));
+        
+        let response = ServerResponse::ack(msg.module, msg.ack_id().unwrap());
+        let json: serde_json::Value = serde_json::from_str(&response.to_json()).unwrap();
+        assert_eq!(json, serde_json::json!({"module": "llm", "type": "ack", "ref_id": "m-1"}));
+        
+        // 未请求回执或缺少消息 ID 时不回执
+        let msg = router.parse_message(r#"{"module": "llm", "type": "stream_cancel", "msg_id": "m-1"}"#).unwrap();
+        assert_eq!(msg.ack_id(), None);
+        let msg = router.parse_message(r#"{"module": "llm", "type": "stream_cancel", "ack": true}"#).unwrap();
+        assert_eq!(msg.ack_id(), None);
+    }
+    
     #[test]
     fn test_module_type_display() {
         assert_eq!(format!("{}", ModuleType::Voice), "voice");
@@ -457,23 +587,51 @@ mod tests {
         assert!(router.is_module_implemented(ModuleType::Voice));
     }
     
+    #[tokio::test]
+    async fn test_system_status() {
+        let stats = Arc::new(ServerStats::new());
+        let _client = stats.track_client();
+        let router = MessageRouter::with_stats(Arc::clone(&stats));
+        
+        let msg = router.parse_message(r#"{"module": "system", "type": "status"}"#).unwrap();
+        let response = router.route(msg).await.unwrap().unwrap();
+        
+        assert_eq!(response.module, ModuleType::System);
+        assert_eq!(response.msg_type, "status");
+        assert_eq!(response.payload["connected_clients"], 1);
+        assert_eq!(response.payload["active_realtime_tasks"], 0);
+        
+        {
+            let _task = stats.track_realtime_task();
+            assert_eq!(stats.active_realtime_tasks(), 1);
+        }
+        assert_eq!(stats.active_realtime_tasks(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_system_config_before_voice_config() {
+        let router = MessageRouter::new();
+        
+        let msg = router.parse_message(r#"{"module": "system", "type": "config"}"#).unwrap();
+        let response = router.route(msg).await.unwrap().unwrap();
+        
+        assert_eq!(response.msg_type, "config");
+        assert_eq!(response.payload["modules"], serde_json::json!(["voice", "llm", "utils", "system"]));
+        assert!(response.payload["port"].is_null());
+        assert!(response.payload["voice"]["asr"].is_null());
+    }
+    
     #[test]
-    fn test_module_message_get_field() {
+    fn test_module_message_payload_fields() {
         let router = MessageRouter::new();
         let json = r#"{"module": "voice", "type": "start_recording", "mode": "press", "duration": 30}"#;
         
         let msg = router.parse_message(json).unwrap();
         
-        // 测试获取不同类型的字段
-        let mode: Option<String> = msg.get_field("mode");
-        assert_eq!(mode, Some("press".to_string()));
-        
-        let duration: Option<u32> = msg.get_field("duration");
-        assert_eq!(duration, Some(30));
-        
-        // 测试获取不存在的字段
-        let missing: Option<String> = msg.get_field("nonexistent");
-        assert_eq!(missing, None);
+        // 消息中的业务字段从负载读取
+        assert_eq!(msg.payload["mode"], "press");
+        assert_eq!(msg.payload["duration"], 30);
+        assert!(msg.payload.get("nonexistent").is_none());
     }
     
     #[test]
@@ -2,9 +2,13 @@
 // 统一的 WebSocket 服务器，处理所有模块的消息
 
 use tokio::net::TcpListener;
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio_tungstenite::{accept_async, tungstenite::{self, Message}};
 use futures_util::{StreamExt, SinkExt};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
 use tokio::sync::Mutex as TokioMutex;
 
 use crate::router::{MessageRouter, ModuleType, RouterError, ServerResponse};
@@ -30,6 +34,130 @@ macro_rules! log_debug {
     };
 }
 
+// ============================================================================
+// 服务器错误
+// ============================================================================
+
+/// 服务器错误类型
+#[derive(Debug, Error)]
+pub enum ServerError {
+    /// 绑定监听地址失败 (如端口已被占用)
+    #[error("无法绑定地址 {addr}: {source}")]
+    BindFailed {
+        addr: String,
+        #[source]
+        source: std::io::Error,
+    },
+    
+    /// WebSocket 握手失败
+    #[error("WebSocket 升级失败: {0}")]
+    UpgradeFailed(String),
+    
+    /// TLS 错误
+    #[error("TLS 错误: {0}")]
+    TlsError(String),
+    
+    /// 连接收发错误
+    #[error("连接错误: {0}")]
+    ConnectionError(String),
+}
+
+impl From<tungstenite::Error> for ServerError {
+    fn from(err: tungstenite::Error) -> Self {
+        match err {
+            tungstenite::Error::Tls(e) => ServerError::TlsError(e.to_string()),
+            other => ServerError::ConnectionError(other.to_string()),
+        }
+    }
+}
+
+// ============================================================================
+// 运行状态统计
+// ============================================================================
+
+/// 服务器运行状态计数器
+/// 
+/// 所有连接共享同一实例，用于 `system/status` 诊断查询
+#[derive(Debug)]
+pub struct ServerStats {
+    /// 服务器启动时间
+    started_at: Instant,
+    /// 当前连接的 WebSocket 客户端数
+    connected_clients: Arc<AtomicUsize>,
+    /// 正在运行的实时转录任务数
+    active_realtime_tasks: Arc<AtomicUsize>,
+    /// 实际监听地址 (绑定成功后设置)
+    local_addr: OnceLock<SocketAddr>,
+}
+
+impl ServerStats {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            connected_clients: Arc::new(AtomicUsize::new(0)),
+            active_realtime_tasks: Arc::new(AtomicUsize::new(0)),
+            local_addr: OnceLock::new(),
+        }
+    }
+    
+    /// 实际监听地址 (服务器未启动时为空)
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr.get().copied()
+    }
+    
+    /// 服务器已运行秒数
+    pub fn uptime_secs(&self) -> u64 {
+        self.started_at.elapsed().as_secs()
+    }
+    
+    /// 当前连接的客户端数
+    pub fn connected_clients(&self) -> usize {
+        self.connected_clients.load(Ordering::SeqCst)
+    }
+    
+    /// 正在运行的实时转录任务数
+    pub fn active_realtime_tasks(&self) -> usize {
+        self.active_realtime_tasks.load(Ordering::SeqCst)
+    }
+    
+    /// 记录一个客户端连接，返回的守卫释放时计数自动减一
+    pub fn track_client(&self) -> StatsGuard {
+        StatsGuard::new(Arc::clone(&self.connected_clients))
+    }
+    
+    /// 记录一个实时转录任务，返回的守卫释放时计数自动减一
+    pub fn track_realtime_task(&self) -> StatsGuard {
+        StatsGuard::new(Arc::clone(&self.active_realtime_tasks))
+    }
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 计数守卫
+/// 
+/// 创建时计数加一，Drop 时减一，保证异常退出路径下计数也能回收
+#[derive(Debug)]
+pub struct StatsGuard {
+    counter: Arc<AtomicUsize>,
+}
+
+impl StatsGuard {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self { counter }
+    }
+}
+
+impl Drop for StatsGuard {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 // ============================================================================
 // 服务器配置和实现
 // ============================================================================
@@ -42,19 +170,28 @@ pub struct ServerConfig {
 /// WebSocket 服务器
 pub struct Server {
     config: ServerConfig,
+    stats: Arc<ServerStats>,
 }
 
 impl Server {
     pub fn new(config: ServerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            stats: Arc::new(ServerStats::new()),
+        }
     }
 
     /// 启动服务器
-    pub async fn start(&self) -> Result<u16, Box<dyn std::error::Error>> {
+    /// 
+    /// 仅在启动阶段失败时返回错误，单个连接的错误只记录日志，不影响服务器运行
+    pub async fn start(&self) -> Result<u16, ServerError> {
         let addr = format!("127.0.0.1:{}", self.config.port);
-        let listener = TcpListener::bind(&addr).await?;
-        let local_addr = listener.local_addr()?;
+        let listener = TcpListener::bind(&addr).await
+            .map_err(|source| ServerError::BindFailed { addr: addr.clone(), source })?;
+        let local_addr = listener.local_addr()
+            .map_err(|source| ServerError::BindFailed { addr: addr.clone(), source })?;
         let port = local_addr.port();
+        let _ = self.stats.local_addr.set(local_addr);
 
         log_info!("服务器绑定到 {}", local_addr);
 
@@ -67,15 +204,26 @@ impl Server {
         );
 
         // 主循环：接受 WebSocket 连接
+        let stats = Arc::clone(&self.stats);
         tokio::spawn(async move {
             log_info!("正在监听 WebSocket 连接...");
-            while let Ok((stream, addr)) = listener.accept().await {
-                log_debug!("接受来自 {} 的连接", addr);
-                tokio::spawn(async move {
-                    if let Err(e) = handle_connection(stream).await {
-                        log_error!("连接处理错误: {}", e);
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => {
+                        log_debug!("接受来自 {} 的连接", addr);
+                        let stats = Arc::clone(&stats);
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream, stats).await {
+                                log_error!("连接处理错误: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        // 接受失败 (如文件描述符耗尽) 不终止服务器，稍后重试
+                        log_error!("接受连接失败: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
                     }
-                });
+                }
             }
         });
 
@@ -96,18 +244,23 @@ pub type WsSender = Arc<TokioMutex<futures_util::stream::SplitSink<
 /// 处理单个 WebSocket 连接
 async fn handle_connection(
     stream: tokio::net::TcpStream,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    stats: Arc<ServerStats>,
+) -> Result<(), ServerError> {
     // 升级到 WebSocket
-    let ws_stream = accept_async(stream).await?;
+    let ws_stream = accept_async(stream).await.map_err(|e| match e {
+        tungstenite::Error::Tls(e) => ServerError::TlsError(e.to_string()),
+        other => ServerError::UpgradeFailed(other.to_string()),
+    })?;
     
     log_info!("WebSocket 连接已建立");
+    let _client_guard = stats.track_client();
     
     // 分离读写流
     let (ws_sender, mut ws_receiver) = ws_stream.split();
     let ws_sender: WsSender = Arc::new(TokioMutex::new(ws_sender));
     
     // 创建消息路由器
-    let router = Arc::new(MessageRouter::new());
+    let router = Arc::new(MessageRouter::with_stats(stats));
     
     // 设置 WebSocket 发送器
     router.set_ws_sender(Arc::clone(&ws_sender)).await;
@@ -129,6 +282,16 @@ async fn handle_connection(
                             log_error!("消息处理错误: {}", e);
                         }
                     }
+                    Message::Binary(data) => {
+                        // 处理二进制消息 (客户端音频流)
+                        if let Err(e) = handle_binary_message(
+                            &data,
+                            &router,
+                            &ws_sender
+                        ).await {
+                            log_error!("二进制消息处理错误: {}", e);
+                        }
+                    }
                     Message::Close(_) => {
                         log_info!("客户端关闭连接");
                         break;
@@ -177,6 +340,7 @@ async fn handle_text_message(
     match router.parse_message(text) {
         Ok(msg) => {
             let module = msg.module;
+            let ack_id = msg.ack_id().map(str::to_string);
             
             // 路由消息到对应模块
             match router.route(msg).await {
@@ -193,8 +357,14 @@ async fn handle_text_message(
                     log_error!("模块处理错误: {}", e);
                     let error_response = router.create_error_response(module, &e);
                     send_response(ws_sender, &error_response).await?;
+                    return Ok(());
                 }
             }
+            
+            // 客户端请求回执时，处理成功后确认
+            if let Some(ref_id) = ack_id {
+                send_response(ws_sender, &ServerResponse::ack(module, &ref_id)).await?;
+            }
         }
         Err(e) => {
             // 消息解析错误
@@ -210,6 +380,21 @@ async fn handle_text_message(
     Ok(())
 }
 
+/// 处理二进制消息
+async fn handle_binary_message(
+    data: &[u8],
+    router: &Arc<MessageRouter>,
+    ws_sender: &WsSender,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Err(e) = router.route_binary(data).await {
+        log_error!("二进制帧处理错误: {}", e);
+        let error_response = router.create_error_response(ModuleType::Voice, &e);
+        send_response(ws_sender, &error_response).await?;
+    }
+    
+    Ok(())
+}
+
 /// 从 JSON 中提取 module 字段
 fn extract_module_from_json(text: &str) -> ModuleType {
     // 尝试解析 JSON 并提取 module 字段
@@ -219,6 +404,7 @@ fn extract_module_from_json(text: &str) -> ModuleType {
                 "voice" => return ModuleType::Voice,
                 "llm" => return ModuleType::Llm,
                 "utils" => return ModuleType::Utils,
+                "system" => return ModuleType::System,
                 _ => {}
             }
         }
@@ -259,6 +445,16 @@ pub async fn send_json(
     Ok(())
 }
 
+/// 发送纯文本消息 (不经 JSON 包装)
+pub async fn send_text(
+    ws_sender: &WsSender,
+    text: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut sender = ws_sender.lock().await;
+    sender.send(Message::Text(text.to_string().into())).await?;
+    Ok(())
+}
+
 /// 发送二进制消息
 #[allow(dead_code)]
 pub async fn send_binary(
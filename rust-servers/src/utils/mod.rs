@@ -107,8 +107,7 @@ impl UtilsHandler {
         msg: &ModuleMessage,
     ) -> Result<Option<ServerResponse>, RouterError> {
         // 解析请求
-        let request: DetectLanguageRequest = serde_json::from_value(msg.payload.clone())
-            .map_err(|e| RouterError::ModuleError(format!("Invalid detect_language request: {}", e)))?;
+        let request: DetectLanguageRequest = msg.parse()?;
         
         log_debug!("语言检测请求: request_id={}, text_len={}", 
             request.request_id, request.text.len());
@@ -0,0 +1,198 @@
+// 录音存档模块
+// 将每次录音的 WAV 与转录结果 (sidecar JSON) 保存到指定目录，便于追溯和重新转录
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::voice::asr::TranscriptionResult;
+use crate::voice::audio::{
+    AudioData, WavEncoder, WavSampleFormat, INFO_COMMENT, INFO_CREATION_DATE, INFO_SOFTWARE,
+};
+use crate::voice::config::ASRConfig;
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [archive] {}", format!($($arg)*));
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        eprintln!("[WARN] [archive] {}", format!($($arg)*));
+    };
+}
+
+/// 存档文件名前缀
+const FILE_PREFIX: &str = "recording-";
+
+/// 写入 WAV 元数据的软件名称
+const SOFTWARE_NAME: &str = "Smart Workflow";
+
+/// 录音存档
+pub struct RecordingArchive {
+    dir: PathBuf,
+    /// 最多保留的录音数 (0 不限制)
+    max_recordings: usize,
+    /// 配置的主引擎供应商 (转录失败时写入 WAV 元数据)
+    provider: Option<String>,
+}
+
+impl RecordingArchive {
+    pub fn new(dir: impl Into<PathBuf>, max_recordings: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            max_recordings,
+            provider: None,
+        }
+    }
+
+    /// 设置主引擎供应商
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    /// 按配置创建 (未配置存档目录时返回 None)
+    pub fn from_config(config: &ASRConfig) -> Option<Self> {
+        config
+            .recording_archive_dir
+            .as_deref()
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| {
+                Self::new(dir, config.recording_archive_max)
+                    .with_provider(config.primary.provider.to_string())
+            })
+    }
+
+    /// 保存录音与转录结果，返回 WAV 文件路径
+    ///
+    /// 文件名为 `recording-<毫秒时间戳>.wav` / `.json`，先写临时文件再重命名，
+    /// 写入后按保留数量删除最旧的录音。WAV 以 32 位浮点无损保存采集数据，LIST/INFO 中记录
+    /// 引擎、时长与时间戳。同步文件操作，异步上下文中应放入 `spawn_blocking`
+    pub fn save(
+        &self,
+        audio: &AudioData,
+        result: &Result<TranscriptionResult, String>,
+    ) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let stem = format!("{}{:013}", FILE_PREFIX, timestamp_ms);
+
+        // 成功时记录实际转录的引擎 (可能为兜底引擎)，失败时记录配置的主引擎
+        let engine = result.as_ref().ok().map(|r| r.engine.as_str()).or(self.provider.as_deref());
+        let comment = match engine {
+            Some(engine) => format!("engine={} duration={}ms", engine, audio.duration_ms),
+            None => format!("duration={}ms", audio.duration_ms),
+        };
+        let wav = WavEncoder::new(audio.sample_rate, audio.channels, 32)
+            .with_sample_format(WavSampleFormat::Float32)
+            .with_info(INFO_SOFTWARE, SOFTWARE_NAME)
+            .with_info(INFO_CREATION_DATE, timestamp_ms.to_string())
+            .with_info(INFO_COMMENT, comment)
+            .encode(audio)
+            .map_err(io::Error::other)?;
+        let sidecar = serde_json::json!({
+            "timestamp_ms": timestamp_ms,
+            "audio_duration_ms": audio.duration_ms,
+            "sample_rate": audio.sample_rate,
+            "result": result.as_ref().ok(),
+            "error": result.as_ref().err(),
+        });
+        let sidecar = serde_json::to_vec_pretty(&sidecar).map_err(io::Error::other)?;
+
+        let wav_path = self.dir.join(format!("{}.wav", stem));
+        write_atomic(&wav_path, &wav)?;
+        write_atomic(&self.dir.join(format!("{}.json", stem)), &sidecar)?;
+        log_info!("录音已存档: {}", wav_path.display());
+
+        if let Err(e) = self.prune() {
+            log_warn!("清理旧录音失败: {}", e);
+        }
+
+        Ok(wav_path)
+    }
+
+    /// 删除超出保留数量的最旧录音 (连同 sidecar)
+    fn prune(&self) -> io::Result<()> {
+        if self.max_recordings == 0 {
+            return Ok(());
+        }
+
+        let mut stems: Vec<String> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let stem = name.strip_prefix(FILE_PREFIX)?.strip_suffix(".wav")?;
+                Some(format!("{}{}", FILE_PREFIX, stem))
+            })
+            .collect();
+
+        if stems.len() <= self.max_recordings {
+            return Ok(());
+        }
+
+        // 时间戳定宽，按文件名排序即按时间排序
+        stems.sort();
+        let excess = stems.len() - self.max_recordings;
+        for stem in &stems[..excess] {
+            fs::remove_file(self.dir.join(format!("{}.wav", stem)))?;
+            match fs::remove_file(self.dir.join(format!("{}.json", stem))) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 先写入同目录临时文件再重命名，避免留下不完整的文件
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_path);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_writes_sidecar_and_prunes_oldest() {
+        let dir = std::env::temp_dir().join(format!("recording-archive-{}", uuid::Uuid::new_v4()));
+        let archive = RecordingArchive::new(&dir, 2);
+        let audio = AudioData::new(vec![0.1; 1600], 16000, 1);
+
+        let mut paths = Vec::new();
+        for i in 0..3 {
+            let result = Ok(TranscriptionResult::new(format!("第{}段", i), "qwen".to_string(), false, 10));
+            paths.push(archive.save(&audio, &result).unwrap());
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        assert!(!paths[0].exists());
+        assert!(!paths[0].with_extension("json").exists());
+        assert!(paths[2].exists());
+
+        let sidecar: serde_json::Value =
+            serde_json::from_slice(&fs::read(paths[2].with_extension("json")).unwrap()).unwrap();
+        assert_eq!(sidecar["result"]["text"], "第2段");
+        assert!(sidecar["error"].is_null());
+
+        let wav = fs::read(&paths[2]).unwrap();
+        let spec = hound::WavReader::new(std::io::Cursor::new(&wav)).unwrap().spec();
+        assert_eq!((spec.sample_format, spec.bits_per_sample), (hound::SampleFormat::Float, 32));
+        let info = String::from_utf8_lossy(&wav);
+        assert!(info.contains("engine=qwen duration=100ms"), "{}", info);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+// 分段转录模块
+// 供应商限制单次请求音频时长时，将长录音在静音处切分后逐段转录再拼接
+
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::voice::asr::segments::{prefixed_partial_callback, transcribe_segments};
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, RealtimeSession, SharedPartialCallback, Timings,
+};
+use crate::voice::audio::AudioData;
+use crate::voice::config::OversizedAudio;
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [chunked] {}", format!($($arg)*));
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        eprintln!("[WARN] [chunked] {}", format!($($arg)*));
+    };
+}
+
+tokio::task_local! {
+    /// 当前转录调用中已完成分段的拼接文本 (含分隔符)，拼在当前分段的中间结果之前
+    static PARTIAL_PREFIX: RefCell<String>;
+}
+
+/// 分段转录引擎
+///
+/// 包装 HTTP 引擎：音频超过时长上限时切分为多段依次转录，任一段失败即返回该段的错误；
+/// 也可配置为截断到上限或直接拒绝
+pub struct ChunkedEngine {
+    inner: Box<dyn ASREngine>,
+    max_audio_ms: u64,
+    separator: String,
+    oversized: OversizedAudio,
+    /// 最近一次分段转录各段耗时的合计 (未分段时为空，取内层引擎的耗时)
+    segment_timings: Mutex<Option<Timings>>,
+}
+
+impl ChunkedEngine {
+    pub fn new(inner: Box<dyn ASREngine>, max_audio_ms: u64, separator: String) -> Self {
+        Self {
+            inner,
+            max_audio_ms,
+            separator,
+            oversized: OversizedAudio::default(),
+            segment_timings: Mutex::new(None),
+        }
+    }
+
+    /// 设置超过时长上限时的处理方式 (默认切分)
+    pub fn with_oversized_audio(mut self, oversized: OversizedAudio) -> Self {
+        self.oversized = oversized;
+        self
+    }
+}
+
+#[async_trait]
+impl ASREngine for ChunkedEngine {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supported_modes(&self) -> Vec<ASRMode> {
+        self.inner.supported_modes()
+    }
+
+    async fn transcribe(&self, audio: &AudioData) -> Result<String, ASRError> {
+        *self.segment_timings.lock().unwrap() = None;
+        if audio.duration_ms <= self.max_audio_ms {
+            return self.inner.transcribe(audio).await;
+        }
+
+        match self.oversized {
+            OversizedAudio::Chunk => {}
+            OversizedAudio::Truncate => {
+                log_warn!(
+                    "音频时长 {}ms 超过 {} 上限 {}ms，截断后转录，丢弃末尾 {}ms",
+                    audio.duration_ms,
+                    self.inner.name(),
+                    self.max_audio_ms,
+                    audio.duration_ms - self.max_audio_ms
+                );
+                return self.inner.transcribe(&audio.slice_ms(0, self.max_audio_ms)).await;
+            }
+            OversizedAudio::Reject => {
+                return Err(ASRError::InvalidAudio(format!(
+                    "音频时长 {}ms 超过 {} 上限 {}ms",
+                    audio.duration_ms,
+                    self.inner.name(),
+                    self.max_audio_ms
+                )));
+            }
+        }
+
+        let segments = audio.split_at_silence(self.max_audio_ms);
+        log_info!(
+            "音频时长 {}ms 超过 {} 上限 {}ms，切分为 {} 段转录",
+            audio.duration_ms,
+            self.inner.name(),
+            self.max_audio_ms,
+            segments.len()
+        );
+
+        let output = transcribe_segments(&*self.inner, &segments, &self.separator, &PARTIAL_PREFIX, |_| {})
+            .await
+            .map_err(|failure| with_segment_context(failure.error, failure.index, segments.len()))?;
+        *self.segment_timings.lock().unwrap() = Some(output.timings);
+
+        Ok(output.text)
+    }
+
+    async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
+        self.inner.create_realtime_session().await
+    }
+
+    fn last_timings(&self) -> Timings {
+        self.segment_timings.lock().unwrap().unwrap_or_else(|| self.inner.last_timings())
+    }
+
+    /// 分段转录时中间结果前拼接已完成分段的文本，前端看到的始终是完整文本
+    fn set_partial_callback(&mut self, callback: SharedPartialCallback) {
+        self.inner.set_partial_callback(prefixed_partial_callback(&PARTIAL_PREFIX, callback));
+    }
+}
+
+/// 在错误信息中标注失败的分段 (保留错误类型，兜底条件判断不受影响)
+fn with_segment_context(error: ASRError, index: usize, total: usize) -> ASRError {
+    let context = |message: String| format!("第 {}/{} 段: {}", index + 1, total, message);
+    match error {
+        ASRError::NetworkError(message) => ASRError::NetworkError(context(message)),
+        ASRError::InvalidAudio(message) => ASRError::InvalidAudio(context(message)),
+        ASRError::InternalError(message) => ASRError::InternalError(context(message)),
+        ASRError::AuthFailed { engine, message } => ASRError::AuthFailed {
+            engine,
+            message: context(message),
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::mock::MockEngine;
+    use crate::voice::asr::PartialTranscription;
+    use std::sync::Arc;
+
+    fn engine(fail_after: usize) -> ChunkedEngine {
+        let inner = MockEngine::new("mock")
+            .with_text_fn(|_, call| format!("第{}段", call))
+            .failing_after(fail_after, ASRError::NetworkError("连接中断".to_string()))
+            .with_timings(Timings {
+                connect_ms: None,
+                upload_ms: Some(10),
+                inference_ms: Some(20),
+            });
+        ChunkedEngine::new(Box::new(inner), 2000, "".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_long_audio_is_transcribed_in_segments() {
+        let audio = AudioData::new(vec![0.3f32; 16000 * 5], 16000, 1);
+
+        assert_eq!(engine(usize::MAX).transcribe(&audio).await.unwrap(), "第1段第2段第3段");
+
+        let short = AudioData::new(vec![0.3f32; 16000], 16000, 1);
+        assert_eq!(engine(usize::MAX).transcribe(&short).await.unwrap(), "第1段");
+    }
+
+    #[tokio::test]
+    async fn test_segment_timings_are_summed() {
+        let chunked = engine(usize::MAX);
+        let audio = AudioData::new(vec![0.3f32; 16000 * 5], 16000, 1);
+        chunked.transcribe(&audio).await.unwrap();
+        let timings = chunked.last_timings();
+        assert_eq!((timings.connect_ms, timings.upload_ms, timings.inference_ms), (None, Some(30), Some(60)));
+
+        // 未分段时取内层引擎的耗时
+        chunked.transcribe(&AudioData::new(vec![0.3f32; 16000], 16000, 1)).await.unwrap();
+        assert_eq!(chunked.last_timings().upload_ms, Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_audio_truncate_and_reject() {
+        let audio = AudioData::new(vec![0.3f32; 16000 * 5], 16000, 1);
+
+        let truncating = engine(usize::MAX).with_oversized_audio(OversizedAudio::Truncate);
+        assert_eq!(truncating.transcribe(&audio).await.unwrap(), "第1段");
+
+        let rejecting = engine(usize::MAX).with_oversized_audio(OversizedAudio::Reject);
+        let err = rejecting.transcribe(&audio).await.unwrap_err();
+        assert!(matches!(err, ASRError::InvalidAudio(_)));
+    }
+
+    #[tokio::test]
+    async fn test_segment_failure_names_the_segment() {
+        let audio = AudioData::new(vec![0.3f32; 16000 * 5], 16000, 1);
+
+        let err = engine(1).transcribe(&audio).await.unwrap_err();
+        assert!(matches!(err, ASRError::NetworkError(ref msg) if msg.starts_with("第 2/3 段")));
+    }
+    #[tokio::test]
+    async fn test_concurrent_calls_keep_their_own_partial_prefix() {
+        let partials = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&partials);
+        let mut chunked = ChunkedEngine::new(Box::new(
+            MockEngine::new("mock")
+                .with_text_fn(|audio, _| if audio.samples[0] > 0.5 { "A" } else { "B" }.to_string())
+                .emitting_partials(),
+        ), 2000, "".to_string());
+        chunked.set_partial_callback(Arc::new(move |partial: &PartialTranscription| {
+            sink.lock().unwrap().push(partial.text.clone());
+        }));
+
+        let first = AudioData::new(vec![0.8f32; 16000 * 5], 16000, 1);
+        let second = AudioData::new(vec![0.3f32; 16000 * 5], 16000, 1);
+        let (a, b) = tokio::join!(chunked.transcribe(&first), chunked.transcribe(&second));
+        assert_eq!((a.unwrap().as_str(), b.unwrap().as_str()), ("AAA", "BBB"));
+
+        let partials = partials.lock().unwrap();
+        assert_eq!(partials.len(), 6);
+        assert!(partials.iter().all(|text| text.chars().all(|c| c == 'A') || text.chars().all(|c| c == 'B')));
+    }
+}
@@ -1,12 +1,17 @@
 // 兜底策略模块
 // 实现主引擎重试和备用引擎并行执行的智能兜底机制
 
+use std::borrow::Cow;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
-use crate::voice::asr::{ASREngine, ASRError, RetryConfig, TranscriptionResult};
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRErrorKind, RetryConfig, SharedPartialCallback, TranscriptionResult,
+};
+use crate::voice::audio::utils::VAD_VOICE_THRESHOLD;
 use crate::voice::audio::AudioData;
-use crate::voice::config::ASRConfig;
+use crate::voice::config::{ASRConfig, DEFAULT_MIN_DURATION_MS};
 
 /// 兜底策略
 pub struct FallbackStrategy {
@@ -14,6 +19,13 @@ pub struct FallbackStrategy {
     fallbacks: Vec<Box<dyn ASREngine>>,
     enable_fallback: bool,
     retry_config: RetryConfig,
+    min_duration_ms: u64,
+    silence_skip_ratio: Option<f32>,
+    fallback_on: Vec<ASRErrorKind>,
+    pad_start_ms: u32,
+    pad_end_ms: u32,
+    overall_timeout_ms: Option<u64>,
+    best_of: Option<BestOfStrategy>,
 }
 
 impl FallbackStrategy {
@@ -27,6 +39,13 @@ impl FallbackStrategy {
             fallbacks,
             enable_fallback,
             retry_config: RetryConfig::default(),
+            min_duration_ms: DEFAULT_MIN_DURATION_MS,
+            silence_skip_ratio: None,
+            fallback_on: Vec::new(),
+            pad_start_ms: 0,
+            pad_end_ms: 0,
+            overall_timeout_ms: None,
+            best_of: None,
         }
     }
     
@@ -41,39 +60,140 @@ impl FallbackStrategy {
             fallbacks,
             enable_fallback,
             retry_config,
+            min_duration_ms: DEFAULT_MIN_DURATION_MS,
+            silence_skip_ratio: None,
+            fallback_on: Vec::new(),
+            pad_start_ms: 0,
+            pad_end_ms: 0,
+            overall_timeout_ms: None,
+            best_of: None,
         }
     }
     
+    /// 设置最短音频时长 (毫秒)，短于该时长的音频直接拒绝，不发起请求
+    pub fn with_min_duration_ms(mut self, min_duration_ms: u64) -> Self {
+        self.min_duration_ms = min_duration_ms;
+        self
+    }
+    
+    /// 设置静音跳过比例 (None 不检查)，静音窗口占比达到该值的音频直接返回 `NoSpeechDetected`
+    pub fn with_silence_skip_ratio(mut self, ratio: Option<f32>) -> Self {
+        self.silence_skip_ratio = ratio;
+        self
+    }
+    
+    /// 设置发送前在音频首尾补充的静音时长 (毫秒，0 不补充)
+    pub fn with_silence_padding(mut self, pad_start_ms: u32, pad_end_ms: u32) -> Self {
+        self.pad_start_ms = pad_start_ms;
+        self.pad_end_ms = pad_end_ms;
+        self
+    }
+    
+    /// 设置单次转录的总时长上限 (毫秒，None 不限制)，超出后放弃剩余的重试与兜底
+    pub fn with_overall_timeout_ms(mut self, overall_timeout_ms: Option<u64>) -> Self {
+        self.overall_timeout_ms = overall_timeout_ms;
+        self
+    }
+    
+    /// 设置多选一策略 (None 不启用)，启用后通过时长与静音检查的音频改由其转录，不再重试与逐个兜底
+    pub fn with_best_of(mut self, best_of: Option<BestOfStrategy>) -> Self {
+        self.best_of = best_of;
+        self
+    }
+    
+    /// 设置触发兜底的错误类型 (空则任意错误都触发兜底)
+    pub fn with_fallback_on(mut self, fallback_on: Vec<ASRErrorKind>) -> Self {
+        self.fallback_on = fallback_on;
+        self
+    }
+    
+    /// 设置主引擎的流式中间结果回调 (兜底引擎不上报中间结果，避免文本来回跳变)
+    pub fn with_partial_callback(mut self, callback: Option<SharedPartialCallback>) -> Self {
+        if let Some(callback) = callback {
+            self.primary.set_partial_callback(callback);
+        }
+        self
+    }
+    
+    /// 判断主引擎错误是否应触发兜底
+    fn should_fallback(&self, kind: ASRErrorKind) -> bool {
+        self.fallback_on.is_empty() || self.fallback_on.contains(&kind)
+    }
+    
     pub fn from_config(config: &ASRConfig) -> Result<Self, ASRError> {
-        let primary = crate::voice::asr::create_engine(&config.primary)?;
+        let separator = config.transcription_separator();
+        let primary = crate::voice::asr::create_engine_with_separator(&config.primary, separator)?;
 
         let mut fallbacks = Vec::new();
         for fallback_config in &config.fallbacks {
-            fallbacks.push(crate::voice::asr::create_engine(fallback_config)?);
+            fallbacks.push(crate::voice::asr::create_engine_with_separator(fallback_config, separator)?);
         }
+        let best_of = if config.best_of {
+            Some(BestOfStrategy::from_config(config.clone())?)
+        } else {
+            None
+        };
 
-        Ok(Self::new(primary, fallbacks, config.enable_fallback))
+        Ok(Self::new(primary, fallbacks, config.enable_fallback)
+            .with_min_duration_ms(config.min_duration_ms)
+            .with_silence_skip_ratio(config.silence_skip_ratio)
+            .with_fallback_on(config.fallback_on.clone())
+            .with_silence_padding(config.pad_start_ms, config.pad_end_ms)
+            .with_overall_timeout_ms(config.overall_timeout_ms)
+            .with_best_of(best_of))
     }
     
     pub async fn transcribe(&self, audio: &AudioData) -> Result<TranscriptionResult, ASRError> {
+        self.transcribe_cancellable(audio, &CancellationToken::new()).await
+    }
+    
+    /// 可取消的转录
+    /// 
+    /// 令牌取消时立即返回 `ASRError::Cancelled`，进行中的请求 future 随之被丢弃
+    pub async fn transcribe_cancellable(
+        &self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<TranscriptionResult, ASRError> {
+        check_audio(audio, self.min_duration_ms, self.silence_skip_ratio)?;
+        
+        let audio = pad_silence(audio, self.pad_start_ms, self.pad_end_ms);
+        let audio = audio.as_ref();
+        
+        if let Some(ref best_of) = self.best_of {
+            return tokio::select! {
+                _ = cancel_token.cancelled() => Err(ASRError::Cancelled),
+                result = best_of.transcribe(audio) => result,
+            };
+        }
+        
         let start_time = Instant::now();
+        let deadline = Deadline::new(start_time, self.overall_timeout_ms);
         let mut primary_errors: Vec<String> = Vec::new();
+        let mut last_error_kind: Option<ASRErrorKind> = None;
         
         for attempt in 0..=self.retry_config.max_retries {
             if attempt > 0 {
                 let delay = Duration::from_millis(
                     self.retry_config.base_delay_ms * (1 << (attempt - 1))
                 );
+                if !deadline.allows(delay) {
+                    eprintln!("[WARN] 已达转录总时长上限 {}ms，放弃剩余重试", deadline.timeout_ms);
+                    break;
+                }
                 eprintln!(
                     "[INFO] 主引擎重试 {}/{}, 等待 {}ms",
                     attempt,
                     self.retry_config.max_retries,
                     delay.as_millis()
                 );
-                tokio::time::sleep(delay).await;
+                tokio::select! {
+                    _ = cancel_token.cancelled() => return Err(ASRError::Cancelled),
+                    _ = tokio::time::sleep(delay) => {}
+                }
             }
             
-            match self.primary.transcribe(audio).await {
+            match deadline.run(self.primary.transcribe_cancellable(audio, cancel_token)).await {
                 Ok(text) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     eprintln!(
@@ -87,8 +207,9 @@ impl FallbackStrategy {
                         self.primary.name().to_string(),
                         false,
                         duration_ms,
-                    ));
+                    ).with_timings(self.primary.last_timings()));
                 }
+                Err(ASRError::Cancelled) => return Err(ASRError::Cancelled),
                 Err(e) => {
                     eprintln!(
                         "[WARN] 主引擎 {} 转录失败 (尝试 {}/{}): {}",
@@ -97,17 +218,33 @@ impl FallbackStrategy {
                         self.retry_config.max_retries + 1,
                         e
                     );
+                    last_error_kind = Some(e.kind());
                     primary_errors.push(e.to_string());
                 }
             }
         }
         
-        // 主引擎失败，尝试备用引擎
-        if self.enable_fallback && !self.fallbacks.is_empty() {
+        // 主引擎失败，仅当错误类型可由备用引擎解决时才尝试兜底
+        let mut fallback_available = self.enable_fallback && !self.fallbacks.is_empty();
+        if let Some(kind) = last_error_kind.filter(|_| fallback_available) {
+            if !self.should_fallback(kind) {
+                eprintln!("[INFO] 主引擎错误类型 {:?} 不在兜底条件中，跳过兜底引擎", kind);
+                fallback_available = false;
+            }
+        }
+        if fallback_available && deadline.is_expired() {
+            eprintln!("[WARN] 已达转录总时长上限 {}ms，跳过兜底引擎", deadline.timeout_ms);
+            fallback_available = false;
+        }
+        if fallback_available {
             let mut fallback_errors: Vec<String> = Vec::new();
             for fallback in &self.fallbacks {
+                if deadline.is_expired() {
+                    fallback_errors.push(deadline.error().to_string());
+                    break;
+                }
                 eprintln!("[INFO] 主引擎所有重试失败，尝试兜底引擎 {}...", fallback.name());
-                match fallback.transcribe(audio).await {
+                match deadline.run(fallback.transcribe_cancellable(audio, cancel_token)).await {
                     Ok(text) => {
                         let duration_ms = start_time.elapsed().as_millis() as u64;
                         eprintln!(
@@ -120,8 +257,9 @@ impl FallbackStrategy {
                             fallback.name().to_string(),
                             true,
                             duration_ms,
-                        ));
+                        ).with_timings(fallback.last_timings()));
                     }
+                    Err(ASRError::Cancelled) => return Err(ASRError::Cancelled),
                     Err(fallback_error) => {
                         fallback_errors.push(format!("{}: {}", fallback.name(), fallback_error));
                     }
@@ -149,12 +287,93 @@ impl FallbackStrategy {
     }
 }
 
+/// 转录前检查音频，不值得发起请求的录音直接返回错误
+pub(crate) fn check_audio(
+    audio: &AudioData,
+    min_duration_ms: u64,
+    silence_skip_ratio: Option<f32>,
+) -> Result<(), ASRError> {
+    // 误触产生的极短录音直接拒绝，避免浪费一次 API 调用
+    if audio.duration_ms < min_duration_ms {
+        return Err(ASRError::InvalidAudio(format!(
+            "音频过短 ({}ms，最短 {}ms)",
+            audio.duration_ms, min_duration_ms
+        )));
+    }
+    
+    // 长时间录到的几乎全是静音时同样不发起请求
+    if let Some(ratio) = silence_skip_ratio {
+        if audio.is_mostly_silence(VAD_VOICE_THRESHOLD, ratio) {
+            eprintln!("[INFO] 录音基本为静音，跳过转录 ({}ms)", audio.duration_ms);
+            return Err(ASRError::NoSpeechDetected);
+        }
+    }
+    Ok(())
+}
+
+/// 按需在音频首尾补充静音，未启用时不复制音频
+pub(crate) fn pad_silence(audio: &AudioData, pad_start_ms: u32, pad_end_ms: u32) -> Cow<'_, AudioData> {
+    if pad_start_ms == 0 && pad_end_ms == 0 {
+        return Cow::Borrowed(audio);
+    }
+    
+    let mut padded = audio.clone();
+    padded.pad_start(pad_start_ms);
+    padded.pad_end(pad_end_ms);
+    Cow::Owned(padded)
+}
+
+/// 单次转录的总时长期限 (主引擎重试与兜底引擎共用)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    at: Option<Instant>,
+    timeout_ms: u64,
+}
+
+impl Deadline {
+    pub(crate) fn new(start_time: Instant, timeout_ms: Option<u64>) -> Self {
+        Self {
+            at: timeout_ms.map(|ms| start_time + Duration::from_millis(ms)),
+            timeout_ms: timeout_ms.unwrap_or(0),
+        }
+    }
+    
+    pub(crate) fn is_expired(&self) -> bool {
+        self.at.is_some_and(|at| Instant::now() >= at)
+    }
+    
+    /// 等待 `delay` 后是否仍在期限内
+    fn allows(&self, delay: Duration) -> bool {
+        self.at.is_none_or(|at| Instant::now() + delay < at)
+    }
+    
+    pub(crate) fn error(&self) -> ASRError {
+        ASRError::Timeout { timeout_ms: self.timeout_ms }
+    }
+    
+    /// 在期限内执行，超出时返回 `ASRError::Timeout`
+    pub(crate) async fn run<F, T>(&self, future: F) -> Result<T, ASRError>
+    where
+        F: std::future::Future<Output = Result<T, ASRError>>,
+    {
+        match self.at {
+            Some(at) => tokio::time::timeout_at(at.into(), future)
+                .await
+                .unwrap_or_else(|_| Err(self.error())),
+            None => future.await,
+        }
+    }
+}
+
 /// 带并行执行的兜底策略
 pub struct ParallelFallbackStrategy {
     primary_config: crate::voice::config::ASRProviderConfig,
     fallback_config: Option<crate::voice::config::ASRProviderConfig>,
     enable_fallback: bool,
     retry_config: RetryConfig,
+    overall_timeout_ms: Option<u64>,
+    /// 转录拼接分隔符 (分块 / 分段引擎使用)
+    separator: String,
 }
 
 /// 竞速策略：主备并行执行，重试前优先检查备引擎结果
@@ -163,14 +382,19 @@ pub struct RaceStrategy {
     fallback_config: Option<crate::voice::config::ASRProviderConfig>,
     enable_fallback: bool,
     retry_config: RetryConfig,
+    overall_timeout_ms: Option<u64>,
+    /// 转录拼接分隔符 (分块 / 分段引擎使用)
+    separator: String,
 }
 
 impl RaceStrategy {
     pub fn from_config(config: ASRConfig) -> Self {
+        let separator = config.transcription_separator().to_string();
         let ASRConfig {
             primary,
             fallbacks,
             enable_fallback,
+            overall_timeout_ms,
             ..
         } = config;
         let fallback_config = fallbacks.into_iter().next();
@@ -179,6 +403,8 @@ impl RaceStrategy {
             fallback_config,
             enable_fallback,
             retry_config: RetryConfig::default(),
+            overall_timeout_ms,
+            separator,
         }
     }
 
@@ -187,18 +413,26 @@ impl RaceStrategy {
         self
     }
 
+    /// 设置单次转录的总时长上限 (毫秒，None 不限制)
+    pub fn with_overall_timeout_ms(mut self, overall_timeout_ms: Option<u64>) -> Self {
+        self.overall_timeout_ms = overall_timeout_ms;
+        self
+    }
+
     pub async fn transcribe(&self, audio: &AudioData) -> Result<TranscriptionResult, ASRError> {
         let start_time = Instant::now();
+        let deadline = Deadline::new(start_time, self.overall_timeout_ms);
         let fallback_result: Arc<Mutex<Option<Result<String, String>>>> =
             Arc::new(Mutex::new(None));
 
         let mut fallback_handle = if self.enable_fallback && self.fallback_config.is_some() {
             let fallback_config = self.fallback_config.clone().unwrap();
+            let separator = self.separator.clone();
             let audio_clone = audio.clone();
             let result_holder = Arc::clone(&fallback_result);
 
             Some(tokio::spawn(async move {
-                let engine = crate::voice::asr::create_engine(&fallback_config)?;
+                let engine = crate::voice::asr::create_engine_with_separator(&fallback_config, &separator)?;
                 let result = engine.transcribe(&audio_clone).await;
                 let mut holder = result_holder.lock().unwrap();
                 match &result {
@@ -215,7 +449,8 @@ impl RaceStrategy {
             None
         };
 
-        let primary_engine = crate::voice::asr::create_engine(&self.primary_config)?;
+        let primary_engine =
+            crate::voice::asr::create_engine_with_separator(&self.primary_config, &self.separator)?;
         let primary_name = primary_engine.name().to_string();
         let fallback_name = self
             .fallback_config
@@ -248,6 +483,10 @@ impl RaceStrategy {
                 let delay = Duration::from_millis(
                     self.retry_config.base_delay_ms * (1 << (attempt - 1)),
                 );
+                if !deadline.allows(delay) {
+                    eprintln!("[WARN] 已达转录总时长上限 {}ms，放弃剩余重试", deadline.timeout_ms);
+                    break;
+                }
                 eprintln!(
                     "[INFO] 主引擎重试 {}/{}, 等待 {}ms",
                     attempt,
@@ -257,7 +496,7 @@ impl RaceStrategy {
                 tokio::time::sleep(delay).await;
             }
 
-            match primary_engine.transcribe(audio).await {
+            match deadline.run(primary_engine.transcribe(audio)).await {
                 Ok(text) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     eprintln!(
@@ -276,7 +515,7 @@ impl RaceStrategy {
                         primary_name,
                         false,
                         duration_ms,
-                    ));
+                    ).with_timings(primary_engine.last_timings()));
                 }
                 Err(e) => {
                     eprintln!(
@@ -294,7 +533,19 @@ impl RaceStrategy {
         if let Some(handle) = fallback_handle {
             eprintln!("[INFO] 主引擎所有重试失败，等待兜底引擎结果...");
 
-            match handle.await {
+            let abort_handle = handle.abort_handle();
+            let joined = match deadline.run(async { Ok(handle.await) }).await {
+                Ok(joined) => joined,
+                Err(timeout) => {
+                    abort_handle.abort();
+                    return Err(ASRError::AllEnginesFailed {
+                        primary_error: primary_errors.join("; "),
+                        fallback_error: Some(timeout.to_string()),
+                    });
+                }
+            };
+            
+            match joined {
                 Ok(Ok(text)) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     eprintln!(
@@ -342,10 +593,12 @@ impl RaceStrategy {
 
 impl ParallelFallbackStrategy {
     pub fn from_config(config: ASRConfig) -> Self {
+        let separator = config.transcription_separator().to_string();
         let ASRConfig {
             primary,
             fallbacks,
             enable_fallback,
+            overall_timeout_ms,
             ..
         } = config;
         let fallback_config = fallbacks.into_iter().next();
@@ -354,6 +607,8 @@ impl ParallelFallbackStrategy {
             fallback_config,
             enable_fallback,
             retry_config: RetryConfig::default(),
+            overall_timeout_ms,
+            separator,
         }
     }
     
@@ -361,24 +616,33 @@ impl ParallelFallbackStrategy {
         self.retry_config = retry_config;
         self
     }
+
+    /// 设置单次转录的总时长上限 (毫秒，None 不限制)
+    pub fn with_overall_timeout_ms(mut self, overall_timeout_ms: Option<u64>) -> Self {
+        self.overall_timeout_ms = overall_timeout_ms;
+        self
+    }
     
     pub async fn transcribe(&self, audio: &AudioData) -> Result<TranscriptionResult, ASRError> {
         let start_time = Instant::now();
+        let deadline = Deadline::new(start_time, self.overall_timeout_ms);
         
         // 启动备用引擎后台任务
         let fallback_handle = if self.enable_fallback && self.fallback_config.is_some() {
             let fallback_config = self.fallback_config.clone().unwrap();
+            let separator = self.separator.clone();
             let audio_clone = audio.clone();
             
             Some(tokio::spawn(async move {
-                let engine = crate::voice::asr::create_engine(&fallback_config)?;
+                let engine = crate::voice::asr::create_engine_with_separator(&fallback_config, &separator)?;
                 engine.transcribe(&audio_clone).await
             }))
         } else {
             None
         };
         
-        let primary_engine = crate::voice::asr::create_engine(&self.primary_config)?;
+        let primary_engine =
+            crate::voice::asr::create_engine_with_separator(&self.primary_config, &self.separator)?;
         let primary_name = primary_engine.name().to_string();
         
         let mut primary_errors: Vec<String> = Vec::new();
@@ -388,6 +652,10 @@ impl ParallelFallbackStrategy {
                 let delay = Duration::from_millis(
                     self.retry_config.base_delay_ms * (1 << (attempt - 1))
                 );
+                if !deadline.allows(delay) {
+                    eprintln!("[WARN] 已达转录总时长上限 {}ms，放弃剩余重试", deadline.timeout_ms);
+                    break;
+                }
                 eprintln!(
                     "[INFO] 主引擎重试 {}/{}, 等待 {}ms",
                     attempt,
@@ -397,7 +665,7 @@ impl ParallelFallbackStrategy {
                 tokio::time::sleep(delay).await;
             }
             
-            match primary_engine.transcribe(audio).await {
+            match deadline.run(primary_engine.transcribe(audio)).await {
                 Ok(text) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     eprintln!(
@@ -416,7 +684,7 @@ impl ParallelFallbackStrategy {
                         primary_name,
                         false,
                         duration_ms,
-                    ));
+                    ).with_timings(primary_engine.last_timings()));
                 }
                 Err(e) => {
                     eprintln!(
@@ -435,7 +703,19 @@ impl ParallelFallbackStrategy {
         if let Some(handle) = fallback_handle {
             eprintln!("[INFO] 主引擎所有重试失败，等待兜底引擎结果...");
             
-            match handle.await {
+            let abort_handle = handle.abort_handle();
+            let joined = match deadline.run(async { Ok(handle.await) }).await {
+                Ok(joined) => joined,
+                Err(timeout) => {
+                    abort_handle.abort();
+                    return Err(ASRError::AllEnginesFailed {
+                        primary_error: primary_errors.join("; "),
+                        fallback_error: Some(timeout.to_string()),
+                    });
+                }
+            };
+            
+            match joined {
                 Ok(Ok(text)) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     let fallback_name = self.fallback_config
@@ -485,3 +765,407 @@ impl ParallelFallbackStrategy {
         self.enable_fallback && self.fallback_config.is_some()
     }
 }
+
+/// 多选一策略的结果选择函数：从全部成功结果中返回选中结果的下标
+pub type BestOfSelector = Box<dyn Fn(&[TranscriptionResult]) -> usize + Send + Sync>;
+
+/// 多选一策略：同一段音频交给多个引擎并行转录 (同一引擎的多次转录依次执行)，按选择函数挑出结果
+///
+/// 以成本换准确率，适合重要内容；选中结果的 `alternatives` 附带其余成功结果，
+/// 默认按词元一致性选择 (见 `select_by_agreement`)
+pub struct BestOfStrategy {
+    engines: Vec<Box<dyn ASREngine>>,
+    attempts: u32,
+    selector: BestOfSelector,
+    overall_timeout_ms: Option<u64>,
+}
+
+impl BestOfStrategy {
+    /// 每个引擎各转录一次
+    pub fn new(engines: Vec<Box<dyn ASREngine>>) -> Self {
+        Self {
+            engines,
+            attempts: 1,
+            selector: Box::new(select_by_agreement),
+            overall_timeout_ms: None,
+        }
+    }
+
+    /// 由主引擎与所有备用引擎组成候选
+    pub fn from_config(config: ASRConfig) -> Result<Self, ASRError> {
+        let separator = config.transcription_separator();
+        let engines = std::iter::once(&config.primary)
+            .chain(config.fallbacks.iter())
+            .map(|cfg| crate::voice::asr::create_engine_with_separator(cfg, separator))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(engines).with_overall_timeout_ms(config.overall_timeout_ms))
+    }
+
+    /// 设置每个引擎的转录次数 (至少 1 次)
+    ///
+    /// 分块 / 分段等引擎在实例上保存单次转录的状态，同一引擎的多次转录不并发执行
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// 设置结果选择函数
+    pub fn with_selector<F>(mut self, selector: F) -> Self
+    where
+        F: Fn(&[TranscriptionResult]) -> usize + Send + Sync + 'static,
+    {
+        self.selector = Box::new(selector);
+        self
+    }
+
+    /// 设置单次转录的总时长上限 (毫秒，None 不限制)，超时的候选视为失败
+    pub fn with_overall_timeout_ms(mut self, overall_timeout_ms: Option<u64>) -> Self {
+        self.overall_timeout_ms = overall_timeout_ms;
+        self
+    }
+
+    pub async fn transcribe(&self, audio: &AudioData) -> Result<TranscriptionResult, ASRError> {
+        let start_time = Instant::now();
+        let deadline = Deadline::new(start_time, self.overall_timeout_ms);
+
+        let runs = self.engines.iter().map(|engine| async move {
+            let mut outcomes = Vec::with_capacity(self.attempts as usize);
+            for _ in 0..self.attempts {
+                let attempt_start = Instant::now();
+                let result = deadline
+                    .run(engine.transcribe(audio))
+                    .await
+                    .map(|text| (text, engine.last_timings()));
+                outcomes.push((engine.name(), attempt_start.elapsed().as_millis() as u64, result));
+            }
+            outcomes
+        });
+        let outcomes = futures_util::future::join_all(runs).await;
+
+        let mut candidates = Vec::new();
+        let mut errors = Vec::new();
+        for (engine_name, duration_ms, result) in outcomes.into_iter().flatten() {
+            match result {
+                Ok((text, timings)) => candidates.push(
+                    TranscriptionResult::new(text, engine_name.to_string(), false, duration_ms)
+                        .with_timings(timings),
+                ),
+                Err(e) => {
+                    eprintln!("[WARN] 候选引擎 {} 转录失败: {}", engine_name, e);
+                    errors.push(format!("{}: {}", engine_name, e));
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            return Err(ASRError::AllEnginesFailed {
+                primary_error: errors.join("; "),
+                fallback_error: None,
+            });
+        }
+
+        let chosen = (self.selector)(&candidates).min(candidates.len() - 1);
+        let mut result = candidates.remove(chosen);
+        eprintln!(
+            "[INFO] 多选一转录完成: 选中 {} ({}/{} 个候选成功), 耗时 {}ms",
+            result.engine,
+            candidates.len() + 1,
+            self.engines.len() * self.attempts as usize,
+            start_time.elapsed().as_millis()
+        );
+        result.duration_ms = start_time.elapsed().as_millis() as u64;
+        Ok(result.with_alternatives(candidates))
+    }
+}
+
+/// 选择文本最长的结果 (按字符数，相同时取靠前的)
+pub fn select_longest(results: &[TranscriptionResult]) -> usize {
+    results
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, result)| (result.text.trim().chars().count(), std::cmp::Reverse(*index)))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+/// 选择与其他结果词元一致性最高的结果 (默认选择函数)
+///
+/// 每个结果与其余结果逐一计算词元集合的 Jaccard 相似度并求和，得分最高者胜出；
+/// 得分相同 (如只有两个候选) 时取文本最长的
+pub fn select_by_agreement(results: &[TranscriptionResult]) -> usize {
+    let token_sets: Vec<std::collections::HashSet<String>> = results
+        .iter()
+        .map(|result| tokenize(&result.text).into_iter().collect())
+        .collect();
+
+    let score = |index: usize| -> f64 {
+        token_sets
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, other)| {
+                let union = token_sets[index].union(other).count();
+                if union == 0 {
+                    0.0
+                } else {
+                    token_sets[index].intersection(other).count() as f64 / union as f64
+                }
+            })
+            .sum()
+    };
+
+    (0..results.len())
+        .map(|index| (index, score(index), results[index].text.trim().chars().count()))
+        .max_by(|a, b| {
+            a.1.total_cmp(&b.1)
+                .then(a.2.cmp(&b.2))
+                .then(b.0.cmp(&a.0))
+        })
+        .map(|(index, _, _)| index)
+        .unwrap_or(0)
+}
+
+/// 切分词元：拉丁字母与数字按单词切分 (忽略大小写)，CJK 字符逐字切分，标点与空白作为分隔
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::mock::MockEngine;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_rejects_audio_shorter_than_min_duration() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let engine = MockEngine::new("counting").counting_calls(&calls);
+        let strategy = FallbackStrategy::new(Box::new(engine), Vec::new(), false);
+
+        // 50ms @ 16kHz
+        let audio = AudioData::new(vec![0.1; 800], 16000, 1);
+        let err = strategy.transcribe(&audio).await.unwrap_err();
+
+        assert!(matches!(err, ASRError::InvalidAudio(ref msg) if msg.contains("50ms")));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        // 500ms @ 16kHz
+        let audio = AudioData::new(vec![0.1; 8000], 16000, 1);
+        let result = strategy.transcribe(&audio).await.unwrap();
+        assert_eq!(result.text, "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_skips_mostly_silent_audio() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let engine = MockEngine::new("counting").counting_calls(&calls);
+        let strategy = FallbackStrategy::new(Box::new(engine), Vec::new(), false)
+            .with_silence_skip_ratio(Some(0.95));
+
+        // 2 秒静音
+        let silent = AudioData::new(vec![0.0; 32000], 16000, 1);
+        assert!(matches!(strategy.transcribe(&silent).await, Err(ASRError::NoSpeechDetected)));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let speech = AudioData::new(vec![0.1; 32000], 16000, 1);
+        assert_eq!(strategy.transcribe(&speech).await.unwrap().text, "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_only_on_configured_error_kinds() {
+        let no_retry = RetryConfig { max_retries: 0, base_delay_ms: 0, ..RetryConfig::default() };
+        let audio = AudioData::new(vec![0.1; 8000], 16000, 1);
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let strategy = FallbackStrategy::with_retry_config(
+            Box::new(MockEngine::new("failing").with_error(ASRError::InvalidAudio("空音频".to_string()))),
+            vec![Box::new(MockEngine::new("counting").counting_calls(&calls))],
+            true,
+            no_retry.clone(),
+        )
+        .with_fallback_on(vec![ASRErrorKind::Timeout, ASRErrorKind::QuotaExceeded]);
+        let err = strategy.transcribe(&audio).await.unwrap_err();
+        assert!(matches!(err, ASRError::AllEnginesFailed { fallback_error: None, .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let strategy = FallbackStrategy::with_retry_config(
+            Box::new(MockEngine::new("failing").with_error(ASRError::Timeout { timeout_ms: 1000 })),
+            vec![Box::new(MockEngine::new("counting").counting_calls(&calls))],
+            true,
+            no_retry,
+        )
+        .with_fallback_on(vec![ASRErrorKind::Timeout, ASRErrorKind::QuotaExceeded]);
+        let result = strategy.transcribe(&audio).await.unwrap();
+        assert!(result.used_fallback);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    fn fixed(name: &'static str, text: Option<&'static str>) -> Box<dyn ASREngine> {
+        let engine = MockEngine::new(name);
+        Box::new(match text {
+            Some(text) => engine.with_text(text),
+            None => engine.with_error(ASRError::NetworkError("连接失败".to_string())),
+        })
+    }
+
+    #[test]
+    fn test_select_by_agreement_prefers_majority() {
+        let results: Vec<TranscriptionResult> = ["今天天气很好", "今天天气真的很好啊朋友们", "今天天汽很好"]
+            .iter()
+            .map(|text| TranscriptionResult::new(text.to_string(), "mock".to_string(), false, 0))
+            .collect();
+        assert_eq!(select_longest(&results), 1);
+        assert_eq!(select_by_agreement(&results), 0);
+
+        assert_eq!(tokenize("Hello, world 你好"), vec!["hello", "world", "你", "好"]);
+    }
+
+    #[tokio::test]
+    async fn test_best_of_attaches_alternatives() {
+        let strategy = BestOfStrategy::new(vec![
+            fixed("a", Some("short")),
+            fixed("b", None),
+            fixed("c", Some("a much longer text")),
+        ])
+        .with_selector(select_longest);
+
+        let audio = AudioData::new(vec![0.1; 16000], 16000, 1);
+        let result = strategy.transcribe(&audio).await.unwrap();
+        assert_eq!(result.text, "a much longer text");
+        assert_eq!(result.engine, "c");
+        assert!(!result.used_fallback);
+        assert_eq!(result.alternatives.len(), 1);
+        assert_eq!(result.alternatives[0].engine, "a");
+
+        let failing = BestOfStrategy::new(vec![fixed("b", None)]).with_attempts(2);
+        assert!(matches!(
+            failing.transcribe(&audio).await,
+            Err(ASRError::AllEnginesFailed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_best_of_runs_repeated_attempts_sequentially() {
+        let max_active = Arc::new(AtomicUsize::new(0));
+        let engine = MockEngine::new("counting")
+            .with_text_fn(|_, call| format!("第 {} 次", call))
+            .with_delay(Duration::from_millis(5))
+            .with_timings_fn(|completed| crate::voice::asr::Timings {
+                inference_ms: Some(completed as u64),
+                ..Default::default()
+            })
+            .tracking_concurrency(&Arc::new(AtomicUsize::new(0)), &max_active);
+        let strategy = BestOfStrategy::new(vec![Box::new(engine)])
+            .with_attempts(3)
+            .with_selector(|_| 0);
+
+        let audio = AudioData::new(vec![0.1; 16000], 16000, 1);
+        let result = strategy.transcribe(&audio).await.unwrap();
+        assert_eq!(max_active.load(Ordering::SeqCst), 1);
+        assert_eq!(result.text, "第 1 次");
+        assert_eq!(result.timings.inference_ms, Some(1));
+        assert_eq!(result.alternatives[1].timings.inference_ms, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_best_of_replaces_retry_and_fallback() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let strategy = FallbackStrategy::new(
+            Box::new(MockEngine::new("unused").counting_calls(&calls)),
+            Vec::new(),
+            false,
+        )
+        .with_best_of(Some(BestOfStrategy::new(vec![
+            fixed("a", Some("今天天气很好")),
+            fixed("b", Some("今天天汽很好")),
+            fixed("c", Some("今天天气很好啊")),
+        ])));
+
+        // 过短的音频仍在发起请求前拒绝
+        let short = AudioData::new(vec![0.1; 800], 16000, 1);
+        assert!(matches!(strategy.transcribe(&short).await, Err(ASRError::InvalidAudio(_))));
+
+        let audio = AudioData::new(vec![0.1; 8000], 16000, 1);
+        let result = strategy.transcribe(&audio).await.unwrap();
+        assert_eq!(result.engine, "a");
+        assert_eq!(result.alternatives.len(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    /// 长时间无响应的模拟引擎
+    fn stalled() -> MockEngine {
+        MockEngine::new("stalled").with_text("late").with_delay(Duration::from_secs(30))
+    }
+
+    #[tokio::test]
+    async fn test_overall_timeout_stops_remaining_attempts() {
+        let audio = AudioData::new(vec![0.1; 8000], 16000, 1);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let strategy = FallbackStrategy::new(
+            Box::new(stalled()),
+            vec![Box::new(MockEngine::new("counting").counting_calls(&calls))],
+            true,
+        )
+        .with_overall_timeout_ms(Some(50));
+
+        let started = Instant::now();
+        let err = strategy.transcribe(&audio).await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(matches!(
+            err,
+            ASRError::AllEnginesFailed { ref primary_error, fallback_error: None } if primary_error.contains("50ms")
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_in_flight_transcription() {
+        let audio = AudioData::new(vec![0.1; 8000], 16000, 1);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let strategy = FallbackStrategy::new(
+            Box::new(stalled()),
+            vec![Box::new(MockEngine::new("counting").counting_calls(&calls))],
+            true,
+        );
+
+        let cancel_token = CancellationToken::new();
+        let canceller = cancel_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let started = Instant::now();
+        let err = strategy.transcribe_cancellable(&audio, &cancel_token).await.unwrap_err();
+
+        // 进行中的请求被丢弃，不再重试或转入备用引擎
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(matches!(err, ASRError::Cancelled));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
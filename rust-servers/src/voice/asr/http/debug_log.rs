@@ -0,0 +1,120 @@
+// HTTP 请求/响应调试日志
+// 各 HTTP 引擎共享，输出前截断音频等超长字段并隐藏鉴权请求头
+
+use serde_json::Value;
+
+/// 日志中字符串字段的最大长度 (超出部分截断，避免输出 base64 音频)
+const MAX_LOGGED_STRING_CHARS: usize = 64;
+
+/// 需要脱敏的请求头 (小写)
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "x-api-access-key", "x-api-key"];
+
+/// 调试日志记录器
+///
+/// 默认关闭，由供应商配置的 `debug_logging` 开启
+#[derive(Debug, Clone, Copy)]
+pub struct DebugLogger {
+    engine: &'static str,
+    enabled: bool,
+}
+
+impl DebugLogger {
+    pub fn new(engine: &'static str) -> Self {
+        Self { engine, enabled: false }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 记录请求 (请求头脱敏，请求体截断)
+    pub fn log_request(&self, url: &str, headers: &[(&str, &str)], body: &Value) {
+        if !self.enabled {
+            return;
+        }
+
+        let headers: Vec<String> = headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, redact_header(name, value)))
+            .collect();
+
+        eprintln!(
+            "[DEBUG] [{}] 请求 POST {}\n  headers: {:?}\n  body: {}",
+            self.engine,
+            url,
+            headers,
+            redact_body(body)
+        );
+    }
+
+    /// 记录响应 (响应体截断)
+    pub fn log_response(&self, status: &str, body: &Value) {
+        if !self.enabled {
+            return;
+        }
+
+        eprintln!(
+            "[DEBUG] [{}] 响应 status={}\n  body: {}",
+            self.engine,
+            status,
+            redact_body(body)
+        );
+    }
+}
+
+/// 请求头脱敏：鉴权类请求头只保留长度信息
+pub fn redact_header(name: &str, value: &str) -> String {
+    if SENSITIVE_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+        format!("<redacted {} chars>", value.chars().count())
+    } else {
+        value.to_string()
+    }
+}
+
+/// 递归截断 JSON 中的超长字符串 (如 base64 音频)
+pub fn redact_body(body: &Value) -> Value {
+    match body {
+        Value::String(s) if s.chars().count() > MAX_LOGGED_STRING_CHARS => {
+            let prefix: String = s.chars().take(MAX_LOGGED_STRING_CHARS).collect();
+            Value::String(format!("{}...<{} chars>", prefix, s.chars().count()))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact_body).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), redact_body(v)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sensitive_headers() {
+        assert_eq!(redact_header("Authorization", "Bearer sk-123"), "<redacted 13 chars>");
+        assert_eq!(redact_header("X-Api-Access-Key", "abcd"), "<redacted 4 chars>");
+        assert_eq!(redact_header("Content-Type", "application/json"), "application/json");
+    }
+
+    #[test]
+    fn test_redact_body_truncates_long_strings() {
+        let audio = "A".repeat(1000);
+        let body = serde_json::json!({
+            "audio": {"data": audio},
+            "request": {"model_name": "bigmodel"}
+        });
+
+        let redacted = redact_body(&body);
+        let data = redacted["audio"]["data"].as_str().unwrap();
+        assert!(data.ends_with("...<1000 chars>"));
+        assert!(data.len() < 100);
+        assert_eq!(redacted["request"]["model_name"], "bigmodel");
+    }
+}
@@ -3,10 +3,17 @@
 
 use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose};
+use crate::voice::asr::ids::generate_request_id;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 use crate::voice::asr::{ASREngine, ASRError, ASRMode, RealtimeSession, RetryConfig};
-use crate::voice::audio::AudioData;
+use crate::voice::asr::http::debug_log::DebugLogger;
+use crate::voice::asr::http::{build_extra_headers, parse_retry_after, prepare_for_upload, read_error_body, read_json_body, retry_delay, shared_client};
+use crate::voice::asr::text::apply_punctuation_mode;
+use crate::voice::config::{PunctuationMode, DEFAULT_ENABLE_ITN};
+use crate::voice::audio::{AudioData, TARGET_SAMPLE_RATE};
 
 const DOUBAO_API_URL: &str = "https://openspeech.bytedance.com/api/v3/auc/bigmodel/recognize/flash";
 const RESOURCE_ID: &str = "volc.bigasr.auc_turbo";
@@ -16,6 +23,11 @@ pub struct DoubaoHttpEngine {
     access_key: String,
     client: reqwest::Client,
     retry_config: RetryConfig,
+    language: Option<String>,
+    punctuation_mode: PunctuationMode,
+    debug_log: DebugLogger,
+    extra_headers: HeaderMap,
+    enable_itn: bool,
 }
 
 impl DoubaoHttpEngine {
@@ -24,28 +36,52 @@ impl DoubaoHttpEngine {
     }
     
     pub fn with_config(app_id: String, access_key: String, retry_config: RetryConfig) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_millis(retry_config.timeout_ms))
-            .build()
-            .unwrap_or_default();
-        
         Self {
             app_id,
             access_key,
-            client,
+            client: shared_client(),
             retry_config,
+            language: None,
+            punctuation_mode: PunctuationMode::default(),
+            debug_log: DebugLogger::new("doubao"),
+            extra_headers: HeaderMap::new(),
+            enable_itn: DEFAULT_ENABLE_ITN,
         }
     }
     
-    async fn transcribe_once(&self, audio: &AudioData) -> Result<String, ASRError> {
-        let wav_data = audio.to_wav()
-            .map_err(|e| ASRError::InvalidAudio(e.to_string()))?;
-        
-        let audio_base64 = general_purpose::STANDARD.encode(&wav_data);
-        
-        eprintln!("[INFO] 豆包 ASR: 音频数据大小 {} bytes", wav_data.len());
-        
-        let request_body = serde_json::json!({
+    /// 设置识别语言 (None 使用默认语言)
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+    
+    /// 设置标点处理模式
+    pub fn with_punctuation_mode(mut self, mode: PunctuationMode) -> Self {
+        self.punctuation_mode = mode;
+        self
+    }
+    
+    /// 设置是否启用逆文本规范化
+    pub fn with_enable_itn(mut self, enabled: bool) -> Self {
+        self.enable_itn = enabled;
+        self
+    }
+    
+    /// 设置附加到每个请求的自定义请求头 (不合法的请求头跳过并记录警告)
+    pub fn with_extra_headers(mut self, headers: &HashMap<String, String>) -> Self {
+        self.extra_headers = build_extra_headers("doubao", headers);
+        self
+    }
+    
+    /// 开启请求/响应调试日志 (已脱敏)
+    pub fn with_debug_logging(mut self, enabled: bool) -> Self {
+        self.debug_log.set_enabled(enabled);
+        self
+    }
+    
+    /// 构建请求体 (音频以 base64 内联)
+    fn request_body(&self, audio_base64: String) -> serde_json::Value {
+        serde_json::json!({
             "user": {
                 "uid": &self.app_id
             },
@@ -53,12 +89,35 @@ impl DoubaoHttpEngine {
                 "data": audio_base64
             },
             "request": {
-                "model_name": "bigmodel"
+                "model_name": "bigmodel",
+                "enable_itn": self.enable_itn
             }
-        });
+        })
+    }
+    
+    async fn transcribe_once(&self, audio: &AudioData) -> Result<String, ASRError> {
+        let timeout_ms = self.retry_config.effective_request_timeout_ms();
+        let wav_data = prepare_for_upload(audio, TARGET_SAMPLE_RATE, 1)?;
+        
+        let audio_base64 = general_purpose::STANDARD.encode(&wav_data);
+        
+        eprintln!("[INFO] 豆包 ASR: 音频数据大小 {} bytes", wav_data.len());
+        
+        let request_body = self.request_body(audio_base64);
         
         let request_id = generate_request_id();
         
+        self.debug_log.log_request(
+            DOUBAO_API_URL,
+            &[
+                ("X-Api-App-Key", &self.app_id),
+                ("X-Api-Access-Key", &self.access_key),
+                ("X-Api-Resource-Id", RESOURCE_ID),
+                ("X-Api-Request-Id", &request_id),
+            ],
+            &request_body,
+        );
+        
         let response = self.client
             .post(DOUBAO_API_URL)
             .header("X-Api-App-Key", &self.app_id)
@@ -66,22 +125,48 @@ impl DoubaoHttpEngine {
             .header("X-Api-Resource-Id", RESOURCE_ID)
             .header("X-Api-Request-Id", &request_id)
             .header("X-Api-Sequence", "-1")
+            .headers(self.extra_headers.clone())
             .json(&request_body)
+            .timeout(Duration::from_millis(timeout_ms))
             .send()
             .await
             .map_err(|e| {
                 if e.is_timeout() {
-                    ASRError::Timeout { timeout_ms: self.retry_config.timeout_ms }
+                    ASRError::Timeout { timeout_ms }
                 } else {
                     ASRError::NetworkError(e.to_string())
                 }
             })?;
         
+        // 网关等中间层返回的错误不带豆包状态头，按 HTTP 状态码处理
+        let status = response.status();
+        if !status.is_success() && response.headers().get("X-Api-Status-Code").is_none() {
+            let retry_after_ms = parse_retry_after(response.headers());
+            let error_text = read_error_body(response).await;
+            self.debug_log.log_response(status.as_str(), &serde_json::Value::String(error_text.clone()));
+            
+            return match status.as_u16() {
+                401 | 403 => Err(ASRError::AuthFailed {
+                    engine: "doubao".to_string(),
+                    message: error_text,
+                }),
+                429 => Err(ASRError::QuotaExceeded {
+                    engine: "doubao".to_string(),
+                    retry_after_ms,
+                }),
+                _ => Err(ASRError::NetworkError(format!(
+                    "API 请求失败 ({}): {}",
+                    status, error_text
+                ))),
+            };
+        }
+        
         let status_code = response
             .headers()
             .get("X-Api-Status-Code")
             .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
+            .unwrap_or("")
+            .to_string();
         
         let api_message = response
             .headers()
@@ -92,13 +177,14 @@ impl DoubaoHttpEngine {
         eprintln!("[INFO] 豆包 ASR 响应: status_code={}, message={}", status_code, api_message);
         
         if status_code != "20000000" {
-            return match status_code {
+            return match status_code.as_str() {
                 "40100001" | "40100002" | "40300001" => Err(ASRError::AuthFailed {
                     engine: "doubao".to_string(),
                     message: api_message.to_string(),
                 }),
                 "42900001" => Err(ASRError::QuotaExceeded {
                     engine: "doubao".to_string(),
+                    retry_after_ms: parse_retry_after(response.headers()),
                 }),
                 _ => Err(ASRError::NetworkError(format!(
                     "豆包 ASR 失败 ({}): {}",
@@ -107,10 +193,9 @@ impl DoubaoHttpEngine {
             };
         }
         
-        let result: serde_json::Value = response.json().await
-            .map_err(|e| ASRError::InternalError(format!("解析响应失败: {}", e)))?;
+        let result: serde_json::Value = read_json_body(response).await?;
         
-        eprintln!("[DEBUG] 豆包 ASR 响应体: {}", serde_json::to_string_pretty(&result).unwrap_or_default());
+        self.debug_log.log_response(&status_code, &result);
         
         let text = result["result"]["text"]
             .as_str()
@@ -119,8 +204,7 @@ impl DoubaoHttpEngine {
                 result
             )))?;
         
-        let mut text = text.to_string();
-        strip_trailing_punctuation(&mut text);
+        let text = apply_punctuation_mode(text, self.punctuation_mode, self.language.as_deref(), false);
         
         Ok(text)
     }
@@ -146,12 +230,13 @@ impl ASREngine for DoubaoHttpEngine {
         
         for attempt in 0..=self.retry_config.max_retries {
             if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(
-                    self.retry_config.base_delay_ms * (1 << (attempt - 1))
-                )).await;
+                tokio::time::sleep(retry_delay(&self.retry_config, attempt, last_error.as_ref())).await;
             }
             
-            match self.transcribe_once(audio).await {
+            let attempt_start = Instant::now();
+            let result = self.transcribe_once(audio).await;
+            self.retry_config.record_attempt(attempt_start.elapsed(), &result);
+            match result {
                 Ok(text) => {
                     let duration = start_time.elapsed().as_millis() as u64;
                     eprintln!("[INFO] 豆包 HTTP 转录成功，耗时 {}ms: {}", duration, text);
@@ -179,26 +264,19 @@ impl ASREngine for DoubaoHttpEngine {
     }
 }
 
-fn generate_request_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    format!("req_{}", timestamp)
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn strip_trailing_punctuation(text: &mut String) {
-    let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"',
-                       '.', ',', '!', '?', ';', ':', '"', '\'',
-                       '（', '）', '(', ')', '【', '】', '[', ']',
-                       '《', '》', '<', '>', '—', '…', '·'];
-    
-    while let Some(c) = text.chars().last() {
-        if punctuation.contains(&c) {
-            text.pop();
-        } else {
-            break;
-        }
+    #[test]
+    fn test_request_body_carries_enable_itn() {
+        let engine = DoubaoHttpEngine::new("app".to_string(), "token".to_string());
+        let body = engine.request_body("UklGRg==".to_string());
+        assert_eq!(body["user"]["uid"], "app");
+        assert_eq!(body["audio"]["data"], "UklGRg==");
+        assert_eq!(body["request"]["enable_itn"], true);
+
+        let engine = DoubaoHttpEngine::new("app".to_string(), "token".to_string()).with_enable_itn(false);
+        assert_eq!(engine.request_body(String::new())["request"]["enable_itn"], false);
     }
 }
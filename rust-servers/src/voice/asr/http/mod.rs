@@ -4,7 +4,416 @@
 pub mod qwen;
 pub mod doubao;
 pub mod sensevoice;
+pub mod debug_log;
 
 pub use qwen::QwenHttpEngine;
 pub use doubao::DoubaoHttpEngine;
 pub use sensevoice::SenseVoiceHttpEngine;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use tokio_util::sync::CancellationToken;
+
+use crate::voice::asr::{ASRError, RetryConfig};
+use crate::voice::audio::{AudioData, WavEncoder};
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        eprintln!("[WARN] [http] {}", format!($($arg)*));
+    };
+}
+
+/// 建立连接的超时时长 (秒)
+const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// 空闲连接在连接池中的保留时长 (秒)，超时后主动关闭
+const POOL_IDLE_TIMEOUT_SECS: u64 = 60;
+
+/// 进程内共享的 HTTP 客户端
+static SHARED_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// 获取共享的 HTTP 客户端
+///
+/// 各 HTTP 引擎共用同一连接池，兜底/竞速策略每次转录重新创建引擎时也能复用已建立的 TLS 连接；
+/// 请求超时由引擎在每个请求上单独设置
+pub(crate) fn shared_client() -> reqwest::Client {
+    SHARED_CLIENT
+        .get_or_init(|| {
+            reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+                .pool_idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default()
+        })
+        .clone()
+}
+
+/// 将音频整理为供应商要求的格式并编码为 WAV
+///
+/// 各 HTTP 引擎上传前统一经过此处：采样率或声道数不一致时重采样、下混，格式问题只需在这里修正；
+/// 接近满幅的峰值软限幅后量化，减轻削波失真对识别的影响。空音频或格式无效时返回 `InvalidAudio`
+pub(crate) fn prepare_for_upload(
+    audio: &AudioData,
+    expected_rate: u32,
+    expected_channels: u16,
+) -> Result<Vec<u8>, ASRError> {
+    audio.to_target(expected_rate, expected_channels)
+        .and_then(|audio| {
+            WavEncoder::new(audio.sample_rate, audio.channels, 16)
+                .with_soft_limit(true)
+                .encode(&audio)
+        })
+        .map_err(|e| ASRError::InvalidAudio(e.to_string()))
+}
+
+/// 将配置的自定义请求头转换为 HeaderMap
+///
+/// 名称或值不合法 (如含空格、换行) 的请求头跳过并记录警告，不影响请求发送
+pub(crate) fn build_extra_headers(engine: &str, headers: &HashMap<String, String>) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = match HeaderName::from_bytes(name.trim().as_bytes()) {
+            Ok(header_name) => header_name,
+            Err(_) => {
+                log_warn!("{} 自定义请求头名称不合法，已跳过: {:?}", engine, name);
+                continue;
+            }
+        };
+        match HeaderValue::from_str(value.trim()) {
+            Ok(header_value) => {
+                map.insert(header_name, header_value);
+            }
+            Err(_) => {
+                log_warn!("{} 自定义请求头 {} 的值不合法，已跳过", engine, header_name);
+            }
+        }
+    }
+    map
+}
+
+/// 流式上传时每块的字节数
+const UPLOAD_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 将请求体拆分为流式上传的请求体
+///
+/// 每块交给连接发送前回调 `on_progress(已发送字节数, 总字节数)`；令牌取消后停止产出数据块，请求以错误结束
+pub(crate) fn upload_body<F>(
+    data: Vec<u8>,
+    cancel_token: CancellationToken,
+    mut on_progress: F,
+) -> reqwest::Body
+where
+    F: FnMut(u64, u64) + Send + 'static,
+{
+    let total = data.len() as u64;
+    let chunks: Vec<Vec<u8>> = data.chunks(UPLOAD_CHUNK_BYTES).map(<[u8]>::to_vec).collect();
+    let mut sent = 0u64;
+    let stream = futures_util::stream::iter(chunks).map(move |chunk| {
+        if cancel_token.is_cancelled() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "上传已取消"));
+        }
+        sent += chunk.len() as u64;
+        on_progress(sent, total);
+        Ok(chunk)
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+/// `Retry-After` 等待时长上限 (毫秒)，避免服务端给出的过长等待使转录长时间无响应
+pub const MAX_RETRY_AFTER_MS: u64 = 10_000;
+
+/// 解析 `Retry-After` 响应头 (秒数或 HTTP 日期)，返回需等待的毫秒数
+pub(crate) fn parse_retry_after(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs.saturating_mul(1000));
+    }
+    let retry_at = parse_http_date(value)?;
+    let wait = retry_at
+        .duration_since(SystemTime::now())
+        .unwrap_or(Duration::ZERO);
+    Some(wait.as_millis() as u64)
+}
+
+/// 第 `attempt` 次重试前的等待时长
+///
+/// 指数退避；上次失败为配额超限且服务端给出 `Retry-After` 时，至少等待该时长 (上限 `MAX_RETRY_AFTER_MS`)
+pub(crate) fn retry_delay(
+    retry_config: &RetryConfig,
+    attempt: u32,
+    last_error: Option<&ASRError>,
+) -> Duration {
+    let backoff_ms = retry_config.base_delay_ms * (1 << attempt.saturating_sub(1));
+    let retry_after_ms = match last_error {
+        Some(ASRError::QuotaExceeded { retry_after_ms: Some(ms), .. }) => (*ms).min(MAX_RETRY_AFTER_MS),
+        _ => 0,
+    };
+    Duration::from_millis(backoff_ms.max(retry_after_ms))
+}
+
+/// 错误信息中响应体的最大字符数 (超出部分截断)
+const MAX_ERROR_BODY_CHARS: usize = 300;
+
+/// 读取错误响应体，整理为可读的错误信息
+///
+/// 读取失败或为空时给出说明；HTML 页面 (如网关错误页) 优先取标题，否则去掉标签；过长时截断
+pub(crate) async fn read_error_body(response: reqwest::Response) -> String {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    match response.bytes().await {
+        Ok(bytes) => format_error_body(content_type.as_deref(), &bytes),
+        Err(e) => format!("无法读取错误响应: {}", e),
+    }
+}
+
+/// 读取并解析 JSON 响应体，解析失败时错误信息附带响应内容片段
+pub(crate) async fn read_json_body<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, ASRError> {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| ASRError::NetworkError(format!("读取响应失败: {}", e)))?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        ASRError::InternalError(format!(
+            "解析响应失败: {}，响应: {}",
+            e,
+            format_error_body(content_type.as_deref(), &bytes)
+        ))
+    })
+}
+
+/// 按内容类型整理响应体 (非 UTF-8 字节按替换字符解码)
+fn format_error_body(content_type: Option<&str>, bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim();
+    if text.is_empty() {
+        return "(空响应体)".to_string();
+    }
+
+    let is_html = content_type.is_some_and(|ct| ct.to_ascii_lowercase().contains("html"))
+        || text.starts_with('<');
+    let readable = if is_html { html_summary(text) } else { text.to_string() };
+    truncate_chars(&readable, MAX_ERROR_BODY_CHARS)
+}
+
+/// HTML 页面摘要：有 `<title>` 时取标题，否则去掉标签后的正文
+fn html_summary(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    if let (Some(start), Some(end)) = (lower.find("<title>"), lower.find("</title>")) {
+        let title = html[start + "<title>".len()..end.max(start + "<title>".len())].trim();
+        if !title.is_empty() {
+            return title.to_string();
+        }
+    }
+
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text.to_string();
+    }
+    let prefix: String = text.chars().take(max_chars).collect();
+    format!("{}...(共 {} 字符)", prefix, count)
+}
+
+/// SSE (`text/event-stream`) 响应解析器
+///
+/// 按行缓冲任意切分的响应字节，空行结束一个事件，返回事件的 `data` 字段 (多行 data 以换行拼接)；
+/// 其余字段 (id、event、注释等) 忽略
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// 输入一段响应字节，返回其中已完整的事件数据
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+        }
+        events
+    }
+
+    /// 响应结束时取出未以空行结尾的最后一个事件
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        let mut events = self.push(b"\n\n");
+        events.pop()
+    }
+}
+
+/// 解析 IMF-fixdate 格式的 HTTP 日期 (如 "Sun, 06 Nov 1994 08:49:37 GMT")
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_weekday, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| m == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let mut hms = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (hms.next()??, hms.next()??, hms.next()??);
+
+    // 公历日期转 Unix 纪元天数
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(secs)
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_sse_parser_handles_split_events() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"id:1\nevent:result\n:HTTP_STATUS/200\nda").is_empty());
+        assert_eq!(parser.push(b"ta:{\"a\":1}\r\n\r\ndata: {\"a\":2}\n\n"), vec!["{\"a\":1}", "{\"a\":2}"]);
+        // 多字节字符被切分在两段之间
+        let bytes = "data:你好\n".as_bytes();
+        assert!(parser.push(&bytes[..7]).is_empty());
+        assert!(parser.push(&bytes[7..]).is_empty());
+        assert_eq!(parser.finish().as_deref(), Some("你好"));
+        assert_eq!(parser.finish(), None);
+    }
+
+    #[test]
+    fn test_prepare_for_upload_normalizes_format() {
+        // 0.5 秒 48kHz 立体声 -> 16kHz 单声道 16 位 WAV
+        let stereo = AudioData::new(vec![0.25f32; 48000], 48000, 2);
+        let wav = prepare_for_upload(&stereo, 16000, 1).unwrap();
+        let reader = hound::WavReader::new(std::io::Cursor::new(wav)).unwrap();
+        let spec = reader.spec();
+        assert_eq!((spec.sample_rate, spec.channels, spec.bits_per_sample), (16000, 1, 16));
+        assert_eq!(reader.len(), 8000);
+        
+        let empty = AudioData::new(Vec::new(), 16000, 1);
+        assert!(matches!(prepare_for_upload(&empty, 16000, 1), Err(ASRError::InvalidAudio(_))));
+    }
+    
+    #[test]
+    fn test_build_extra_headers_skips_invalid() {
+        let headers = HashMap::from([
+            ("X-Gateway-Key".to_string(), " gw-key ".to_string()),
+            ("Bad Header".to_string(), "value".to_string()),
+            ("X-Multiline".to_string(), "a\nb".to_string()),
+        ]);
+        
+        let map = build_extra_headers("qwen", &headers);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("x-gateway-key").unwrap(), "gw-key");
+    }
+    
+    #[test]
+    fn test_format_error_body() {
+        assert_eq!(format_error_body(None, b"  "), "(空响应体)");
+        assert_eq!(
+            format_error_body(Some("application/json"), br#"{"code":"InvalidApiKey"}"#),
+            r#"{"code":"InvalidApiKey"}"#
+        );
+
+        let page = b"<html><head><title>502 Bad Gateway</title></head><body><h1>502</h1></body></html>";
+        assert_eq!(format_error_body(Some("text/html; charset=utf-8"), page), "502 Bad Gateway");
+        let page = b"<html><body><h1>Service</h1>\n<p>Unavailable</p></body></html>";
+        assert_eq!(format_error_body(Some("text/html"), page), "Service Unavailable");
+
+        // 非 UTF-8 字节不影响其余内容
+        assert_eq!(format_error_body(Some("text/plain; charset=gbk"), b"error \xff"), "error \u{fffd}");
+
+        let long = "x".repeat(1000);
+        let formatted = format_error_body(None, long.as_bytes());
+        assert!(formatted.ends_with("...(共 1000 字符)"));
+        assert_eq!(formatted.chars().filter(|&c| c == 'x').count(), MAX_ERROR_BODY_CHARS);
+    }
+
+    #[test]
+    fn test_parse_retry_after_seconds_and_date() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(parse_retry_after(&headers), Some(3000));
+
+        // 过去的日期无需等待
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(parse_retry_after(&headers), Some(0));
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_retry_delay_honors_retry_after_with_cap() {
+        let config = RetryConfig { base_delay_ms: 500, ..RetryConfig::default() };
+        let quota = |retry_after_ms| ASRError::QuotaExceeded {
+            engine: "qwen".to_string(),
+            retry_after_ms,
+        };
+
+        assert_eq!(retry_delay(&config, 2, None), Duration::from_millis(1000));
+        assert_eq!(retry_delay(&config, 1, Some(&quota(None))), Duration::from_millis(500));
+        assert_eq!(retry_delay(&config, 1, Some(&quota(Some(3000)))), Duration::from_millis(3000));
+        assert_eq!(
+            retry_delay(&config, 1, Some(&quota(Some(120_000)))),
+            Duration::from_millis(MAX_RETRY_AFTER_MS)
+        );
+    }
+}
@@ -3,19 +3,43 @@
 
 use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose};
+use flate2::{write::GzEncoder, Compression};
+use futures_util::StreamExt;
+use std::io::Write;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
-use crate::voice::asr::{ASREngine, ASRError, ASRMode, RealtimeSession, RetryConfig};
-use crate::voice::audio::AudioData;
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, PartialTranscription, RealtimeSession, RetryConfig,
+    SharedPartialCallback, Timings,
+};
+use crate::voice::asr::http::debug_log::DebugLogger;
+use crate::voice::asr::http::{build_extra_headers, parse_retry_after, prepare_for_upload, read_error_body, read_json_body, retry_delay, shared_client, SseParser};
+use crate::voice::asr::text::{apply_punctuation_mode, DEFAULT_LANGUAGE};
+use crate::voice::config::{PunctuationMode, DEFAULT_DASHSCOPE_BASE_URL, DEFAULT_ENABLE_ITN};
+use crate::voice::audio::{AudioData, TARGET_SAMPLE_RATE};
 
-const QWEN_API_URL: &str = "https://dashscope.aliyuncs.com/api/v1/services/aigc/multimodal-generation/generation";
+const QWEN_API_PATH: &str = "/api/v1/services/aigc/multimodal-generation/generation";
 const DEFAULT_MODEL: &str = "qwen3-asr-flash";
 
 pub struct QwenHttpEngine {
     api_key: String,
     client: reqwest::Client,
     retry_config: RetryConfig,
+    language: Option<String>,
+    context_prompt: Option<String>,
+    punctuation_mode: PunctuationMode,
+    debug_log: DebugLogger,
+    extra_headers: HeaderMap,
     model: String,
+    gzip_request: bool,
+    api_url: String,
+    enable_itn: bool,
+    streaming: bool,
+    partial_callback: Option<SharedPartialCallback>,
+    timings: Mutex<Timings>,
 }
 
 impl QwenHttpEngine {
@@ -24,16 +48,22 @@ impl QwenHttpEngine {
     }
     
     pub fn with_config(api_key: String, retry_config: RetryConfig) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_millis(retry_config.timeout_ms))
-            .build()
-            .unwrap_or_default();
-        
         Self {
             api_key,
-            client,
+            client: shared_client(),
             retry_config,
+            language: None,
+            context_prompt: None,
+            punctuation_mode: PunctuationMode::default(),
+            debug_log: DebugLogger::new("qwen"),
+            extra_headers: HeaderMap::new(),
             model: DEFAULT_MODEL.to_string(),
+            gzip_request: false,
+            api_url: format!("{}{}", DEFAULT_DASHSCOPE_BASE_URL, QWEN_API_PATH),
+            enable_itn: DEFAULT_ENABLE_ITN,
+            streaming: false,
+            partial_callback: None,
+            timings: Mutex::new(Timings::default()),
         }
     }
     
@@ -42,19 +72,69 @@ impl QwenHttpEngine {
         self
     }
     
-    async fn transcribe_once(&self, audio: &AudioData) -> Result<String, ASRError> {
-        let wav_data = audio.to_wav()
-            .map_err(|e| ASRError::InvalidAudio(e.to_string()))?;
-        
-        let audio_base64 = general_purpose::STANDARD.encode(&wav_data);
-        
-        let request_body = serde_json::json!({
+    /// 设置识别语言 (None 使用默认语言)
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+    
+    /// 设置识别上下文提示 (如 "医学笔记"、"Rust 代码")，用于提升领域词汇的识别准确率
+    pub fn with_context_prompt(mut self, prompt: Option<String>) -> Self {
+        self.context_prompt = prompt.filter(|prompt| !prompt.trim().is_empty());
+        self
+    }
+    
+    /// 设置标点处理模式
+    pub fn with_punctuation_mode(mut self, mode: PunctuationMode) -> Self {
+        self.punctuation_mode = mode;
+        self
+    }
+    
+    /// 设置是否启用逆文本规范化
+    pub fn with_enable_itn(mut self, enabled: bool) -> Self {
+        self.enable_itn = enabled;
+        self
+    }
+    
+    /// 设置 DashScope 服务地址 (地域)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.api_url = format!("{}{}", base_url.trim_end_matches('/'), QWEN_API_PATH);
+        self
+    }
+    
+    /// 设置是否以 gzip 压缩请求体 (`Content-Encoding: gzip`)
+    pub fn with_gzip_request(mut self, enabled: bool) -> Self {
+        self.gzip_request = enabled;
+        self
+    }
+    
+    /// 设置是否以 SSE 流式接收结果 (`X-DashScope-SSE: enable`)，转录过程中上报中间结果
+    pub fn with_streaming(mut self, enabled: bool) -> Self {
+        self.streaming = enabled;
+        self
+    }
+    
+    /// 设置附加到每个请求的自定义请求头 (不合法的请求头跳过并记录警告)
+    pub fn with_extra_headers(mut self, headers: &HashMap<String, String>) -> Self {
+        self.extra_headers = build_extra_headers("qwen", headers);
+        self
+    }
+    
+    /// 开启请求/响应调试日志 (已脱敏)
+    pub fn with_debug_logging(mut self, enabled: bool) -> Self {
+        self.debug_log.set_enabled(enabled);
+        self
+    }
+    
+    /// 构建请求体 (音频以 base64 data URL 内联)
+    fn request_body(&self, audio_base64: &str) -> serde_json::Value {
+        let mut body = serde_json::json!({
             "model": self.model,
             "input": {
                 "messages": [
                     {
                         "role": "system",
-                        "content": [{"text": ""}]
+                        "content": [{"text": self.context_prompt.as_deref().unwrap_or("")}]
                     },
                     {
                         "role": "user",
@@ -66,32 +146,73 @@ impl QwenHttpEngine {
             },
             "parameters": {
                 "result_format": "message",
-                "enable_itn": false,
+                "enable_itn": self.enable_itn,
                 "disfluency_removal": true,
-                "language": "zh"
+                "language": self.language.as_deref().unwrap_or(DEFAULT_LANGUAGE)
             }
         });
+        if self.streaming {
+            // SSE 模式下每个事件返回完整的累计文本，而非增量片段
+            body["parameters"]["incremental_output"] = serde_json::json!(false);
+        }
+        body
+    }
+    
+    async fn transcribe_once(&self, audio: &AudioData) -> Result<String, ASRError> {
+        let timeout_ms = self.retry_config.effective_request_timeout_ms();
+        let wav_data = prepare_for_upload(audio, TARGET_SAMPLE_RATE, 1)?;
         
-        let response = self.client
-            .post(QWEN_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request_body)
+        let audio_base64 = general_purpose::STANDARD.encode(&wav_data);
+        
+        let request_body = self.request_body(&audio_base64);
+        
+        let authorization = format!("Bearer {}", self.api_key);
+        let mut headers = vec![("Authorization", authorization.as_str()), ("Content-Type", "application/json")];
+        if self.gzip_request {
+            headers.push(("Content-Encoding", "gzip"));
+        }
+        if self.streaming {
+            headers.push(("X-DashScope-SSE", "enable"));
+        }
+        self.debug_log.log_request(&self.api_url, &headers, &request_body);
+        
+        let body = serde_json::to_vec(&request_body)
+            .map_err(|e| ASRError::InternalError(format!("序列化请求失败: {}", e)))?;
+        let body = if self.gzip_request { gzip_compress(&body)? } else { body };
+        
+        let mut request = self.client
+            .post(&self.api_url)
+            .header("Authorization", &authorization)
+            .header("Content-Type", "application/json");
+        if self.gzip_request {
+            request = request.header("Content-Encoding", "gzip");
+        }
+        if self.streaming {
+            request = request.header("X-DashScope-SSE", "enable");
+        }
+        request = request.headers(self.extra_headers.clone());
+        
+        let request_start = Instant::now();
+        let response = request
+            .body(body)
+            .timeout(Duration::from_millis(timeout_ms))
             .send()
             .await
             .map_err(|e| {
                 if e.is_timeout() {
-                    ASRError::Timeout { timeout_ms: self.retry_config.timeout_ms }
+                    ASRError::Timeout { timeout_ms }
                 } else {
                     ASRError::NetworkError(e.to_string())
                 }
             })?;
         
+        let headers_received = Instant::now();
         let status = response.status();
         
         if !status.is_success() {
-            let error_text = response.text().await
-                .unwrap_or_else(|_| "无法读取错误响应".to_string());
+            let retry_after_ms = parse_retry_after(response.headers());
+            let error_text = read_error_body(response).await;
+            self.debug_log.log_response(status.as_str(), &serde_json::Value::String(error_text.clone()));
             
             return match status.as_u16() {
                 401 | 403 => Err(ASRError::AuthFailed {
@@ -100,6 +221,7 @@ impl QwenHttpEngine {
                 }),
                 429 => Err(ASRError::QuotaExceeded {
                     engine: "qwen".to_string(),
+                    retry_after_ms,
                 }),
                 _ => Err(ASRError::NetworkError(format!(
                     "API 请求失败 ({}): {}",
@@ -108,27 +230,95 @@ impl QwenHttpEngine {
             };
         }
         
-        let result: serde_json::Value = response.json().await
-            .map_err(|e| ASRError::InternalError(format!("解析响应失败: {}", e)))?;
+        let text = if self.streaming {
+            self.read_stream(response, timeout_ms).await?
+        } else {
+            let result: serde_json::Value = read_json_body(response).await?;
+            self.debug_log.log_response(status.as_str(), &result);
+            
+            extract_text(&result)
+                .ok_or_else(|| ASRError::InternalError(format!(
+                    "无法解析转录结果，响应格式: {:?}",
+                    result
+                )))?
+                .to_string()
+        };
+        
+        // SSE 模式收到请求即返回响应头，之后才开始推理；非流式响应头在推理完成后才返回，无法区分
+        *self.timings.lock().unwrap() = if self.streaming {
+            Timings {
+                connect_ms: None,
+                upload_ms: Some((headers_received - request_start).as_millis() as u64),
+                inference_ms: Some(headers_received.elapsed().as_millis() as u64),
+            }
+        } else {
+            Timings::default()
+        };
+        
+        let text = apply_punctuation_mode(&text, self.punctuation_mode, self.language.as_deref(), false);
+        
+        Ok(text)
+    }
+    
+    /// 读取 SSE 流式响应，每个事件的文本作为中间结果上报，返回最后一个事件的文本
+    /// 
+    /// 请求未开启增量输出，每个事件的文本都是截至当前的完整文本
+    async fn read_stream(&self, response: reqwest::Response, timeout_ms: u64) -> Result<String, ASRError> {
+        let mut stream = response.bytes_stream();
+        let mut parser = SseParser::default();
+        let mut text = None;
+        
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| {
+                if e.is_timeout() {
+                    ASRError::Timeout { timeout_ms }
+                } else {
+                    ASRError::NetworkError(format!("读取流式响应失败: {}", e))
+                }
+            })?;
+            for data in parser.push(&chunk) {
+                text = self.handle_stream_event(&data)?.or(text);
+            }
+        }
+        if let Some(data) = parser.finish() {
+            text = self.handle_stream_event(&data)?.or(text);
+        }
         
-        let text = result["output"]["choices"]
-            .as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|choice| choice["message"]["content"].as_array())
-            .and_then(|content| content.first())
-            .and_then(|item| item["text"].as_str())
-            .ok_or_else(|| ASRError::InternalError(format!(
-                "无法解析转录结果，响应格式: {:?}",
-                result
-            )))?;
+        text.ok_or_else(|| ASRError::InternalError("流式响应中没有转录结果".to_string()))
+    }
+    
+    /// 处理一个 SSE 事件，返回其中的转录文本 (没有文本的事件返回 None)
+    fn handle_stream_event(&self, data: &str) -> Result<Option<String>, ASRError> {
+        let event: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| ASRError::InternalError(format!("解析流式响应失败: {}", e)))?;
+        self.debug_log.log_response("sse", &event);
         
-        let mut text = text.to_string();
-        strip_trailing_punctuation(&mut text);
+        if let Some(code) = event["code"].as_str().filter(|code| !code.is_empty()) {
+            return Err(ASRError::NetworkError(format!(
+                "流式响应错误 ({}): {}",
+                code,
+                event["message"].as_str().unwrap_or_default()
+            )));
+        }
         
+        let text = extract_text(&event).map(str::to_string);
+        if let (Some(text), Some(callback)) = (&text, &self.partial_callback) {
+            callback(&PartialTranscription::new(text.clone(), false));
+        }
         Ok(text)
     }
 }
 
+/// 从响应 (或 SSE 事件) 中取出转录文本
+fn extract_text(result: &serde_json::Value) -> Option<&str> {
+    result["output"]["choices"]
+        .as_array()
+        .and_then(|arr| arr.first())
+        .and_then(|choice| choice["message"]["content"].as_array())
+        .and_then(|content| content.first())
+        .and_then(|item| item["text"].as_str())
+}
+
 #[async_trait]
 impl ASREngine for QwenHttpEngine {
     fn name(&self) -> &str {
@@ -149,12 +339,13 @@ impl ASREngine for QwenHttpEngine {
         
         for attempt in 0..=self.retry_config.max_retries {
             if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(
-                    self.retry_config.base_delay_ms * (1 << (attempt - 1))
-                )).await;
+                tokio::time::sleep(retry_delay(&self.retry_config, attempt, last_error.as_ref())).await;
             }
             
-            match self.transcribe_once(audio).await {
+            let attempt_start = Instant::now();
+            let result = self.transcribe_once(audio).await;
+            self.retry_config.record_attempt(attempt_start.elapsed(), &result);
+            match result {
                 Ok(text) => {
                     let duration = start_time.elapsed().as_millis() as u64;
                     eprintln!("[INFO] Qwen HTTP 转录成功，耗时 {}ms", duration);
@@ -180,19 +371,127 @@ impl ASREngine for QwenHttpEngine {
             "QwenHttpEngine 不支持 Realtime 模式，请使用 QwenRealtimeEngine".to_string()
         ))
     }
+    
+    fn set_partial_callback(&mut self, callback: SharedPartialCallback) {
+        self.partial_callback = Some(callback);
+    }
+    
+    fn last_timings(&self) -> Timings {
+        *self.timings.lock().unwrap()
+    }
 }
 
-fn strip_trailing_punctuation(text: &mut String) {
-    let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"',
-                       '.', ',', '!', '?', ';', ':', '"', '\'',
-                       '（', '）', '(', ')', '【', '】', '[', ']',
-                       '《', '》', '<', '>', '—', '…', '·'];
-    
-    while let Some(c) = text.chars().last() {
-        if punctuation.contains(&c) {
-            text.pop();
-        } else {
-            break;
-        }
+/// gzip 压缩请求体
+fn gzip_compress(data: &[u8]) -> Result<Vec<u8>, ASRError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)
+        .map_err(|e| ASRError::InternalError(format!("Gzip 压缩失败: {}", e)))?;
+    encoder.finish()
+        .map_err(|e| ASRError::InternalError(format!("Gzip 压缩失败: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_request_body_parameters() {
+        let engine = QwenHttpEngine::new("key".to_string());
+        let body = engine.request_body("UklGRg==");
+        assert_eq!(body["input"]["messages"][1]["content"][0]["audio"], "data:audio/wav;base64,UklGRg==");
+        assert_eq!(body["parameters"]["enable_itn"], true);
+        assert!(body["parameters"].get("incremental_output").is_none());
+
+        let engine = QwenHttpEngine::new("key".to_string())
+            .with_enable_itn(false)
+            .with_streaming(true);
+        let body = engine.request_body("UklGRg==");
+        assert_eq!(body["parameters"]["enable_itn"], false);
+        assert_eq!(body["parameters"]["incremental_output"], false);
+    }
+
+    /// 启动只响应一次请求的 HTTP 服务，返回 SSE 响应体，并回传收到的请求头和请求体
+    async fn spawn_sse_server(body: String) -> (String, tokio::task::JoinHandle<(String, String)>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let header_end = loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+            let content_length: usize = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map(|value| value.trim().parse().unwrap())
+                .unwrap_or(0);
+            while request.len() < header_end + content_length {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+            (headers, String::from_utf8_lossy(&request[header_end..]).into_owned())
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_streaming_reports_partials_and_returns_last_text() {
+        let event = |text: &str| {
+            format!(
+                "id:1\nevent:result\ndata:{}\n\n",
+                serde_json::json!({"output": {"choices": [{"message": {"content": [{"text": text}]}}]}})
+            )
+        };
+        let (url, server) = spawn_sse_server(format!("{}{}", event("你好"), event("你好世界"))).await;
+
+        let partials = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&partials);
+        let mut engine = QwenHttpEngine::with_config("test-key".to_string(), RetryConfig::default())
+            .with_base_url(&url)
+            .with_streaming(true)
+            .with_punctuation_mode(PunctuationMode::Keep)
+            .with_context_prompt(Some("Rust 代码".to_string()));
+        engine.set_partial_callback(Arc::new(move |partial: &PartialTranscription| {
+            sink.lock().unwrap().push(partial.text.clone());
+        }));
+
+        let audio = AudioData::new(vec![0.1; 1600], TARGET_SAMPLE_RATE, 1);
+        assert_eq!(engine.transcribe_once(&audio).await.unwrap(), "你好世界");
+        assert_eq!(*partials.lock().unwrap(), vec!["你好", "你好世界"]);
+        let (headers, request_body) = server.await.unwrap();
+        assert!(headers.contains("x-dashscope-sse: enable"));
+        let request_body: serde_json::Value = serde_json::from_str(&request_body).unwrap();
+        assert_eq!(request_body["input"]["messages"][0]["content"][0]["text"], "Rust 代码");
+    }
+
+    #[test]
+    fn test_gzip_compress_roundtrip() {
+        let body = serde_json::to_vec(&serde_json::json!({
+            "audio": "A".repeat(4096),
+        })).unwrap();
+
+        let compressed = gzip_compress(&body).unwrap();
+        assert!(compressed.len() < body.len());
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(compressed.as_slice()).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, body);
     }
 }
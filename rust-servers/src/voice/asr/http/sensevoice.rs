@@ -2,19 +2,46 @@
 // 使用硅基流动 (SiliconFlow) API 进行语音识别
 
 use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
-use crate::voice::asr::{ASREngine, ASRError, ASRMode, RealtimeSession, RetryConfig};
-use crate::voice::audio::AudioData;
+use crate::voice::asr::{ASREngine, ASRError, ASRMode, RealtimeSession, RetryConfig, Timings};
+use crate::voice::asr::http::debug_log::DebugLogger;
+use crate::voice::asr::http::{build_extra_headers, parse_retry_after, prepare_for_upload, read_error_body, read_json_body, retry_delay, shared_client, upload_body};
+use crate::voice::asr::text::apply_punctuation_mode;
+use crate::voice::config::PunctuationMode;
+use crate::voice::audio::{AudioData, TARGET_SAMPLE_RATE};
 
 const SILICONFLOW_API_URL: &str = "https://api.siliconflow.cn/v1/audio/transcriptions";
 const DEFAULT_MODEL: &str = "FunAudioLLM/SenseVoiceSmall";
 
+/// 调试日志中上传进度的输出步长 (百分比)
+const UPLOAD_PROGRESS_LOG_STEP: u64 = 25;
+
+/// SenseVoice 支持的语言代码，其余语言不传 language 字段 (由模型自动识别)
+const SUPPORTED_LANGUAGES: &[&str] = &["auto", "zh", "en", "yue", "ja", "ko"];
+
+/// 将配置的语言 (如 "zh-CN"、"en_US") 映射为 SenseVoice 语言代码，不支持时返回 None
+fn sensevoice_language(language: &str) -> Option<&'static str> {
+    let language = language.to_ascii_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or("");
+    SUPPORTED_LANGUAGES.iter().copied().find(|&code| code == primary)
+}
+
 pub struct SenseVoiceHttpEngine {
     api_key: String,
     client: reqwest::Client,
     retry_config: RetryConfig,
+    language: Option<String>,
+    punctuation_mode: PunctuationMode,
+    use_itn: Option<bool>,
+    debug_log: DebugLogger,
+    extra_headers: HeaderMap,
     model: String,
+    timings: Mutex<Timings>,
 }
 
 impl SenseVoiceHttpEngine {
@@ -23,16 +50,17 @@ impl SenseVoiceHttpEngine {
     }
     
     pub fn with_config(api_key: String, retry_config: RetryConfig) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_millis(retry_config.timeout_ms))
-            .build()
-            .unwrap_or_default();
-        
         Self {
             api_key,
-            client,
+            client: shared_client(),
             retry_config,
+            language: None,
+            punctuation_mode: PunctuationMode::default(),
+            use_itn: None,
+            debug_log: DebugLogger::new("sensevoice"),
+            extra_headers: HeaderMap::new(),
             model: DEFAULT_MODEL.to_string(),
+            timings: Mutex::new(Timings::default()),
         }
     }
     
@@ -41,30 +69,175 @@ impl SenseVoiceHttpEngine {
         self
     }
     
-    async fn transcribe_once(&self, audio: &AudioData) -> Result<String, ASRError> {
-        let wav_data = audio.to_wav()
-            .map_err(|e| ASRError::InvalidAudio(e.to_string()))?;
+    /// 设置识别语言 (None 使用默认语言)
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+    
+    /// 设置标点处理模式
+    pub fn with_punctuation_mode(mut self, mode: PunctuationMode) -> Self {
+        self.punctuation_mode = mode;
+        self
+    }
+    
+    /// 设置是否启用逆文本规范化 (None 不传该字段，沿用服务端默认)
+    pub fn with_use_itn(mut self, use_itn: Option<bool>) -> Self {
+        self.use_itn = use_itn;
+        self
+    }
+    
+    /// 可选表单字段：仅包含已配置且 SenseVoice 支持的参数
+    fn optional_form_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        
+        if let Some(language) = self.language.as_deref() {
+            match sensevoice_language(language) {
+                Some(code) => fields.push(("language", code.to_string())),
+                None => eprintln!("[WARN] SenseVoice 不支持语言 {}，交由模型自动识别", language),
+            }
+        }
+        if let Some(use_itn) = self.use_itn {
+            fields.push(("use_itn", use_itn.to_string()));
+        }
+        
+        fields
+    }
+    
+    /// 设置附加到每个请求的自定义请求头 (不合法的请求头跳过并记录警告)
+    pub fn with_extra_headers(mut self, headers: &HashMap<String, String>) -> Self {
+        self.extra_headers = build_extra_headers("sensevoice", headers);
+        self
+    }
+    
+    /// 开启请求/响应调试日志 (已脱敏)
+    pub fn with_debug_logging(mut self, enabled: bool) -> Self {
+        self.debug_log.set_enabled(enabled);
+        self
+    }
+    
+    /// 带重试的转录，令牌取消时中止上传、请求或重试等待
+    async fn transcribe_with_cancel(
+        &self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<String, ASRError> {
+        if audio.is_empty() {
+            return Err(ASRError::InvalidAudio("音频数据为空".to_string()));
+        }
+        
+        let start_time = Instant::now();
+        let mut last_error = None;
+        
+        for attempt in 0..=self.retry_config.max_retries {
+            if attempt > 0 {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => return Err(ASRError::Cancelled),
+                    _ = tokio::time::sleep(retry_delay(&self.retry_config, attempt, last_error.as_ref())) => {}
+                }
+            }
+            
+            let attempt_start = Instant::now();
+            let result = tokio::select! {
+                _ = cancel_token.cancelled() => Err(ASRError::Cancelled),
+                result = self.transcribe_once(audio, cancel_token) => result,
+            };
+            self.retry_config.record_attempt(attempt_start.elapsed(), &result);
+            match result {
+                Ok(text) => {
+                    let duration = start_time.elapsed().as_millis() as u64;
+                    eprintln!("[INFO] SenseVoice HTTP 转录成功，耗时 {}ms: {}", duration, text);
+                    return Ok(text);
+                }
+                Err(ASRError::Cancelled) => {
+                    eprintln!("[INFO] SenseVoice HTTP 转录已取消");
+                    return Err(ASRError::Cancelled);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[WARN] SenseVoice HTTP 转录失败 (尝试 {}/{}): {}",
+                        attempt + 1,
+                        self.retry_config.max_retries + 1,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| ASRError::InternalError("转录失败，未知错误".to_string())))
+    }
+    
+    async fn transcribe_once(
+        &self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<String, ASRError> {
+        let timeout_ms = self.retry_config.effective_request_timeout_ms();
+        let wav_data = prepare_for_upload(audio, TARGET_SAMPLE_RATE, 1)?;
         
         eprintln!("[INFO] SenseVoice ASR: 音频数据大小 {} bytes", wav_data.len());
         
-        let file_part = reqwest::multipart::Part::bytes(wav_data)
+        let authorization = format!("Bearer {}", self.api_key);
+        let optional_fields = self.optional_form_fields();
+        
+        if self.debug_log.is_enabled() {
+            let mut logged_body = serde_json::json!({
+                "model": self.model,
+                "file": format!("<audio.wav {} bytes>", wav_data.len()),
+            });
+            for (name, value) in &optional_fields {
+                logged_body[*name] = serde_json::Value::String(value.clone());
+            }
+            self.debug_log.log_request(
+                SILICONFLOW_API_URL,
+                &[("Authorization", authorization.as_str())],
+                &logged_body,
+            );
+        }
+        
+        // 音频以文件形式流式上传 (而非内联 base64)，长录音上传期间可取消并输出进度
+        let upload_len = wav_data.len() as u64;
+        let debug_log = self.debug_log;
+        let mut logged_percent = 0;
+        let upload_done: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
+        let upload_done_at = Arc::clone(&upload_done);
+        let body = upload_body(wav_data, cancel_token.clone(), move |sent, total| {
+            if sent >= total {
+                *upload_done_at.lock().unwrap() = Some(Instant::now());
+            }
+            let percent = sent * 100 / total.max(1);
+            if debug_log.is_enabled() && percent >= logged_percent + UPLOAD_PROGRESS_LOG_STEP {
+                logged_percent = percent - percent % UPLOAD_PROGRESS_LOG_STEP;
+                eprintln!("[DEBUG] [sensevoice] 上传进度 {}% ({}/{} bytes)", percent, sent, total);
+            }
+        });
+        let file_part = reqwest::multipart::Part::stream_with_length(body, upload_len)
             .file_name("audio.wav")
             .mime_str("audio/wav")
             .map_err(|e| ASRError::InternalError(format!("创建文件部分失败: {}", e)))?;
         
-        let form = reqwest::multipart::Form::new()
-            .part("file", file_part)
-            .text("model", self.model.clone());
+        let form = optional_fields.into_iter().fold(
+            reqwest::multipart::Form::new()
+                .part("file", file_part)
+                .text("model", self.model.clone()),
+            |form, (name, value)| form.text(name, value),
+        );
         
+        let request_start = Instant::now();
         let response = self.client
             .post(SILICONFLOW_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Authorization", &authorization)
+            .headers(self.extra_headers.clone())
             .multipart(form)
+            .timeout(Duration::from_millis(timeout_ms))
             .send()
             .await
             .map_err(|e| {
-                if e.is_timeout() {
-                    ASRError::Timeout { timeout_ms: self.retry_config.timeout_ms }
+                if cancel_token.is_cancelled() {
+                    ASRError::Cancelled
+                } else if e.is_timeout() {
+                    ASRError::Timeout { timeout_ms }
                 } else {
                     ASRError::NetworkError(e.to_string())
                 }
@@ -73,8 +246,9 @@ impl SenseVoiceHttpEngine {
         let status = response.status();
         
         if !status.is_success() {
-            let error_text = response.text().await
-                .unwrap_or_else(|_| "无法读取错误响应".to_string());
+            let retry_after_ms = parse_retry_after(response.headers());
+            let error_text = read_error_body(response).await;
+            self.debug_log.log_response(status.as_str(), &serde_json::Value::String(error_text.clone()));
             
             return match status.as_u16() {
                 401 => Err(ASRError::AuthFailed {
@@ -83,6 +257,7 @@ impl SenseVoiceHttpEngine {
                 }),
                 429 => Err(ASRError::QuotaExceeded {
                     engine: "sensevoice".to_string(),
+                    retry_after_ms,
                 }),
                 404 => Err(ASRError::ConfigError(format!(
                     "模型不存在或服务不可用: {}",
@@ -99,13 +274,22 @@ impl SenseVoiceHttpEngine {
             };
         }
         
-        let result: SenseVoiceResponse = response.json().await
-            .map_err(|e| ASRError::InternalError(format!("解析响应失败: {}", e)))?;
+        let result: SenseVoiceResponse = read_json_body(response).await?;
+        
+        // 最后一块交给连接时视为上传完成 (含建立连接)，之后到解析出结果为推理耗时
+        let upload_done = *upload_done.lock().unwrap();
+        *self.timings.lock().unwrap() = match upload_done {
+            Some(done) => Timings {
+                connect_ms: None,
+                upload_ms: Some(done.saturating_duration_since(request_start).as_millis() as u64),
+                inference_ms: Some(done.elapsed().as_millis() as u64),
+            },
+            None => Timings::default(),
+        };
         
-        eprintln!("[DEBUG] SenseVoice ASR 响应: text={}", result.text);
+        self.debug_log.log_response(status.as_str(), &serde_json::json!({ "text": result.text }));
         
-        let mut text = result.text;
-        strip_trailing_punctuation(&mut text);
+        let text = apply_punctuation_mode(&result.text, self.punctuation_mode, self.language.as_deref(), false);
         
         Ok(text)
     }
@@ -127,39 +311,15 @@ impl ASREngine for SenseVoiceHttpEngine {
     }
     
     async fn transcribe(&self, audio: &AudioData) -> Result<String, ASRError> {
-        if audio.is_empty() {
-            return Err(ASRError::InvalidAudio("音频数据为空".to_string()));
-        }
-        
-        let start_time = Instant::now();
-        let mut last_error = None;
-        
-        for attempt in 0..=self.retry_config.max_retries {
-            if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(
-                    self.retry_config.base_delay_ms * (1 << (attempt - 1))
-                )).await;
-            }
-            
-            match self.transcribe_once(audio).await {
-                Ok(text) => {
-                    let duration = start_time.elapsed().as_millis() as u64;
-                    eprintln!("[INFO] SenseVoice HTTP 转录成功，耗时 {}ms: {}", duration, text);
-                    return Ok(text);
-                }
-                Err(e) => {
-                    eprintln!(
-                        "[WARN] SenseVoice HTTP 转录失败 (尝试 {}/{}): {}",
-                        attempt + 1,
-                        self.retry_config.max_retries + 1,
-                        e
-                    );
-                    last_error = Some(e);
-                }
-            }
-        }
-        
-        Err(last_error.unwrap_or_else(|| ASRError::InternalError("转录失败，未知错误".to_string())))
+        self.transcribe_with_cancel(audio, &CancellationToken::new()).await
+    }
+    
+    async fn transcribe_cancellable(
+        &self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<String, ASRError> {
+        self.transcribe_with_cancel(audio, cancel_token).await
     }
     
     async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
@@ -169,17 +329,44 @@ impl ASREngine for SenseVoiceHttpEngine {
     }
 }
 
-fn strip_trailing_punctuation(text: &mut String) {
-    let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"',
-                       '.', ',', '!', '?', ';', ':', '"', '\'',
-                       '（', '）', '(', ')', '【', '】', '[', ']',
-                       '《', '》', '<', '>', '—', '…', '·'];
-    
-    while let Some(c) = text.chars().last() {
-        if punctuation.contains(&c) {
-            text.pop();
-        } else {
-            break;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sensevoice_language_mapping() {
+        assert_eq!(sensevoice_language("zh-CN"), Some("zh"));
+        assert_eq!(sensevoice_language("en_US"), Some("en"));
+        assert_eq!(sensevoice_language("yue"), Some("yue"));
+        assert_eq!(sensevoice_language("fr"), None);
+    }
+
+    #[test]
+    fn test_optional_form_fields_omit_unset_and_unsupported() {
+        let engine = SenseVoiceHttpEngine::new("key".to_string());
+        assert!(engine.optional_form_fields().is_empty());
+
+        let engine = SenseVoiceHttpEngine::new("key".to_string())
+            .with_language(Some("de".to_string()))
+            .with_use_itn(Some(true));
+        assert_eq!(engine.optional_form_fields(), vec![("use_itn", "true".to_string())]);
+
+        let engine = SenseVoiceHttpEngine::new("key".to_string()).with_use_itn(Some(false));
+        assert_eq!(engine.optional_form_fields(), vec![("use_itn", "false".to_string())]);
+
+        let engine = SenseVoiceHttpEngine::new("key".to_string())
+            .with_language(Some("ja-JP".to_string()));
+        assert_eq!(engine.optional_form_fields(), vec![("language", "ja".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_token_stops_transcription() {
+        let engine = SenseVoiceHttpEngine::new("key".to_string());
+        let audio = AudioData::new(vec![0.1; TARGET_SAMPLE_RATE as usize], TARGET_SAMPLE_RATE, 1);
+        let cancel_token = CancellationToken::new();
+        cancel_token.cancel();
+
+        let result = engine.transcribe_cancellable(&audio, &cancel_token).await;
+        assert!(matches!(result, Err(ASRError::Cancelled)));
     }
 }
@@ -0,0 +1,38 @@
+// ASR 请求标识生成
+// 实时/HTTP 引擎共用的请求 ID 与 WebSocket 握手 Key
+
+use base64::{Engine as _, engine::general_purpose};
+use uuid::Uuid;
+
+/// 生成请求 ID (随机 UUID v4，用于请求追踪)
+pub fn generate_request_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// 生成 Sec-WebSocket-Key
+///
+/// RFC 6455 要求为 16 字节随机数的 base64 编码
+pub fn generate_websocket_key() -> String {
+    general_purpose::STANDARD.encode(Uuid::new_v4().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_ids_are_unique_uuids() {
+        let a = generate_request_id();
+        let b = generate_request_id();
+        assert_ne!(a, b);
+        assert!(Uuid::parse_str(&a).is_ok());
+    }
+
+    #[test]
+    fn test_websocket_key_is_16_bytes_base64() {
+        let key = generate_websocket_key();
+        let decoded = general_purpose::STANDARD.decode(&key).unwrap();
+        assert_eq!(decoded.len(), 16);
+        assert_ne!(key, generate_websocket_key());
+    }
+}
@@ -0,0 +1,173 @@
+// 并发限制模块
+// 按供应商限制同时进行的转录请求数，超出时排队等待而不是触发服务端 429
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, PartialResultCallback, RealtimeSession, SharedPartialCallback, Timings,
+};
+use crate::voice::audio::AudioData;
+use crate::voice::config::ASRProvider;
+
+/// 按 (供应商, 并发上限) 索引的信号量表
+type LimiterMap = HashMap<(ASRProvider, usize), Arc<Semaphore>>;
+
+/// 进程内共享的供应商信号量 (同一供应商、同一并发上限的引擎共用一个信号量)
+static LIMITERS: OnceLock<Mutex<LimiterMap>> = OnceLock::new();
+
+/// 获取供应商对应的共享信号量
+pub fn provider_semaphore(provider: &ASRProvider, max_concurrency: usize) -> Arc<Semaphore> {
+    let mut limiters = LIMITERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    limiters
+        .entry((provider.clone(), max_concurrency))
+        .or_insert_with(|| Arc::new(Semaphore::new(max_concurrency)))
+        .clone()
+}
+
+/// 限制并发的引擎
+///
+/// 每次 `transcribe` 和实时会话建立前先获取许可，许可在请求结束 (或实时会话被丢弃) 后释放
+pub struct LimitedEngine {
+    inner: Box<dyn ASREngine>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl LimitedEngine {
+    pub fn new(inner: Box<dyn ASREngine>, semaphore: Arc<Semaphore>) -> Self {
+        Self { inner, semaphore }
+    }
+
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit, ASRError> {
+        Arc::clone(&self.semaphore)
+            .acquire_owned()
+            .await
+            .map_err(|e| ASRError::InternalError(format!("获取并发许可失败: {}", e)))
+    }
+}
+
+/// 持有并发许可的实时会话，会话存续期间占用供应商的一个并发名额
+struct LimitedSession {
+    inner: Box<dyn RealtimeSession>,
+    _permit: OwnedSemaphorePermit,
+}
+
+#[async_trait]
+impl RealtimeSession for LimitedSession {
+    async fn send_chunk(&mut self, chunk: &[u8]) -> Result<(), ASRError> {
+        self.inner.send_chunk(chunk).await
+    }
+
+    async fn commit(&mut self) -> Result<(), ASRError> {
+        self.inner.commit().await
+    }
+
+    async fn close(&mut self) -> Result<String, ASRError> {
+        self.inner.close().await
+    }
+
+    fn set_partial_callback(&mut self, callback: PartialResultCallback) {
+        self.inner.set_partial_callback(callback);
+    }
+}
+
+#[async_trait]
+impl ASREngine for LimitedEngine {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supported_modes(&self) -> Vec<ASRMode> {
+        self.inner.supported_modes()
+    }
+
+    async fn transcribe(&self, audio: &AudioData) -> Result<String, ASRError> {
+        let _permit = self.acquire().await?;
+        self.inner.transcribe(audio).await
+    }
+
+    async fn transcribe_cancellable(
+        &self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<String, ASRError> {
+        let _permit = tokio::select! {
+            _ = cancel_token.cancelled() => return Err(ASRError::Cancelled),
+            permit = self.acquire() => permit?,
+        };
+        self.inner.transcribe_cancellable(audio, cancel_token).await
+    }
+
+    async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
+        let permit = self.acquire().await?;
+        let inner = self.inner.create_realtime_session().await?;
+        Ok(Box::new(LimitedSession { inner, _permit: permit }))
+    }
+
+    fn set_partial_callback(&mut self, callback: SharedPartialCallback) {
+        self.inner.set_partial_callback(callback);
+    }
+
+    fn last_timings(&self) -> Timings {
+        self.inner.last_timings()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::mock::MockEngine;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_realtime_session_holds_permit_until_dropped() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let engine = LimitedEngine::new(
+            Box::new(MockEngine::new("probe")),
+            Arc::clone(&semaphore),
+        );
+
+        let mut session = engine.create_realtime_session().await.unwrap();
+        session.send_chunk(&[0; 4]).await.unwrap();
+        assert_eq!(semaphore.available_permits(), 0);
+
+        drop(session);
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_engines_share_provider_limit() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let engine = LimitedEngine::new(
+                    Box::new(
+                        MockEngine::new("probe")
+                            .with_delay(Duration::from_millis(20))
+                            .tracking_concurrency(&active, &peak),
+                    ),
+                    provider_semaphore(&ASRProvider::SenseVoice, 2),
+                );
+                tokio::spawn(async move {
+                    let audio = AudioData::new(vec![0.0; 160], 16000, 1);
+                    engine.transcribe(&audio).await
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap(), "ok");
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
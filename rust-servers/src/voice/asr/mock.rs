@@ -0,0 +1,242 @@
+// 测试用模拟引擎
+// 各模块单元测试共用的可配置 ASREngine / RealtimeSession
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, PartialResultCallback, PartialTranscription, RealtimeSession,
+    SharedPartialCallback, Timings,
+};
+use crate::voice::audio::AudioData;
+
+/// 按音频与调用序号 (从 1 开始) 生成识别文本
+type TextFn = Box<dyn Fn(&AudioData, usize) -> String + Send + Sync>;
+
+/// 可配置的模拟引擎
+///
+/// 默认立即返回 "ok"；可设置固定文本或按调用序号生成的文本、前若干次成功后返回的错误、
+/// 每次转录的延迟与耗时分解，并通过共享计数器记录调用次数与最大并发数
+pub struct MockEngine {
+    name: &'static str,
+    text: TextFn,
+    error: Option<ASRError>,
+    /// 返回错误前成功的调用次数 (仅设置了错误时生效)
+    succeed_calls: usize,
+    delay: Duration,
+    timings: Box<dyn Fn(usize) -> Timings + Send + Sync>,
+    calls: Arc<AtomicUsize>,
+    completed: AtomicUsize,
+    active: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+    emit_partials: bool,
+    partial_callback: Option<SharedPartialCallback>,
+}
+
+impl MockEngine {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            text: Box::new(|_, _| "ok".to_string()),
+            error: None,
+            succeed_calls: 0,
+            delay: Duration::ZERO,
+            timings: Box::new(|_| Timings::default()),
+            calls: Arc::new(AtomicUsize::new(0)),
+            completed: AtomicUsize::new(0),
+            active: Arc::new(AtomicUsize::new(0)),
+            peak: Arc::new(AtomicUsize::new(0)),
+            emit_partials: false,
+            partial_callback: None,
+        }
+    }
+
+    /// 返回固定文本
+    pub fn with_text(self, text: &'static str) -> Self {
+        self.with_text_fn(move |_, _| text.to_string())
+    }
+
+    /// 按音频与调用序号生成文本
+    pub fn with_text_fn<F>(mut self, text: F) -> Self
+    where
+        F: Fn(&AudioData, usize) -> String + Send + Sync + 'static,
+    {
+        self.text = Box::new(text);
+        self
+    }
+
+    /// 每次调用都返回错误
+    pub fn with_error(self, error: ASRError) -> Self {
+        self.failing_after(0, error)
+    }
+
+    /// 前 `succeed_calls` 次成功，之后返回错误
+    pub fn failing_after(mut self, succeed_calls: usize, error: ASRError) -> Self {
+        self.error = Some(error);
+        self.succeed_calls = succeed_calls;
+        self
+    }
+
+    /// 每次转录前等待
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// 固定的耗时分解
+    pub fn with_timings(self, timings: Timings) -> Self {
+        self.with_timings_fn(move |_| timings)
+    }
+
+    /// 按已完成的转录次数生成耗时分解
+    pub fn with_timings_fn<F>(mut self, timings: F) -> Self
+    where
+        F: Fn(usize) -> Timings + Send + Sync + 'static,
+    {
+        self.timings = Box::new(timings);
+        self
+    }
+
+    /// 使用共享的调用计数器
+    pub fn counting_calls(mut self, calls: &Arc<AtomicUsize>) -> Self {
+        self.calls = Arc::clone(calls);
+        self
+    }
+
+    /// 使用共享的并发计数器 (多个引擎共用时统计总并发)
+    pub fn tracking_concurrency(mut self, active: &Arc<AtomicUsize>, peak: &Arc<AtomicUsize>) -> Self {
+        self.active = Arc::clone(active);
+        self.peak = Arc::clone(peak);
+        self
+    }
+
+    /// 成功时先以识别文本上报一次中间结果
+    pub fn emitting_partials(mut self) -> Self {
+        self.emit_partials = true;
+        self
+    }
+}
+
+#[async_trait]
+impl ASREngine for MockEngine {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn supported_modes(&self) -> Vec<ASRMode> {
+        vec![ASRMode::Http]
+    }
+
+    async fn transcribe(&self, audio: &AudioData) -> Result<String, ASRError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(active, Ordering::SeqCst);
+
+        if self.delay.is_zero() {
+            tokio::task::yield_now().await;
+        } else {
+            tokio::time::sleep(self.delay).await;
+        }
+        let result = match self.error {
+            Some(ref error) if call > self.succeed_calls => Err(error.clone()),
+            _ => Ok((self.text)(audio, call)),
+        };
+        if let (Ok(text), Some(callback)) = (&result, &self.partial_callback) {
+            if self.emit_partials {
+                callback(&PartialTranscription::new(text.clone(), false));
+                tokio::task::yield_now().await;
+            }
+        }
+
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        self.completed.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+
+    async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
+        Ok(Box::new(MockSession::new()))
+    }
+
+    fn set_partial_callback(&mut self, callback: SharedPartialCallback) {
+        self.partial_callback = Some(callback);
+    }
+
+    fn last_timings(&self) -> Timings {
+        (self.timings)(self.completed.load(Ordering::SeqCst))
+    }
+}
+
+/// 可配置的模拟实时会话
+///
+/// 默认接受所有音频、关闭时返回空文本；可记录收到的音频块大小、收到音频即推送中间结果，
+/// 或关闭时始终等不到最终结果
+pub struct MockSession {
+    text: &'static str,
+    chunks: Option<Arc<Mutex<Vec<usize>>>>,
+    partial: Option<&'static str>,
+    stuck: bool,
+    callback: Option<PartialResultCallback>,
+}
+
+impl MockSession {
+    pub fn new() -> Self {
+        Self {
+            text: "",
+            chunks: None,
+            partial: None,
+            stuck: false,
+            callback: None,
+        }
+    }
+
+    /// 关闭时返回的文本
+    pub fn with_text(mut self, text: &'static str) -> Self {
+        self.text = text;
+        self
+    }
+
+    /// 记录收到的音频块大小 (字节)
+    pub fn recording_chunks(mut self, chunks: &Arc<Mutex<Vec<usize>>>) -> Self {
+        self.chunks = Some(Arc::clone(chunks));
+        self
+    }
+
+    /// 每收到一块音频推送一次中间结果
+    pub fn emitting_partial(mut self, text: &'static str) -> Self {
+        self.partial = Some(text);
+        self
+    }
+
+    /// 关闭时始终给不出最终结果
+    pub fn stuck(mut self) -> Self {
+        self.stuck = true;
+        self
+    }
+}
+
+#[async_trait]
+impl RealtimeSession for MockSession {
+    async fn send_chunk(&mut self, chunk: &[u8]) -> Result<(), ASRError> {
+        if let Some(ref chunks) = self.chunks {
+            chunks.lock().unwrap().push(chunk.len());
+        }
+        if let (Some(text), Some(callback)) = (self.partial, &self.callback) {
+            callback(&PartialTranscription::new(text.to_string(), false));
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<String, ASRError> {
+        if self.stuck {
+            std::future::pending::<()>().await;
+        }
+        Ok(self.text.to_string())
+    }
+
+    fn set_partial_callback(&mut self, callback: PartialResultCallback) {
+        self.callback = Some(callback);
+    }
+}
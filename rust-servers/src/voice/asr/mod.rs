@@ -2,21 +2,36 @@
 // 包含 ASR 引擎抽象层和各供应商实现
 
 use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
 use crate::voice::audio::AudioData;
 use crate::voice::config::{ASRProviderConfig, ASRProvider, ASRMode as ConfigASRMode};
 
+pub mod chunked;
 pub mod http;
 pub mod realtime;
 pub mod realtime_task;
+pub mod segmented;
+pub mod segments;
 pub mod fallback;
+pub mod ids;
+pub mod limiter;
+#[cfg(test)]
+pub(crate) mod mock;
+pub mod service;
+pub mod stats;
+pub mod text;
 
 pub use http::QwenHttpEngine;
 pub use http::DoubaoHttpEngine;
 pub use http::SenseVoiceHttpEngine;
 pub use realtime::QwenRealtimeEngine;
 pub use realtime::DoubaoRealtimeEngine;
-pub use realtime_task::{RealtimeTranscriptionTask, PartialResultCallback, RealtimeTaskResult};
-pub use fallback::{FallbackStrategy, ParallelFallbackStrategy, RaceStrategy};
+pub use realtime_task::{RealtimeTranscriptionTask, PartialResultCallback, PreconnectedSession, RealtimeTaskResult, SessionStatus, SessionStatusCallback, transcribe_stream};
+pub use chunked::ChunkedEngine;
+pub use segmented::SegmentedHttpTranscriber;
+pub use limiter::LimitedEngine;
+pub use fallback::{BestOfStrategy, BestOfSelector, FallbackStrategy, ParallelFallbackStrategy, RaceStrategy};
+pub use service::{transcribe_with, TranscriptionService};
 
 // ============================================================================
 // 错误类型
@@ -36,6 +51,8 @@ pub enum ASRError {
     #[error("配额超限 ({engine})")]
     QuotaExceeded {
         engine: String,
+        /// 服务端 `Retry-After` 建议的等待时长 (毫秒)
+        retry_after_ms: Option<u64>,
     },
     
     #[error("无效的音频格式: {0}")]
@@ -66,6 +83,52 @@ pub enum ASRError {
     
     #[error("内部错误: {0}")]
     InternalError(String),
+    
+    #[error("转录已取消")]
+    Cancelled,
+    
+    #[error("未检测到语音")]
+    NoSpeechDetected,
+}
+
+/// ASR 错误分类 (不含错误详情，用于配置兜底触发条件)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ASRErrorKind {
+    Network,
+    AuthFailed,
+    QuotaExceeded,
+    InvalidAudio,
+    Timeout,
+    WebSocket,
+    AllEnginesFailed,
+    NotInitialized,
+    UnsupportedOperation,
+    Config,
+    Internal,
+    Cancelled,
+    NoSpeech,
+}
+
+impl ASRError {
+    /// 获取错误分类
+    pub fn kind(&self) -> ASRErrorKind {
+        match self {
+            ASRError::NetworkError(_) => ASRErrorKind::Network,
+            ASRError::AuthFailed { .. } => ASRErrorKind::AuthFailed,
+            ASRError::QuotaExceeded { .. } => ASRErrorKind::QuotaExceeded,
+            ASRError::InvalidAudio(_) => ASRErrorKind::InvalidAudio,
+            ASRError::Timeout { .. } => ASRErrorKind::Timeout,
+            ASRError::WebSocketError(_) => ASRErrorKind::WebSocket,
+            ASRError::AllEnginesFailed { .. } => ASRErrorKind::AllEnginesFailed,
+            ASRError::NotInitialized => ASRErrorKind::NotInitialized,
+            ASRError::UnsupportedOperation(_) => ASRErrorKind::UnsupportedOperation,
+            ASRError::ConfigError(_) => ASRErrorKind::Config,
+            ASRError::InternalError(_) => ASRErrorKind::Internal,
+            ASRError::Cancelled => ASRErrorKind::Cancelled,
+            ASRError::NoSpeechDetected => ASRErrorKind::NoSpeech,
+        }
+    }
 }
 
 // ============================================================================
@@ -100,12 +163,57 @@ impl std::fmt::Display for ASRMode {
 // 转录结果
 // ============================================================================
 
+/// 转录耗时分解 (毫秒，尽力而为，无法单独测量的阶段为空)
+///
+/// 用于区分慢在网络还是模型：HTTP 请求经连接池发送，建立连接的耗时并入上传阶段；
+/// 实时会话的上传阶段为边录边传的推流时长
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Timings {
+    /// 建立连接 (实时会话握手与初始化)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_ms: Option<u64>,
+    /// 上传音频
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upload_ms: Option<u64>,
+    /// 音频上传完成到收到最终结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inference_ms: Option<u64>,
+}
+
+impl Timings {
+    /// 逐项累加 (分段转录时合计各段耗时)，任一侧为空时取另一侧
+    pub fn combine(self, other: Timings) -> Timings {
+        let sum = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        Timings {
+            connect_ms: sum(self.connect_ms, other.connect_ms),
+            upload_ms: sum(self.upload_ms, other.upload_ms),
+            inference_ms: sum(self.inference_ms, other.inference_ms),
+        }
+    }
+
+    /// 各阶段均未测量
+    pub fn is_empty(&self) -> bool {
+        *self == Timings::default()
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct TranscriptionResult {
     pub text: String,
     pub engine: String,
     pub used_fallback: bool,
     pub duration_ms: u64,
+    /// 是否经过自动重试 (实时会话首次未收到结果后重新转录)
+    pub retried: bool,
+    /// 耗时分解 (`duration_ms` 的组成部分，尽力而为)
+    #[serde(skip_serializing_if = "Timings::is_empty")]
+    pub timings: Timings,
+    /// 未被选中的其他候选结果 (多选一策略)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<TranscriptionResult>,
 }
 
 impl TranscriptionResult {
@@ -115,11 +223,43 @@ impl TranscriptionResult {
             engine,
             used_fallback,
             duration_ms,
+            retried: false,
+            timings: Timings::default(),
+            alternatives: Vec::new(),
         }
     }
+    
+    /// 标记结果是否经过自动重试
+    pub fn with_retried(mut self, retried: bool) -> Self {
+        self.retried = retried;
+        self
+    }
+    
+    /// 附加耗时分解
+    pub fn with_timings(mut self, timings: Timings) -> Self {
+        self.timings = timings;
+        self
+    }
+    
+    /// 附加未被选中的候选结果
+    pub fn with_alternatives(mut self, alternatives: Vec<TranscriptionResult>) -> Self {
+        self.alternatives = alternatives;
+        self
+    }
+    
+    /// 文本为空或仅含空白时 (静音、噪声) 返回 `NoSpeechDetected`
+    pub fn require_speech(self) -> Result<Self, ASRError> {
+        if self.text.trim().is_empty() {
+            return Err(ASRError::NoSpeechDetected);
+        }
+        Ok(self)
+    }
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+/// 部分转录结果 (实时模式与 HTTP 流式模式)
+///
+/// `text` 始终为截至当前的完整文本而非增量，前端直接整体替换显示即可
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub struct PartialTranscription {
     pub text: String,
     pub is_final: bool,
@@ -131,6 +271,15 @@ impl PartialTranscription {
     }
 }
 
+/// HTTP 流式转录的中间结果回调 (引擎可能在多个任务间共享，需要 `Sync`)
+pub type SharedPartialCallback = std::sync::Arc<dyn Fn(&PartialTranscription) + Send + Sync>;
+
+/// 将部分结果回调转为可在引擎间共享的回调
+pub fn shared_partial_callback(callback: PartialResultCallback) -> SharedPartialCallback {
+    let callback = std::sync::Mutex::new(callback);
+    std::sync::Arc::new(move |partial| (callback.lock().unwrap())(partial))
+}
+
 // ============================================================================
 // ASR 引擎 Trait
 // ============================================================================
@@ -145,7 +294,34 @@ pub trait ASREngine: Send + Sync {
     }
     
     async fn transcribe(&self, audio: &AudioData) -> Result<String, ASRError>;
+    
+    /// 可取消的转录，令牌取消时返回 `ASRError::Cancelled`
+    /// 
+    /// 默认丢弃进行中的请求；需要在取消时中止上传或重试等待的引擎可覆盖
+    async fn transcribe_cancellable(
+        &self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<String, ASRError> {
+        tokio::select! {
+            _ = cancel_token.cancelled() => Err(ASRError::Cancelled),
+            result = self.transcribe(audio) => result,
+        }
+    }
+    
     async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError>;
+    
+    /// 设置 HTTP 流式转录的中间结果回调
+    /// 
+    /// 默认忽略，仅支持流式响应且已开启流式的 HTTP 引擎会上报
+    fn set_partial_callback(&mut self, _callback: SharedPartialCallback) {}
+    
+    /// 最近一次成功转录的耗时分解
+    /// 
+    /// 默认全部为空，能区分上传与推理阶段的引擎覆盖
+    fn last_timings(&self) -> Timings {
+        Timings::default()
+    }
 }
 
 // ============================================================================
@@ -161,18 +337,38 @@ pub trait RealtimeSession: Send {
     }
     
     async fn close(&mut self) -> Result<String, ASRError>;
-    fn set_partial_callback(&mut self, callback: Box<dyn Fn(&str) + Send + 'static>);
+    fn set_partial_callback(&mut self, callback: PartialResultCallback);
 }
 
 // ============================================================================
 // 重试配置
 // ============================================================================
 
+/// HTTP 单次请求默认超时 (毫秒)
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 6000;
+/// 实时会话关闭后等待最终结果的默认超时 (毫秒)，长录音收尾耗时明显更长
+pub const DEFAULT_SESSION_TIMEOUT_MS: u64 = 30000;
+/// 自适应请求超时的默认上限 (毫秒)
+pub const DEFAULT_ADAPTIVE_TIMEOUT_MAX_MS: u64 = 30000;
+
+/// 自适应请求超时：按供应商最近请求耗时调整，下限为固定超时
+#[derive(Debug, Clone)]
+pub struct AdaptiveTimeout {
+    pub stats: std::sync::Arc<stats::EngineStats>,
+    /// 超时上限 (毫秒)
+    pub max_timeout_ms: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct RetryConfig {
     pub max_retries: u32,
     pub base_delay_ms: u64,
-    pub timeout_ms: u64,
+    /// HTTP 单次请求超时 (毫秒)，开启自适应超时时作为下限
+    pub request_timeout_ms: u64,
+    /// 实时会话 close 等待最终结果的超时 (毫秒)
+    pub session_timeout_ms: u64,
+    /// 自适应请求超时 (空则始终使用固定超时)
+    pub adaptive_timeout: Option<AdaptiveTimeout>,
 }
 
 impl Default for RetryConfig {
@@ -180,7 +376,52 @@ impl Default for RetryConfig {
         Self {
             max_retries: 2,
             base_delay_ms: 500,
-            timeout_ms: 6000,
+            request_timeout_ms: DEFAULT_REQUEST_TIMEOUT_MS,
+            session_timeout_ms: DEFAULT_SESSION_TIMEOUT_MS,
+            adaptive_timeout: None,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// 按供应商配置覆盖超时 (未配置的项使用默认值)
+    pub fn from_provider_config(config: &ASRProviderConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            request_timeout_ms: config.request_timeout_ms.unwrap_or(defaults.request_timeout_ms),
+            session_timeout_ms: config.session_timeout_ms.unwrap_or(defaults.session_timeout_ms),
+            adaptive_timeout: config.adaptive_timeout.then(|| AdaptiveTimeout {
+                stats: stats::engine_stats(&config.provider),
+                max_timeout_ms: config
+                    .adaptive_timeout_max_ms
+                    .unwrap_or(DEFAULT_ADAPTIVE_TIMEOUT_MAX_MS),
+            }),
+            ..defaults
+        }
+    }
+    
+    /// 本次请求使用的超时 (毫秒)
+    pub fn effective_request_timeout_ms(&self) -> u64 {
+        match self.adaptive_timeout {
+            Some(ref adaptive) => adaptive
+                .stats
+                .adaptive_timeout_ms(self.request_timeout_ms, adaptive.max_timeout_ms),
+            None => self.request_timeout_ms,
+        }
+    }
+    
+    /// 记录一次请求的耗时 (仅开启自适应超时时统计)
+    ///
+    /// 超时的请求按当次超时时长计入，否则始终慢于初始超时的供应商永远没有样本，超时无法上调；
+    /// 其他失败不计入
+    pub fn record_attempt<T>(&self, latency: std::time::Duration, result: &Result<T, ASRError>) {
+        let Some(ref adaptive) = self.adaptive_timeout else {
+            return;
+        };
+        match result {
+            Ok(_) => adaptive.stats.record_latency(latency.as_millis() as u64),
+            Err(ASRError::Timeout { timeout_ms }) => adaptive.stats.record_latency(*timeout_ms),
+            Err(_) => {}
         }
     }
 }
@@ -241,20 +482,75 @@ impl EngineCredentials {
 }
 
 /// 创建 ASR 引擎
+/// 
+/// HTTP 模式音频超过供应商时长上限时分段转录 (开启按语句分段时先按语句切分)，按识别语言的默认分隔符拼接
 pub fn create_engine(config: &ASRProviderConfig) -> Result<Box<dyn ASREngine>, ASRError> {
+    create_engine_with_separator(config, text::default_separator(config.language.as_deref()))
+}
+
+/// 创建 ASR 引擎，分段转录结果使用指定分隔符拼接
+pub fn create_engine_with_separator(
+    config: &ASRProviderConfig,
+    separator: &str,
+) -> Result<Box<dyn ASREngine>, ASRError> {
+    let mut engine = create_provider_engine(config)?;
+    if let Some(max_concurrency) = config.max_concurrency.filter(|&n| n > 0) {
+        let semaphore = limiter::provider_semaphore(&config.provider, max_concurrency as usize);
+        engine = Box::new(LimitedEngine::new(engine, semaphore));
+    }
+    if config.mode != ConfigASRMode::Http {
+        return Ok(engine);
+    }
+    if let Some(max_audio_ms) = config.max_audio_ms() {
+        engine = Box::new(
+            ChunkedEngine::new(engine, max_audio_ms, separator.to_string())
+                .with_oversized_audio(config.oversized_audio)
+        );
+    }
+    if config.utterance_segmentation {
+        engine = Box::new(SegmentedHttpTranscriber::new(engine, separator.to_string()));
+    }
+    Ok(engine)
+}
+
+/// 按供应商和模式创建引擎
+fn create_provider_engine(config: &ASRProviderConfig) -> Result<Box<dyn ASREngine>, ASRError> {
     config.validate().map_err(|e| ASRError::ConfigError(e.to_string()))?;
     
     let engine_type = EngineType::from(config.provider.clone());
     let mode = ASRMode::from(config.mode.clone());
+    let retry_config = RetryConfig::from_provider_config(config);
     
     match engine_type {
         EngineType::Qwen => {
             let api_key = config.dashscope_api_key.clone()
                 .ok_or_else(|| ASRError::ConfigError("缺少 dashscope_api_key".to_string()))?;
+            let language = config.language.clone();
+            let punctuation_mode = config.punctuation_mode;
             
             match mode {
-                ASRMode::Http => Ok(Box::new(QwenHttpEngine::new(api_key))),
-                ASRMode::Realtime => Ok(Box::new(QwenRealtimeEngine::new(api_key))),
+                ASRMode::Http => Ok(Box::new(
+                    QwenHttpEngine::with_config(api_key, retry_config)
+                        .with_language(language)
+                        .with_punctuation_mode(punctuation_mode)
+                        .with_gzip_request(config.qwen_gzip_request)
+                        .with_streaming(config.qwen_http_streaming)
+                        .with_context_prompt(config.context_prompt.clone())
+                        .with_enable_itn(config.enable_itn)
+                        .with_base_url(config.dashscope_base_url())
+                        .with_extra_headers(&config.extra_headers)
+                        .with_debug_logging(config.debug_logging)
+                )),
+                ASRMode::Realtime => Ok(Box::new(
+                    QwenRealtimeEngine::new(api_key)
+                        .with_retry_config(retry_config)
+                        .with_base_url(config.dashscope_base_url())
+                        .with_language(language)
+                        .with_context_prompt(config.context_prompt.clone())
+                        .with_punctuation_mode(punctuation_mode)
+                        .with_audio_format(config.realtime_audio_format)
+                        .with_reconnect_policy(config.realtime_reconnect)
+                )),
             }
         }
         EngineType::Doubao => {
@@ -264,14 +560,35 @@ pub fn create_engine(config: &ASRProviderConfig) -> Result<Box<dyn ASREngine>, A
                 .ok_or_else(|| ASRError::ConfigError("缺少 access_token".to_string()))?;
             
             match mode {
-                ASRMode::Http => Ok(Box::new(DoubaoHttpEngine::new(app_id, access_token))),
-                ASRMode::Realtime => Ok(Box::new(DoubaoRealtimeEngine::new(app_id, access_token))),
+                ASRMode::Http => Ok(Box::new(
+                    DoubaoHttpEngine::with_config(app_id, access_token, retry_config)
+                        .with_language(config.language.clone())
+                        .with_punctuation_mode(config.punctuation_mode)
+                        .with_enable_itn(config.enable_itn)
+                        .with_extra_headers(&config.extra_headers)
+                        .with_debug_logging(config.debug_logging)
+                )),
+                ASRMode::Realtime => Ok(Box::new(
+                    DoubaoRealtimeEngine::new(app_id, access_token)
+                        .with_retry_config(retry_config)
+                        .with_enable_itn(config.enable_itn)
+                        .with_stream_mode(config.doubao_stream_mode)
+                        .with_audio_format(config.realtime_audio_format)
+                        .with_reconnect_policy(config.realtime_reconnect)
+                )),
             }
         }
         EngineType::SenseVoice => {
             let api_key = config.siliconflow_api_key.clone()
                 .ok_or_else(|| ASRError::ConfigError("缺少 siliconflow_api_key".to_string()))?;
-            Ok(Box::new(SenseVoiceHttpEngine::new(api_key)))
+            Ok(Box::new(
+                SenseVoiceHttpEngine::with_config(api_key, retry_config)
+                    .with_language(config.language.clone())
+                    .with_punctuation_mode(config.punctuation_mode)
+                    .with_use_itn(config.sensevoice_use_itn.or(Some(config.enable_itn)))
+                    .with_extra_headers(&config.extra_headers)
+                    .with_debug_logging(config.debug_logging)
+            ))
         }
     }
 }
@@ -310,3 +627,19 @@ pub fn create_engine_by_type(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_timings_are_not_serialized() {
+        let result = TranscriptionResult::new("你好".to_string(), "qwen".to_string(), false, 100);
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json.get("timings").is_none());
+
+        let result = result.with_timings(Timings { inference_ms: Some(40), ..Timings::default() });
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["timings"], serde_json::json!({ "inference_ms": 40 }));
+    }
+}
@@ -2,35 +2,41 @@
 // 使用字节跳动豆包 WebSocket API 进行实时流式语音识别（二进制协议）
 
 use async_trait::async_trait;
-use base64::{Engine as _, engine::general_purpose};
+use crate::voice::asr::ids::{generate_request_id, generate_websocket_key};
 use flate2::{write::GzEncoder, read::GzDecoder, Compression};
-use futures_util::{SinkExt, StreamExt, stream::SplitSink};
+use futures_util::{SinkExt, StreamExt};
 use std::io::{Write, Read};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::Duration;
 use tokio::sync::{Mutex, mpsc, oneshot};
-use tokio::net::TcpStream;
-use tokio_tungstenite::{
-    connect_async, 
-    tungstenite::{Message, http},
-    MaybeTlsStream, 
-    WebSocketStream
-};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::{Message, http};
 
-use crate::voice::asr::{ASREngine, ASRError, ASRMode, RealtimeSession, RetryConfig};
+use crate::voice::asr::realtime::{host_header, open_websocket, TranscriptAccumulator, WsSink};
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, PartialResultCallback, PartialTranscription, RealtimeSession,
+    RetryConfig, DEFAULT_SESSION_TIMEOUT_MS,
+};
 use crate::voice::audio::AudioData;
+use crate::voice::config::{DoubaoStreamMode, RealtimeAudioFormat, ReconnectPolicy, DEFAULT_ENABLE_ITN};
 
-const WEBSOCKET_URL: &str = "wss://openspeech.bytedance.com/api/v3/sauc/bigmodel_nostream";
+/// 非流式返回接口 (DoubaoStreamMode::NoStream)
+/// 音频流式上传，发送结束包后才返回完整结果，准确率更高
+const NOSTREAM_URL: &str = "wss://openspeech.bytedance.com/api/v3/sauc/bigmodel_nostream";
+/// 双向流式接口 (DoubaoStreamMode::Stream)
+/// 识别结果变化时即返回，配合 result_type=full 每包携带当前完整文本
+const STREAM_URL: &str = "wss://openspeech.bytedance.com/api/v3/sauc/bigmodel_async";
+/// 资源 ID：豆包流式语音识别模型 2.0 小时版，两种接口共用，仅决定计费方式和模型版本
 const RESOURCE_ID: &str = "volc.seedasr.sauc.duration";
-const TRANSCRIPTION_TIMEOUT_SECS: u64 = 10;
-
-type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 
 pub struct DoubaoRealtimeEngine {
     app_id: String,
     access_key: String,
-    #[allow(dead_code)]
+    stream_mode: DoubaoStreamMode,
+    audio_format: RealtimeAudioFormat,
     retry_config: RetryConfig,
+    reconnect_policy: ReconnectPolicy,
+    enable_itn: bool,
 }
 
 impl DoubaoRealtimeEngine {
@@ -38,9 +44,43 @@ impl DoubaoRealtimeEngine {
         Self {
             app_id,
             access_key,
+            stream_mode: DoubaoStreamMode::default(),
+            audio_format: RealtimeAudioFormat::default(),
             retry_config: RetryConfig::default(),
+            reconnect_policy: ReconnectPolicy::default(),
+            enable_itn: DEFAULT_ENABLE_ITN,
         }
     }
+    
+    /// 设置重试与超时配置 (实时模式使用 session_timeout_ms 作为 close 等待超时)
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+    
+    /// 设置建立实时会话时的重连策略
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+    
+    /// 设置实时识别接口类型
+    pub fn with_stream_mode(mut self, stream_mode: DoubaoStreamMode) -> Self {
+        self.stream_mode = stream_mode;
+        self
+    }
+    
+    /// 设置是否启用逆文本规范化
+    pub fn with_enable_itn(mut self, enabled: bool) -> Self {
+        self.enable_itn = enabled;
+        self
+    }
+    
+    /// 设置音频线上格式 (协议仅声明位深，不支持大端序)
+    pub fn with_audio_format(mut self, format: RealtimeAudioFormat) -> Self {
+        self.audio_format = format;
+        self
+    }
 }
 
 #[async_trait]
@@ -60,10 +100,24 @@ impl ASREngine for DoubaoRealtimeEngine {
     }
     
     async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
-        let session = DoubaoRealtimeSession::connect(
-            self.app_id.clone(),
-            self.access_key.clone(),
-        ).await?;
+        // 流式接口的 pcm 格式只接受 16 位小端采样 (bits 仅支持 16)
+        if self.audio_format != RealtimeAudioFormat::Pcm16Le {
+            return Err(ASRError::UnsupportedOperation(format!(
+                "豆包实时模式不支持音频格式 {}",
+                self.audio_format
+            )));
+        }
+        
+        let mut session = self.reconnect_policy.connect("豆包", || {
+            DoubaoRealtimeSession::connect(
+                self.app_id.clone(),
+                self.access_key.clone(),
+                self.stream_mode,
+                self.audio_format,
+                self.enable_itn,
+            )
+        }).await?;
+        session.session_timeout = Duration::from_millis(self.retry_config.session_timeout_ms);
         
         Ok(Box::new(session))
     }
@@ -77,19 +131,43 @@ enum SessionCommand {
 pub struct DoubaoRealtimeSession {
     cmd_sender: mpsc::Sender<SessionCommand>,
     result_receiver: Option<oneshot::Receiver<Result<String, ASRError>>>,
-    partial_callback: Option<Arc<Mutex<Box<dyn Fn(&str) + Send + 'static>>>>,
+    partial_callback: Arc<StdMutex<Option<PartialResultCallback>>>,
+    read_task: JoinHandle<()>,
+    /// close 等待最终结果的超时
+    session_timeout: Duration,
 }
 
 impl DoubaoRealtimeSession {
-    async fn connect(app_id: String, access_key: String) -> Result<Self, ASRError> {
+    async fn connect(
+        app_id: String,
+        access_key: String,
+        stream_mode: DoubaoStreamMode,
+        audio_format: RealtimeAudioFormat,
+        enable_itn: bool,
+    ) -> Result<Self, ASRError> {
+        let url = match stream_mode {
+            DoubaoStreamMode::NoStream => NOSTREAM_URL,
+            DoubaoStreamMode::Stream => STREAM_URL,
+        };
+        Self::connect_to(url, app_id, access_key, stream_mode, audio_format, enable_itn).await
+    }
+    
+    async fn connect_to(
+        url: &str,
+        app_id: String,
+        access_key: String,
+        stream_mode: DoubaoStreamMode,
+        audio_format: RealtimeAudioFormat,
+        enable_itn: bool,
+    ) -> Result<Self, ASRError> {
         let websocket_key = generate_websocket_key();
         let request_id = generate_request_id();
         
-        eprintln!("[INFO] 创建豆包 Realtime WebSocket 连接");
+        eprintln!("[INFO] 创建豆包 Realtime WebSocket 连接: {}", url);
         
         let request = http::Request::builder()
-            .uri(WEBSOCKET_URL)
-            .header("Host", "openspeech.bytedance.com")
+            .uri(url)
+            .header("Host", host_header(url)?)
             .header("Connection", "Upgrade")
             .header("Upgrade", "websocket")
             .header("Sec-WebSocket-Version", "13")
@@ -101,18 +179,13 @@ impl DoubaoRealtimeSession {
             .body(())
             .map_err(|e| ASRError::WebSocketError(format!("构建请求失败: {}", e)))?;
         
-        let (ws_stream, _) = connect_async(request).await
-            .map_err(|e| ASRError::WebSocketError(format!("WebSocket 连接失败: {}", e)))?;
+        let ws_stream = open_websocket(request).await?;
         
         eprintln!("[INFO] 豆包 Realtime WebSocket 连接成功");
         
         let (mut write, mut read) = ws_stream.split();
         
-        let config = serde_json::json!({
-            "user": {"uid": &app_id},
-            "audio": {"format": "pcm", "rate": 16000, "bits": 16, "channel": 1},
-            "request": {"model_name": "bigmodel", "enable_itn": true, "enable_punc": true}
-        });
+        let config = full_client_request(&app_id, stream_mode, audio_format, enable_itn);
         
         eprintln!("[DEBUG] 豆包 Full Client Request: {}", serde_json::to_string_pretty(&config).unwrap_or_default());
         
@@ -152,7 +225,7 @@ impl DoubaoRealtimeSession {
         
         let (cmd_tx, mut cmd_rx) = mpsc::channel::<SessionCommand>(100);
         let (result_tx, result_rx) = oneshot::channel::<Result<String, ASRError>>();
-        let (partial_tx, mut partial_rx) = mpsc::channel::<String>(100);
+        let (partial_tx, mut partial_rx) = mpsc::channel::<PartialTranscription>(100);
         
         let write: Arc<Mutex<WsSink>> = Arc::new(Mutex::new(write));
         let write_clone = Arc::clone(&write);
@@ -193,15 +266,17 @@ impl DoubaoRealtimeSession {
                                 eprintln!("[ERROR] 豆包构建结束消息失败: {}", e);
                             }
                         }
-                        break;
                     }
                 }
             }
+            
+            // 结束包发出后仍需等待服务端返回最终结果，直到会话被丢弃 (命令通道关闭) 才发送 Close 帧
+            let _ = write_clone.lock().await.close().await;
         });
         
         let partial_tx_clone = partial_tx.clone();
-        tokio::spawn(async move {
-            let mut accumulated_text = String::new();
+        let read_task = tokio::spawn(async move {
+            let mut transcript = TranscriptAccumulator::new();
             let mut result_tx = Some(result_tx);
             
             while let Some(msg) = read.next().await {
@@ -210,13 +285,14 @@ impl DoubaoRealtimeSession {
                         eprintln!("[DEBUG] 豆包 WebSocket 收到二进制消息: {} bytes", data.len());
                         match parse_response(&data) {
                             Ok((text, is_final)) => {
+                                // 豆包每个响应包携带截至当前的完整文本，直接替换
                                 if !text.is_empty() {
-                                    accumulated_text = text.clone();
-                                    eprintln!("[DEBUG] 豆包累积文本: {}", accumulated_text);
-                                    let _ = partial_tx_clone.send(accumulated_text.clone()).await;
+                                    let partial = transcript.replace(&text, is_final);
+                                    eprintln!("[DEBUG] 豆包累积文本: {}", partial.text);
+                                    let _ = partial_tx_clone.send(partial).await;
                                 }
                                 if is_final {
-                                    let final_text = accumulated_text.clone();
+                                    let final_text = transcript.text().to_string();
                                     eprintln!("[INFO] 豆包流式转录结果（最终包）: {}", final_text);
                                     if let Some(tx) = result_tx.take() {
                                         let _ = tx.send(Ok(final_text));
@@ -231,10 +307,10 @@ impl DoubaoRealtimeSession {
                     }
                     Ok(Message::Close(frame)) => {
                         eprintln!("[WARN] 豆包 WebSocket 连接关闭: {:?}", frame);
-                        if !accumulated_text.is_empty() {
-                            eprintln!("[INFO] 豆包连接关闭，返回累积文本: {}", accumulated_text);
+                        if !transcript.is_empty() {
+                            eprintln!("[INFO] 豆包连接关闭，返回累积文本: {}", transcript.text());
                             if let Some(tx) = result_tx.take() {
-                                let _ = tx.send(Ok(accumulated_text.clone()));
+                                let _ = tx.send(Ok(transcript.text().to_string()));
                             }
                         } else {
                             eprintln!("[WARN] 豆包连接关闭，无转录结果");
@@ -262,10 +338,10 @@ impl DoubaoRealtimeSession {
             }
             
             if result_tx.is_some() {
-                if !accumulated_text.is_empty() {
-                    eprintln!("[INFO] 豆包连接结束，返回累积文本: {}", accumulated_text);
+                if !transcript.is_empty() {
+                    eprintln!("[INFO] 豆包连接结束，返回累积文本: {}", transcript.text());
                     if let Some(tx) = result_tx.take() {
-                        let _ = tx.send(Ok(accumulated_text));
+                        let _ = tx.send(Ok(transcript.text().to_string()));
                     }
                 } else {
                     eprintln!("[WARN] 豆包连接结束，无转录结果");
@@ -279,13 +355,13 @@ impl DoubaoRealtimeSession {
             eprintln!("[DEBUG] 豆包 WebSocket 接收任务结束");
         });
         
-        let partial_callback: Option<Arc<Mutex<Box<dyn Fn(&str) + Send + 'static>>>> = None;
-        let partial_callback_clone = partial_callback.clone();
+        // 回调槽位与转发任务共享，连接建立后再设置的回调同样生效
+        let partial_callback: Arc<StdMutex<Option<PartialResultCallback>>> = Arc::new(StdMutex::new(None));
+        let partial_callback_clone = Arc::clone(&partial_callback);
         tokio::spawn(async move {
-            while let Some(text) = partial_rx.recv().await {
-                if let Some(ref callback) = partial_callback_clone {
-                    let cb = callback.lock().await;
-                    cb(&text);
+            while let Some(partial) = partial_rx.recv().await {
+                if let Some(ref callback) = *partial_callback_clone.lock().unwrap() {
+                    callback(&partial);
                 }
             }
         });
@@ -294,6 +370,8 @@ impl DoubaoRealtimeSession {
             cmd_sender: cmd_tx,
             result_receiver: Some(result_rx),
             partial_callback,
+            read_task,
+            session_timeout: Duration::from_millis(DEFAULT_SESSION_TIMEOUT_MS),
         })
     }
 }
@@ -312,36 +390,45 @@ impl RealtimeSession for DoubaoRealtimeSession {
             .ok_or_else(|| ASRError::InternalError("会话已关闭".to_string()))?;
         
         let result = tokio::time::timeout(
-            Duration::from_secs(TRANSCRIPTION_TIMEOUT_SECS),
+            self.session_timeout,
             result_rx
         ).await
-            .map_err(|_| ASRError::Timeout { timeout_ms: TRANSCRIPTION_TIMEOUT_SECS * 1000 })?
+            .map_err(|_| ASRError::Timeout { timeout_ms: self.session_timeout.as_millis() as u64 })?
             .map_err(|_| ASRError::InternalError("结果通道已关闭".to_string()))?;
         
         result
     }
     
-    fn set_partial_callback(&mut self, callback: Box<dyn Fn(&str) + Send + 'static>) {
-        self.partial_callback = Some(Arc::new(Mutex::new(callback)));
+    fn set_partial_callback(&mut self, callback: PartialResultCallback) {
+        *self.partial_callback.lock().unwrap() = Some(callback);
     }
 }
 
-fn generate_websocket_key() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    general_purpose::STANDARD.encode(format!("{}", timestamp).as_bytes())
+impl Drop for DoubaoRealtimeSession {
+    fn drop(&mut self) {
+        // 未调用 close 即被丢弃时 (如任务被中止)，命令通道随之关闭，写任务发送 Close 帧；
+        // 读任务在此直接终止，避免与供应商的连接半开占用并发额度
+        self.read_task.abort();
+    }
 }
 
-fn generate_request_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    format!("req_{}", timestamp)
+/// 构建会话初始化 (Full Client Request) 的配置
+fn full_client_request(
+    app_id: &str,
+    stream_mode: DoubaoStreamMode,
+    audio_format: RealtimeAudioFormat,
+    enable_itn: bool,
+) -> serde_json::Value {
+    let mut config = serde_json::json!({
+        "user": {"uid": app_id},
+        "audio": {"format": "pcm", "rate": 16000, "bits": audio_format.bits_per_sample(), "channel": 1},
+        "request": {"model_name": "bigmodel", "enable_itn": enable_itn, "enable_punc": true}
+    });
+    if stream_mode == DoubaoStreamMode::Stream {
+        // 每个响应返回当前完整文本，接收端直接覆盖累积文本即可
+        config["request"]["result_type"] = serde_json::json!("full");
+    }
+    config
 }
 
 fn build_message(
@@ -382,6 +469,13 @@ fn parse_response(data: &[u8]) -> Result<(String, bool), ASRError> {
     }
     
     let header_size = (data[0] & 0x0f) as usize * 4;
+    if header_size < 4 || header_size > data.len() {
+        return Err(ASRError::InternalError(format!(
+            "无效的 header 长度: {} bytes，响应 {} bytes",
+            header_size,
+            data.len()
+        )));
+    }
     let message_type = data[1] >> 4;
     let message_flags = data[1] & 0x0f;
     let compression = data[2] & 0x0f;
@@ -432,15 +526,18 @@ fn parse_response(data: &[u8]) -> Result<(String, bool), ASRError> {
     ]) as usize;
     offset += 4;
     
-    if data.len() < offset + payload_size {
-        return Err(ASRError::InternalError(format!(
-            "数据不完整: 需要 {} bytes，实际 {} bytes",
-            offset + payload_size,
-            data.len()
-        )));
-    }
+    let payload_end = match offset.checked_add(payload_size) {
+        Some(end) if end <= data.len() => end,
+        _ => {
+            return Err(ASRError::InternalError(format!(
+                "数据不完整: payload {} bytes，实际剩余 {} bytes",
+                payload_size,
+                data.len() - offset
+            )));
+        }
+    };
     
-    let payload_data = &data[offset..offset + payload_size];
+    let payload_data = &data[offset..payload_end];
     let json_str = if compression == 0x1 {
         let mut decoder = GzDecoder::new(payload_data);
         let mut s = String::new();
@@ -466,3 +563,84 @@ fn parse_response(data: &[u8]) -> Result<(String, bool), ASRError> {
     
     Err(ASRError::InternalError("中间响应，等待更多数据".to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::realtime::{spawn_close_counting_server, wait_for_count};
+    use proptest::prelude::*;
+
+    proptest! {
+        /// 任意字节序列都只能返回结果或错误，不能 panic
+        #[test]
+        fn parse_response_never_panics(data in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let _ = parse_response(&data);
+        }
+
+        /// 声明的 payload 长度超过实际数据时返回错误
+        #[test]
+        fn parse_response_rejects_truncated_payload(
+            payload_size in 1u32..=u32::MAX,
+            tail in proptest::collection::vec(any::<u8>(), 0..64),
+        ) {
+            prop_assume!((payload_size as usize) > tail.len());
+            let mut data = vec![0x11, 0x90, 0x10, 0x00];
+            data.extend_from_slice(&payload_size.to_be_bytes());
+            data.extend_from_slice(&tail);
+            prop_assert!(parse_response(&data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_full_client_request_fields() {
+        let config = full_client_request("app", DoubaoStreamMode::NoStream, RealtimeAudioFormat::Pcm16Le, DEFAULT_ENABLE_ITN);
+        assert_eq!(config["user"]["uid"], "app");
+        assert_eq!(config["audio"]["bits"], 16);
+        assert_eq!(config["request"]["enable_itn"], true);
+        assert!(config["request"].get("result_type").is_none());
+
+        let config = full_client_request("app", DoubaoStreamMode::Stream, RealtimeAudioFormat::Pcm16Le, false);
+        assert_eq!(config["request"]["enable_itn"], false);
+        assert_eq!(config["request"]["result_type"], "full");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_formats_other_than_pcm16_le() {
+        for format in [RealtimeAudioFormat::Pcm16Be, RealtimeAudioFormat::PcmF32Le] {
+            let engine = DoubaoRealtimeEngine::new("app".to_string(), "token".to_string())
+                .with_audio_format(format);
+            let result = engine.create_realtime_session().await;
+            assert!(matches!(result, Err(ASRError::UnsupportedOperation(_))), "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_parse_response_rejects_invalid_header_size() {
+        // header 长度为 0
+        assert!(parse_response(&[0x10, 0x90, 0x10, 0x00, 0, 0, 0, 0]).is_err());
+        // header 长度超过数据长度
+        assert!(parse_response(&[0x1f, 0x90, 0x10, 0x00]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_sessions_send_close_frame() {
+        let (url, closed) = spawn_close_counting_server(Some(vec![0u8; 4])).await;
+
+        for _ in 0..8 {
+            let mut session = DoubaoRealtimeSession::connect_to(
+                &url,
+                "app".to_string(),
+                "token".to_string(),
+                DoubaoStreamMode::default(),
+                RealtimeAudioFormat::default(),
+                DEFAULT_ENABLE_ITN,
+            )
+            .await
+            .unwrap();
+            session.send_chunk(&[0u8; 320]).await.unwrap();
+            drop(session);
+        }
+
+        assert_eq!(wait_for_count(&closed, 8).await, 8);
+    }
+}
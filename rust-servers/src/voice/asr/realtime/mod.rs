@@ -6,3 +6,276 @@ pub mod doubao;
 
 pub use qwen::QwenRealtimeEngine;
 pub use doubao::DoubaoRealtimeEngine;
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::stream::SplitSink;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{http, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::voice::asr::{ASRError, ASRErrorKind, PartialTranscription};
+use crate::voice::config::ReconnectPolicy;
+
+pub(crate) type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub(crate) type WsSink = SplitSink<WsStream, Message>;
+
+/// 会话关闭时尚未收到任何转录结果的错误信息
+///
+/// 已发送音频时多为服务端结果与关闭时序的竞争，实时转录任务据此重试一次
+pub(crate) const NO_RESULT_MESSAGE: &str = "未收到转录结果";
+
+/// 建立 WebSocket 连接 (各供应商的握手请求头由调用方构建)
+pub(crate) async fn open_websocket(request: http::Request<()>) -> Result<WsStream, ASRError> {
+    let (ws_stream, _) = connect_async(request)
+        .await
+        .map_err(|e| ASRError::WebSocketError(format!("WebSocket 连接失败: {}", e)))?;
+    Ok(ws_stream)
+}
+
+/// 握手请求的 Host 头 (取自连接地址，自定义服务地址时与之一致)
+pub(crate) fn host_header(url: &str) -> Result<String, ASRError> {
+    let uri: http::Uri = url
+        .parse()
+        .map_err(|e| ASRError::ConfigError(format!("无效的 WebSocket 地址 {}: {}", url, e)))?;
+    let authority = uri
+        .authority()
+        .ok_or_else(|| ASRError::ConfigError(format!("WebSocket 地址缺少主机名: {}", url)))?;
+    // Host 头不含用户信息
+    let host = authority.as_str();
+    Ok(host.rsplit_once('@').map_or(host, |(_, host)| host).to_string())
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次重试前的等待时长 (attempt 从 1 开始，不含抖动)
+    fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_millis(self.base_delay_ms.saturating_mul(factor).min(self.max_delay_ms))
+    }
+
+    /// 第 `attempt` 次重试前的等待时长 (含抖动)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // 取随机 UUID 的低 53 位 (不含版本与变体位)，得到 [-1.0, 1.0] 范围内的随机数
+        let bits = uuid::Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1);
+        let random = bits as f64 / (1u64 << 53) as f64 * 2.0 - 1.0;
+        self.base_delay(attempt).mul_f64(1.0 + jitter * random)
+    }
+
+    /// 按策略执行连接，失败且可重试时等待后重新连接
+    pub async fn connect<T, F, Fut>(&self, engine: &str, mut connect: F) -> Result<T, ASRError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ASRError>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match connect().await {
+                Ok(session) => return Ok(session),
+                Err(e) if attempt < max_attempts && is_reconnectable(&e) => {
+                    let delay = self.delay(attempt);
+                    eprintln!(
+                        "[WARN] {} 实时会话连接失败 (尝试 {}/{}): {}，{}ms 后重连",
+                        engine,
+                        attempt,
+                        max_attempts,
+                        e,
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// 是否为重连可能恢复的错误
+fn is_reconnectable(error: &ASRError) -> bool {
+    matches!(
+        error.kind(),
+        ASRErrorKind::Network | ASRErrorKind::WebSocket | ASRErrorKind::Timeout
+    )
+}
+
+/// 实时转录文本累积器
+///
+/// 统一各供应商的部分结果语义：无论供应商推送增量 (Qwen delta) 还是
+/// 截至当前的完整文本 (豆包)，对外发出的 `PartialTranscription` 均为完整文本
+#[derive(Debug, Default)]
+pub(crate) struct TranscriptAccumulator {
+    text: String,
+}
+
+impl TranscriptAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加增量文本，返回追加后的完整文本
+    pub fn apply_delta(&mut self, delta: &str) -> PartialTranscription {
+        self.text.push_str(delta);
+        PartialTranscription::new(self.text.clone(), false)
+    }
+
+    /// 以供应商给出的完整文本替换当前文本
+    pub fn replace(&mut self, text: &str, is_final: bool) -> PartialTranscription {
+        self.text.clear();
+        self.text.push_str(text);
+        PartialTranscription::new(self.text.clone(), is_final)
+    }
+
+    /// 将当前文本标记为最终结果
+    pub fn finalize(&self) -> PartialTranscription {
+        PartialTranscription::new(self.text.clone(), true)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+/// 测试用本地 WebSocket 服务端，统计收到 Close 帧的连接数
+///
+/// `reply` 非空时在收到首条消息后回复一次 (模拟豆包 Full Client Request 响应)
+#[cfg(test)]
+pub(crate) async fn spawn_close_counting_server(
+    reply: Option<Vec<u8>>,
+) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let closed = Arc::new(AtomicUsize::new(0));
+    let closed_clone = Arc::clone(&closed);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let closed = Arc::clone(&closed_clone);
+            let reply = reply.clone();
+            tokio::spawn(async move {
+                let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                let mut replied = reply.is_none();
+                while let Some(Ok(msg)) = ws.next().await {
+                    if msg.is_close() {
+                        closed.fetch_add(1, Ordering::SeqCst);
+                        break;
+                    }
+                    if !replied {
+                        replied = true;
+                        let _ = ws.send(Message::Binary(reply.clone().unwrap().into())).await;
+                    }
+                }
+            });
+        }
+    });
+
+    (url, closed)
+}
+
+/// 等待计数达到期望值 (最长 2 秒)
+#[cfg(test)]
+pub(crate) async fn wait_for_count(counter: &std::sync::atomic::AtomicUsize, expected: usize) -> usize {
+    for _ in 0..100 {
+        if counter.load(std::sync::atomic::Ordering::SeqCst) >= expected {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    counter.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_host_header_follows_url() {
+        assert_eq!(
+            host_header("wss://dashscope-intl.aliyuncs.com/api-ws/v1/realtime?model=m").unwrap(),
+            "dashscope-intl.aliyuncs.com"
+        );
+        assert_eq!(host_header("ws://user:pass@127.0.0.1:9001/ws").unwrap(), "127.0.0.1:9001");
+        assert!(matches!(host_header("/relative"), Err(ASRError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_reconnect_delay_backs_off_with_cap_and_jitter() {
+        let policy = ReconnectPolicy {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+            jitter: 0.5,
+        };
+        assert_eq!(policy.base_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.base_delay(2), Duration::from_millis(200));
+        assert_eq!(policy.base_delay(3), Duration::from_millis(300));
+
+        for _ in 0..50 {
+            let delay = policy.delay(2).as_millis();
+            assert!((100..=300).contains(&delay), "delay {}ms", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_retries_only_network_errors() {
+        let policy = ReconnectPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            max_delay_ms: 1,
+            jitter: 0.0,
+        };
+
+        let calls = AtomicU32::new(0);
+        let result = policy
+            .connect("mock", || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(ASRError::WebSocketError("连接被重置".to_string())),
+                    _ => Ok("session"),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "session");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<(), ASRError> = policy
+            .connect("mock", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(ASRError::UnsupportedOperation("格式不支持".to_string()))
+            })
+            .await;
+        assert!(matches!(result, Err(ASRError::UnsupportedOperation(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_qwen_deltas_accumulate_to_full_text() {
+        let mut acc = TranscriptAccumulator::new();
+        assert_eq!(acc.apply_delta("你好"), PartialTranscription::new("你好".to_string(), false));
+        assert_eq!(acc.apply_delta("，世界"), PartialTranscription::new("你好，世界".to_string(), false));
+        assert_eq!(acc.finalize(), PartialTranscription::new("你好，世界".to_string(), true));
+    }
+
+    #[test]
+    fn test_doubao_full_text_replaces_previous() {
+        let mut acc = TranscriptAccumulator::new();
+        acc.replace("今天", false);
+        assert_eq!(acc.replace("今天天气", false).text, "今天天气");
+        assert_eq!(acc.replace("今天天气不错", true), PartialTranscription::new("今天天气不错".to_string(), true));
+        assert_eq!(acc.text(), "今天天气不错");
+    }
+}
@@ -3,32 +3,36 @@
 
 use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose};
-use futures_util::{SinkExt, StreamExt, stream::SplitSink};
-use std::sync::Arc;
+use crate::voice::asr::ids::generate_websocket_key;
+use futures_util::{SinkExt, StreamExt};
+use std::sync::{Arc, Mutex as StdMutex};
 use std::time::Duration;
 use tokio::sync::{Mutex, mpsc, oneshot};
-use tokio::net::TcpStream;
-use tokio_tungstenite::{
-    connect_async, 
-    tungstenite::{Message, http},
-    MaybeTlsStream, 
-    WebSocketStream
-};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::{Message, http};
 
-use crate::voice::asr::{ASREngine, ASRError, ASRMode, RealtimeSession, RetryConfig};
+use crate::voice::asr::realtime::{host_header, open_websocket, NO_RESULT_MESSAGE, TranscriptAccumulator, WsSink};
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, PartialResultCallback, PartialTranscription, RealtimeSession,
+    RetryConfig, DEFAULT_SESSION_TIMEOUT_MS,
+};
+use crate::voice::asr::text::{apply_punctuation_mode, DEFAULT_LANGUAGE};
+use crate::voice::config::{PunctuationMode, RealtimeAudioFormat, ReconnectPolicy, DEFAULT_DASHSCOPE_BASE_URL};
 use crate::voice::audio::AudioData;
 
-const WEBSOCKET_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/realtime";
+const WEBSOCKET_PATH: &str = "/api-ws/v1/realtime";
 const DEFAULT_MODEL: &str = "qwen3-asr-flash-realtime";
-const TRANSCRIPTION_TIMEOUT_SECS: u64 = 10;
-
-type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 
 pub struct QwenRealtimeEngine {
     api_key: String,
     model: String,
-    #[allow(dead_code)]
+    language: Option<String>,
+    context_prompt: Option<String>,
+    punctuation_mode: PunctuationMode,
+    audio_format: RealtimeAudioFormat,
     retry_config: RetryConfig,
+    reconnect_policy: ReconnectPolicy,
+    websocket_url: String,
 }
 
 impl QwenRealtimeEngine {
@@ -36,14 +40,62 @@ impl QwenRealtimeEngine {
         Self {
             api_key,
             model: DEFAULT_MODEL.to_string(),
+            language: None,
+            context_prompt: None,
+            punctuation_mode: PunctuationMode::default(),
+            audio_format: RealtimeAudioFormat::default(),
             retry_config: RetryConfig::default(),
+            reconnect_policy: ReconnectPolicy::default(),
+            websocket_url: websocket_url(DEFAULT_DASHSCOPE_BASE_URL),
         }
     }
     
+    /// 设置 DashScope 服务地址 (地域)，http(s) 地址转换为对应的 ws(s) 地址
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.websocket_url = websocket_url(base_url);
+        self
+    }
+    
+    /// 设置重试与超时配置 (实时模式使用 session_timeout_ms 作为 close 等待超时)
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+    
+    /// 设置建立实时会话时的重连策略
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+    
     pub fn with_model(mut self, model: String) -> Self {
         self.model = model;
         self
     }
+    
+    /// 设置识别语言 (None 使用默认语言)
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+    
+    /// 设置识别上下文提示 (如 "医学笔记"、"Rust 代码")，用于提升领域词汇的识别准确率
+    pub fn with_context_prompt(mut self, prompt: Option<String>) -> Self {
+        self.context_prompt = prompt.filter(|prompt| !prompt.trim().is_empty());
+        self
+    }
+    
+    /// 设置标点处理模式
+    pub fn with_punctuation_mode(mut self, mode: PunctuationMode) -> Self {
+        self.punctuation_mode = mode;
+        self
+    }
+    
+    /// 设置音频线上格式 (DashScope 实时接口仅接受 16 位小端 PCM)
+    pub fn with_audio_format(mut self, format: RealtimeAudioFormat) -> Self {
+        self.audio_format = format;
+        self
+    }
 }
 
 #[async_trait]
@@ -63,15 +115,35 @@ impl ASREngine for QwenRealtimeEngine {
     }
     
     async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
-        let session = QwenRealtimeSession::connect(
-            self.api_key.clone(),
-            self.model.clone(),
-        ).await?;
+        let input_audio_format = input_audio_format(self.audio_format).ok_or_else(|| {
+            ASRError::UnsupportedOperation(format!("Qwen 实时模式不支持音频格式 {}", self.audio_format))
+        })?;
+        
+        let mut session = self.reconnect_policy.connect("Qwen", || {
+            QwenRealtimeSession::connect(
+                &self.websocket_url,
+                self.api_key.clone(),
+                self.model.clone(),
+                self.language.clone(),
+                self.context_prompt.clone(),
+                self.punctuation_mode,
+                input_audio_format,
+            )
+        }).await?;
+        session.session_timeout = Duration::from_millis(self.retry_config.session_timeout_ms);
         
         Ok(Box::new(session))
     }
 }
 
+/// 音频格式对应的 `input_audio_format` 取值 (仅支持 16 位小端 PCM)
+fn input_audio_format(format: RealtimeAudioFormat) -> Option<&'static str> {
+    match format {
+        RealtimeAudioFormat::Pcm16Le => Some("pcm"),
+        RealtimeAudioFormat::Pcm16Be | RealtimeAudioFormat::PcmF32Le => None,
+    }
+}
+
 enum SessionCommand {
     SendAudio(Vec<u8>),
     Commit,
@@ -81,21 +153,43 @@ enum SessionCommand {
 pub struct QwenRealtimeSession {
     cmd_sender: mpsc::Sender<SessionCommand>,
     result_receiver: Option<oneshot::Receiver<Result<String, ASRError>>>,
-    partial_callback: Option<Arc<Mutex<Box<dyn Fn(&str) + Send + 'static>>>>,
+    partial_callback: Arc<StdMutex<Option<PartialResultCallback>>>,
     #[allow(dead_code)]
-    partial_sender: mpsc::Sender<String>,
+    partial_sender: mpsc::Sender<PartialTranscription>,
+    read_task: JoinHandle<()>,
+    /// close 等待最终结果的超时
+    session_timeout: Duration,
 }
 
 impl QwenRealtimeSession {
-    async fn connect(api_key: String, model: String) -> Result<Self, ASRError> {
-        let url = format!("{}?model={}", WEBSOCKET_URL, model);
+    async fn connect(
+        websocket_url: &str,
+        api_key: String,
+        model: String,
+        language: Option<String>,
+        context_prompt: Option<String>,
+        punctuation_mode: PunctuationMode,
+        input_audio_format: &'static str,
+    ) -> Result<Self, ASRError> {
+        let url = format!("{}?model={}", websocket_url, model);
+        Self::connect_to(&url, api_key, language, context_prompt, punctuation_mode, input_audio_format).await
+    }
+    
+    async fn connect_to(
+        url: &str,
+        api_key: String,
+        language: Option<String>,
+        context_prompt: Option<String>,
+        punctuation_mode: PunctuationMode,
+        input_audio_format: &'static str,
+    ) -> Result<Self, ASRError> {
         eprintln!("[INFO] 创建 Qwen Realtime WebSocket 连接: {}", url);
         
         let request = http::Request::builder()
-            .uri(&url)
+            .uri(url)
             .header("Authorization", format!("Bearer {}", api_key))
             .header("OpenAI-Beta", "realtime=v1")
-            .header("Host", "dashscope.aliyuncs.com")
+            .header("Host", host_header(url)?)
             .header("Connection", "Upgrade")
             .header("Upgrade", "websocket")
             .header("Sec-WebSocket-Version", "13")
@@ -103,23 +197,27 @@ impl QwenRealtimeSession {
             .body(())
             .map_err(|e| ASRError::WebSocketError(format!("构建请求失败: {}", e)))?;
         
-        let (ws_stream, _) = connect_async(request).await
-            .map_err(|e| ASRError::WebSocketError(format!("WebSocket 连接失败: {}", e)))?;
+        let ws_stream = open_websocket(request).await?;
         
         eprintln!("[INFO] Qwen Realtime WebSocket 连接成功");
         
         let (mut write, mut read) = ws_stream.split();
         
+        let mut transcription = serde_json::json!({
+            "language": language.as_deref().unwrap_or(DEFAULT_LANGUAGE)
+        });
+        if let Some(prompt) = context_prompt {
+            transcription["corpus"] = serde_json::json!({ "text": prompt });
+        }
+        
         let session_update = serde_json::json!({
             "event_id": format!("event_{}", timestamp_ms()),
             "type": "session.update",
             "session": {
                 "modalities": ["text"],
-                "input_audio_format": "pcm",
+                "input_audio_format": input_audio_format,
                 "sample_rate": 16000,
-                "input_audio_transcription": {
-                    "language": "zh"
-                },
+                "input_audio_transcription": transcription,
                 "turn_detection": serde_json::Value::Null
             }
         });
@@ -131,7 +229,7 @@ impl QwenRealtimeSession {
         
         let (cmd_tx, mut cmd_rx) = mpsc::channel::<SessionCommand>(100);
         let (result_tx, result_rx) = oneshot::channel::<Result<String, ASRError>>();
-        let (partial_tx, mut partial_rx) = mpsc::channel::<String>(100);
+        let (partial_tx, mut partial_rx) = mpsc::channel::<PartialTranscription>(100);
         
         let write: Arc<Mutex<WsSink>> = Arc::new(Mutex::new(write));
         let write_clone = Arc::clone(&write);
@@ -168,15 +266,18 @@ impl QwenRealtimeSession {
                     SessionCommand::Close => {
                         let mut w = write_clone.lock().await;
                         let _ = w.close().await;
-                        break;
+                        return;
                     }
                 }
             }
+            
+            // 会话被丢弃 (命令通道关闭) 或发送失败时，同样发送 Close 帧释放连接
+            let _ = write_clone.lock().await.close().await;
         });
         
         let partial_tx_clone = partial_tx.clone();
-        tokio::spawn(async move {
-            let mut final_text = String::new();
+        let read_task = tokio::spawn(async move {
+            let mut transcript = TranscriptAccumulator::new();
             let mut has_result = false;
             let mut result_tx = Some(result_tx);
             
@@ -195,24 +296,26 @@ impl QwenRealtimeSession {
                                         eprintln!("[INFO] 音频缓冲区已提交");
                                     }
                                     "conversation.item.input_audio_transcription.completed" => {
-                                        if let Some(transcript) = data["transcript"].as_str() {
-                                            final_text = transcript.to_string();
+                                        if let Some(text) = data["transcript"].as_str() {
+                                            let partial = transcript.replace(text, true);
                                             has_result = true;
-                                            eprintln!("[INFO] 转录完成: {}", final_text);
+                                            eprintln!("[INFO] 转录完成: {}", partial.text);
+                                            let _ = partial_tx_clone.send(partial).await;
                                         }
                                     }
                                     "response.audio_transcript.delta" => {
                                         if let Some(delta) = data["delta"].as_str() {
-                                            final_text.push_str(delta);
-                                            let _ = partial_tx_clone.send(final_text.clone()).await;
+                                            let _ = partial_tx_clone.send(transcript.apply_delta(delta)).await;
                                         }
                                     }
                                     "response.audio_transcript.done" => {
-                                        if let Some(transcript) = data["transcript"].as_str() {
-                                            final_text = transcript.to_string();
-                                        }
+                                        let partial = match data["transcript"].as_str() {
+                                            Some(text) => transcript.replace(text, true),
+                                            None => transcript.finalize(),
+                                        };
                                         has_result = true;
-                                        eprintln!("[INFO] 转录完成: {}", final_text);
+                                        eprintln!("[INFO] 转录完成: {}", partial.text);
+                                        let _ = partial_tx_clone.send(partial).await;
                                     }
                                     "response.done" => {
                                         has_result = true;
@@ -253,8 +356,8 @@ impl QwenRealtimeSession {
                     _ => {}
                 }
                 
-                if has_result && !final_text.is_empty() {
-                    let cleaned_text = strip_punctuation(&final_text);
+                if has_result && !transcript.is_empty() {
+                    let cleaned_text = apply_punctuation_mode(transcript.text(), punctuation_mode, language.as_deref(), true);
                     if let Some(tx) = result_tx.take() {
                         let _ = tx.send(Ok(cleaned_text));
                     }
@@ -264,18 +367,18 @@ impl QwenRealtimeSession {
             
             if !has_result {
                 if let Some(tx) = result_tx.take() {
-                    let _ = tx.send(Err(ASRError::InternalError("未收到转录结果".to_string())));
+                    let _ = tx.send(Err(ASRError::InternalError(NO_RESULT_MESSAGE.to_string())));
                 }
             }
         });
         
-        let partial_callback: Option<Arc<Mutex<Box<dyn Fn(&str) + Send + 'static>>>> = None;
-        let partial_callback_clone = partial_callback.clone();
+        // 回调槽位与转发任务共享，连接建立后再设置的回调同样生效
+        let partial_callback: Arc<StdMutex<Option<PartialResultCallback>>> = Arc::new(StdMutex::new(None));
+        let partial_callback_clone = Arc::clone(&partial_callback);
         tokio::spawn(async move {
-            while let Some(text) = partial_rx.recv().await {
-                if let Some(ref callback) = partial_callback_clone {
-                    let cb = callback.lock().await;
-                    cb(&text);
+            while let Some(partial) = partial_rx.recv().await {
+                if let Some(ref callback) = *partial_callback_clone.lock().unwrap() {
+                    callback(&partial);
                 }
             }
         });
@@ -285,6 +388,8 @@ impl QwenRealtimeSession {
             result_receiver: Some(result_rx),
             partial_callback,
             partial_sender: partial_tx,
+            read_task,
+            session_timeout: Duration::from_millis(DEFAULT_SESSION_TIMEOUT_MS),
         })
     }
 }
@@ -308,10 +413,10 @@ impl RealtimeSession for QwenRealtimeSession {
             .ok_or_else(|| ASRError::InternalError("会话已关闭".to_string()))?;
         
         let result = tokio::time::timeout(
-            Duration::from_secs(TRANSCRIPTION_TIMEOUT_SECS),
+            self.session_timeout,
             result_rx
         ).await
-            .map_err(|_| ASRError::Timeout { timeout_ms: TRANSCRIPTION_TIMEOUT_SECS * 1000 })?
+            .map_err(|_| ASRError::Timeout { timeout_ms: self.session_timeout.as_millis() as u64 })?
             .map_err(|_| ASRError::InternalError("结果通道已关闭".to_string()))?;
         
         let _ = self.cmd_sender.send(SessionCommand::Close).await;
@@ -319,18 +424,17 @@ impl RealtimeSession for QwenRealtimeSession {
         result
     }
     
-    fn set_partial_callback(&mut self, callback: Box<dyn Fn(&str) + Send + 'static>) {
-        self.partial_callback = Some(Arc::new(Mutex::new(callback)));
+    fn set_partial_callback(&mut self, callback: PartialResultCallback) {
+        *self.partial_callback.lock().unwrap() = Some(callback);
     }
 }
 
-fn generate_websocket_key() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    general_purpose::STANDARD.encode(format!("{}", timestamp).as_bytes())
+impl Drop for QwenRealtimeSession {
+    fn drop(&mut self) {
+        // 未调用 close 即被丢弃时 (如任务被中止)，命令通道随之关闭，写任务发送 Close 帧；
+        // 读任务在此直接终止，避免与供应商的连接半开占用并发额度
+        self.read_task.abort();
+    }
 }
 
 fn timestamp_ms() -> u128 {
@@ -341,14 +445,62 @@ fn timestamp_ms() -> u128 {
         .as_millis()
 }
 
-fn strip_punctuation(text: &str) -> String {
-    let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"',
-                       '.', ',', '!', '?', ';', ':', '"', '\'',
-                       '（', '）', '(', ')', '【', '】', '[', ']',
-                       '《', '》', '<', '>', '—', '…', '·',
-                       '\u{2018}', '\u{2019}'];
-    
-    text.chars()
-        .filter(|c| !punctuation.contains(c))
-        .collect()
+/// 由 DashScope 服务地址得到实时识别 WebSocket 地址
+fn websocket_url(base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let ws_base = if let Some(rest) = base_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = base_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        base_url.to_string()
+    };
+    format!("{}{}", ws_base, WEBSOCKET_PATH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::realtime::{spawn_close_counting_server, wait_for_count};
+
+    #[tokio::test]
+    async fn test_dropped_sessions_send_close_frame() {
+        let (url, closed) = spawn_close_counting_server(None).await;
+
+        for _ in 0..8 {
+            let session = QwenRealtimeSession::connect_to(
+                &url,
+                "test-key".to_string(),
+                None,
+                None,
+                PunctuationMode::default(),
+                "pcm",
+            )
+            .await
+            .unwrap();
+            drop(session);
+        }
+
+        assert_eq!(wait_for_count(&closed, 8).await, 8);
+    }
+
+    #[test]
+    fn test_input_audio_format_follows_config() {
+        assert_eq!(input_audio_format(RealtimeAudioFormat::Pcm16Le), Some("pcm"));
+        assert_eq!(input_audio_format(RealtimeAudioFormat::Pcm16Be), None);
+        assert_eq!(input_audio_format(RealtimeAudioFormat::PcmF32Le), None);
+    }
+
+    #[test]
+    fn test_websocket_url_from_base_url() {
+        assert_eq!(
+            websocket_url(DEFAULT_DASHSCOPE_BASE_URL),
+            "wss://dashscope.aliyuncs.com/api-ws/v1/realtime"
+        );
+        assert_eq!(
+            websocket_url("https://dashscope-intl.aliyuncs.com/"),
+            "wss://dashscope-intl.aliyuncs.com/api-ws/v1/realtime"
+        );
+        assert_eq!(websocket_url("http://127.0.0.1:8080"), "ws://127.0.0.1:8080/api-ws/v1/realtime");
+    }
 }
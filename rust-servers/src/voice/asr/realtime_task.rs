@@ -1,12 +1,16 @@
 // 实时转录任务模块
 // 协调 StreamingRecorder 和 RealtimeSession，实现边录边转录
 
-use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex, oneshot};
+use std::time::{Duration, Instant};
+use futures_util::{Stream, StreamExt};
+use tokio::sync::{mpsc, oneshot};
 
-use crate::voice::asr::{ASRError, TranscriptionResult, create_engine};
-use crate::voice::audio::streaming::AudioChunkData;
-use crate::voice::config::ASRProviderConfig;
+use crate::voice::asr::{ASRError, PartialTranscription, RealtimeSession, Timings, TranscriptionResult, create_engine};
+use crate::voice::asr::realtime::NO_RESULT_MESSAGE;
+use crate::voice::audio::AudioData;
+use crate::voice::audio::recorder::{convert_f32_to_i16, DEFAULT_MAX_RECORDING_SECS, TARGET_SAMPLE_RATE};
+use crate::voice::audio::streaming::{AudioChunkData, CHUNK_CHANNEL_BUFFER};
+use crate::voice::config::{ASRProvider, ASRProviderConfig, PartialGranularity, RealtimeAudioFormat};
 
 macro_rules! log_info {
     ($($arg:tt)*) => {
@@ -21,9 +25,9 @@ macro_rules! log_debug {
 }
 
 macro_rules! log_warn {
-    ($($arg:tt)*) => {
-        eprintln!("[WARN] [realtime_task] {}", format!($($arg)*));
-    };
+    ($($arg:tt)*) => {{
+        eprintln!("[WARN] [realtime_task] {}", format!($($arg)*))
+    }};
 }
 
 macro_rules! log_error {
@@ -64,15 +68,88 @@ impl RealtimeTaskResult {
     }
 }
 
+/// 保活静音帧样本数 (0.1 秒 @ 16kHz)
+const KEEPALIVE_SILENCE_SAMPLES: usize = 1600;
+
+/// 重试时重发音频的分块样本数 (0.2 秒 @ 16kHz)
+const RETRY_CHUNK_SAMPLES: usize = 3200;
+
+/// 实时会话默认最长持续时间 (秒)：录音时长上限再留 1 分钟连接与收尾余量，正常录音不会触发
+pub const DEFAULT_MAX_SESSION_SECS: u64 = DEFAULT_MAX_RECORDING_SECS as u64 + 60;
+
+/// 预连接会话的默认保活窗口 (秒)，超时未使用则关闭
+pub const DEFAULT_PRECONNECT_KEEPALIVE_SECS: u64 = 15;
+
+/// 各供应商的默认保活间隔 (毫秒)，略短于服务端的空闲断开时长
+fn default_keepalive_ms(provider: &ASRProvider) -> u64 {
+    match provider {
+        // 豆包流式识别约 10 秒无音频即断开
+        ASRProvider::Doubao => 8_000,
+        // DashScope 实时识别空闲超时较长，保守取值
+        ASRProvider::Qwen => 15_000,
+        // SenseVoice 仅支持 HTTP 模式
+        ASRProvider::SenseVoice => 0,
+    }
+}
+
 /// 部分结果回调类型
-pub type PartialResultCallback = Box<dyn Fn(&str) + Send + 'static>;
+pub type PartialResultCallback = Box<dyn Fn(&PartialTranscription) + Send + 'static>;
+
+/// 实时会话连接状态
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SessionStatus {
+    /// 正在连接供应商
+    Connecting { provider: String },
+    /// 连接与握手已完成，可以开始说话
+    Connected { engine: String, preconnected: bool },
+}
+
+/// 会话状态回调类型
+pub type SessionStatusCallback = Box<dyn Fn(&SessionStatus) + Send + 'static>;
+
+/// 预连接的实时会话
+/// 
+/// 在用户开始说话前完成 WebSocket 握手，保活窗口内被录音任务取用；
+/// 窗口过期后由持有者丢弃以关闭连接
+pub struct PreconnectedSession {
+    asr_config: ASRProviderConfig,
+    engine_name: String,
+    session: Box<dyn RealtimeSession>,
+    expires_at: Instant,
+}
+
+impl PreconnectedSession {
+    /// 是否已超过保活窗口
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+    
+    /// 保活窗口剩余时长
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+    
+    /// 是否由相同配置建立 (配置变化后不可复用)
+    pub fn matches(&self, asr_config: &ASRProviderConfig) -> bool {
+        &self.asr_config == asr_config
+    }
+    
+    pub fn engine_name(&self) -> &str {
+        &self.engine_name
+    }
+}
 
 /// 实时转录任务
 pub struct RealtimeTranscriptionTask {
     asr_config: ASRProviderConfig,
     chunk_receiver: mpsc::Receiver<AudioChunkData>,
-    partial_callback: Arc<Mutex<Option<PartialResultCallback>>>,
+    partial_callback: Option<PartialResultCallback>,
     stop_receiver: Option<oneshot::Receiver<()>>,
+    preconnected: Option<PreconnectedSession>,
+    partial_granularity: PartialGranularity,
+    status_callback: Option<SessionStatusCallback>,
+    max_session_duration: Option<Duration>,
 }
 
 impl RealtimeTranscriptionTask {
@@ -83,16 +160,105 @@ impl RealtimeTranscriptionTask {
     ) -> (Self, oneshot::Sender<()>) {
         let (stop_tx, stop_rx) = oneshot::channel();
         
+        let max_session_secs = asr_config.realtime_max_session_secs.unwrap_or(DEFAULT_MAX_SESSION_SECS);
         let task = Self {
+            partial_granularity: asr_config.partial_granularity,
+            max_session_duration: (max_session_secs > 0).then(|| Duration::from_secs(max_session_secs)),
             asr_config,
             chunk_receiver,
-            partial_callback: Arc::new(Mutex::new(partial_callback)),
+            partial_callback,
             stop_receiver: Some(stop_rx),
+            preconnected: None,
+            status_callback: None,
         };
         
         (task, stop_tx)
     }
     
+    /// 从任意音频流创建任务 (文件、其他采集库等非 cpal 来源)
+    ///
+    /// 流中每项为 16kHz 单声道 PCM 样本，流结束即视为录音结束；
+    /// 后台任务将流转发到内部音频通道，其余流程与录音器来源一致
+    pub fn from_stream<S>(
+        asr_config: ASRProviderConfig,
+        stream: S,
+        partial_callback: Option<PartialResultCallback>,
+    ) -> (Self, oneshot::Sender<()>)
+    where
+        S: Stream<Item = Vec<i16>> + Send + 'static,
+    {
+        let (chunk_tx, chunk_rx) = mpsc::channel::<AudioChunkData>(CHUNK_CHANNEL_BUFFER);
+        
+        tokio::spawn(async move {
+            let mut stream = std::pin::pin!(stream);
+            let mut samples_sent = 0u64;
+            while let Some(samples) = stream.next().await {
+                let timestamp_ms = samples_sent * 1000 / TARGET_SAMPLE_RATE as u64;
+                samples_sent += samples.len() as u64;
+                if chunk_tx.send(AudioChunkData { samples, timestamp_ms }).await.is_err() {
+                    log_debug!("转录任务已结束，停止转发音频流");
+                    break;
+                }
+            }
+        });
+        
+        Self::new(asr_config, chunk_rx, partial_callback)
+    }
+    
+    /// 预连接实时会话：提前完成 WebSocket 握手和会话初始化
+    /// 
+    /// 会话在配置的保活窗口 (默认 `DEFAULT_PRECONNECT_KEEPALIVE_SECS`) 内有效
+    pub async fn preconnect(asr_config: &ASRProviderConfig) -> Result<PreconnectedSession, ASRError> {
+        let engine = create_engine(asr_config)?;
+        let engine_name = engine.name().to_string();
+        let keepalive = Duration::from_secs(
+            asr_config.realtime_preconnect_keepalive_secs.unwrap_or(DEFAULT_PRECONNECT_KEEPALIVE_SECS),
+        );
+        
+        log_info!("预连接实时会话，供应商: {}", asr_config.provider);
+        let session = engine.create_realtime_session().await?;
+        
+        Ok(PreconnectedSession {
+            asr_config: asr_config.clone(),
+            engine_name,
+            session,
+            expires_at: Instant::now() + keepalive,
+        })
+    }
+    
+    /// 使用预连接的会话 (配置不一致或已过期时忽略)
+    pub fn with_preconnected(mut self, preconnected: Option<PreconnectedSession>) -> Self {
+        self.preconnected = preconnected
+            .filter(|session| session.matches(&self.asr_config) && !session.is_expired());
+        self
+    }
+    
+    /// 设置部分结果推送粒度 (默认取自配置)
+    pub fn with_partial_granularity(mut self, granularity: PartialGranularity) -> Self {
+        self.partial_granularity = granularity;
+        self
+    }
+    
+    /// 设置会话状态回调 (开始连接、连接完成时调用)
+    pub fn with_status_callback(mut self, callback: Option<SessionStatusCallback>) -> Self {
+        self.status_callback = callback;
+        self
+    }
+    
+    /// 设置会话最长持续时间 (None 不限制，默认取自配置)
+    ///
+    /// 网络卡死、迟迟收不到最终结果而音频仍在持续时，超时后强制关闭会话
+    pub fn with_max_session_duration(mut self, max_session_duration: Option<Duration>) -> Self {
+        self.max_session_duration = max_session_duration;
+        self
+    }
+    
+    fn notify_status(&self, status: SessionStatus) {
+        if let Some(ref callback) = self.status_callback {
+            callback(&status);
+        }
+    }
+    
     pub async fn run(self) -> Result<TranscriptionResult, ASRError> {
         match self.run_with_details().await {
             RealtimeTaskResult::Success(result) => Ok(result),
@@ -107,7 +273,7 @@ impl RealtimeTranscriptionTask {
     }
     
     pub async fn run_with_details(mut self) -> RealtimeTaskResult {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
         let mut engine_name = String::from("unknown");
         let mut chunk_count = 0u64;
         let mut total_samples = 0u64;
@@ -118,52 +284,79 @@ impl RealtimeTranscriptionTask {
             self.asr_config.mode
         );
         
-        let engine = match create_engine(&self.asr_config) {
-            Ok(e) => e,
-            Err(e) => {
-                log_error!("创建 ASR 引擎失败: {}", e);
-                return RealtimeTaskResult::Failed {
-                    error: e,
-                    engine_name,
-                    chunks_sent: 0,
-                    samples_sent: 0,
-                };
-            }
-        };
-        engine_name = engine.name().to_string();
-        
-        log_debug!("创建 ASR 引擎: {}", engine_name);
-        
-        let mut session = match engine.create_realtime_session().await {
-            Ok(s) => s,
-            Err(e) => {
-                log_error!("创建实时会话失败 (WebSocket 连接失败): {}", e);
-                return RealtimeTaskResult::Failed {
-                    error: e,
-                    engine_name,
-                    chunks_sent: 0,
-                    samples_sent: 0,
-                };
+        let mut session = if let Some(preconnected) = self.preconnected.take() {
+            engine_name = preconnected.engine_name;
+            log_info!("使用预连接的实时会话: {}", engine_name);
+            self.notify_status(SessionStatus::Connected {
+                engine: engine_name.clone(),
+                preconnected: true,
+            });
+            preconnected.session
+        } else {
+            self.notify_status(SessionStatus::Connecting {
+                provider: self.asr_config.provider.to_string(),
+            });
+            let engine = match create_engine(&self.asr_config) {
+                Ok(e) => e,
+                Err(e) => {
+                    log_error!("创建 ASR 引擎失败: {}", e);
+                    return RealtimeTaskResult::Failed {
+                        error: e,
+                        engine_name,
+                        chunks_sent: 0,
+                        samples_sent: 0,
+                    };
+                }
+            };
+            engine_name = engine.name().to_string();
+            
+            log_debug!("创建 ASR 引擎: {}", engine_name);
+            
+            match engine.create_realtime_session().await {
+                Ok(s) => {
+                    self.notify_status(SessionStatus::Connected {
+                        engine: engine_name.clone(),
+                        preconnected: false,
+                    });
+                    s
+                }
+                Err(e) => {
+                    log_error!("创建实时会话失败 (WebSocket 连接失败): {}", e);
+                    return RealtimeTaskResult::Failed {
+                        error: e,
+                        engine_name,
+                        chunks_sent: 0,
+                        samples_sent: 0,
+                    };
+                }
             }
         };
         
         log_info!("实时会话已创建");
+        let connect_ms = start_time.elapsed().as_millis() as u64;
+        let streaming_start = Instant::now();
         
-        let partial_callback = Arc::clone(&self.partial_callback);
-        session.set_partial_callback(Box::new(move |text| {
-            let text_owned = text.to_string();
-            let callback = partial_callback.clone();
-            tokio::spawn(async move {
-                if let Some(ref cb) = *callback.lock().await {
-                    cb(&text_owned);
-                }
-            });
-        }));
+        if let Some(callback) = self.partial_callback.take() {
+            let callback = match self.partial_granularity {
+                PartialGranularity::Delta => callback,
+                PartialGranularity::Utterance => utterance_partials(callback),
+            };
+            session.set_partial_callback(dedup_partials(callback));
+        }
         
         let mut stop_rx = self.stop_receiver.take();
         let mut consecutive_send_failures = 0u32;
         const MAX_CONSECUTIVE_FAILURES: u32 = 5;
         
+        // 停顿期间 VAD 会丢弃静音块，长时间无音频时补发静音帧，避免服务端因空闲断开
+        let keepalive_ms = self.asr_config.realtime_keepalive_ms
+            .unwrap_or_else(|| default_keepalive_ms(&self.asr_config.provider));
+        let keepalive = (keepalive_ms > 0).then(|| Duration::from_millis(keepalive_ms));
+        let mut last_sent = tokio::time::Instant::now();
+        let audio_format = self.asr_config.realtime_audio_format;
+        let session_deadline = self.max_session_duration.map(|max| tokio::time::Instant::now() + max);
+        let mut timed_out = false;
+        
         loop {
             tokio::select! {
                 _ = async {
@@ -177,14 +370,43 @@ impl RealtimeTranscriptionTask {
                     break;
                 }
                 
+                _ = async {
+                    match session_deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    log_warn!(
+                        "实时会话已持续 {}ms，超过最长持续时间，强制关闭",
+                        streaming_start.elapsed().as_millis()
+                    );
+                    timed_out = true;
+                    break;
+                }
+                
+                _ = async {
+                    match keepalive {
+                        Some(interval) => tokio::time::sleep_until(last_sent + interval).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                } => {
+                    log_debug!("{}ms 未发送音频，发送保活静音帧", keepalive_ms);
+                    let silence = samples_to_bytes(&[0i16; KEEPALIVE_SILENCE_SAMPLES], audio_format);
+                    if let Err(e) = session.send_chunk(&silence).await {
+                        log_warn!("发送保活静音帧失败: {}", e);
+                    }
+                    last_sent = tokio::time::Instant::now();
+                }
+                
                 chunk = self.chunk_receiver.recv() => {
                     match chunk {
                         Some(audio_chunk) => {
                             chunk_count += 1;
                             total_samples += audio_chunk.samples.len() as u64;
                             
-                            let pcm_bytes = samples_to_bytes(&audio_chunk.samples);
+                            let pcm_bytes = samples_to_bytes(&audio_chunk.samples, audio_format);
                             
+                            last_sent = tokio::time::Instant::now();
                             match session.send_chunk(&pcm_bytes).await {
                                 Ok(()) => {
                                     consecutive_send_failures = 0;
@@ -237,7 +459,20 @@ impl RealtimeTranscriptionTask {
             total_samples as f64 / 16000.0
         );
         
+        let upload_ms = streaming_start.elapsed().as_millis() as u64;
+        let close_start = Instant::now();
+        
         log_info!("关闭 ASR 会话，等待最终结果...");
+        // 超时后仍有音频未送出，已有结果不完整：报告失败，由调用方用完整录音走 HTTP 回退
+        if timed_out {
+            let timeout_ms = self.max_session_duration.map_or(0, |max| max.as_millis() as u64);
+            return RealtimeTaskResult::Failed {
+                error: ASRError::Timeout { timeout_ms },
+                engine_name,
+                chunks_sent: chunk_count,
+                samples_sent: total_samples,
+            };
+        }
         let final_text = match session.close().await {
             Ok(text) => text,
             Err(e) => {
@@ -252,6 +487,22 @@ impl RealtimeTranscriptionTask {
         };
         
         let duration_ms = start_time.elapsed().as_millis() as u64;
+        // 推流阶段与录音同步进行，收尾阶段才是用户感知的等待时间
+        let timings = Timings {
+            connect_ms: Some(connect_ms),
+            upload_ms: Some(upload_ms),
+            inference_ms: Some(close_start.elapsed().as_millis() as u64),
+        };
+        
+        if final_text.trim().is_empty() {
+            log_info!("实时转录完成，耗时 {}ms，未检测到语音", duration_ms);
+            return RealtimeTaskResult::Failed {
+                error: ASRError::NoSpeechDetected,
+                engine_name,
+                chunks_sent: chunk_count,
+                samples_sent: total_samples,
+            };
+        }
         
         log_info!(
             "实时转录完成，耗时 {}ms，结果: {}",
@@ -263,19 +514,305 @@ impl RealtimeTranscriptionTask {
             }
         );
         
-        RealtimeTaskResult::Success(TranscriptionResult::new(
-            final_text,
-            engine_name,
-            false,
-            duration_ms,
-        ))
+        RealtimeTaskResult::Success(
+            TranscriptionResult::new(final_text, engine_name, false, duration_ms)
+                .with_timings(timings),
+        )
+    }
+}
+
+/// 将音频流送入实时会话转录，流结束后返回最终结果
+pub async fn transcribe_stream<S>(
+    asr_config: ASRProviderConfig,
+    stream: S,
+    partial_callback: Option<PartialResultCallback>,
+) -> Result<TranscriptionResult, ASRError>
+where
+    S: Stream<Item = Vec<i16>> + Send + 'static,
+{
+    let (task, _stop_tx) = RealtimeTranscriptionTask::from_stream(asr_config, stream, partial_callback);
+    task.run().await
+}
+
+/// 包装部分结果回调，跳过与上一条完全相同的结果
+fn dedup_partials(callback: PartialResultCallback) -> PartialResultCallback {
+    let last: std::sync::Mutex<Option<PartialTranscription>> = std::sync::Mutex::new(None);
+    Box::new(move |partial| {
+        let mut last = last.lock().unwrap();
+        if last.as_ref() == Some(partial) {
+            return;
+        }
+        *last = Some(partial.clone());
+        drop(last);
+        callback(partial);
+    })
+}
+
+/// 包装部分结果回调，只推送句子结束 (`is_final`) 的结果
+///
+/// 结果文本为截至当前的完整文本，每次推送即为已完成的全部句子
+fn utterance_partials(callback: PartialResultCallback) -> PartialResultCallback {
+    Box::new(move |partial| {
+        if partial.is_final {
+            callback(partial);
+        }
+    })
+}
+
+/// 会话关闭时未收到任何结果 (服务端时序竞争导致，重试通常可恢复)
+pub fn is_transient_empty_result(error: &ASRError) -> bool {
+    matches!(error, ASRError::InternalError(message) if message == NO_RESULT_MESSAGE)
+}
+
+/// 新建会话重发录音器保存的完整音频并等待最终结果 (不推送部分结果)
+///
+/// 任务本身不缓存已发送的音频，重试由持有录音的调用方发起
+pub async fn retry_session(asr_config: &ASRProviderConfig, audio: &AudioData) -> Result<TranscriptionResult, ASRError> {
+    let start_time = Instant::now();
+    let audio = audio
+        .to_target(TARGET_SAMPLE_RATE, 1)
+        .map_err(|e| ASRError::InvalidAudio(e.to_string()))?;
+    let samples = convert_f32_to_i16(&audio.samples);
+
+    let engine = create_engine(asr_config)?;
+    let engine_name = engine.name().to_string();
+    let mut session = engine.create_realtime_session().await?;
+    replay_samples(session.as_mut(), &samples, asr_config.realtime_audio_format).await?;
+    let text = session.close().await?;
+    if text.trim().is_empty() {
+        return Err(ASRError::NoSpeechDetected);
     }
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    Ok(TranscriptionResult::new(text, engine_name, false, duration_ms).with_retried(true))
 }
 
-fn samples_to_bytes(samples: &[i16]) -> Vec<u8> {
-    let mut bytes = Vec::with_capacity(samples.len() * 2);
-    for sample in samples {
-        bytes.extend_from_slice(&sample.to_le_bytes());
+/// 将已录制的音频按固定分块重新发送到会话
+async fn replay_samples(
+    session: &mut dyn RealtimeSession,
+    samples: &[i16],
+    format: RealtimeAudioFormat,
+) -> Result<(), ASRError> {
+    for chunk in samples.chunks(RETRY_CHUNK_SAMPLES) {
+        session.send_chunk(&samples_to_bytes(chunk, format)).await?;
+    }
+    Ok(())
+}
+
+/// 按线上格式打包 PCM 采样
+fn samples_to_bytes(samples: &[i16], format: RealtimeAudioFormat) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * format.bits_per_sample() as usize / 8);
+    for &sample in samples {
+        match format {
+            RealtimeAudioFormat::Pcm16Le => bytes.extend_from_slice(&sample.to_le_bytes()),
+            RealtimeAudioFormat::Pcm16Be => bytes.extend_from_slice(&sample.to_be_bytes()),
+            RealtimeAudioFormat::PcmF32Le => {
+                bytes.extend_from_slice(&(sample as f32 / 32768.0).to_le_bytes())
+            }
+        }
     }
     bytes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::mock::MockSession;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_dedup_partials_skips_identical() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let callback = dedup_partials(Box::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        callback(&PartialTranscription::new("你好".to_string(), false));
+        callback(&PartialTranscription::new("你好".to_string(), false));
+        callback(&PartialTranscription::new("你好".to_string(), true));
+        callback(&PartialTranscription::new("你好世界".to_string(), true));
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_utterance_partials_only_forward_final() {
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        let callback = utterance_partials(Box::new(move |partial| {
+            sink.lock().unwrap().push(partial.text.clone());
+        }));
+
+        callback(&PartialTranscription::new("你".to_string(), false));
+        callback(&PartialTranscription::new("你好".to_string(), true));
+        callback(&PartialTranscription::new("你好世".to_string(), false));
+        callback(&PartialTranscription::new("你好世界".to_string(), true));
+
+        assert_eq!(*received.lock().unwrap(), vec!["你好", "你好世界"]);
+    }
+
+    fn preconnected(config: &ASRProviderConfig, keepalive: Duration) -> PreconnectedSession {
+        PreconnectedSession {
+            asr_config: config.clone(),
+            engine_name: "mock".to_string(),
+            session: Box::new(MockSession::new().emitting_partial("已说的话").stuck()),
+            expires_at: Instant::now() + keepalive,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preconnected_session_expires_after_keepalive() {
+        let config = ASRProviderConfig::qwen(crate::voice::config::ASRMode::Realtime, "key".to_string());
+        let session = preconnected(&config, Duration::from_millis(20));
+        assert!(!session.is_expired());
+        assert!(session.remaining() > Duration::ZERO);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(session.is_expired());
+        assert_eq!(session.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_with_preconnected_discards_stale_session() {
+        let config = ASRProviderConfig::qwen(crate::voice::config::ASRMode::Realtime, "key".to_string());
+        let mut other = config.clone();
+        other.dashscope_api_key = Some("other-key".to_string());
+
+        let task = |session: PreconnectedSession| {
+            let (_chunk_tx, chunk_rx) = mpsc::channel(1);
+            let (task, _stop_tx) = RealtimeTranscriptionTask::new(config.clone(), chunk_rx, None);
+            task.with_preconnected(Some(session)).preconnected.is_some()
+        };
+
+        assert!(task(preconnected(&config, Duration::from_secs(60))));
+        assert!(!task(preconnected(&config, Duration::ZERO)));
+        assert!(!task(preconnected(&other, Duration::from_secs(60))));
+    }
+
+    #[tokio::test]
+    async fn test_max_session_duration_force_closes() {
+        let config = ASRProviderConfig::doubao(
+            crate::voice::config::ASRMode::Realtime,
+            "app".to_string(),
+            "token".to_string(),
+        );
+        let session = PreconnectedSession {
+            asr_config: config.clone(),
+            engine_name: "mock".to_string(),
+            session: Box::new(MockSession::new().emitting_partial("已说的话").stuck()),
+            expires_at: Instant::now() + Duration::from_secs(60),
+        };
+
+        // 音频通道保持打开，也不发送停止信号
+        let (chunk_tx, chunk_rx) = mpsc::channel(8);
+        chunk_tx.send(AudioChunkData { samples: vec![0; 160], timestamp_ms: 0 }).await.unwrap();
+        let (task, _stop_tx) = RealtimeTranscriptionTask::new(config, chunk_rx, Some(Box::new(|_| {})));
+        let result = task
+            .with_preconnected(Some(session))
+            .with_max_session_duration(Some(Duration::from_millis(50)))
+            .run_with_details()
+            .await;
+
+        // 已有部分结果也报告失败，由调用方用完整录音回退
+        assert!(matches!(
+            result,
+            RealtimeTaskResult::Failed { error: ASRError::Timeout { timeout_ms: 50 }, chunks_sent: 1, .. }
+        ));
+        assert!(DEFAULT_MAX_SESSION_SECS >= DEFAULT_MAX_RECORDING_SECS as u64);
+    }
+
+    #[test]
+    fn test_samples_to_bytes_formats() {
+        let samples = [1i16, -16384];
+        assert_eq!(samples_to_bytes(&samples, RealtimeAudioFormat::Pcm16Le), vec![0x01, 0x00, 0x00, 0xC0]);
+        assert_eq!(samples_to_bytes(&samples, RealtimeAudioFormat::Pcm16Be), vec![0x00, 0x01, 0xC0, 0x00]);
+
+        let bytes = samples_to_bytes(&samples, RealtimeAudioFormat::PcmF32Le);
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(f32::from_le_bytes(bytes[4..8].try_into().unwrap()), -0.5);
+    }
+
+    #[tokio::test]
+    async fn test_replay_samples_resends_all_audio() {
+        let chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut session = MockSession::new().with_text("done").recording_chunks(&chunks);
+
+        let samples = vec![0i16; RETRY_CHUNK_SAMPLES * 2 + 100];
+        replay_samples(&mut session, &samples, RealtimeAudioFormat::Pcm16Le).await.unwrap();
+
+        assert_eq!(
+            *chunks.lock().unwrap(),
+            vec![RETRY_CHUNK_SAMPLES * 2, RETRY_CHUNK_SAMPLES * 2, 200]
+        );
+        assert!(is_transient_empty_result(&ASRError::InternalError(NO_RESULT_MESSAGE.to_string())));
+        assert!(!is_transient_empty_result(&ASRError::InternalError("其他错误".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_sends_silence_when_idle() {
+        let mut config = ASRProviderConfig::doubao(
+            crate::voice::config::ASRMode::Realtime,
+            "app".to_string(),
+            "token".to_string(),
+        );
+        config.realtime_keepalive_ms = Some(20);
+
+        let chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let session = PreconnectedSession {
+            asr_config: config.clone(),
+            engine_name: "mock".to_string(),
+            session: Box::new(MockSession::new().with_text("done").recording_chunks(&chunks)),
+            expires_at: Instant::now() + Duration::from_secs(60),
+        };
+
+        // 保持音频通道打开但不发送任何块，模拟说话停顿
+        let (_chunk_tx, chunk_rx) = mpsc::channel(8);
+        let (task, stop_tx) = RealtimeTranscriptionTask::new(config, chunk_rx, None);
+        let handle = tokio::spawn(task.with_preconnected(Some(session)).run_with_details());
+
+        tokio::time::sleep(Duration::from_millis(90)).await;
+        let _ = stop_tx.send(());
+        assert!(handle.await.unwrap().is_success());
+
+        let chunks = chunks.lock().unwrap();
+        assert!(chunks.len() >= 2, "保活帧数量: {}", chunks.len());
+        assert!(chunks.iter().all(|&len| len == KEEPALIVE_SILENCE_SAMPLES * 2));
+    }
+
+    #[tokio::test]
+    async fn test_from_stream_sends_all_chunks() {
+        let config = ASRProviderConfig::doubao(
+            crate::voice::config::ASRMode::Realtime,
+            "app".to_string(),
+            "token".to_string(),
+        );
+
+        let chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let session = PreconnectedSession {
+            asr_config: config.clone(),
+            engine_name: "mock".to_string(),
+            session: Box::new(MockSession::new().with_text("done").recording_chunks(&chunks)),
+            expires_at: Instant::now() + Duration::from_secs(60),
+        };
+
+        let stream = futures_util::stream::iter(vec![vec![0i16; 160], vec![1i16; 320], vec![2i16; 80]]);
+        let statuses = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&statuses);
+        let (task, _stop_tx) = RealtimeTranscriptionTask::from_stream(config, stream, None);
+        let result = task
+            .with_preconnected(Some(session))
+            .with_status_callback(Some(Box::new(move |status| sink.lock().unwrap().push(status.clone()))))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(result.text, "done");
+        assert_eq!(*chunks.lock().unwrap(), vec![320, 640, 160]);
+        assert_eq!(
+            *statuses.lock().unwrap(),
+            vec![SessionStatus::Connected { engine: "mock".to_string(), preconnected: true }]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// 按语句分段转录模块
+// HTTP 模式下将录音在语句间的静音处切开逐段转录，每段完成即推送中间结果，长录音也能渐进显示
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::voice::asr::segments::{prefixed_partial_callback, transcribe_segments};
+use crate::voice::asr::{
+    ASREngine, ASRError, ASRMode, PartialTranscription, RealtimeSession, SharedPartialCallback,
+    Timings,
+};
+use crate::voice::audio::AudioData;
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [segmented] {}", format!($($arg)*));
+    };
+}
+
+tokio::task_local! {
+    /// 当前转录调用中已完成语句的拼接文本 (含分隔符)，拼在当前语句的中间结果之前
+    static PARTIAL_PREFIX: RefCell<String>;
+}
+
+/// 默认语句间最短静音时长 (毫秒)，短于该时长的停顿不切分
+pub const DEFAULT_MIN_SILENCE_MS: u32 = 600;
+
+/// 按语句分段的 HTTP 转录器
+///
+/// 包装 HTTP 引擎：按静音切分为语句后依次转录，跳过纯静音分段，
+/// 每段完成后以 `is_final` 中间结果推送截至当前的完整文本，最终返回拼接结果；
+/// 单段语句超过供应商时长上限时由内层的分段转录引擎继续切分
+pub struct SegmentedHttpTranscriber {
+    inner: Box<dyn ASREngine>,
+    separator: String,
+    min_silence_ms: u32,
+    partial_callback: Option<SharedPartialCallback>,
+    /// 最近一次分段转录各段耗时的合计 (未分段时为空，取内层引擎的耗时)
+    segment_timings: Mutex<Option<Timings>>,
+}
+
+impl SegmentedHttpTranscriber {
+    pub fn new(inner: Box<dyn ASREngine>, separator: String) -> Self {
+        Self {
+            inner,
+            separator,
+            min_silence_ms: DEFAULT_MIN_SILENCE_MS,
+            partial_callback: None,
+            segment_timings: Mutex::new(None),
+        }
+    }
+
+    /// 设置语句间最短静音时长 (毫秒)
+    pub fn with_min_silence_ms(mut self, min_silence_ms: u32) -> Self {
+        self.min_silence_ms = min_silence_ms;
+        self
+    }
+}
+
+#[async_trait]
+impl ASREngine for SegmentedHttpTranscriber {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn supported_modes(&self) -> Vec<ASRMode> {
+        self.inner.supported_modes()
+    }
+
+    async fn transcribe(&self, audio: &AudioData) -> Result<String, ASRError> {
+        *self.segment_timings.lock().unwrap() = None;
+        let segments = audio.split_utterances(self.min_silence_ms);
+        // 未检测到语音时整段交给供应商判断，避免 VAD 阈值误判丢失轻声录音
+        if segments.len() <= 1 {
+            return self.inner.transcribe(audio).await;
+        }
+
+        log_info!(
+            "音频时长 {}ms 按语句切分为 {} 段，语音共 {}ms",
+            audio.duration_ms,
+            segments.len(),
+            segments.iter().map(|segment| segment.duration_ms).sum::<u64>()
+        );
+
+        let on_segment = |joined: &str| {
+            if let Some(callback) = &self.partial_callback {
+                callback(&PartialTranscription::new(joined.to_string(), true));
+            }
+        };
+        let output = transcribe_segments(&*self.inner, &segments, &self.separator, &PARTIAL_PREFIX, on_segment)
+            .await
+            .map_err(|failure| failure.error)?;
+        *self.segment_timings.lock().unwrap() = Some(output.timings);
+
+        Ok(output.text)
+    }
+
+    async fn create_realtime_session(&self) -> Result<Box<dyn RealtimeSession>, ASRError> {
+        self.inner.create_realtime_session().await
+    }
+
+    fn last_timings(&self) -> Timings {
+        self.segment_timings.lock().unwrap().unwrap_or_else(|| self.inner.last_timings())
+    }
+
+    /// 内层引擎的流式中间结果前拼接已完成语句的文本
+    fn set_partial_callback(&mut self, callback: SharedPartialCallback) {
+        self.partial_callback = Some(Arc::clone(&callback));
+        self.inner.set_partial_callback(prefixed_partial_callback(&PARTIAL_PREFIX, callback));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::asr::mock::MockEngine;
+
+    fn utterances(count: usize) -> AudioData {
+        let mut samples = Vec::new();
+        for _ in 0..count {
+            samples.extend(std::iter::repeat_n(0.3f32, 16000));
+            samples.extend(std::iter::repeat_n(0.0f32, 16000));
+        }
+        AudioData::new(samples, 16000, 1)
+    }
+
+    #[tokio::test]
+    async fn test_emits_partial_per_utterance() {
+        let mut transcriber = SegmentedHttpTranscriber::new(
+            Box::new(MockEngine::new("mock").with_text_fn(|_, call| format!("句{}", call))),
+            " ".to_string(),
+        );
+        let partials = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&partials);
+        transcriber.set_partial_callback(Arc::new(move |partial: &PartialTranscription| {
+            sink.lock().unwrap().push(partial.clone());
+        }));
+
+        let text = transcriber.transcribe(&utterances(3)).await.unwrap();
+        assert_eq!(text, "句1 句2 句3");
+        let partials = partials.lock().unwrap();
+        let texts: Vec<&str> = partials.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(texts, vec!["句1", "句1 句2", "句1 句2 句3"]);
+        assert!(partials.iter().all(|p| p.is_final));
+    }
+
+    #[tokio::test]
+    async fn test_silent_or_single_utterance_passes_through() {
+        let transcriber = SegmentedHttpTranscriber::new(
+            Box::new(MockEngine::new("mock").with_text_fn(|_, call| format!("句{}", call))),
+            " ".to_string(),
+        );
+
+        let silent = AudioData::new(vec![0.0f32; 32000], 16000, 1);
+        assert_eq!(transcriber.transcribe(&silent).await.unwrap(), "句1");
+        assert_eq!(transcriber.transcribe(&utterances(1)).await.unwrap(), "句2");
+    }
+}
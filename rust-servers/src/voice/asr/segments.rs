@@ -0,0 +1,93 @@
+// 逐段转录模块
+// 分段转录引擎与按语句分段转录器共用的逐段转录、耗时合计与中间结果前缀拼接
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use tokio::task::LocalKey;
+
+use crate::voice::asr::text::join_transcriptions;
+use crate::voice::asr::{
+    ASREngine, ASRError, PartialTranscription, SharedPartialCallback, Timings, TranscriptionResult,
+};
+use crate::voice::audio::AudioData;
+
+/// 单次转录调用内已完成分段的拼接文本 (含分隔符)
+///
+/// 由调用方以 `tokio::task_local!` 声明；包装层可能嵌套 (按语句分段 → 按时长分段)，
+/// 每层需使用各自的 key
+pub type PartialPrefix = LocalKey<RefCell<String>>;
+
+/// 逐段转录的结果
+pub struct SegmentsOutput {
+    /// 各段文本的拼接结果
+    pub text: String,
+    /// 各段耗时的合计
+    pub timings: Timings,
+}
+
+/// 逐段转录失败的分段
+pub struct SegmentFailure {
+    /// 失败分段的序号 (从 0 开始)
+    pub index: usize,
+    pub error: ASRError,
+}
+
+/// 依次转录各分段并拼接结果，任一段失败即返回
+///
+/// 在本次调用独立的 `prefix` 作用域内执行，每段完成后更新前缀，
+/// 同一引擎上的并发转录互不影响；`on_segment` 在每段完成后收到截至当前的拼接文本
+pub async fn transcribe_segments(
+    inner: &dyn ASREngine,
+    segments: &[AudioData],
+    separator: &str,
+    prefix: &'static PartialPrefix,
+    mut on_segment: impl FnMut(&str) + Send,
+) -> Result<SegmentsOutput, SegmentFailure> {
+    prefix
+        .scope(RefCell::new(String::new()), async {
+            let mut parts = Vec::with_capacity(segments.len());
+            let mut timings = Timings::default();
+            for (index, segment) in segments.iter().enumerate() {
+                let text = inner
+                    .transcribe(segment)
+                    .await
+                    .map_err(|error| SegmentFailure { index, error })?;
+                timings = timings.combine(inner.last_timings());
+                parts.push(TranscriptionResult::new(
+                    text,
+                    inner.name().to_string(),
+                    false,
+                    segment.duration_ms,
+                ));
+
+                let joined = join_transcriptions(&parts, separator);
+                on_segment(&joined);
+                let next = if joined.is_empty() { joined } else { joined + separator };
+                prefix.with(|p| *p.borrow_mut() = next);
+            }
+
+            Ok(SegmentsOutput {
+                text: join_transcriptions(&parts, separator),
+                timings,
+            })
+        })
+        .await
+}
+
+/// 包装中间结果回调：在当前分段的中间结果前拼接已完成分段的文本
+///
+/// 在 [`transcribe_segments`] 作用域外触发时原样转发
+pub fn prefixed_partial_callback(
+    prefix: &'static PartialPrefix,
+    callback: SharedPartialCallback,
+) -> SharedPartialCallback {
+    Arc::new(move |partial: &PartialTranscription| {
+        let prefix = prefix.try_with(|p| p.borrow().clone()).unwrap_or_default();
+        if prefix.is_empty() {
+            callback(partial);
+        } else {
+            callback(&PartialTranscription::new(prefix + &partial.text, partial.is_final));
+        }
+    })
+}
@@ -0,0 +1,277 @@
+// 转录服务模块
+// 持有 ASR 配置，支持运行时切换供应商 / 模式，转录策略在下次转录时按需重建
+
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::voice::asr::{ASRError, FallbackStrategy, SharedPartialCallback, TranscriptionResult};
+use crate::voice::audio::AudioData;
+use crate::voice::config::{ASRConfig, ASRMode, ASRProvider, ASRProviderConfig};
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [asr-service] {}", format!($($arg)*));
+    };
+}
+
+/// 可切换的供应商列表
+const ALL_PROVIDERS: [ASRProvider; 3] = [
+    ASRProvider::Qwen,
+    ASRProvider::Doubao,
+    ASRProvider::SenseVoice,
+];
+
+/// 转录服务
+pub struct TranscriptionService {
+    config: ASRConfig,
+    /// 按当前配置构建的转录策略 (配置变更后置空，下次转录时重建)
+    ///
+    /// 策略持有的引擎保留自适应超时等统计，多次转录间共享
+    strategy: Option<Arc<FallbackStrategy>>,
+    /// 主引擎的流式中间结果回调 (HTTP 流式模式)
+    partial_callback: Option<SharedPartialCallback>,
+}
+
+impl TranscriptionService {
+    pub fn new(config: ASRConfig) -> Self {
+        Self {
+            config,
+            strategy: None,
+            partial_callback: None,
+        }
+    }
+
+    /// 设置主引擎的流式中间结果回调 (仅开启 HTTP 流式的引擎会上报)
+    pub fn with_partial_callback(mut self, callback: Option<SharedPartialCallback>) -> Self {
+        self.partial_callback = callback;
+        self
+    }
+
+    /// 替换配置，与当前配置不同时丢弃已构建的转录策略
+    pub fn set_config(&mut self, config: ASRConfig) {
+        if self.config != config {
+            self.config = config;
+            self.strategy = None;
+        }
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &ASRConfig {
+        &self.config
+    }
+
+    /// 取出当前配置
+    pub fn into_config(self) -> ASRConfig {
+        self.config
+    }
+
+    /// 凭证齐全、可直接切换到的供应商列表
+    pub fn available_providers(&self) -> Vec<ASRProvider> {
+        ALL_PROVIDERS
+            .iter()
+            .filter(|provider| self.resolve_provider(provider).is_ok())
+            .cloned()
+            .collect()
+    }
+
+    /// 切换主引擎供应商
+    ///
+    /// 优先沿用主引擎配置中该供应商的凭证，否则提升同供应商的备用引擎为主引擎；
+    /// 凭证缺失时返回 `ConfigError`，配置保持不变
+    pub fn switch_provider(&mut self, provider: ASRProvider) -> Result<(), ASRError> {
+        if self.config.primary.provider == provider {
+            return Ok(());
+        }
+
+        match self.resolve_provider(&provider)? {
+            Some(index) => {
+                let promoted = self.config.fallbacks.remove(index);
+                let previous = std::mem::replace(&mut self.config.primary, promoted);
+                self.config.fallbacks.insert(index, previous);
+            }
+            None => {
+                self.config.primary = self.primary_with_provider(&provider);
+            }
+        }
+
+        log_info!(
+            "主引擎已切换: provider={}, mode={}",
+            self.config.primary.provider,
+            self.config.primary.mode
+        );
+        self.strategy = None;
+        Ok(())
+    }
+
+    /// 切换主引擎模式
+    pub fn switch_mode(&mut self, mode: ASRMode) -> Result<(), ASRError> {
+        if self.config.primary.mode == mode {
+            return Ok(());
+        }
+
+        let mut candidate = self.config.primary.clone();
+        candidate.mode = mode.clone();
+        candidate
+            .validate()
+            .map_err(|e| ASRError::ConfigError(format!("无法切换到 {} 模式: {}", mode, e)))?;
+
+        log_info!("主引擎模式已切换: {}", mode);
+        self.config.primary = candidate;
+        self.strategy = None;
+        Ok(())
+    }
+
+    /// 按当前配置获取转录策略 (懒加载，配置不变时复用)
+    ///
+    /// 返回的策略可移入后台任务转录，不阻塞之后的配置切换
+    pub fn strategy(&mut self) -> Result<Arc<FallbackStrategy>, ASRError> {
+        if self.strategy.is_none() {
+            self.config
+                .validate()
+                .map_err(|e| ASRError::ConfigError(e.to_string()))?;
+
+            let strategy = FallbackStrategy::from_config(&self.config)?
+                .with_partial_callback(self.partial_callback.clone());
+            let fallback_providers: Vec<String> = self
+                .config
+                .fallbacks
+                .iter()
+                .map(|config| config.provider.to_string())
+                .collect();
+            log_info!(
+                "使用 ASR 引擎: primary={}, fallbacks={:?}, enable_fallback={}",
+                strategy.primary_provider(),
+                fallback_providers,
+                strategy.is_fallback_enabled()
+            );
+            self.strategy = Some(Arc::new(strategy));
+        }
+
+        self.strategy.clone().ok_or(ASRError::NotInitialized)
+    }
+
+    /// 执行转录 (策略按当前配置懒加载)，识别结果为空时返回 `NoSpeechDetected`
+    pub async fn transcribe(
+        &mut self,
+        audio: &AudioData,
+        cancel_token: &CancellationToken,
+    ) -> Result<TranscriptionResult, ASRError> {
+        transcribe_with(&*self.strategy()?, audio, cancel_token).await
+    }
+
+    /// 主引擎配置替换供应商后的候选配置 (SenseVoice 仅支持 HTTP，模式随之调整)
+    fn primary_with_provider(&self, provider: &ASRProvider) -> ASRProviderConfig {
+        let mut candidate = self.config.primary.clone();
+        candidate.provider = provider.clone();
+        if *provider == ASRProvider::SenseVoice {
+            candidate.mode = ASRMode::Http;
+        }
+        candidate
+    }
+
+    /// 确定切换到目标供应商所用的配置
+    ///
+    /// 返回 `None` 表示沿用主引擎配置，`Some(index)` 表示提升对应的备用引擎
+    fn resolve_provider(&self, provider: &ASRProvider) -> Result<Option<usize>, ASRError> {
+        if self.config.primary.provider == *provider {
+            return self
+                .config
+                .primary
+                .validate()
+                .map(|_| None)
+                .map_err(|e| ASRError::ConfigError(e.to_string()));
+        }
+
+        let primary_error = match self.primary_with_provider(provider).validate() {
+            Ok(()) => return Ok(None),
+            Err(e) => e,
+        };
+
+        self.config
+            .fallbacks
+            .iter()
+            .position(|fallback| fallback.provider == *provider && fallback.validate().is_ok())
+            .map(Some)
+            .ok_or_else(|| {
+                ASRError::ConfigError(format!("无法切换到 {}: {}", provider, primary_error))
+            })
+    }
+}
+
+/// 使用已构建的转录策略转录，识别结果为空时返回 `NoSpeechDetected`
+pub async fn transcribe_with(
+    strategy: &FallbackStrategy,
+    audio: &AudioData,
+    cancel_token: &CancellationToken,
+) -> Result<TranscriptionResult, ASRError> {
+    strategy
+        .transcribe_cancellable(audio, cancel_token)
+        .await
+        .and_then(TranscriptionResult::require_speech)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qwen_with_doubao_fallback() -> ASRConfig {
+        ASRConfig::with_fallbacks(
+            ASRProviderConfig::qwen(ASRMode::Realtime, "qwen-key".to_string()),
+            vec![ASRProviderConfig::doubao(
+                ASRMode::Http,
+                "app".to_string(),
+                "token".to_string(),
+            )],
+        )
+    }
+
+    #[test]
+    fn test_switch_provider_promotes_fallback() {
+        let mut service = TranscriptionService::new(qwen_with_doubao_fallback());
+        assert_eq!(
+            service.available_providers(),
+            vec![ASRProvider::Qwen, ASRProvider::Doubao]
+        );
+
+        service.switch_provider(ASRProvider::Doubao).unwrap();
+        assert_eq!(service.config().primary.provider, ASRProvider::Doubao);
+        assert_eq!(service.config().fallbacks[0].provider, ASRProvider::Qwen);
+    }
+
+    #[test]
+    fn test_switch_provider_requires_credentials() {
+        let mut service = TranscriptionService::new(qwen_with_doubao_fallback());
+        let err = service.switch_provider(ASRProvider::SenseVoice).unwrap_err();
+
+        assert!(matches!(err, ASRError::ConfigError(ref msg) if msg.contains("siliconflow_api_key")));
+        assert_eq!(service.config().primary.provider, ASRProvider::Qwen);
+    }
+
+    #[test]
+    fn test_switch_mode_validates_provider_support() {
+        let mut config = qwen_with_doubao_fallback();
+        config.primary.siliconflow_api_key = Some("sf-key".to_string());
+        let mut service = TranscriptionService::new(config);
+
+        service.switch_provider(ASRProvider::SenseVoice).unwrap();
+        assert_eq!(service.config().primary.mode, ASRMode::Http);
+        assert!(service.switch_mode(ASRMode::Realtime).is_err());
+
+        service.switch_provider(ASRProvider::Qwen).unwrap();
+        service.switch_mode(ASRMode::Realtime).unwrap();
+        assert_eq!(service.config().primary.mode, ASRMode::Realtime);
+    }
+
+    #[test]
+    fn test_strategy_reused_until_config_changes() {
+        let mut service = TranscriptionService::new(qwen_with_doubao_fallback());
+        let first = service.strategy().unwrap();
+        assert!(Arc::ptr_eq(&first, &service.strategy().unwrap()));
+
+        service.set_config(qwen_with_doubao_fallback());
+        assert!(Arc::ptr_eq(&first, &service.strategy().unwrap()));
+
+        service.switch_provider(ASRProvider::Doubao).unwrap();
+        assert!(!Arc::ptr_eq(&first, &service.strategy().unwrap()));
+    }
+}
@@ -0,0 +1,135 @@
+// 引擎统计模块
+// 按供应商记录最近的请求耗时，用于自适应请求超时
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::voice::config::ASRProvider;
+
+/// 参与统计的最近请求数
+const LATENCY_WINDOW: usize = 50;
+
+/// 开始自适应前至少需要的样本数 (样本不足时使用固定超时)
+const MIN_LATENCY_SAMPLES: usize = 5;
+
+/// 自适应超时在 p95 耗时基础上的余量 (百分比)
+const ADAPTIVE_TIMEOUT_MARGIN_PERCENT: u64 = 50;
+
+/// 进程内共享的供应商统计 (引擎每次转录重新创建，统计需跨引擎保留)
+static ENGINE_STATS: OnceLock<Mutex<HashMap<ASRProvider, Arc<EngineStats>>>> = OnceLock::new();
+
+/// 获取供应商对应的共享统计
+pub fn engine_stats(provider: &ASRProvider) -> Arc<EngineStats> {
+    let mut stats = ENGINE_STATS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    stats
+        .entry(provider.clone())
+        .or_insert_with(|| Arc::new(EngineStats::default()))
+        .clone()
+}
+
+/// 单个供应商的请求统计
+#[derive(Debug, Default)]
+pub struct EngineStats {
+    /// 最近成功请求的耗时 (毫秒)，按完成顺序排列
+    latencies_ms: Mutex<VecDeque<u64>>,
+}
+
+impl EngineStats {
+    /// 记录一次成功请求的耗时
+    pub fn record_latency(&self, latency_ms: u64) {
+        let mut latencies = self.latencies_ms.lock().unwrap();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency_ms);
+    }
+
+    /// 最近请求耗时的 p95 (样本不足时为空)
+    pub fn p95_latency_ms(&self) -> Option<u64> {
+        let mut latencies: Vec<u64> = self.latencies_ms.lock().unwrap().iter().copied().collect();
+        if latencies.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        latencies.sort_unstable();
+        let index = (latencies.len() * 95).div_ceil(100) - 1;
+        Some(latencies[index])
+    }
+
+    /// 按最近耗时计算请求超时：p95 加余量，限制在 [min_ms, max_ms] 内；样本不足时返回 min_ms
+    pub fn adaptive_timeout_ms(&self, min_ms: u64, max_ms: u64) -> u64 {
+        let Some(p95) = self.p95_latency_ms() else {
+            return min_ms;
+        };
+        let timeout = p95.saturating_mul(100 + ADAPTIVE_TIMEOUT_MARGIN_PERCENT) / 100;
+        timeout.clamp(min_ms, max_ms.max(min_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_timeout_follows_p95_within_bounds() {
+        let stats = EngineStats::default();
+        for _ in 0..MIN_LATENCY_SAMPLES - 1 {
+            stats.record_latency(8000);
+        }
+        // 样本不足时使用固定超时
+        assert_eq!(stats.adaptive_timeout_ms(6000, 30000), 6000);
+
+        stats.record_latency(8000);
+        assert_eq!(stats.p95_latency_ms(), Some(8000));
+        assert_eq!(stats.adaptive_timeout_ms(6000, 30000), 12000);
+        assert_eq!(stats.adaptive_timeout_ms(6000, 10000), 10000);
+
+        // 只保留最近的样本，供应商变快后超时回落到下限
+        for _ in 0..LATENCY_WINDOW {
+            stats.record_latency(1000);
+        }
+        assert_eq!(stats.adaptive_timeout_ms(6000, 30000), 6000);
+    }
+
+    #[test]
+    fn test_adaptive_timeout_rises_when_every_attempt_times_out() {
+        use crate::voice::asr::{ASRError, AdaptiveTimeout, RetryConfig};
+
+        let retry_config = RetryConfig {
+            adaptive_timeout: Some(AdaptiveTimeout {
+                stats: Arc::new(EngineStats::default()),
+                max_timeout_ms: 30000,
+            }),
+            ..RetryConfig::default()
+        };
+
+        // 供应商始终慢于当前超时：每次请求都以当次超时时长失败
+        let mut timeouts = Vec::new();
+        for _ in 0..30 {
+            let timeout_ms = retry_config.effective_request_timeout_ms();
+            timeouts.push(timeout_ms);
+            let result: Result<String, ASRError> = Err(ASRError::Timeout { timeout_ms });
+            retry_config.record_attempt(std::time::Duration::from_millis(timeout_ms), &result);
+        }
+
+        assert_eq!(timeouts[0], 6000);
+        assert!(timeouts.windows(2).all(|w| w[1] >= w[0]));
+        assert_eq!(*timeouts.last().unwrap(), 30000);
+
+        // 非超时错误不计入
+        let retry_config = RetryConfig {
+            adaptive_timeout: Some(AdaptiveTimeout {
+                stats: Arc::new(EngineStats::default()),
+                max_timeout_ms: 30000,
+            }),
+            ..RetryConfig::default()
+        };
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            let result: Result<String, ASRError> = Err(ASRError::NetworkError("断开".to_string()));
+            retry_config.record_attempt(std::time::Duration::from_millis(20000), &result);
+        }
+        assert_eq!(retry_config.effective_request_timeout_ms(), 6000);
+    }
+}
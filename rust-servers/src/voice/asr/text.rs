@@ -0,0 +1,277 @@
+// 转录文本后处理模块
+// 各 ASR 引擎共享的标点处理函数
+
+use crate::voice::asr::TranscriptionResult;
+use crate::voice::config::PunctuationMode;
+
+/// 默认识别语言
+pub const DEFAULT_LANGUAGE: &str = "zh";
+
+/// 中日韩语言使用的标点集合 (全角 + 半角混合)
+const CJK_PUNCTUATION: &[char] = &[
+    '。', '，', '！', '？', '、', '；', '：', '"', '“', '”',
+    '.', ',', '!', '?', ';', ':', '\'',
+    '（', '）', '(', ')', '【', '】', '[', ']',
+    '《', '》', '<', '>', '—', '…', '·',
+    '\u{2018}', '\u{2019}',
+];
+
+/// 拉丁语系的句读标点 (不含撇号和连字符，避免破坏 "don't"、"well-known" 等单词)
+const LATIN_PUNCTUATION: &[char] = &[
+    '.', ',', '!', '?', ';', ':', '"', '“', '”',
+    '(', ')', '[', ']', '<', '>', '…',
+];
+
+/// 单词内部允许保留的标点 (撇号、连字符)
+const INTRA_WORD_MARKS: &[char] = &['\'', '\u{2019}', '-'];
+
+/// 判断语言是否使用 CJK 标点集合
+///
+/// 未配置语言时按默认语言 (中文) 处理
+fn uses_cjk_punctuation(language: Option<&str>) -> bool {
+    let language = language.unwrap_or(DEFAULT_LANGUAGE).to_ascii_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or("");
+    matches!(primary, "zh" | "ja" | "ko" | "yue" | "auto" | "")
+}
+
+/// 获取语言对应的标点集合
+fn punctuation_set(language: Option<&str>) -> &'static [char] {
+    if uses_cjk_punctuation(language) {
+        CJK_PUNCTUATION
+    } else {
+        LATIN_PUNCTUATION
+    }
+}
+
+/// 去除末尾的句读标点
+///
+/// 仅移除末尾的句子标点；拉丁语系下保留撇号和连字符 (如 "students'")
+pub fn strip_trailing_punctuation(text: &mut String, language: Option<&str>) {
+    let punctuation = punctuation_set(language);
+
+    while let Some(c) = text.chars().last() {
+        if punctuation.contains(&c) {
+            text.pop();
+        } else {
+            break;
+        }
+    }
+}
+
+/// 去除文本中的所有标点
+///
+/// 拉丁语系下位于两个字母/数字之间的撇号和连字符视为单词的一部分而保留
+pub fn strip_punctuation(text: &str, language: Option<&str>) -> String {
+    let punctuation = punctuation_set(language);
+    let keep_intra_word = !uses_cjk_punctuation(language);
+    let chars: Vec<char> = text.chars().collect();
+
+    let mut result = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if keep_intra_word && INTRA_WORD_MARKS.contains(&c) {
+            let prev_is_word = i > 0 && chars[i - 1].is_alphanumeric();
+            let next_is_word = chars.get(i + 1).is_some_and(|n| n.is_alphanumeric());
+            if prev_is_word && next_is_word {
+                result.push(c);
+            }
+            continue;
+        }
+        if !punctuation.contains(&c) {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// 标点宽度风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunctuationWidth {
+    /// 半角
+    Half,
+    /// 全角
+    Full,
+}
+
+/// 全角与半角标点对照表
+const WIDTH_PAIRS: &[(char, char)] = &[
+    ('，', ','), ('。', '.'), ('！', '!'), ('？', '?'),
+    ('；', ';'), ('：', ':'), ('（', '('), ('）', ')'),
+    ('【', '['), ('】', ']'), ('～', '~'), ('\u{3000}', ' '),
+];
+
+/// 统一标点宽度并合并连续空格，不删除任何标点
+///
+/// 半角转全角时，位于两个 ASCII 字母/数字之间的 `.`、`,`、`:` (如 "3.14"、"10:30")
+/// 以及撇号保持不变
+pub fn normalize_punctuation(text: &str, width: PunctuationWidth) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut open_quote = true;
+
+    for (i, &c) in chars.iter().enumerate() {
+        let converted = match width {
+            PunctuationWidth::Half => match c {
+                '“' | '”' => '"',
+                '‘' | '’' => '\'',
+                '、' => ',',
+                _ => WIDTH_PAIRS
+                    .iter()
+                    .find(|(full, _)| *full == c)
+                    .map(|(_, half)| *half)
+                    .unwrap_or(c),
+            },
+            PunctuationWidth::Full => {
+                let between_ascii_words = i > 0
+                    && chars[i - 1].is_ascii_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|n| n.is_ascii_alphanumeric());
+                match c {
+                    '"' => {
+                        let quote = if open_quote { '“' } else { '”' };
+                        open_quote = !open_quote;
+                        quote
+                    }
+                    '.' | ',' | ':' if between_ascii_words => c,
+                    ' ' => ' ',
+                    _ => WIDTH_PAIRS
+                        .iter()
+                        .find(|(_, half)| *half == c)
+                        .map(|(full, _)| *full)
+                        .unwrap_or(c),
+                }
+            }
+        };
+
+        if converted == ' ' && result.ends_with(' ') {
+            continue;
+        }
+        result.push(converted);
+    }
+
+    result
+}
+
+/// 按标点模式处理转录文本
+///
+/// `Strip` 模式下 `strip_all` 决定去除全部标点还是仅去除末尾标点
+pub fn apply_punctuation_mode(
+    text: &str,
+    mode: PunctuationMode,
+    language: Option<&str>,
+    strip_all: bool,
+) -> String {
+    match mode {
+        PunctuationMode::Keep => text.to_string(),
+        PunctuationMode::Strip if strip_all => strip_punctuation(text, language),
+        PunctuationMode::Strip => {
+            let mut text = text.to_string();
+            strip_trailing_punctuation(&mut text, language);
+            text
+        }
+        PunctuationMode::NormalizeHalf => normalize_punctuation(text, PunctuationWidth::Half),
+        PunctuationMode::NormalizeFull => normalize_punctuation(text, PunctuationWidth::Full),
+    }
+}
+
+/// 获取语言对应的默认转录拼接分隔符
+///
+/// 中日韩语言不加空格，其余语言使用单个空格
+pub fn default_separator(language: Option<&str>) -> &'static str {
+    if uses_cjk_punctuation(language) {
+        ""
+    } else {
+        " "
+    }
+}
+
+/// 拼接多段转录结果
+///
+/// 各段首尾空白会被去除，空结果直接跳过
+pub fn join_transcriptions(parts: &[TranscriptionResult], sep: &str) -> String {
+    parts
+        .iter()
+        .map(|part| part.text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_trailing_chinese() {
+        let mut text = "你好，世界。".to_string();
+        strip_trailing_punctuation(&mut text, Some("zh"));
+        assert_eq!(text, "你好，世界");
+
+        let mut text = "你好！？".to_string();
+        strip_trailing_punctuation(&mut text, None);
+        assert_eq!(text, "你好");
+    }
+
+    #[test]
+    fn test_strip_trailing_english_keeps_apostrophe() {
+        let mut text = "The students' books.".to_string();
+        strip_trailing_punctuation(&mut text, Some("en"));
+        assert_eq!(text, "The students' books");
+
+        let mut text = "I met the students'".to_string();
+        strip_trailing_punctuation(&mut text, Some("en-US"));
+        assert_eq!(text, "I met the students'");
+    }
+
+    #[test]
+    fn test_strip_punctuation_english_keeps_intra_word_marks() {
+        let text = "Don't stop, it's a well-known fact - really!";
+        assert_eq!(
+            strip_punctuation(text, Some("en")),
+            "Don't stop it's a well-known fact  really"
+        );
+    }
+
+    #[test]
+    fn test_strip_punctuation_chinese() {
+        assert_eq!(strip_punctuation("“你好”，世界！", Some("zh")), "你好世界");
+    }
+
+    #[test]
+    fn test_normalize_to_half_width() {
+        assert_eq!(
+            normalize_punctuation("你好，世界！  “测试”。", PunctuationWidth::Half),
+            "你好,世界! \"测试\"."
+        );
+    }
+
+    #[test]
+    fn test_normalize_to_full_width_keeps_numbers() {
+        assert_eq!(
+            normalize_punctuation("你好, 版本 3.14 在 10:30 发布!", PunctuationWidth::Full),
+            "你好， 版本 3.14 在 10:30 发布！"
+        );
+        assert_eq!(
+            normalize_punctuation("他说\"好\"", PunctuationWidth::Full),
+            "他说“好”"
+        );
+    }
+
+    #[test]
+    fn test_apply_punctuation_mode() {
+        assert_eq!(apply_punctuation_mode("你好，世界。", PunctuationMode::Keep, None, false), "你好，世界。");
+        assert_eq!(apply_punctuation_mode("你好，世界。", PunctuationMode::Strip, None, false), "你好，世界");
+        assert_eq!(apply_punctuation_mode("你好，世界。", PunctuationMode::Strip, None, true), "你好世界");
+        assert_eq!(apply_punctuation_mode("你好，世界。", PunctuationMode::NormalizeHalf, None, false), "你好,世界.");
+    }
+
+    #[test]
+    fn test_join_transcriptions_with_separator() {
+        let parts: Vec<TranscriptionResult> = ["你好。", "  ", " 世界 "]
+            .iter()
+            .map(|text| TranscriptionResult::new(text.to_string(), "qwen".to_string(), false, 0))
+            .collect();
+        assert_eq!(join_transcriptions(&parts, default_separator(Some("zh"))), "你好。世界");
+        assert_eq!(join_transcriptions(&parts, "\n"), "你好。\n世界");
+        assert_eq!(default_separator(Some("en-US")), " ");
+        assert_eq!(join_transcriptions(&[], " "), "");
+    }
+}
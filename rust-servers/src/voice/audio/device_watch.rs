@@ -0,0 +1,199 @@
+// 默认录音设备切换跟随
+// cpal 不提供设备变化事件，后台线程轮询系统默认输入设备，切换后在新设备上继续采集
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [device-watch] {}", format!($($arg)*));
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {{
+        eprintln!("[WARN] [device-watch] {}", format!($($arg)*))
+    }};
+}
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use cpal::traits::StreamTrait;
+use cpal::Stream;
+
+use super::recorder::{build_f32_input_stream, to_mono, RecordingError, StreamingResampler};
+use super::{default_input_device_name, invalidate_input_device_cache, resolve_input_device};
+
+/// 轮询默认输入设备的间隔 (毫秒)
+const DEVICE_POLL_INTERVAL_MS: u64 = 1000;
+
+/// 检查停止标志的间隔 (毫秒)，决定停止录音时等待监听线程退出的最长时间
+const STOP_CHECK_INTERVAL_MS: u64 = 100;
+
+/// 已采集样本的接收方 (样本已转换为录音开始时设备的采样率与声道数)
+pub(crate) type CaptureSink = Arc<dyn Fn(&[f32]) + Send + Sync>;
+
+/// 设备切换后采集数据的去向
+#[derive(Clone)]
+pub(crate) struct SwitchTarget {
+    /// 录音缓冲的采样率 (录音开始时设备的采样率)
+    pub sample_rate: u32,
+    /// 录音缓冲的声道数
+    pub channels: u16,
+    /// 当前有效的音频流代次，原设备为 0，每次切换加一，旧代次的回调丢弃数据
+    pub generation: Arc<AtomicU32>,
+    /// 录音流出错标志，切换成功后清除
+    pub device_lost: Arc<Mutex<bool>>,
+    pub sink: CaptureSink,
+}
+
+/// 将新设备的样本转换为录音缓冲的格式 (下混、重采样、复制声道)
+pub(crate) struct FormatAdapter {
+    from_channels: u16,
+    to_channels: u16,
+    resampler: StreamingResampler,
+}
+
+impl FormatAdapter {
+    pub fn new(from_rate: u32, from_channels: u16, to_rate: u32, to_channels: u16) -> Self {
+        Self {
+            from_channels,
+            to_channels,
+            resampler: StreamingResampler::new(from_rate, to_rate),
+        }
+    }
+
+    pub fn process(&mut self, data: &[f32]) -> Vec<f32> {
+        let mono = self.resampler.process(&to_mono(data, self.from_channels));
+        if self.to_channels == 1 {
+            mono
+        } else {
+            mono.iter()
+                .flat_map(|&s| std::iter::repeat_n(s, self.to_channels as usize))
+                .collect()
+        }
+    }
+}
+
+/// 默认输入设备监听器
+///
+/// 录音期间在后台线程轮询系统默认输入设备，名称变化时在新设备上打开音频流，
+/// 采集数据转换为原格式后写入同一录音缓冲；新音频流由监听线程持有，释放监听器时一并关闭
+pub(crate) struct DeviceWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    /// 启动监听 (当前音频后端无法查询默认设备名称时返回 None)
+    pub fn spawn(target: SwitchTarget) -> Option<Self> {
+        let Some(initial) = default_input_device_name() else {
+            log_warn!("当前音频后端无法查询默认输入设备，不跟随设备切换");
+            return None;
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = std::thread::Builder::new()
+            .name("device-watch".to_string())
+            .spawn(move || watch_default_device(initial, target, thread_stop))
+            .map_err(|e| log_warn!("无法启动设备监听线程: {}", e))
+            .ok()?;
+
+        Some(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn watch_default_device(mut current: String, target: SwitchTarget, stop: Arc<AtomicBool>) {
+    // 切换后的音频流只在本线程内创建和释放
+    let mut _stream: Option<Stream> = None;
+    let mut last_poll = Instant::now();
+
+    while !stop.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(STOP_CHECK_INTERVAL_MS));
+        if last_poll.elapsed() < Duration::from_millis(DEVICE_POLL_INTERVAL_MS) {
+            continue;
+        }
+        last_poll = Instant::now();
+
+        let Some(name) = default_input_device_name() else {
+            continue;
+        };
+        if name == current {
+            continue;
+        }
+
+        log_info!("默认输入设备已切换: {} -> {}", current, name);
+        // 无论是否切换成功都记下新名称，避免打开失败时每次轮询重复尝试
+        current = name;
+        invalidate_input_device_cache();
+        match open_switched_stream(&target) {
+            Ok(stream) => {
+                _stream = Some(stream);
+                *target.device_lost.lock().unwrap() = false;
+                log_info!("已在新设备上继续录音: {}", current);
+            }
+            Err(e) => log_warn!("无法在新设备上继续录音: {}", e),
+        }
+    }
+}
+
+/// 在当前默认输入设备上打开音频流，启动成功后使之前的音频流失效 (失败时原音频流不受影响)
+fn open_switched_stream(target: &SwitchTarget) -> Result<Stream, RecordingError> {
+    let (device, supported_config) = resolve_input_device(None)?;
+    let config = supported_config.config();
+    let mut adapter = FormatAdapter::new(
+        config.sample_rate.0,
+        config.channels,
+        target.sample_rate,
+        target.channels,
+    );
+
+    let generation = target.generation.load(Ordering::SeqCst) + 1;
+    let current_generation = Arc::clone(&target.generation);
+    let sink = Arc::clone(&target.sink);
+    let on_data = move |data: &[f32]| {
+        if current_generation.load(Ordering::SeqCst) == generation {
+            sink(&adapter.process(data));
+        }
+    };
+    let err_fn = |err| log_warn!("切换后的录音流错误: {}", err);
+
+    let stream = build_f32_input_stream(&device, &supported_config, on_data, err_fn)?;
+    stream.play().map_err(|e| RecordingError::DeviceError(e.to_string()))?;
+    target.generation.store(generation, Ordering::SeqCst);
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_adapter_converts_to_buffer_format() {
+        // 新设备 48kHz 立体声，录音缓冲 16kHz 立体声
+        let mut adapter = FormatAdapter::new(48000, 2, 16000, 2);
+        let data: Vec<f32> = (0..4800).flat_map(|_| [0.2f32, 0.4]).collect();
+        let converted = adapter.process(&data);
+
+        assert_eq!(converted.len() % 2, 0);
+        assert!((converted.len() as i64 - 3200).abs() <= 2);
+        assert!(converted.iter().all(|&s| (s - 0.3).abs() < 1e-4));
+
+        // 格式一致时原样通过
+        let mut passthrough = FormatAdapter::new(16000, 1, 16000, 1);
+        assert_eq!(passthrough.process(&[0.1, 0.2, 0.3]), vec![0.1, 0.2, 0.3]);
+    }
+}
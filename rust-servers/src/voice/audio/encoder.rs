@@ -2,7 +2,7 @@
 // 使用 hound 实现 WAV 编码
 
 use hound::{SampleFormat, WavSpec, WavWriter};
-use std::io::Cursor;
+use std::io::{Cursor, Seek, Write};
 use thiserror::Error;
 
 use super::recorder::TARGET_SAMPLE_RATE;
@@ -19,6 +19,18 @@ pub enum EncodingError {
 
     #[error("无效的音频数据")]
     InvalidAudioData,
+
+    /// 写入样本中途失败，已写入的部分不完整，不会返回给调用方
+    #[error("写入第 {written}/{total} 个样本时失败: {reason}")]
+    SampleWriteFailed {
+        written: usize,
+        total: usize,
+        reason: String,
+    },
+
+    /// 样本已全部写入，但更新 WAV 头 (文件长度、data 块长度) 失败
+    #[error("WAV 头更新失败: {0}")]
+    FinalizeFailed(String),
 }
 
 impl From<hound::Error> for EncodingError {
@@ -33,11 +45,58 @@ impl From<std::io::Error> for EncodingError {
     }
 }
 
+/// LIST/INFO 元数据字段：软件名称
+pub const INFO_SOFTWARE: [u8; 4] = *b"ISFT";
+/// LIST/INFO 元数据字段：创建日期
+pub const INFO_CREATION_DATE: [u8; 4] = *b"ICRD";
+/// LIST/INFO 元数据字段：注释 (如使用的 ASR 引擎)
+pub const INFO_COMMENT: [u8; 4] = *b"ICMT";
+
+/// 削波样本占比超过该阈值时输出告警 (0.1%)
+const CLIPPING_WARN_RATIO: f64 = 0.001;
+
+/// 软限幅的起始幅值，低于该幅值的样本保持线性
+const SOFT_LIMIT_KNEE: f32 = 0.8;
+
+/// WAV 样本格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WavSampleFormat {
+    /// 整数 PCM (位深由编码器的 `bits_per_sample` 决定，ASR 上传使用 16 位)
+    #[default]
+    Int,
+    /// 32 位浮点 (无损保留采集到的 f32 数据，用于存档)
+    Float32,
+}
+
+/// 编码统计
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodeStats {
+    /// 编码的样本总数
+    pub total_samples: usize,
+    /// 超出 i16 范围被截断的样本数 (软限幅时为超出满幅、被压缩的样本数)
+    pub clipped_samples: usize,
+}
+
+impl EncodeStats {
+    /// 被截断样本的占比
+    pub fn clipped_ratio(&self) -> f64 {
+        if self.total_samples == 0 {
+            0.0
+        } else {
+            self.clipped_samples as f64 / self.total_samples as f64
+        }
+    }
+}
+
 /// WAV 编码器
 pub struct WavEncoder {
     sample_rate: u32,
     channels: u16,
     bits_per_sample: u16,
+    sample_format: WavSampleFormat,
+    soft_limit: bool,
+    /// LIST/INFO 元数据 (为空时输出最简 WAV)
+    metadata: Vec<([u8; 4], String)>,
 }
 
 impl WavEncoder {
@@ -47,9 +106,32 @@ impl WavEncoder {
             sample_rate,
             channels,
             bits_per_sample,
+            sample_format: WavSampleFormat::default(),
+            soft_limit: false,
+            metadata: Vec::new(),
         }
     }
 
+    /// 添加 LIST/INFO 元数据字段 (如 `INFO_SOFTWARE`)，写在 data 块之后
+    pub fn with_info(mut self, id: [u8; 4], value: impl Into<String>) -> Self {
+        self.metadata.push((id, value.into()));
+        self
+    }
+
+    /// 设置样本格式 (`Float32` 时忽略 `bits_per_sample`，固定写入 32 位浮点)
+    pub fn with_sample_format(mut self, sample_format: WavSampleFormat) -> Self {
+        self.sample_format = sample_format;
+        self
+    }
+
+    /// 设置整数量化前是否软限幅 (默认直接截断)
+    ///
+    /// 开启后接近满幅的峰值按 tanh 曲线平滑压缩到满幅以内，削波失真比硬截断轻
+    pub fn with_soft_limit(mut self, enabled: bool) -> Self {
+        self.soft_limit = enabled;
+        self
+    }
+
     /// 创建默认配置的 WAV 编码器 (16kHz, 单声道, 16位)
     pub fn default_config() -> Self {
         Self::new(TARGET_SAMPLE_RATE, 1, 16)
@@ -57,82 +139,178 @@ impl WavEncoder {
 
     /// 将 AudioData 编码为 WAV 格式字节数组
     pub fn encode(&self, audio: &AudioData) -> Result<Vec<u8>, EncodingError> {
-        if audio.is_empty() {
-            return Err(EncodingError::InvalidAudioData);
-        }
-
-        let spec = WavSpec {
-            channels: self.channels,
-            sample_rate: self.sample_rate,
-            bits_per_sample: self.bits_per_sample,
-            sample_format: SampleFormat::Int,
-        };
-
-        let mut cursor = Cursor::new(Vec::new());
-        {
-            let mut writer = WavWriter::new(&mut cursor, spec)?;
-            for &sample in audio.samples.iter() {
-                let amplitude =
-                    (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-                writer.write_sample(amplitude)?;
-            }
-            writer.finalize()?;
-        }
+        self.encode_with_stats(audio).map(|(wav, _)| wav)
+    }
 
-        Ok(cursor.into_inner())
+    /// 将 AudioData 编码为 WAV，同时返回削波统计
+    pub fn encode_with_stats(&self, audio: &AudioData) -> Result<(Vec<u8>, EncodeStats), EncodingError> {
+        self.encode_samples_with_stats(&audio.samples)
     }
 
     /// 将 f32 采样数组编码为 WAV 格式字节数组
     pub fn encode_samples(&self, samples: &[f32]) -> Result<Vec<u8>, EncodingError> {
+        self.encode_samples_with_stats(samples).map(|(wav, _)| wav)
+    }
+
+    /// 将 f32 采样数组编码为 WAV，同时返回削波统计
+    ///
+    /// 整数格式下超出 i16 范围的样本会被截断，截断比例超过阈值时输出告警 (录音削波会明显降低识别准确率)；
+    /// 浮点格式原样写入，不截断
+    pub fn encode_samples_with_stats(
+        &self,
+        samples: &[f32],
+    ) -> Result<(Vec<u8>, EncodeStats), EncodingError> {
         if samples.is_empty() {
             return Err(EncodingError::InvalidAudioData);
         }
 
-        let spec = WavSpec {
-            channels: self.channels,
-            sample_rate: self.sample_rate,
-            bits_per_sample: self.bits_per_sample,
-            sample_format: SampleFormat::Int,
-        };
+        if self.sample_format == WavSampleFormat::Float32 {
+            let wav = self.encode_f32_samples(samples)?;
+            let stats = EncodeStats {
+                total_samples: samples.len(),
+                clipped_samples: 0,
+            };
+            return Ok((wav, stats));
+        }
 
-        let mut cursor = Cursor::new(Vec::new());
-        {
-            let mut writer = WavWriter::new(&mut cursor, spec)?;
-            for &sample in samples.iter() {
-                let amplitude =
-                    (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-                writer.write_sample(amplitude)?;
+        let mut stats = EncodeStats {
+            total_samples: samples.len(),
+            clipped_samples: 0,
+        };
+        let wav = self.write_wav(samples.iter().map(|&sample| {
+            let scaled = sample * i16::MAX as f32;
+            if scaled > i16::MAX as f32 || scaled < i16::MIN as f32 {
+                stats.clipped_samples += 1;
             }
-            writer.finalize()?;
+            let scaled = if self.soft_limit {
+                soft_limit(sample) * i16::MAX as f32
+            } else {
+                scaled
+            };
+            scaled.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        }))?;
+
+        if stats.clipped_ratio() > CLIPPING_WARN_RATIO {
+            eprintln!(
+                "[WARN] [encoder] 音频削波: {}/{} 样本被截断 ({:.2}%)，请降低输入音量",
+                stats.clipped_samples,
+                stats.total_samples,
+                stats.clipped_ratio() * 100.0
+            );
         }
 
-        Ok(cursor.into_inner())
+        Ok((wav, stats))
     }
 
-    /// 将 i16 采样数组编码为 WAV 格式字节数组
+    /// 将 i16 采样数组编码为 WAV 格式字节数组 (浮点格式下先换算为 -1.0 到 1.0)
     pub fn encode_i16_samples(&self, samples: &[i16]) -> Result<Vec<u8>, EncodingError> {
         if samples.is_empty() {
             return Err(EncodingError::InvalidAudioData);
         }
 
-        let spec = WavSpec {
-            channels: self.channels,
-            sample_rate: self.sample_rate,
-            bits_per_sample: self.bits_per_sample,
-            sample_format: SampleFormat::Int,
-        };
+        if self.sample_format == WavSampleFormat::Float32 {
+            let samples: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
+            return self.encode_f32_samples(&samples);
+        }
+
+        self.write_wav(samples.iter().copied())
+    }
 
+    /// 以 32 位浮点写入样本
+    fn encode_f32_samples(&self, samples: &[f32]) -> Result<Vec<u8>, EncodingError> {
+        self.write_wav(samples.iter().copied())
+    }
+
+    /// 写入样本并追加元数据块，任一步失败都不返回写了一半的数据
+    fn write_wav<S: hound::Sample>(
+        &self,
+        samples: impl ExactSizeIterator<Item = S>,
+    ) -> Result<Vec<u8>, EncodingError> {
         let mut cursor = Cursor::new(Vec::new());
-        {
-            let mut writer = WavWriter::new(&mut cursor, spec)?;
-            for &sample in samples.iter() {
-                writer.write_sample(sample)?;
+        write_samples(&mut cursor, self.spec(), samples)?;
+        Ok(self.append_info_chunk(cursor.into_inner()))
+    }
+
+    fn spec(&self) -> WavSpec {
+        match self.sample_format {
+            WavSampleFormat::Int => WavSpec {
+                channels: self.channels,
+                sample_rate: self.sample_rate,
+                bits_per_sample: self.bits_per_sample,
+                sample_format: SampleFormat::Int,
+            },
+            WavSampleFormat::Float32 => WavSpec {
+                channels: self.channels,
+                sample_rate: self.sample_rate,
+                bits_per_sample: 32,
+                sample_format: SampleFormat::Float,
+            },
+        }
+    }
+
+    /// 追加 LIST/INFO 块并修正 RIFF 长度
+    ///
+    /// 解码器会跳过未知块，因此附加在 data 块之后不影响读取
+    fn append_info_chunk(&self, mut wav: Vec<u8>) -> Vec<u8> {
+        if self.metadata.is_empty() {
+            return wav;
+        }
+
+        let mut info = Vec::new();
+        info.extend_from_slice(b"INFO");
+        for (id, value) in &self.metadata {
+            let mut data = value.as_bytes().to_vec();
+            data.push(0);
+            info.extend_from_slice(id);
+            info.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            info.extend_from_slice(&data);
+            // RIFF 子块按偶数字节对齐
+            if data.len() % 2 == 1 {
+                info.push(0);
             }
-            writer.finalize()?;
         }
 
-        Ok(cursor.into_inner())
+        wav.extend_from_slice(b"LIST");
+        wav.extend_from_slice(&(info.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&info);
+
+        let riff_size = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        wav
+    }
+}
+
+/// 将样本写入 WAV 输出，区分样本写入失败与 WAV 头更新失败
+fn write_samples<W: Write + Seek, S: hound::Sample>(
+    sink: W,
+    spec: WavSpec,
+    samples: impl ExactSizeIterator<Item = S>,
+) -> Result<(), EncodingError> {
+    let total = samples.len();
+    let mut writer = WavWriter::new(sink, spec)?;
+    for (written, sample) in samples.enumerate() {
+        writer
+            .write_sample(sample)
+            .map_err(|e| EncodingError::SampleWriteFailed {
+                written,
+                total,
+                reason: e.to_string(),
+            })?;
+    }
+    writer
+        .finalize()
+        .map_err(|e| EncodingError::FinalizeFailed(e.to_string()))
+}
+
+/// 软限幅：`SOFT_LIMIT_KNEE` 以内保持线性，以上按 tanh 曲线渐近满幅
+fn soft_limit(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= SOFT_LIMIT_KNEE {
+        return sample;
     }
+    let headroom = 1.0 - SOFT_LIMIT_KNEE;
+    let limited = SOFT_LIMIT_KNEE + headroom * ((magnitude - SOFT_LIMIT_KNEE) / headroom).tanh();
+    limited.copysign(sample)
 }
 
 /// 将 AudioData 编码为 WAV 格式 (便捷函数)
@@ -160,3 +338,146 @@ pub fn encode_i16_to_wav(
     let encoder = WavEncoder::new(sample_rate, channels, 16);
     encoder.encode_i16_samples(samples)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_with_stats_counts_clipped_samples() {
+        let samples = [0.5, 1.5, -2.0, 1.0, -1.0, 0.0];
+        let (wav, stats) = WavEncoder::default_config()
+            .encode_samples_with_stats(&samples)
+            .unwrap();
+
+        assert_eq!(stats.total_samples, 6);
+        assert_eq!(stats.clipped_samples, 2);
+        assert!((stats.clipped_ratio() - 2.0 / 6.0).abs() < 1e-9);
+
+        let mut reader = hound::WavReader::new(std::io::Cursor::new(wav)).unwrap();
+        let decoded: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert_eq!(decoded[1], i16::MAX);
+        assert_eq!(decoded[2], i16::MIN);
+    }
+
+    #[test]
+    fn test_soft_limit_compresses_peaks_below_full_scale() {
+        let samples = [0.5, 0.9, 1.0, 1.5, -2.0];
+        let decode = |soft_limit: bool| -> Vec<i16> {
+            let wav = WavEncoder::default_config()
+                .with_soft_limit(soft_limit)
+                .encode_samples(&samples)
+                .unwrap();
+            let mut reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+            reader.samples::<i16>().map(|s| s.unwrap()).collect()
+        };
+        let hard = decode(false);
+        let soft = decode(true);
+
+        // 线性区不变
+        assert_eq!(soft[0], hard[0]);
+        // 硬截断的峰值顶到满幅，软限幅压缩到满幅以内且保持单调
+        assert_eq!((hard[3], hard[4]), (i16::MAX, i16::MIN));
+        assert!(soft[1] < hard[1] && soft[1] < soft[2] && soft[2] < soft[3] && soft[3] < i16::MAX);
+        assert!(soft[4] > i16::MIN && soft[4] < 0);
+    }
+
+    #[test]
+    fn test_encode_float32_round_trip_preserves_precision() {
+        // 包含 i16 无法精确表示的值和超出 [-1, 1] 的值
+        let samples = [0.0, 0.123_456_79, -0.987_654_3, 1.5, -2.0, 1e-7];
+        let (wav, stats) = WavEncoder::default_config()
+            .with_sample_format(WavSampleFormat::Float32)
+            .encode_samples_with_stats(&samples)
+            .unwrap();
+        assert_eq!(stats.clipped_samples, 0);
+
+        let mut reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+        let spec = reader.spec();
+        assert_eq!((spec.sample_format, spec.bits_per_sample), (SampleFormat::Float, 32));
+        let decoded: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(decoded, samples);
+    }
+
+    /// 写入指定字节数后失败的输出 (可选在定位时失败，模拟 WAV 头更新失败)
+    struct FailingWriter {
+        inner: Cursor<Vec<u8>>,
+        fail_after_bytes: usize,
+        fail_seek: bool,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.inner.get_ref().len() + buf.len() > self.fail_after_bytes {
+                return Err(std::io::Error::other("磁盘已满"));
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for FailingWriter {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            if self.fail_seek {
+                return Err(std::io::Error::other("不支持定位"));
+            }
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_write_failures_are_distinguished() {
+        const HEADER_BYTES: usize = 44;
+        let spec = WavEncoder::default_config().spec();
+        let samples = [100i16; 10];
+
+        // 写入 3 个样本后失败
+        let sink = FailingWriter {
+            inner: Cursor::new(Vec::new()),
+            fail_after_bytes: HEADER_BYTES + 3 * 2,
+            fail_seek: false,
+        };
+        let err = write_samples(sink, spec, samples.iter().copied()).unwrap_err();
+        assert!(matches!(
+            err,
+            EncodingError::SampleWriteFailed { written: 3, total: 10, .. }
+        ));
+
+        // 样本全部写入，更新 WAV 头失败
+        let sink = FailingWriter {
+            inner: Cursor::new(Vec::new()),
+            fail_after_bytes: usize::MAX,
+            fail_seek: true,
+        };
+        let err = write_samples(sink, spec, samples.iter().copied()).unwrap_err();
+        assert!(matches!(err, EncodingError::FinalizeFailed(_)));
+    }
+
+    #[test]
+    fn test_encode_without_metadata_has_no_list_chunk() {
+        let wav = WavEncoder::default_config().encode_i16_samples(&[0, 1, -1]).unwrap();
+
+        assert!(!wav.windows(4).any(|w| w == b"LIST"));
+    }
+
+    #[test]
+    fn test_encode_with_metadata_round_trip() {
+        let samples = [0i16, 1000, -1000, 32767];
+        let wav = WavEncoder::default_config()
+            .with_info(INFO_SOFTWARE, "Smart Workflow")
+            .with_info(INFO_COMMENT, "qwen")
+            .encode_i16_samples(&samples)
+            .unwrap();
+
+        let riff_size = u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]) as usize;
+        assert_eq!(riff_size, wav.len() - 8);
+        assert!(wav.windows(4).any(|w| w == b"ISFT"));
+
+        let mut reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+        let decoded: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert_eq!(decoded, samples);
+    }
+}
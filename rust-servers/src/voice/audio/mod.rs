@@ -1,17 +1,24 @@
 // 音频模块
 // 包含录音、流式处理、编码和工具函数
 
+pub mod device_watch;
 pub mod encoder;
 pub mod recorder;
+pub mod state;
 pub mod streaming;
 pub mod utils;
 
+use std::borrow::Cow;
+use std::sync::{Mutex, OnceLock};
+
 use cpal::traits::{DeviceTrait, HostTrait};
 
 // 重新导出常用类型
-pub use encoder::{encode_to_wav, encode_samples_to_wav, encode_i16_to_wav, WavEncoder, EncodingError};
+pub use encoder::{encode_to_wav, encode_samples_to_wav, encode_i16_to_wav, WavEncoder, WavSampleFormat, EncodeStats, EncodingError};
+pub use encoder::{INFO_COMMENT, INFO_CREATION_DATE, INFO_SOFTWARE};
 pub use recorder::{AudioRecorder, RecordingError, RecordingMode, TARGET_SAMPLE_RATE};
-pub use streaming::{StreamingRecorder, AudioChunkData, CHUNK_SAMPLES};
+pub use state::CaptureState;
+pub use streaming::{StreamingRecorder, AudioChunkData, ChunkProducer, CHUNK_SAMPLES};
 
 /// 输入设备信息
 #[derive(Debug, Clone, serde::Serialize)]
@@ -44,6 +51,13 @@ pub fn list_input_devices() -> Result<Vec<InputDeviceInfo>, RecordingError> {
     Ok(list)
 }
 
+/// 当前系统默认输入设备的名称 (无设备或音频后端无法查询时返回 None)
+pub fn default_input_device_name() -> Option<String> {
+    cpal::default_host()
+        .default_input_device()
+        .and_then(|device| device.name().ok())
+}
+
 /// 选择输入设备（优先使用指定名称，空则使用默认设备）
 pub fn select_input_device(device_name: Option<&str>) -> Result<cpal::Device, RecordingError> {
     let host = cpal::default_host();
@@ -70,6 +84,80 @@ pub fn select_input_device(device_name: Option<&str>) -> Result<cpal::Device, Re
     })
 }
 
+/// 查询默认输入设备的采样率与声道数 (无设备或查询失败时返回 None)
+pub fn default_input_format() -> Option<(u32, u16)> {
+    let (_, config) = resolve_input_device(None).ok()?;
+    Some((config.sample_rate().0, config.channels()))
+}
+
+/// 已解析的输入设备及其默认配置
+struct CachedInputDevice {
+    /// 请求的设备名称 (None 为系统默认设备)
+    requested: Option<String>,
+    /// 设备实际名称
+    name: String,
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+}
+
+/// 最近一次解析的输入设备 (查询设备和默认配置在部分平台上较慢，缓存后缩短录音启动延迟)
+static INPUT_DEVICE_CACHE: OnceLock<Mutex<Option<CachedInputDevice>>> = OnceLock::new();
+
+fn input_device_cache() -> &'static Mutex<Option<CachedInputDevice>> {
+    INPUT_DEVICE_CACHE.get_or_init(|| Mutex::new(None))
+}
+
+/// 解析输入设备及其默认配置，优先使用缓存
+///
+/// 请求默认设备时会检查系统默认设备是否已切换，切换后重新解析
+pub fn resolve_input_device(
+    device_name: Option<&str>,
+) -> Result<(cpal::Device, cpal::SupportedStreamConfig), RecordingError> {
+    let current_default = if device_name.is_none() {
+        cpal::default_host()
+            .default_input_device()
+            .and_then(|device| device.name().ok())
+    } else {
+        None
+    };
+
+    {
+        let cache = input_device_cache().lock().unwrap();
+        if let Some(cached) = cache.as_ref() {
+            let hit = cached.requested.as_deref() == device_name
+                && (device_name.is_some() || current_default.as_deref() == Some(cached.name.as_str()));
+            if hit {
+                return Ok((cached.device.clone(), cached.config.clone()));
+            }
+        }
+    }
+
+    let device = select_input_device(device_name)?;
+    let config = device
+        .default_input_config()
+        .map_err(|e| RecordingError::DeviceError(format!("无法获取默认音频配置: {}", e)))?;
+    let name = device.name().unwrap_or_default();
+
+    *input_device_cache().lock().unwrap() = Some(CachedInputDevice {
+        requested: device_name.map(str::to_string),
+        name,
+        device: device.clone(),
+        config: config.clone(),
+    });
+
+    Ok((device, config))
+}
+
+/// 预先解析输入设备并缓存，使第一次录音也能快速启动
+pub fn prewarm_input_device(device_name: Option<&str>) -> Result<(), RecordingError> {
+    resolve_input_device(device_name).map(|_| ())
+}
+
+/// 清除输入设备缓存 (设备出错、设备列表变化时调用，下次录音重新解析)
+pub fn invalidate_input_device_cache() {
+    *input_device_cache().lock().unwrap() = None;
+}
+
 /// 音频数据
 #[derive(Debug, Clone)]
 pub struct AudioData {
@@ -110,10 +198,257 @@ impl AudioData {
         self.samples.len()
     }
 
+    /// 在开头补充指定时长的静音 (部分 ASR 接口会截掉紧贴开头的语音)
+    pub fn pad_start(&mut self, ms: u32) {
+        let padding = self.silence_samples(ms);
+        if padding > 0 {
+            self.samples.splice(0..0, std::iter::repeat_n(0.0, padding));
+            self.update_duration();
+        }
+    }
+
+    /// 在结尾补充指定时长的静音
+    pub fn pad_end(&mut self, ms: u32) {
+        let padding = self.silence_samples(ms);
+        if padding > 0 {
+            self.samples.resize(self.samples.len() + padding, 0.0);
+            self.update_duration();
+        }
+    }
+
+    /// 截取 `[start_ms, end_ms)` 区间的音频，超出范围的部分按实际时长截断
+    pub fn slice_ms(&self, start_ms: u64, end_ms: u64) -> AudioData {
+        let to_index = |ms: u64| {
+            self.silence_samples(ms.min(u32::MAX as u64) as u32).min(self.samples.len())
+        };
+        let end = to_index(end_ms);
+        let start = to_index(start_ms).min(end);
+        AudioData::new(self.samples[start..end].to_vec(), self.sample_rate, self.channels)
+    }
+
+    /// 按时长上限切分音频，切分点优先选在静音处
+    ///
+    /// 每段不超过 `max_ms`；在每段后半部分由后向前查找静音窗口作为切分点，
+    /// 找不到时在上限处硬切
+    pub fn split_at_silence(&self, max_ms: u64) -> Vec<AudioData> {
+        let max_samples = self.silence_samples(max_ms.min(u32::MAX as u64) as u32);
+        if max_samples == 0 || self.samples.len() <= max_samples {
+            return vec![self.clone()];
+        }
+
+        let window = self.silence_samples(SPLIT_WINDOW_MS).max(self.channels as usize);
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while self.samples.len() - start > max_samples {
+            let limit = start + max_samples;
+            let earliest = start + max_samples / 2;
+            let mut cut = limit;
+            let mut window_end = limit;
+            while window_end >= earliest + window {
+                if utils::is_silence(&self.samples[window_end - window..window_end]) {
+                    // 在静音窗口中点切分，两侧各保留一半静音
+                    cut = window_end - window / 2;
+                    cut -= cut % self.channels as usize;
+                    break;
+                }
+                window_end -= window;
+            }
+            segments.push(AudioData::new(self.samples[start..cut].to_vec(), self.sample_rate, self.channels));
+            start = cut;
+        }
+        segments.push(AudioData::new(self.samples[start..].to_vec(), self.sample_rate, self.channels));
+        segments
+    }
+
+    /// 按语句切分音频：连续静音不短于 `min_silence_ms` 处切开，丢弃不含语音的部分
+    ///
+    /// 以 100ms 窗口判断静音，切分点位于静音段中点；语句前后的长静音被去除，
+    /// 整段都是静音时返回空列表
+    pub fn split_utterances(&self, min_silence_ms: u32) -> Vec<AudioData> {
+        let window = self.silence_samples(SPLIT_WINDOW_MS).max(self.channels as usize);
+        let min_silent_windows = min_silence_ms.div_ceil(SPLIT_WINDOW_MS).max(1) as usize;
+        let voiced: Vec<bool> = self.samples.chunks(window).map(utils::is_voice_active).collect();
+        let to_sample = |window_index: usize| (window_index * window).min(self.samples.len());
+        let segment = |start: usize, end: usize| {
+            AudioData::new(self.samples[to_sample(start)..to_sample(end)].to_vec(), self.sample_rate, self.channels)
+        };
+
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut has_voice = false;
+        let mut silent_run = 0;
+        for (index, &active) in voiced.iter().enumerate() {
+            if !active {
+                silent_run += 1;
+                continue;
+            }
+            if silent_run >= min_silent_windows {
+                let cut = index - silent_run / 2;
+                if has_voice {
+                    segments.push(segment(start, cut));
+                }
+                start = cut;
+            }
+            has_voice = true;
+            silent_run = 0;
+        }
+        if has_voice {
+            let end = if silent_run >= min_silent_windows {
+                voiced.len() - silent_run + silent_run / 2
+            } else {
+                voiced.len()
+            };
+            segments.push(segment(start, end));
+        }
+        segments
+    }
+
+    /// 指定时长对应的采样数 (按整帧计算，保持声道交错对齐)
+    fn silence_samples(&self, ms: u32) -> usize {
+        let frames = self.sample_rate as u64 * ms as u64 / 1000;
+        frames as usize * self.channels as usize
+    }
+
+    fn update_duration(&mut self) {
+        self.duration_ms = utils::calculate_duration_ms(self.samples.len(), self.sample_rate, self.channels);
+    }
+
     /// 编码为 WAV 格式
     pub fn to_wav(&self) -> Result<Vec<u8>, EncodingError> {
         encode_to_wav(self)
     }
+
+    /// 转换为指定采样率和声道数 (重采样、下混或复制声道)，格式已一致时不复制
+    ///
+    /// 源音频为空或目标格式无效时返回 `InvalidAudioData`
+    pub fn to_target(&self, sample_rate: u32, channels: u16) -> Result<Cow<'_, AudioData>, EncodingError> {
+        if self.is_empty() || self.sample_rate == 0 || self.channels == 0 || sample_rate == 0 || channels == 0 {
+            return Err(EncodingError::InvalidAudioData);
+        }
+        if self.sample_rate == sample_rate && self.channels == channels {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let mono = recorder::to_mono(&self.samples, self.channels);
+        let mono = recorder::resample(&mono, self.sample_rate, sample_rate);
+        let samples = if channels == 1 {
+            mono
+        } else {
+            mono.iter()
+                .flat_map(|&s| std::iter::repeat_n(s, channels as usize))
+                .collect()
+        };
+
+        Ok(Cow::Owned(AudioData::new(samples, sample_rate, channels)))
+    }
+
+    /// 按增益混合多路音频 (各路从开头对齐)
+    ///
+    /// 各路先转换为第一路的采样率和声道数，结果长度取最长的一路，混合后限幅到 [-1.0, 1.0]；
+    /// 空音频跳过，全部为空时返回 `InvalidAudioData`
+    pub fn mix(sources: &[(&AudioData, f32)]) -> Result<AudioData, EncodingError> {
+        let mut sources = sources.iter().filter(|(audio, _)| !audio.is_empty());
+        let (first, first_gain) = sources.next().ok_or(EncodingError::InvalidAudioData)?;
+        let (sample_rate, channels) = (first.sample_rate, first.channels);
+
+        let mut mixed: Vec<f32> = first.samples.iter().map(|&s| s * first_gain).collect();
+        for (audio, gain) in sources {
+            let audio = audio.to_target(sample_rate, channels)?;
+            if audio.samples.len() > mixed.len() {
+                mixed.resize(audio.samples.len(), 0.0);
+            }
+            for (out, &s) in mixed.iter_mut().zip(&audio.samples) {
+                *out += s * gain;
+            }
+        }
+        for s in &mut mixed {
+            *s = s.clamp(-1.0, 1.0);
+        }
+
+        Ok(AudioData::new(mixed, sample_rate, channels))
+    }
+
+    /// 按顺序拼接多段音频 (各段转换为第一段的采样率和声道数)
+    ///
+    /// 空音频跳过，全部为空时返回 `InvalidAudioData`
+    pub fn concat(parts: &[AudioData]) -> Result<AudioData, EncodingError> {
+        let mut parts = parts.iter().filter(|audio| !audio.is_empty());
+        let first = parts.next().ok_or(EncodingError::InvalidAudioData)?;
+
+        let mut samples = first.samples.clone();
+        for audio in parts {
+            samples.extend_from_slice(&audio.to_target(first.sample_rate, first.channels)?.samples);
+        }
+
+        Ok(AudioData::new(samples, first.sample_rate, first.channels))
+    }
+
+    /// 判断音频是否基本为静音 (用于转录前跳过无效录音)
+    ///
+    /// 按 `SILENCE_WINDOW_MS` 分窗计算 RMS，RMS 低于 `threshold` 的窗口占比达到 `ratio` 时返回 true；
+    /// 空音频视为静音
+    pub fn is_mostly_silence(&self, threshold: f32, ratio: f32) -> bool {
+        let window = self.silence_samples(SILENCE_WINDOW_MS).max(self.channels.max(1) as usize);
+        let (total, silent) = self
+            .samples
+            .chunks(window)
+            .fold((0usize, 0usize), |(total, silent), chunk| {
+                (total + 1, silent + (utils::calculate_rms(chunk) < threshold) as usize)
+            });
+        total == 0 || silent as f32 >= total as f32 * ratio
+    }
+
+    /// 计算音频能量/时长摘要 (用于日志和 UI 提示)
+    pub fn summary(&self) -> AudioSummary {
+        let rms = utils::calculate_rms(&self.samples);
+        let rms_dbfs = if rms > 0.0 {
+            (20.0 * rms.log10()).max(SILENCE_DBFS)
+        } else {
+            SILENCE_DBFS
+        };
+
+        AudioSummary {
+            duration_ms: utils::calculate_duration_ms(self.samples.len(), self.sample_rate, self.channels),
+            peak: utils::calculate_peak(&self.samples),
+            rms_dbfs,
+            clipped_samples: self.samples.iter().filter(|s| s.abs() >= 1.0).count(),
+        }
+    }
+}
+
+/// 切分时判断静音的窗口时长 (毫秒)
+const SPLIT_WINDOW_MS: u32 = 100;
+
+/// 静音占比统计的窗口时长 (毫秒)
+const SILENCE_WINDOW_MS: u32 = 20;
+
+/// 静音对应的 dBFS 下限 (16-bit 动态范围)
+const SILENCE_DBFS: f32 = -96.0;
+
+/// 音频摘要
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct AudioSummary {
+    /// 时长 (毫秒)
+    pub duration_ms: u64,
+    /// 峰值 (0.0 - 1.0)
+    pub peak: f32,
+    /// RMS 电平 (dBFS，静音为 -96)
+    pub rms_dbfs: f32,
+    /// 削波采样数 (幅值达到 ±1.0)
+    pub clipped_samples: usize,
+}
+
+/// 录音结束原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// 手动停止
+    #[default]
+    Manual,
+    /// 达到录音时长上限
+    MaxDuration,
+    /// 录音设备断开或录音流出错
+    DeviceLost,
 }
 
 /// 音频块 (用于流式传输)
@@ -181,6 +516,79 @@ mod tests {
         assert_eq!(audio.duration_ms, 1000);
     }
 
+    #[test]
+    fn test_split_utterances_skips_silence() {
+        // 1 秒静音 + (1 秒语音 + 1 秒静音) x 2 + 0.2 秒停顿 + 0.5 秒语音
+        let mut samples = vec![0.0f32; 16000];
+        for _ in 0..2 {
+            samples.extend(std::iter::repeat_n(0.3f32, 16000));
+            samples.extend(std::iter::repeat_n(0.0f32, 16000));
+        }
+        samples.truncate(samples.len() - 12800);
+        samples.extend(std::iter::repeat_n(0.3f32, 8000));
+        let audio = AudioData::new(samples, 16000, 1);
+
+        let segments = audio.split_utterances(600);
+        let durations: Vec<u64> = segments.iter().map(|s| s.duration_ms).collect();
+        // 长静音在中点切开 (首段前的静音只保留后一半)，0.2 秒停顿不切分
+        assert_eq!(durations, vec![2000, 2200]);
+        assert!(segments.iter().all(|s| s.samples.iter().any(|&v| v > 0.0)));
+
+        assert!(AudioData::new(vec![0.0f32; 32000], 16000, 1).split_utterances(600).is_empty());
+    }
+
+    #[test]
+    fn test_split_at_silence_prefers_quiet_points() {
+        // 1 秒语音 + 0.5 秒静音，重复 4 次，共 6 秒
+        let mut samples = Vec::new();
+        for _ in 0..4 {
+            samples.extend(std::iter::repeat_n(0.3f32, 16000));
+            samples.extend(std::iter::repeat_n(0.0f32, 8000));
+        }
+        let audio = AudioData::new(samples, 16000, 1);
+
+        let segments = audio.split_at_silence(2000);
+        assert!(segments.iter().all(|segment| segment.duration_ms <= 2000));
+        assert_eq!(segments.iter().map(|s| s.sample_count()).sum::<usize>(), audio.sample_count());
+        // 切分点落在静音段内
+        for segment in &segments[..segments.len() - 1] {
+            assert_eq!(*segment.samples.last().unwrap(), 0.0);
+        }
+
+        // 无静音时在上限处硬切
+        let loud = AudioData::new(vec![0.3f32; 16000 * 5], 16000, 1);
+        let segments = loud.split_at_silence(2000);
+        assert_eq!(segments.iter().map(|s| s.duration_ms).collect::<Vec<_>>(), vec![2000, 2000, 1000]);
+
+        assert_eq!(loud.split_at_silence(10_000).len(), 1);
+    }
+
+    #[test]
+    fn test_slice_ms_clamps_to_audio() {
+        let audio = AudioData::new(vec![0.3f32; 32000 * 3], 16000, 2);
+
+        assert_eq!(audio.slice_ms(0, 2000).duration_ms, 2000);
+        assert_eq!(audio.slice_ms(1000, 1500).sample_count(), 8000 * 2);
+        assert_eq!(audio.slice_ms(2500, 10_000).duration_ms, 500);
+        assert!(audio.slice_ms(5000, 6000).is_empty());
+    }
+
+    #[test]
+    fn test_audio_data_padding() {
+        let mut audio = AudioData::new(vec![0.5; 1600], 16000, 2);
+        audio.pad_start(200);
+        assert_eq!(audio.sample_count(), 1600 + 3200 * 2);
+        assert_eq!(audio.samples[0], 0.0);
+        assert_eq!(audio.samples[6400], 0.5);
+
+        audio.pad_end(100);
+        assert_eq!(audio.sample_count(), 1600 + 3200 * 2 + 1600 * 2);
+        assert_eq!(audio.duration_ms, 350);
+
+        audio.pad_start(0);
+        assert_eq!(audio.sample_count(), 11200);
+    }
+
     #[test]
     fn test_audio_data_to_wav() {
         let samples = vec![0.0f32, 0.5, -0.5];
@@ -191,6 +599,77 @@ mod tests {
         assert_eq!(&wav[0..4], b"RIFF");
     }
 
+    #[test]
+    fn test_audio_data_to_target() {
+        // 48kHz 立体声 -> 16kHz 单声道
+        let stereo = AudioData::new([0.25f32, 0.75].repeat(4800), 48000, 2);
+        let mono = stereo.to_target(16000, 1).unwrap();
+        assert_eq!((mono.sample_rate, mono.channels, mono.sample_count()), (16000, 1, 1600));
+        assert!(mono.samples.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+        assert_eq!(mono.duration_ms, stereo.duration_ms);
+
+        // 单声道 -> 立体声
+        let upmixed = mono.to_target(16000, 2).unwrap();
+        assert_eq!(upmixed.sample_count(), 3200);
+
+        assert!(matches!(mono.to_target(16000, 1).unwrap(), Cow::Borrowed(_)));
+        assert!(matches!(
+            AudioData::new(Vec::new(), 16000, 1).to_target(16000, 1),
+            Err(EncodingError::InvalidAudioData)
+        ));
+    }
+
+    #[test]
+    fn test_audio_data_mix_and_concat() {
+        let primary = AudioData::new(vec![0.5; 1600], 16000, 1);
+        // 第二路为 32kHz、时长更长，混合时重采样并补齐长度
+        let secondary = AudioData::new(vec![0.25; 6400], 32000, 1);
+
+        let mixed = AudioData::mix(&[(&primary, 1.0), (&secondary, 2.0)]).unwrap();
+        assert_eq!((mixed.sample_rate, mixed.channels, mixed.sample_count()), (16000, 1, 3200));
+        assert!(mixed.samples[..1600].iter().all(|&s| (s - 1.0).abs() < 1e-6));
+        assert!(mixed.samples[1600..].iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        let loud = AudioData::mix(&[(&primary, 3.0)]).unwrap();
+        assert!(loud.samples.iter().all(|&s| s == 1.0));
+
+        let joined = AudioData::concat(&[primary.clone(), AudioData::new(Vec::new(), 16000, 1), secondary]).unwrap();
+        assert_eq!(joined.sample_count(), 4800);
+        assert_eq!(joined.duration_ms, 300);
+
+        let empty = AudioData::new(Vec::new(), 16000, 1);
+        assert!(matches!(AudioData::mix(&[(&empty, 1.0)]), Err(EncodingError::InvalidAudioData)));
+        assert!(matches!(AudioData::concat(&[]), Err(EncodingError::InvalidAudioData)));
+    }
+
+    #[test]
+    fn test_audio_data_is_mostly_silence() {
+        // 1 秒音频，前 100ms 有声音
+        let mut samples = vec![0.0f32; 16000];
+        samples[..1600].fill(0.3);
+        let audio = AudioData::new(samples, 16000, 1);
+
+        assert!(audio.is_mostly_silence(0.01, 0.9));
+        assert!(!audio.is_mostly_silence(0.01, 0.95));
+        assert!(!audio.is_mostly_silence(0.5, 1.01));
+        assert!(AudioData::new(Vec::new(), 16000, 1).is_mostly_silence(0.01, 1.0));
+    }
+
+    #[test]
+    fn test_audio_data_summary() {
+        let audio = AudioData::new(vec![0.5, -0.5, 1.0, -1.0], 16000, 1);
+        let summary = audio.summary();
+
+        assert_eq!(summary.peak, 1.0);
+        assert_eq!(summary.clipped_samples, 2);
+        assert!(summary.rms_dbfs < 0.0 && summary.rms_dbfs > -6.0);
+
+        let silent = AudioData::new(vec![0.0; 16000], 16000, 1).summary();
+        assert_eq!(silent.duration_ms, 1000);
+        assert_eq!(silent.rms_dbfs, SILENCE_DBFS);
+        assert_eq!(silent.clipped_samples, 0);
+    }
+
     #[test]
     fn test_waveform_data() {
         let waveform = WaveformData::new(vec![0.5; 9], 1000);
@@ -29,11 +29,15 @@ macro_rules! log_error {
 
 use cpal::traits::{DeviceTrait, StreamTrait};
 use cpal::Stream;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use thiserror::Error;
 
-use super::{AudioData, select_input_device, utils};
+use super::device_watch::{DeviceWatcher, SwitchTarget};
+use super::state::{CaptureState, CaptureStateMachine};
+use super::{AudioData, StopReason, invalidate_input_device_cache, resolve_input_device, utils};
+use crate::voice::beep::{MonitorHandle, MonitorOutput};
 use crate::voice::config::AudioCompressionLevel;
 
 /// API 要求的目标采样率 (16kHz)
@@ -42,6 +46,19 @@ pub const TARGET_SAMPLE_RATE: u32 = 16000;
 /// 音频级别发送间隔 (毫秒)，目标 ~30Hz
 const AUDIO_LEVEL_EMIT_INTERVAL_MS: u128 = 33;
 
+/// 查询不到默认输入设备时使用的设备参数 (start 时以实际设备配置覆盖)
+const DEFAULT_DEVICE_SAMPLE_RATE: u32 = 48000;
+const DEFAULT_CHANNELS: u16 = 1;
+
+/// 默认预热时长 (毫秒)，丢弃音频流启动后的开头部分，避免部分麦克风的爆音被识别为噪声
+pub const DEFAULT_WARMUP_MS: u32 = 50;
+
+/// 录音缓冲默认时长上限 (秒)，超出后停止采集，避免卡住的 Toggle 录音耗尽内存
+pub const DEFAULT_MAX_RECORDING_SECS: u32 = 30 * 60;
+
+/// 缓冲用量达到上限的该比例时输出告警
+const CAPTURE_WARN_RATIO: f64 = 0.9;
+
 /// AGC 按块处理的样本数 (0.2 秒 @ 16kHz)
 const AGC_CHUNK_SAMPLES: usize = 3200;
 
@@ -70,6 +87,12 @@ pub enum RecordingError {
     #[error("未在录音中")]
     NotRecording,
 
+    #[error("当前录音状态 ({state}) 不允许{action}")]
+    InvalidTransition {
+        state: CaptureState,
+        action: &'static str,
+    },
+
     #[error("音频编码错误: {0}")]
     EncodingError(String),
 
@@ -80,36 +103,223 @@ pub enum RecordingError {
 /// 音频级别回调类型
 pub type AudioLevelCallback = Box<dyn Fn(f32, Vec<f32>) + Send + 'static>;
 
+/// 录音采集缓冲
+///
+/// 累积回调交付的原始样本，达到上限后拒绝写入并标记溢出，接近上限时告警一次
+#[derive(Debug)]
+pub struct CaptureBuffer {
+    samples: Vec<f32>,
+    max_samples: usize,
+    warned: bool,
+    overflowed: bool,
+}
+
+impl CaptureBuffer {
+    pub fn new(max_samples: usize) -> Self {
+        Self {
+            samples: Vec::new(),
+            max_samples,
+            warned: false,
+            overflowed: false,
+        }
+    }
+
+    /// 按设备参数计算上限 (原始交错样本数)
+    pub fn for_duration(max_secs: u32, sample_rate: u32, channels: u16) -> Self {
+        Self::new(max_secs as usize * sample_rate as usize * channels as usize)
+    }
+
+    /// 追加样本，缓冲已满时丢弃超出部分并返回 false
+    pub fn push(&mut self, data: &[f32]) -> bool {
+        if self.overflowed {
+            return false;
+        }
+
+        let remaining = self.max_samples.saturating_sub(self.samples.len());
+        if data.len() > remaining {
+            self.samples.extend_from_slice(&data[..remaining]);
+            self.overflowed = true;
+            log_warn!("录音缓冲已达上限 ({} 样本)，停止采集", self.max_samples);
+            return false;
+        }
+
+        self.samples.extend_from_slice(data);
+
+        if !self.warned && self.samples.len() as f64 >= self.max_samples as f64 * CAPTURE_WARN_RATIO {
+            self.warned = true;
+            log_warn!(
+                "录音缓冲接近上限: {}/{} 样本",
+                self.samples.len(),
+                self.max_samples
+            );
+        }
+        true
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// 取出全部采样 (不复制)
+    pub fn into_samples(self) -> Vec<f32> {
+        self.samples
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// 清空缓冲并重新设置上限
+    pub fn reset(&mut self, max_samples: usize) {
+        self.samples = Vec::new();
+        self.max_samples = max_samples;
+        self.warned = false;
+        self.overflowed = false;
+    }
+
+    /// 追加样本，本次写入使缓冲首次达到上限时返回 true (之后的写入不再返回 true)
+    pub fn push_reached_limit(&mut self, data: &[f32]) -> bool {
+        let was_overflowed = self.overflowed;
+        !self.push(data) && !was_overflowed
+    }
+}
+
+/// 可在采集回调中关闭的音频流
+///
+/// 达到录音时长上限时由采集回调请求关闭，无需等待用户停止录音；
+/// 音频流不能在自身的回调线程内释放，关闭在后台线程中进行
+#[derive(Clone, Default)]
+pub(crate) struct SharedStream(Arc<Mutex<Option<Stream>>>);
+
+// 与 AudioRecorder 相同，音频流只在录音器与后台关闭线程之间转移，不会并发使用
+unsafe impl Send for SharedStream {}
+unsafe impl Sync for SharedStream {}
+
+impl SharedStream {
+    pub fn set(&self, stream: Option<Stream>) {
+        *self.0.lock().unwrap() = stream;
+    }
+
+    /// 在后台线程关闭音频流
+    pub fn close_in_background(&self) {
+        let shared = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("capture-close".to_string())
+            .spawn(move || shared.set(None));
+        if let Err(e) = spawned {
+            log_warn!("无法启动音频流关闭线程，录音停止时再关闭: {}", e);
+        }
+    }
+}
+
 /// 音频录制器
 pub struct AudioRecorder {
     device_sample_rate: u32,
     channels: u16,
-    audio_data: Arc<Mutex<Vec<f32>>>,
-    is_recording: Arc<Mutex<bool>>,
-    recording_mode: Arc<Mutex<Option<RecordingMode>>>,
-    stream: Option<Stream>,
+    audio_data: Arc<Mutex<CaptureBuffer>>,
+    max_recording_secs: u32,
+    warmup_ms: u32,
+    warmup_remaining: Arc<Mutex<usize>>,
+    state: CaptureStateMachine,
+    /// 录音流是否出错 (设备断开等)
+    device_lost: Arc<Mutex<bool>>,
+    stream: SharedStream,
     level_callback: Arc<Mutex<Option<AudioLevelCallback>>>,
     smoothed_level: Arc<Mutex<f32>>,
     last_emit_time: Arc<Mutex<Instant>>,
     compression_level: AudioCompressionLevel,
+    monitor_enabled: bool,
+    monitor_volume: f32,
+    monitor: Option<MonitorOutput>,
+    secondary_device: Option<String>,
+    secondary_gain: f32,
+    secondary: Option<Box<AudioRecorder>>,
+    follow_default_device: bool,
+    device_watcher: Option<DeviceWatcher>,
+    /// 当前有效的音频流代次 (跟随默认设备切换时递增，原设备为 0)
+    stream_generation: Arc<AtomicU32>,
 }
 
 impl AudioRecorder {
     pub fn new() -> Result<Self, RecordingError> {
+        let (device_sample_rate, channels) = initial_device_format();
         Ok(Self {
-            device_sample_rate: 48000,
-            channels: 1,
-            audio_data: Arc::new(Mutex::new(Vec::new())),
-            is_recording: Arc::new(Mutex::new(false)),
-            recording_mode: Arc::new(Mutex::new(None)),
-            stream: None,
+            device_sample_rate,
+            channels,
+            audio_data: Arc::new(Mutex::new(CaptureBuffer::for_duration(
+                DEFAULT_MAX_RECORDING_SECS,
+                device_sample_rate,
+                channels,
+            ))),
+            max_recording_secs: DEFAULT_MAX_RECORDING_SECS,
+            warmup_ms: DEFAULT_WARMUP_MS,
+            warmup_remaining: Arc::new(Mutex::new(0)),
+            state: CaptureStateMachine::new(),
+            device_lost: Arc::new(Mutex::new(false)),
+            stream: SharedStream::default(),
             level_callback: Arc::new(Mutex::new(None)),
             smoothed_level: Arc::new(Mutex::new(0.0)),
             last_emit_time: Arc::new(Mutex::new(Instant::now())),
             compression_level: AudioCompressionLevel::Minimum,
+            monitor_enabled: false,
+            monitor_volume: 0.5,
+            monitor: None,
+            secondary_device: None,
+            secondary_gain: 1.0,
+            secondary: None,
+            follow_default_device: false,
+            device_watcher: None,
+            stream_generation: Arc::new(AtomicU32::new(0)),
         })
     }
 
+    /// 设置自我监听 (录音时将采集到的音频回放到默认输出设备)
+    pub fn set_monitor(&mut self, enabled: bool, volume: f32) {
+        self.monitor_enabled = enabled;
+        self.monitor_volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// 设置第二路录音设备 (如访谈时的第二支麦克风)，空则只录主设备
+    ///
+    /// 第二路与主设备同时采集，stop 时按增益混入主录音 (从开头对齐)；
+    /// 第二路打开失败时只录主设备
+    pub fn set_secondary_device(&mut self, device_name: Option<String>, gain: f32) {
+        self.secondary_device = device_name;
+        self.secondary_gain = gain.max(0.0);
+    }
+
+    /// 设置是否跟随系统默认输入设备切换 (仅未指定录音设备时生效)
+    ///
+    /// 录音中默认设备变化 (如插入耳机) 时在新设备上继续录音，已录制的音频保留；
+    /// 音频后端无法查询默认设备时不生效
+    pub fn set_follow_default_device(&mut self, enabled: bool) {
+        self.follow_default_device = enabled;
+    }
+
+    /// 设置录音时长上限 (秒)，达到上限后关闭音频流，stop 返回上限内的录音 (结束原因为 `MaxDuration`)
+    pub fn set_max_recording_secs(&mut self, max_secs: u32) {
+        self.max_recording_secs = max_secs.max(1);
+    }
+
+    /// 设置预热时长 (毫秒)，录音开始后该时长内采集到的音频直接丢弃
+    pub fn set_warmup_ms(&mut self, warmup_ms: u32) {
+        self.warmup_ms = warmup_ms;
+    }
+
+    /// 录音设备的采样率 (录音前为默认输入设备的采样率，录音后为最近一次录音所用设备的采样率)
+    pub fn device_sample_rate(&self) -> u32 {
+        self.device_sample_rate
+    }
+
+    /// 录音设备的声道数 (含义同 `device_sample_rate`)
+    pub fn device_channels(&self) -> u16 {
+        self.channels
+    }
+
     pub fn set_level_callback<F>(&mut self, callback: F)
     where
         F: Fn(f32, Vec<f32>) + Send + 'static,
@@ -124,33 +334,46 @@ impl AudioRecorder {
         device_name: Option<&str>,
         compression_level: AudioCompressionLevel,
     ) -> Result<(), RecordingError> {
-        {
-            let is_recording = self.is_recording.lock().unwrap();
-            if *is_recording {
-                return Err(RecordingError::AlreadyRecording);
-            }
-        }
+        // 先占用录音状态，重复启动或停止过程中启动直接拒绝
+        self.state.start(mode)?;
 
         log_info!("开始录音，模式: {:?}", mode);
-
-        self.audio_data.lock().unwrap().clear();
-        *self.is_recording.lock().unwrap() = true;
-        *self.recording_mode.lock().unwrap() = Some(mode);
-        *self.smoothed_level.lock().unwrap() = 0.0;
-        *self.last_emit_time.lock().unwrap() = Instant::now();
         self.compression_level = compression_level;
 
-        let device = select_input_device(device_name)?;
+        // 打开失败时回到空闲状态，不会残留录音状态
+        if let Err(e) = self.open_stream(device_name) {
+            self.reset();
+            return Err(e);
+        }
+
+        self.secondary = self.start_secondary(mode);
+        if self.follow_default_device && device_name.is_none() {
+            self.device_watcher = DeviceWatcher::spawn(self.switch_target());
+        }
+        log_info!("录音已启动");
+        Ok(())
+    }
 
-        let supported_config = device
-            .default_input_config()
-            .map_err(|e| RecordingError::DeviceError(format!("无法获取默认音频配置: {}", e)))?;
+    /// 打开输入设备并启动音频流
+    fn open_stream(&mut self, device_name: Option<&str>) -> Result<(), RecordingError> {
+        // 复用录音器时清掉上一次录音残留的缓冲、电平平滑值和设备参数
+        self.release();
+
+        let (device, supported_config) = resolve_input_device(device_name)?;
 
         log_debug!("设备支持的配置: {:?}", supported_config);
 
         let config = supported_config.config();
+        self.stream_generation.store(0, Ordering::SeqCst);
         self.device_sample_rate = config.sample_rate.0;
         self.channels = config.channels;
+        self.audio_data.lock().unwrap().reset(
+            self.max_recording_secs as usize * self.device_sample_rate as usize * self.channels as usize,
+        );
+        *self.warmup_remaining.lock().unwrap() = self.warmup_ms as usize
+            * self.device_sample_rate as usize
+            * self.channels as usize
+            / 1000;
         let target_sample_rate = utils::resolve_compression_sample_rate(
             self.device_sample_rate,
             self.compression_level,
@@ -163,121 +386,148 @@ impl AudioRecorder {
             target_sample_rate
         );
 
+        self.monitor = if self.monitor_enabled {
+            match MonitorOutput::open(self.monitor_volume, self.device_sample_rate, self.channels) {
+                Ok(monitor) => Some(monitor),
+                Err(e) => {
+                    log_warn!("无法打开监听输出，继续录音: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let monitor_handle = self.monitor.as_ref().map(|m| m.handle());
+
         let audio_data = Arc::clone(&self.audio_data);
-        let is_recording = Arc::clone(&self.is_recording);
+        let warmup_remaining = Arc::clone(&self.warmup_remaining);
+        let state = self.state.clone();
         let level_callback = Arc::clone(&self.level_callback);
         let smoothed_level = Arc::clone(&self.smoothed_level);
         let last_emit_time = Arc::clone(&self.last_emit_time);
-        let device_sample_rate = self.device_sample_rate;
-        let channels = self.channels;
-
-        let err_fn = |err| log_error!("录音流错误: {}", err);
-
-        let stream = match supported_config.sample_format() {
-            cpal::SampleFormat::F32 => {
-                device
-                    .build_input_stream(
-                        &config,
-                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                            Self::handle_audio_callback(
-                                data,
-                                &audio_data,
-                                &is_recording,
-                                &level_callback,
-                                &smoothed_level,
-                                &last_emit_time,
-                                device_sample_rate,
-                                channels,
-                            );
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| RecordingError::DeviceError(e.to_string()))?
-            }
-            cpal::SampleFormat::I16 => {
-                let audio_data = Arc::clone(&audio_data);
-                let is_recording = Arc::clone(&is_recording);
-                let level_callback = Arc::clone(&level_callback);
-                let smoothed_level = Arc::clone(&smoothed_level);
-
-                device
-                    .build_input_stream(
-                        &config,
-                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                            let f32_data: Vec<f32> = convert_i16_to_f32(data);
-                            Self::handle_audio_callback(
-                                &f32_data,
-                                &audio_data,
-                                &is_recording,
-                                &level_callback,
-                                &smoothed_level,
-                                &last_emit_time,
-                                device_sample_rate,
-                                channels,
-                            );
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| RecordingError::DeviceError(e.to_string()))?
-            }
-            cpal::SampleFormat::U16 => {
-                let audio_data = Arc::clone(&audio_data);
-                let is_recording = Arc::clone(&is_recording);
-                let level_callback = Arc::clone(&level_callback);
-                let smoothed_level = Arc::clone(&smoothed_level);
-
-                device
-                    .build_input_stream(
-                        &config,
-                        move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                            let f32_data: Vec<f32> = convert_u16_to_f32(data);
-                            Self::handle_audio_callback(
-                                &f32_data,
-                                &audio_data,
-                                &is_recording,
-                                &level_callback,
-                                &smoothed_level,
-                                &last_emit_time,
-                                device_sample_rate,
-                                channels,
-                            );
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| RecordingError::DeviceError(e.to_string()))?
-            }
-            format => {
-                return Err(RecordingError::UnsupportedSampleFormat(format!("{:?}", format)));
+
+        let device_lost = Arc::clone(&self.device_lost);
+        let err_generation = Arc::clone(&self.stream_generation);
+        let err_fn = move |err| {
+            log_error!("录音流错误: {}", err);
+            // 已切换到新设备后，原设备断开不影响录音
+            if err_generation.load(Ordering::SeqCst) == 0 {
+                *device_lost.lock().unwrap() = true;
             }
+            invalidate_input_device_cache();
         };
 
-        stream
-            .play()
-            .map_err(|e| RecordingError::DeviceError(e.to_string()))?;
+        let generation = Arc::clone(&self.stream_generation);
+        let shared_stream = self.stream.clone();
+        let on_data = move |data: &[f32]| {
+            // 已切换到新的默认设备时丢弃原设备的数据
+            if generation.load(Ordering::SeqCst) != 0 {
+                return;
+            }
+            Self::handle_audio_callback(
+                data,
+                &audio_data,
+                &shared_stream,
+                &warmup_remaining,
+                &state,
+                &level_callback,
+                &smoothed_level,
+                &last_emit_time,
+                monitor_handle.as_ref(),
+            );
+        };
+        let stream = build_f32_input_stream(&device, &supported_config, on_data, err_fn)?;
 
-        self.stream = Some(stream);
-        log_info!("录音已启动");
+        stream.play().map_err(stream_error)?;
+        self.stream.set(Some(stream));
         Ok(())
     }
 
+    /// 默认设备切换后采集数据的去向：转换为当前录音缓冲的格式后走同一采集流程
+    fn switch_target(&self) -> SwitchTarget {
+        let audio_data = Arc::clone(&self.audio_data);
+        let warmup_remaining = Arc::clone(&self.warmup_remaining);
+        let state = self.state.clone();
+        let level_callback = Arc::clone(&self.level_callback);
+        let smoothed_level = Arc::clone(&self.smoothed_level);
+        let last_emit_time = Arc::clone(&self.last_emit_time);
+        let monitor_handle = self.monitor.as_ref().map(|m| m.handle());
+        let shared_stream = self.stream.clone();
+
+        SwitchTarget {
+            sample_rate: self.device_sample_rate,
+            channels: self.channels,
+            generation: Arc::clone(&self.stream_generation),
+            device_lost: Arc::clone(&self.device_lost),
+            sink: Arc::new(move |data: &[f32]| {
+                Self::handle_audio_callback(
+                    data,
+                    &audio_data,
+                    &shared_stream,
+                    &warmup_remaining,
+                    &state,
+                    &level_callback,
+                    &smoothed_level,
+                    &last_emit_time,
+                    monitor_handle.as_ref(),
+                );
+            }),
+        }
+    }
+
+    /// 启动第二路录音 (失败时记录告警并返回 None)
+    fn start_secondary(&self, mode: RecordingMode) -> Option<Box<AudioRecorder>> {
+        let device_name = self.secondary_device.as_deref()?;
+        let mut secondary = Box::new(AudioRecorder::new().ok()?);
+        secondary.set_max_recording_secs(self.max_recording_secs);
+        secondary.set_warmup_ms(self.warmup_ms);
+
+        match secondary.start(mode, Some(device_name), self.compression_level) {
+            Ok(()) => {
+                log_info!("第二路录音已启动，设备: {}", device_name);
+                Some(secondary)
+            }
+            Err(e) => {
+                log_warn!("无法启动第二路录音 ({})，只录主设备: {}", device_name, e);
+                None
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn handle_audio_callback(
         data: &[f32],
-        audio_data: &Arc<Mutex<Vec<f32>>>,
-        is_recording: &Arc<Mutex<bool>>,
+        audio_data: &Arc<Mutex<CaptureBuffer>>,
+        stream: &SharedStream,
+        warmup_remaining: &Arc<Mutex<usize>>,
+        state: &CaptureStateMachine,
         level_callback: &Arc<Mutex<Option<AudioLevelCallback>>>,
         smoothed_level: &Arc<Mutex<f32>>,
         last_emit_time: &Arc<Mutex<Instant>>,
-        _device_sample_rate: u32,
-        _channels: u16,
+        monitor: Option<&MonitorHandle>,
     ) {
-        if !*is_recording.lock().unwrap() {
+        if !state.is_capturing() {
+            return;
+        }
+
+        let data = skip_warmup(data, &mut warmup_remaining.lock().unwrap());
+        if data.is_empty() {
             return;
         }
 
-        audio_data.lock().unwrap().extend_from_slice(data);
+        {
+            let mut buffer = audio_data.lock().unwrap();
+            if buffer.push_reached_limit(data) {
+                stream.close_in_background();
+            }
+            if buffer.is_overflowed() {
+                return;
+            }
+        }
+
+        if let Some(monitor) = monitor {
+            monitor.push(data);
+        }
 
         let mut last_emit = last_emit_time.lock().unwrap();
         if last_emit.elapsed().as_millis() >= AUDIO_LEVEL_EMIT_INTERVAL_MS {
@@ -294,77 +544,229 @@ impl AudioRecorder {
     }
 
     pub fn stop(&mut self) -> Result<AudioData, RecordingError> {
-        {
-            let is_recording = self.is_recording.lock().unwrap();
-            if !*is_recording {
-                return Err(RecordingError::NotRecording);
-            }
-        }
+        self.state.begin_stop()?;
 
         log_info!("停止录音...");
 
-        *self.is_recording.lock().unwrap() = false;
-        *self.recording_mode.lock().unwrap() = None;
-        self.stream = None;
+        self.device_watcher = None;
+        self.stream.set(None);
+        self.monitor = None;
 
         std::thread::sleep(std::time::Duration::from_millis(100));
 
-        let raw_audio = self.audio_data.lock().unwrap().clone();
-        let original_len = raw_audio.len();
+        let result = self.take_audio();
+        self.state.finish();
+        result
+    }
+
+    /// 取出采集缓冲并转换为目标格式 (单声道、压缩采样率、AGC)，混入第二路录音
+    fn take_audio(&mut self) -> Result<AudioData, RecordingError> {
+        let raw_audio = {
+            let buffer = self.audio_data.lock().unwrap();
+            if buffer.is_overflowed() {
+                log_warn!("录音达到时长上限 ({} 秒)，使用上限内的音频", self.max_recording_secs);
+            }
+            buffer.samples().to_vec()
+        };
 
         if raw_audio.is_empty() {
             log_warn!("没有录制到音频数据");
-            return Ok(AudioData::new(Vec::new(), TARGET_SAMPLE_RATE, 1));
+            return Ok(self.mix_secondary(AudioData::new(Vec::new(), TARGET_SAMPLE_RATE, 1)));
         }
 
-        let mono_audio = to_mono(&raw_audio, self.channels);
-        log_debug!("转单声道: {} -> {} 样本", original_len, mono_audio.len());
-
         let target_sample_rate = utils::resolve_compression_sample_rate(
             self.device_sample_rate,
             self.compression_level,
         );
-        let mut resampled_audio = if target_sample_rate == self.device_sample_rate {
-            mono_audio.clone()
-        } else {
-            resample(&mono_audio, self.device_sample_rate, target_sample_rate)
-        };
+        let mut audio_data = process_capture(
+            &raw_audio,
+            self.channels,
+            self.device_sample_rate,
+            target_sample_rate,
+        );
         log_debug!(
-            "降采样: {}Hz -> {}Hz, {} -> {} 样本",
+            "转换: {} 声道 {}Hz -> 单声道 {}Hz, {} -> {} 样本",
+            self.channels,
             self.device_sample_rate,
             target_sample_rate,
-            mono_audio.len(),
-            resampled_audio.len()
+            raw_audio.len(),
+            audio_data.sample_count()
         );
 
         let mut current_gain = 1.0;
-        for chunk in resampled_audio.chunks_mut(AGC_CHUNK_SAMPLES) {
+        for chunk in audio_data.samples.chunks_mut(AGC_CHUNK_SAMPLES) {
             utils::apply_agc(chunk, &mut current_gain);
         }
 
-        let audio_data = AudioData::new(resampled_audio, target_sample_rate, 1);
+        let audio_data = self.mix_secondary(audio_data);
         log_info!("录音完成，时长: {}ms", audio_data.duration_ms);
 
         Ok(audio_data)
     }
 
-    pub fn cancel(&mut self) {
+    /// 停止第二路录音并混入主录音 (第二路失败时返回主录音)
+    fn mix_secondary(&mut self, primary: AudioData) -> AudioData {
+        let Some(mut secondary) = self.secondary.take() else {
+            return primary;
+        };
+
+        match secondary.stop() {
+            Ok(audio) if !audio.is_empty() => {
+                match AudioData::mix(&[(&primary, 1.0), (&audio, self.secondary_gain)]) {
+                    Ok(mixed) => {
+                        log_info!("已混入第二路录音，时长: {}ms", audio.duration_ms);
+                        mixed
+                    }
+                    Err(_) => primary,
+                }
+            }
+            Ok(_) => primary,
+            Err(e) => {
+                log_warn!("第二路录音停止失败，只使用主录音: {}", e);
+                primary
+            }
+        }
+    }
+
+    /// 取消录音 (停止过程中不可取消)
+    pub fn cancel(&mut self) -> Result<(), RecordingError> {
+        self.state.cancel()?;
         log_info!("取消录音");
-        *self.is_recording.lock().unwrap() = false;
-        *self.recording_mode.lock().unwrap() = None;
-        self.stream = None;
-        self.audio_data.lock().unwrap().clear();
+        self.release();
+        Ok(())
+    }
+
+    /// 暂停录音，音频流保持打开，暂停期间的音频不计入录音
+    pub fn pause(&self) -> Result<(), RecordingError> {
+        self.state.pause()?;
+        if let Some(ref secondary) = self.secondary {
+            let _ = secondary.pause();
+        }
+        log_info!("录音已暂停");
+        Ok(())
+    }
+
+    /// 继续已暂停的录音
+    pub fn resume(&self) -> Result<(), RecordingError> {
+        self.state.resume()?;
+        if let Some(ref secondary) = self.secondary {
+            let _ = secondary.resume();
+        }
+        log_info!("录音已继续");
+        Ok(())
+    }
+
+    /// 重置录音器内部状态，便于同一实例进行下一次录音
+    ///
+    /// 回到空闲状态并释放录音资源
+    pub fn reset(&mut self) {
+        self.state.finish();
+        self.release();
+    }
+
+    /// 释放录音资源 (不改变录音状态)
+    ///
+    /// 关闭音频流 (含第二路录音)，清空缓冲、电平平滑值；设备参数保留最近一次的实际值 (start 时重新读取)，
+    /// 电平回调与监听设置保留
+    fn release(&mut self) {
+        *self.device_lost.lock().unwrap() = false;
+        self.device_watcher = None;
+        self.stream.set(None);
+        self.monitor = None;
+        self.secondary = None;
+        self.audio_data.lock().unwrap().reset(0);
+        *self.warmup_remaining.lock().unwrap() = 0;
+        *self.smoothed_level.lock().unwrap() = 0.0;
+        *self.last_emit_time.lock().unwrap() = Instant::now();
     }
 
     pub fn is_recording(&self) -> bool {
-        *self.is_recording.lock().unwrap()
+        self.state.is_recording()
+    }
+
+    /// 当前录音状态
+    pub fn state(&self) -> CaptureState {
+        self.state.state()
+    }
+
+    /// 最近一次录音的结束原因 (录音流出错时为 `DeviceLost`，达到时长上限时为 `MaxDuration`，否则为 `Manual`)
+    pub fn stop_reason(&self) -> StopReason {
+        if *self.device_lost.lock().unwrap() {
+            StopReason::DeviceLost
+        } else if self.audio_data.lock().unwrap().is_overflowed() {
+            StopReason::MaxDuration
+        } else {
+            StopReason::Manual
+        }
     }
 
     pub fn recording_mode(&self) -> Option<RecordingMode> {
-        *self.recording_mode.lock().unwrap()
+        self.state.mode()
+    }
+
+    /// 录音中切换录音模式 (如将按住录音锁定为切换录音)，不重启音频流
+    pub fn set_mode(&self, mode: RecordingMode) -> Result<(), RecordingError> {
+        self.state.set_mode(mode)
     }
 }
 
+/// 按设备的采样格式创建输入流，采集数据统一转换为 f32 后交给 `on_data`
+pub(crate) fn build_f32_input_stream<D, E>(
+    device: &cpal::Device,
+    supported_config: &cpal::SupportedStreamConfig,
+    mut on_data: D,
+    err_fn: E,
+) -> Result<Stream, RecordingError>
+where
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let config = supported_config.config();
+
+    macro_rules! build {
+        ($sample:ty, $convert:expr) => {
+            device.build_input_stream(
+                &config,
+                move |data: &[$sample], _: &cpal::InputCallbackInfo| on_data(&$convert(data)),
+                err_fn,
+                None,
+            )
+        };
+    }
+
+    let stream = match supported_config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| on_data(data),
+            err_fn,
+            None,
+        ),
+        cpal::SampleFormat::I16 => build!(i16, convert_i16_to_f32),
+        cpal::SampleFormat::U16 => build!(u16, convert_u16_to_f32),
+        cpal::SampleFormat::U8 => build!(u8, convert_u8_to_f32),
+        cpal::SampleFormat::I8 => build!(i8, convert_i8_to_f32),
+        cpal::SampleFormat::I32 => build!(i32, convert_i32_to_f32),
+        format => {
+            return Err(RecordingError::UnsupportedSampleFormat(format!("{:?}", format)));
+        }
+    };
+
+    stream.map_err(stream_error)
+}
+
+/// 音频流创建或启动失败 (设备可能已断开或被占用)，清除设备缓存使下次录音重新解析设备
+pub(crate) fn stream_error(e: impl std::fmt::Display) -> RecordingError {
+    invalidate_input_device_cache();
+    RecordingError::DeviceError(e.to_string())
+}
+
+/// 跳过预热窗口内的采样，返回剩余部分并扣减剩余预热采样数
+fn skip_warmup<'a>(data: &'a [f32], remaining: &mut usize) -> &'a [f32] {
+    let skip = (*remaining).min(data.len());
+    *remaining -= skip;
+    &data[skip..]
+}
+
 // ============================================================================
 // 音频格式转换函数
 // ============================================================================
@@ -381,6 +783,27 @@ pub fn convert_u16_to_f32(data: &[u16]) -> Vec<f32> {
         .collect()
 }
 
+/// 无符号 8 位 PCM 以 128 为零点
+#[inline]
+pub fn convert_u8_to_f32(data: &[u8]) -> Vec<f32> {
+    data.iter().map(|&s| (s as f32 - 128.0) / 128.0).collect()
+}
+
+/// 录音前的设备参数：默认输入设备的实际配置，无设备时为占位值
+pub(crate) fn initial_device_format() -> (u32, u16) {
+    super::default_input_format().unwrap_or((DEFAULT_DEVICE_SAMPLE_RATE, DEFAULT_CHANNELS))
+}
+
+#[inline]
+pub fn convert_i8_to_f32(data: &[i8]) -> Vec<f32> {
+    data.iter().map(|&s| s as f32 / i8::MAX as f32).collect()
+}
+
+#[inline]
+pub fn convert_i32_to_f32(data: &[i32]) -> Vec<f32> {
+    data.iter().map(|&s| (s as f64 / i32::MAX as f64) as f32).collect()
+}
+
 #[inline]
 pub fn convert_f32_to_i16(data: &[f32]) -> Vec<i16> {
     data.iter()
@@ -433,5 +856,295 @@ pub fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     output
 }
 
+/// 将采集到的交错样本转换为单声道、目标采样率的音频 (下混 + 重采样)
+///
+/// 无状态的纯函数，录音器停止时调用，也便于单独测试和基准测试 DSP 流程
+pub fn process_capture(raw: &[f32], channels: u16, from_rate: u32, to_rate: u32) -> AudioData {
+    let mono = to_mono(raw, channels);
+    let samples = if from_rate == to_rate {
+        mono
+    } else {
+        resample(&mono, from_rate, to_rate)
+    };
+    AudioData::new(samples, to_rate, 1)
+}
+
+/// 流式重采样器
+///
+/// 与 `resample` 使用相同的线性插值，但在块之间保留相位与上一块的末尾样本，
+/// 逐块处理时输出位置与一次性处理完全对齐，长时间录音不会累积截断误差。
+pub struct StreamingResampler {
+    from_rate: u32,
+    to_rate: u32,
+    /// 已消费的输入样本总数
+    input_offset: u64,
+    /// 已输出的样本总数
+    output_count: u64,
+    /// 上一块的最后一个样本 (用于跨块插值)
+    last_sample: Option<f32>,
+}
+
+impl StreamingResampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            from_rate,
+            to_rate,
+            input_offset: 0,
+            output_count: 0,
+            last_sample: None,
+        }
+    }
+
+    /// 处理一个输入块，返回可以确定的输出样本
+    ///
+    /// 需要下一块数据才能插值的末尾位置会保留到下一次调用
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.from_rate == self.to_rate || self.from_rate == 0 || self.to_rate == 0 {
+            self.input_offset += input.len() as u64;
+            self.output_count += input.len() as u64;
+            if let Some(&last) = input.last() {
+                self.last_sample = Some(last);
+            }
+            return input.to_vec();
+        }
+
+        let base = self.input_offset;
+        let end = base + input.len() as u64;
+        let capacity = (input.len() as u64 * self.to_rate as u64 / self.from_rate as u64) as usize + 1;
+        let mut output = Vec::with_capacity(capacity);
+
+        loop {
+            let (idx, frac) = self.source_position();
+            if idx + 1 >= end {
+                break;
+            }
+            let current = self.sample_at(input, base, idx);
+            let next = self.sample_at(input, base, idx + 1);
+            output.push((current as f64 * (1.0 - frac) + next as f64 * frac) as f32);
+            self.output_count += 1;
+        }
+
+        self.input_offset = end;
+        if let Some(&last) = input.last() {
+            self.last_sample = Some(last);
+        }
+
+        output
+    }
+
+    /// 输出所有剩余位置 (流结束时调用)，末尾无后继样本时保持最后一个样本
+    pub fn flush(&mut self) -> Vec<f32> {
+        let mut output = Vec::new();
+        let Some(last) = self.last_sample else {
+            return output;
+        };
+
+        if self.from_rate == self.to_rate || self.from_rate == 0 || self.to_rate == 0 {
+            return output;
+        }
+
+        while self.source_position().0 < self.input_offset {
+            output.push(last);
+            self.output_count += 1;
+        }
+
+        output
+    }
+
+    /// 重置状态 (开始新的录音时调用)
+    pub fn reset(&mut self) {
+        self.input_offset = 0;
+        self.output_count = 0;
+        self.last_sample = None;
+    }
+
+    /// 下一个输出样本对应的输入位置 (整数部分, 小数部分)
+    fn source_position(&self) -> (u64, f64) {
+        let numerator = self.output_count * self.from_rate as u64;
+        let to_rate = self.to_rate as u64;
+        (numerator / to_rate, (numerator % to_rate) as f64 / to_rate as f64)
+    }
+
+    fn sample_at(&self, input: &[f32], base: u64, idx: u64) -> f32 {
+        if idx < base {
+            self.last_sample.unwrap_or(0.0)
+        } else {
+            input[(idx - base) as usize]
+        }
+    }
+}
+
 unsafe impl Send for AudioRecorder {}
 unsafe impl Sync for AudioRecorder {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_warmup_discards_leading_samples() {
+        let mut remaining = 5;
+        assert!(skip_warmup(&[0.9; 3], &mut remaining).is_empty());
+        assert_eq!(remaining, 2);
+        assert_eq!(skip_warmup(&[0.1, 0.2, 0.3, 0.4], &mut remaining), &[0.3, 0.4]);
+        assert_eq!(remaining, 0);
+        assert_eq!(skip_warmup(&[0.5], &mut remaining), &[0.5]);
+    }
+
+    #[test]
+    fn test_convert_u8_to_f32_range() {
+        let out = convert_u8_to_f32(&[0, 128, 255]);
+        assert_eq!(out[0], -1.0);
+        assert_eq!(out[1], 0.0);
+        assert!((out[2] - 127.0 / 128.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_convert_i8_to_f32_range() {
+        let out = convert_i8_to_f32(&[i8::MIN, 0, i8::MAX]);
+        assert!(out[0] <= -1.0 && out[0] > -1.01);
+        assert_eq!(out[1], 0.0);
+        assert_eq!(out[2], 1.0);
+    }
+
+    #[test]
+    fn test_convert_i32_to_f32_range() {
+        let out = convert_i32_to_f32(&[i32::MIN, 0, i32::MAX, i32::MAX / 2]);
+        assert!((out[0] + 1.0).abs() < 1e-6);
+        assert_eq!(out[1], 0.0);
+        assert_eq!(out[2], 1.0);
+        assert!((out[3] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_process_capture_downmixes_and_resamples() {
+        // 0.1 秒 48kHz 立体声，左右声道相反，下混后为静音
+        let raw: Vec<f32> = (0..4800).flat_map(|_| [0.5f32, -0.5]).collect();
+        let audio = process_capture(&raw, 2, 48000, 16000);
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.sample_count(), 1600);
+        assert_eq!(audio.duration_ms, 100);
+        assert!(audio.samples.iter().all(|&s| s == 0.0));
+
+        // 采样率一致时只下混
+        let audio = process_capture(&[0.2, 0.4, 0.6, 0.8], 2, 16000, 16000);
+        assert_eq!(audio.samples.len(), 2);
+        assert!((audio.samples[0] - 0.3).abs() < 1e-6 && (audio.samples[1] - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_capture_buffer_caps_samples() {
+        let mut buffer = CaptureBuffer::new(10);
+
+        assert!(buffer.push(&[0.1; 6]));
+        assert!(!buffer.is_overflowed());
+
+        // 超出部分被丢弃，之后的写入全部拒绝
+        assert!(!buffer.push(&[0.2; 6]));
+        assert!(buffer.is_overflowed());
+        assert_eq!(buffer.samples().len(), 10);
+        assert!(!buffer.push(&[0.3; 1]));
+        assert_eq!(buffer.samples().len(), 10);
+
+        buffer.reset(10);
+        assert!(buffer.is_empty());
+        assert!(!buffer.is_overflowed());
+
+        // 只有首次达到上限的写入返回 true
+        assert!(!buffer.push_reached_limit(&[0.1; 6]));
+        assert!(buffer.push_reached_limit(&[0.1; 6]));
+        assert!(!buffer.push_reached_limit(&[0.1; 6]));
+    }
+
+    #[test]
+    fn test_stop_keeps_audio_after_reaching_limit() {
+        let mut recorder = AudioRecorder::new().unwrap();
+        recorder.device_sample_rate = TARGET_SAMPLE_RATE;
+        recorder.channels = 1;
+        recorder.audio_data.lock().unwrap().reset(TARGET_SAMPLE_RATE as usize);
+        recorder.state.start(RecordingMode::Toggle).unwrap();
+
+        recorder.audio_data.lock().unwrap().push(&[0.2; 20000]);
+        assert_eq!(recorder.stop_reason(), StopReason::MaxDuration);
+
+        let audio = recorder.stop().unwrap();
+        assert_eq!(audio.duration_ms, 1000);
+    }
+
+    #[test]
+    fn test_capture_buffer_for_duration() {
+        let buffer = CaptureBuffer::for_duration(2, 48000, 2);
+        assert_eq!(buffer.max_samples, 192_000);
+    }
+
+    #[test]
+    fn test_set_mode_while_recording() {
+        let recorder = AudioRecorder::new().unwrap();
+        assert!(matches!(
+            recorder.set_mode(RecordingMode::Toggle),
+            Err(RecordingError::NotRecording)
+        ));
+
+        recorder.state.start(RecordingMode::Press).unwrap();
+        recorder.set_mode(RecordingMode::Toggle).unwrap();
+        assert_eq!(recorder.recording_mode(), Some(RecordingMode::Toggle));
+    }
+
+    #[test]
+    fn test_reset_clears_recording_state() {
+        let mut recorder = AudioRecorder::new().unwrap();
+        recorder.device_sample_rate = 44100;
+        recorder.channels = 2;
+        recorder.audio_data.lock().unwrap().reset(1024);
+        recorder.audio_data.lock().unwrap().push(&[0.5; 64]);
+        *recorder.smoothed_level.lock().unwrap() = 0.8;
+        recorder.state.start(RecordingMode::Toggle).unwrap();
+
+        recorder.reset();
+
+        assert!(!recorder.is_recording());
+        assert_eq!(recorder.recording_mode(), None);
+        assert!(recorder.audio_data.lock().unwrap().is_empty());
+        assert!(!recorder.audio_data.lock().unwrap().is_overflowed());
+        assert_eq!(*recorder.smoothed_level.lock().unwrap(), 0.0);
+        // 设备参数不回退为占位值
+        assert_eq!(recorder.device_sample_rate(), 44100);
+        assert_eq!(recorder.device_channels(), 2);
+    }
+
+    #[test]
+    fn test_streaming_resampler_matches_single_shot() {
+        // 99 块 × 1000 样本 @ 48kHz -> 16kHz，块长度不是 3 的倍数
+        let input: Vec<f32> = (0..99_000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let single_shot = resample(&input, 48000, 16000);
+
+        let mut resampler = StreamingResampler::new(48000, 16000);
+        let mut streamed = Vec::new();
+        for chunk in input.chunks(1000) {
+            streamed.extend(resampler.process(chunk));
+        }
+        streamed.extend(resampler.flush());
+
+        assert_eq!(streamed.len(), single_shot.len());
+        for (a, b) in streamed.iter().zip(single_shot.iter()) {
+            assert!((a - b).abs() < 1e-4);
+        }
+
+        // 无状态逐块重采样会累积截断误差
+        let stateless: usize = input
+            .chunks(1000)
+            .map(|chunk| resample(chunk, 48000, 16000).len())
+            .sum();
+        assert!(stateless < single_shot.len());
+    }
+
+    #[test]
+    fn test_streaming_resampler_same_rate_passthrough() {
+        let mut resampler = StreamingResampler::new(16000, 16000);
+        let input = vec![0.1f32, 0.2, 0.3];
+
+        assert_eq!(resampler.process(&input), input);
+        assert!(resampler.flush().is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+// 录音状态机
+// 录音器的控制方法与音频回调共享同一状态，状态切换在锁内完成，非法切换返回类型化错误
+
+use std::sync::{Arc, Mutex};
+
+use super::recorder::{RecordingError, RecordingMode};
+
+/// 录音状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureState {
+    /// 空闲 (未录音或已停止)
+    Idle,
+    /// 录音中，音频回调采集数据
+    Recording,
+    /// 已暂停，音频流保持打开但丢弃采集数据
+    Paused,
+    /// 正在停止，等待音频流关闭并整理录音数据
+    Stopping,
+}
+
+impl std::fmt::Display for CaptureState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureState::Idle => write!(f, "idle"),
+            CaptureState::Recording => write!(f, "recording"),
+            CaptureState::Paused => write!(f, "paused"),
+            CaptureState::Stopping => write!(f, "stopping"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct StateInner {
+    state: CaptureState,
+    mode: Option<RecordingMode>,
+}
+
+/// 录音状态机
+///
+/// 克隆后共享同一状态 (音频回调线程持有克隆)；
+/// 合法切换：Idle → Recording ⇄ Paused → Stopping → Idle，录音中或暂停时可取消回到 Idle
+#[derive(Debug, Clone)]
+pub struct CaptureStateMachine {
+    inner: Arc<Mutex<StateInner>>,
+}
+
+impl CaptureStateMachine {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(StateInner {
+                state: CaptureState::Idle,
+                mode: None,
+            })),
+        }
+    }
+
+    /// 当前状态
+    pub fn state(&self) -> CaptureState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// 当前录音模式 (空闲与停止中为空)
+    pub fn mode(&self) -> Option<RecordingMode> {
+        self.inner.lock().unwrap().mode
+    }
+
+    /// 是否处于录音会话中 (录音中或已暂停)
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state(), CaptureState::Recording | CaptureState::Paused)
+    }
+
+    /// 音频回调是否应采集数据
+    pub fn is_capturing(&self) -> bool {
+        self.state() == CaptureState::Recording
+    }
+
+    /// Idle → Recording
+    pub fn start(&self, mode: RecordingMode) -> Result<(), RecordingError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CaptureState::Idle => {
+                inner.state = CaptureState::Recording;
+                inner.mode = Some(mode);
+                Ok(())
+            }
+            CaptureState::Recording | CaptureState::Paused => Err(RecordingError::AlreadyRecording),
+            state => Err(invalid_transition(state, "开始录音")),
+        }
+    }
+
+    /// Recording → Paused
+    pub fn pause(&self) -> Result<(), RecordingError> {
+        self.transition(CaptureState::Recording, CaptureState::Paused, "暂停录音")
+    }
+
+    /// Paused → Recording
+    pub fn resume(&self) -> Result<(), RecordingError> {
+        self.transition(CaptureState::Paused, CaptureState::Recording, "继续录音")
+    }
+
+    /// Recording / Paused → Stopping，完成后须调用 `finish`
+    pub fn begin_stop(&self) -> Result<(), RecordingError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CaptureState::Recording | CaptureState::Paused => {
+                inner.state = CaptureState::Stopping;
+                inner.mode = None;
+                Ok(())
+            }
+            CaptureState::Idle => Err(RecordingError::NotRecording),
+            state => Err(invalid_transition(state, "停止录音")),
+        }
+    }
+
+    /// Recording / Paused → Idle (取消录音；停止过程中不可取消)
+    pub fn cancel(&self) -> Result<(), RecordingError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CaptureState::Recording | CaptureState::Paused => {
+                inner.state = CaptureState::Idle;
+                inner.mode = None;
+                Ok(())
+            }
+            CaptureState::Idle => Err(RecordingError::NotRecording),
+            state => Err(invalid_transition(state, "取消录音")),
+        }
+    }
+
+    /// 录音中或暂停时切换录音模式
+    pub fn set_mode(&self, mode: RecordingMode) -> Result<(), RecordingError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CaptureState::Recording | CaptureState::Paused => {
+                inner.mode = Some(mode);
+                Ok(())
+            }
+            CaptureState::Idle => Err(RecordingError::NotRecording),
+            state => Err(invalid_transition(state, "切换录音模式")),
+        }
+    }
+
+    /// 无条件回到 Idle (停止完成、启动失败或重置录音器时调用)
+    pub fn finish(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = CaptureState::Idle;
+        inner.mode = None;
+    }
+
+    fn transition(
+        &self,
+        from: CaptureState,
+        to: CaptureState,
+        action: &'static str,
+    ) -> Result<(), RecordingError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            state if state == from => {
+                inner.state = to;
+                Ok(())
+            }
+            CaptureState::Idle => Err(RecordingError::NotRecording),
+            state => Err(invalid_transition(state, action)),
+        }
+    }
+}
+
+impl Default for CaptureStateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid_transition(state: CaptureState, action: &'static str) -> RecordingError {
+    RecordingError::InvalidTransition { state, action }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_pause_resume_stop() {
+        let machine = CaptureStateMachine::new();
+        machine.start(RecordingMode::Press).unwrap();
+        assert!(machine.is_capturing());
+        assert_eq!(machine.mode(), Some(RecordingMode::Press));
+
+        machine.pause().unwrap();
+        assert_eq!(machine.state(), CaptureState::Paused);
+        assert!(machine.is_recording() && !machine.is_capturing());
+        machine.set_mode(RecordingMode::Toggle).unwrap();
+
+        machine.resume().unwrap();
+        assert_eq!(machine.mode(), Some(RecordingMode::Toggle));
+
+        machine.begin_stop().unwrap();
+        assert_eq!(machine.state(), CaptureState::Stopping);
+        assert!(!machine.is_recording());
+        machine.finish();
+        assert_eq!(machine.state(), CaptureState::Idle);
+        assert_eq!(machine.mode(), None);
+    }
+
+    #[test]
+    fn test_double_start_and_double_stop_rejected() {
+        let machine = CaptureStateMachine::new();
+        machine.start(RecordingMode::Press).unwrap();
+        assert!(matches!(machine.start(RecordingMode::Press), Err(RecordingError::AlreadyRecording)));
+
+        machine.begin_stop().unwrap();
+        assert!(matches!(
+            machine.begin_stop(),
+            Err(RecordingError::InvalidTransition { state: CaptureState::Stopping, .. })
+        ));
+        assert!(matches!(
+            machine.start(RecordingMode::Press),
+            Err(RecordingError::InvalidTransition { state: CaptureState::Stopping, .. })
+        ));
+
+        machine.finish();
+        assert!(matches!(machine.begin_stop(), Err(RecordingError::NotRecording)));
+    }
+
+    #[test]
+    fn test_cancel_transitions() {
+        let machine = CaptureStateMachine::new();
+        assert!(matches!(machine.cancel(), Err(RecordingError::NotRecording)));
+
+        machine.start(RecordingMode::Toggle).unwrap();
+        machine.pause().unwrap();
+        machine.cancel().unwrap();
+        assert_eq!(machine.state(), CaptureState::Idle);
+
+        // 停止过程中取消被拒绝，停止流程不受干扰
+        machine.start(RecordingMode::Toggle).unwrap();
+        machine.begin_stop().unwrap();
+        assert!(matches!(
+            machine.cancel(),
+            Err(RecordingError::InvalidTransition { state: CaptureState::Stopping, .. })
+        ));
+        assert_eq!(machine.state(), CaptureState::Stopping);
+    }
+
+    #[test]
+    fn test_pause_resume_require_matching_state() {
+        let machine = CaptureStateMachine::new();
+        assert!(matches!(machine.pause(), Err(RecordingError::NotRecording)));
+        assert!(matches!(machine.resume(), Err(RecordingError::NotRecording)));
+        assert!(matches!(machine.set_mode(RecordingMode::Press), Err(RecordingError::NotRecording)));
+
+        machine.start(RecordingMode::Press).unwrap();
+        assert!(matches!(
+            machine.resume(),
+            Err(RecordingError::InvalidTransition { state: CaptureState::Recording, .. })
+        ));
+        machine.pause().unwrap();
+        assert!(matches!(
+            machine.pause(),
+            Err(RecordingError::InvalidTransition { state: CaptureState::Paused, .. })
+        ));
+    }
+}
@@ -1,5 +1,5 @@
 // 流式音频录制模块
-// 支持边录音边发送 PCM 数据块，用于实时 ASR
+// 支持边录音边发送 PCM 数据块，用于实时 ASR；外部音频来源可通过 ChunkProducer 产出相同格式的音频块
 
 macro_rules! log_info {
     ($($arg:tt)*) => {
@@ -19,23 +19,28 @@ macro_rules! log_error {
     }};
 }
 
-use cpal::traits::{DeviceTrait, StreamTrait};
-use cpal::Stream;
+use cpal::traits::StreamTrait;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tokio::sync::mpsc;
 
 use super::recorder::{
-    convert_i16_to_f32, convert_u16_to_f32, resample, to_mono, RecordingError, RecordingMode,
-    TARGET_SAMPLE_RATE,
+    build_f32_input_stream, convert_i16_to_f32, initial_device_format, process_capture, to_mono,
+    CaptureBuffer, RecordingError, RecordingMode, SharedStream, StreamingResampler, stream_error, DEFAULT_MAX_RECORDING_SECS, TARGET_SAMPLE_RATE,
 };
-use super::{select_input_device, utils};
+use super::state::{CaptureState, CaptureStateMachine};
+use super::{invalidate_input_device_cache, resolve_input_device, utils};
+use crate::voice::beep::{MonitorHandle, MonitorOutput};
 use crate::voice::config::AudioCompressionLevel;
-use super::AudioData;
+use super::{AudioData, StopReason};
 
 /// 每个音频块的样本数 (0.2秒 @ 16kHz = 3200 样本)
 pub const CHUNK_SAMPLES: usize = 3200;
 
+/// 帧时长上下限 (毫秒)，对应供应商常见的 20/40/100ms 帧要求
+pub const MIN_FRAME_MS: u32 = 10;
+pub const MAX_FRAME_MS: u32 = 1000;
+
 /// 音频块通道缓冲大小 (约 10 秒的音频)
 pub const CHUNK_CHANNEL_BUFFER: usize = 50;
 
@@ -46,24 +51,170 @@ pub const VAD_HANGOVER_CHUNKS: usize = 3;
 pub const AUDIO_LEVEL_EMIT_INTERVAL_MS: u128 = 33;
 
 /// 音频块数据 (PCM i16 格式)
+///
+/// 录音器与 `ChunkProducer` 产出的音频块均为 16kHz 单声道，`samples` 为一整帧
+/// (帧长由 `set_frame_ms` / `with_frame_ms` 决定，默认 `CHUNK_SAMPLES`)；
+/// `timestamp_ms` 为块首样本相对录音开始的时间
 #[derive(Debug, Clone)]
 pub struct AudioChunkData {
     pub samples: Vec<i16>,
     pub timestamp_ms: u64,
 }
 
+/// 固定帧长缓冲区
+///
+/// 采集回调每次交付的样本数不固定，累积后按固定 N 样本切帧输出，不足一帧的余量留待下次
+#[derive(Debug)]
+pub struct FrameBuffer {
+    frame_samples: usize,
+    pending: Vec<f32>,
+}
+
+impl FrameBuffer {
+    pub fn new(frame_samples: usize) -> Self {
+        let frame_samples = frame_samples.max(1);
+        Self {
+            frame_samples,
+            pending: Vec::with_capacity(frame_samples * 2),
+        }
+    }
+
+    /// 按帧时长 (毫秒) 创建，采样率为 16kHz 目标采样率
+    pub fn with_frame_ms(frame_ms: u32) -> Self {
+        let frame_ms = frame_ms.clamp(MIN_FRAME_MS, MAX_FRAME_MS);
+        Self::new(TARGET_SAMPLE_RATE as usize * frame_ms as usize / 1000)
+    }
+
+    pub fn frame_samples(&self) -> usize {
+        self.frame_samples
+    }
+
+    /// 追加样本，返回本次凑满的所有完整帧
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        self.pending.extend_from_slice(samples);
+
+        let frame_count = self.pending.len() / self.frame_samples;
+        if frame_count == 0 {
+            return Vec::new();
+        }
+
+        let consumed = frame_count * self.frame_samples;
+        let frames = self.pending[..consumed]
+            .chunks_exact(self.frame_samples)
+            .map(|frame| frame.to_vec())
+            .collect();
+        self.pending.drain(..consumed);
+        frames
+    }
+
+    /// 取出不足一帧的余量并补零为完整帧 (无余量时返回 None)
+    pub fn flush(&mut self) -> Option<Vec<f32>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let mut frame = std::mem::take(&mut self.pending);
+        frame.resize(self.frame_samples, 0.0);
+        Some(frame)
+    }
+}
+
+/// 外部音频来源的音频块生产者
+///
+/// 供非 cpal 来源 (文件解码、其他采集库、网络流等) 驱动实时转录：
+/// 任意采样率与声道数的样本经下混、流式重采样到 16kHz 后按固定帧长切块，
+/// 与 `StreamingRecorder` 产出的音频块格式一致，接收端可直接交给 `RealtimeTranscriptionTask`。
+///
+/// 保证：
+/// - 除 `finish` 补零的最后一块外，每块恰好一帧，帧长不随推送的数据量变化
+/// - 重采样在推送之间保留相位，分多次推送与一次推送的输出一致
+/// - 不做 VAD 与 AGC，静音块同样发送，由调用方决定是否过滤
+pub struct ChunkProducer {
+    channels: u16,
+    resampler: StreamingResampler,
+    frames: FrameBuffer,
+    chunk_tx: mpsc::Sender<AudioChunkData>,
+    samples_sent: u64,
+}
+
+impl ChunkProducer {
+    /// 按输入格式创建生产者，返回生产者与音频块接收端 (默认帧长 `CHUNK_SAMPLES`)
+    pub fn new(sample_rate: u32, channels: u16) -> (Self, mpsc::Receiver<AudioChunkData>) {
+        let (chunk_tx, chunk_rx) = mpsc::channel::<AudioChunkData>(CHUNK_CHANNEL_BUFFER);
+        let producer = Self {
+            channels: channels.max(1),
+            resampler: StreamingResampler::new(sample_rate, TARGET_SAMPLE_RATE),
+            frames: FrameBuffer::new(CHUNK_SAMPLES),
+            chunk_tx,
+            samples_sent: 0,
+        };
+        (producer, chunk_rx)
+    }
+
+    /// 设置输出帧时长 (毫秒)，取值限制在 `MIN_FRAME_MS`..=`MAX_FRAME_MS`
+    pub fn with_frame_ms(mut self, frame_ms: u32) -> Self {
+        self.frames = FrameBuffer::with_frame_ms(frame_ms);
+        self
+    }
+
+    pub fn frame_samples(&self) -> usize {
+        self.frames.frame_samples()
+    }
+
+    /// 推送交错排列的 f32 样本 (范围 -1.0..=1.0)，通道已满时等待
+    ///
+    /// 接收端已关闭时返回 `RecordingError::NotRecording`
+    pub async fn push(&mut self, samples: &[f32]) -> Result<(), RecordingError> {
+        let resampled = self.resampler.process(&to_mono(samples, self.channels));
+        for frame in self.frames.push(&resampled) {
+            self.send_frame(frame).await?;
+        }
+        Ok(())
+    }
+
+    /// 推送交错排列的 16-bit PCM 样本
+    pub async fn push_i16(&mut self, samples: &[i16]) -> Result<(), RecordingError> {
+        self.push(&convert_i16_to_f32(samples)).await
+    }
+
+    /// 结束推送：不足一帧的余量补零后发出，随后关闭音频块通道
+    pub async fn finish(mut self) -> Result<(), RecordingError> {
+        if let Some(frame) = self.frames.flush() {
+            self.send_frame(frame).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_frame(&mut self, frame: Vec<f32>) -> Result<(), RecordingError> {
+        let timestamp_ms = self.samples_sent * 1000 / TARGET_SAMPLE_RATE as u64;
+        self.samples_sent += frame.len() as u64;
+        let samples = frame
+            .iter()
+            .map(|&s| (s * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+            .collect();
+        self.chunk_tx
+            .send(AudioChunkData { samples, timestamp_ms })
+            .await
+            .map_err(|_| RecordingError::NotRecording)
+    }
+}
+
 /// 音频级别回调类型
 pub type StreamingLevelCallback = Box<dyn Fn(f32, Vec<f32>) + Send + 'static>;
 
 /// 流式音频录制器
+///
+/// `start_streaming` 返回音频块接收端，采集数据重采样到 16kHz 单声道后按 `set_frame_ms`
+/// 设置的帧长切块发送；静音块经 VAD 过滤不发送，通道满时丢弃新块
 pub struct StreamingRecorder {
     device_sample_rate: u32,
     channels: u16,
-    is_recording: Arc<Mutex<bool>>,
-    recording_mode: Arc<Mutex<Option<RecordingMode>>>,
-    stream: Option<Stream>,
+    state: CaptureStateMachine,
+    /// 录音流是否出错 (设备断开等)
+    device_lost: Arc<Mutex<bool>>,
+    stream: SharedStream,
     chunk_sender: Option<mpsc::Sender<AudioChunkData>>,
-    full_audio_data: Arc<Mutex<Vec<f32>>>,
+    full_audio_data: Arc<Mutex<CaptureBuffer>>,
+    max_recording_secs: u32,
     level_callback: Arc<Mutex<Option<StreamingLevelCallback>>>,
     smoothed_level: Arc<Mutex<f32>>,
     start_time: Arc<Mutex<Option<std::time::Instant>>>,
@@ -71,18 +222,24 @@ pub struct StreamingRecorder {
     agc_gain: Arc<Mutex<f32>>,
     last_emit_time: Arc<Mutex<Instant>>,
     compression_level: AudioCompressionLevel,
+    monitor_enabled: bool,
+    monitor_volume: f32,
+    monitor: Option<MonitorOutput>,
+    frame_samples: usize,
 }
 
 impl StreamingRecorder {
     pub fn new() -> Result<Self, RecordingError> {
+        let (device_sample_rate, channels) = initial_device_format();
         Ok(Self {
-            device_sample_rate: 48000,
-            channels: 1,
-            is_recording: Arc::new(Mutex::new(false)),
-            recording_mode: Arc::new(Mutex::new(None)),
-            stream: None,
+            device_sample_rate,
+            channels,
+            state: CaptureStateMachine::new(),
+            device_lost: Arc::new(Mutex::new(false)),
+            stream: SharedStream::default(),
             chunk_sender: None,
-            full_audio_data: Arc::new(Mutex::new(Vec::new())),
+            full_audio_data: Arc::new(Mutex::new(CaptureBuffer::new(0))),
+            max_recording_secs: DEFAULT_MAX_RECORDING_SECS,
             level_callback: Arc::new(Mutex::new(None)),
             smoothed_level: Arc::new(Mutex::new(0.0)),
             start_time: Arc::new(Mutex::new(None)),
@@ -90,9 +247,29 @@ impl StreamingRecorder {
             agc_gain: Arc::new(Mutex::new(1.0)),
             last_emit_time: Arc::new(Mutex::new(Instant::now())),
             compression_level: AudioCompressionLevel::Minimum,
+            monitor_enabled: false,
+            monitor_volume: 0.5,
+            monitor: None,
+            frame_samples: CHUNK_SAMPLES,
         })
     }
 
+    /// 设置自我监听 (录音时将采集到的音频回放到默认输出设备)
+    pub fn set_monitor(&mut self, enabled: bool, volume: f32) {
+        self.monitor_enabled = enabled;
+        self.monitor_volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// 设置录音时长上限 (秒)，达到上限后关闭音频流、停止发送音频块，停止时返回上限内的录音
+    pub fn set_max_recording_secs(&mut self, max_secs: u32) {
+        self.max_recording_secs = max_secs.max(1);
+    }
+
+    /// 设置输出帧时长 (毫秒)，按供应商要求切分音频块，默认 200ms
+    pub fn set_frame_ms(&mut self, frame_ms: u32) {
+        self.frame_samples = FrameBuffer::with_frame_ms(frame_ms).frame_samples();
+    }
+
     pub fn set_level_callback<F>(&mut self, callback: F)
     where
         F: Fn(f32, Vec<f32>) + Send + 'static,
@@ -107,37 +284,49 @@ impl StreamingRecorder {
         device_name: Option<&str>,
         compression_level: AudioCompressionLevel,
     ) -> Result<mpsc::Receiver<AudioChunkData>, RecordingError> {
-        {
-            let is_recording = self.is_recording.lock().unwrap();
-            if *is_recording {
-                return Err(RecordingError::AlreadyRecording);
-            }
-        }
+        // 先占用录音状态，重复启动或停止过程中启动直接拒绝
+        self.state.start(mode)?;
 
         log_info!("开始流式录音，模式: {:?}", mode);
+        self.compression_level = compression_level;
 
-        self.full_audio_data.lock().unwrap().clear();
-        *self.is_recording.lock().unwrap() = true;
-        *self.recording_mode.lock().unwrap() = Some(mode);
+        // 打开失败时回到空闲状态，不会残留录音状态
+        match self.open_stream(device_name) {
+            Ok(chunk_rx) => {
+                log_info!("流式录音已启动");
+                Ok(chunk_rx)
+            }
+            Err(e) => {
+                self.release();
+                self.state.finish();
+                Err(e)
+            }
+        }
+    }
+
+    /// 打开输入设备并启动音频流，返回音频块接收端
+    fn open_stream(
+        &mut self,
+        device_name: Option<&str>,
+    ) -> Result<mpsc::Receiver<AudioChunkData>, RecordingError> {
+        *self.device_lost.lock().unwrap() = false;
         *self.smoothed_level.lock().unwrap() = 0.0;
         *self.start_time.lock().unwrap() = Some(std::time::Instant::now());
         *self.vad_hangover.lock().unwrap() = 0;
         *self.agc_gain.lock().unwrap() = 1.0;
         *self.last_emit_time.lock().unwrap() = Instant::now();
-        self.compression_level = compression_level;
 
         let (chunk_tx, chunk_rx) = mpsc::channel::<AudioChunkData>(CHUNK_CHANNEL_BUFFER);
         self.chunk_sender = Some(chunk_tx.clone());
 
-        let device = select_input_device(device_name)?;
-
-        let supported_config = device
-            .default_input_config()
-            .map_err(|e| RecordingError::DeviceError(format!("无法获取默认音频配置: {}", e)))?;
+        let (device, supported_config) = resolve_input_device(device_name)?;
 
         let config = supported_config.config();
         self.device_sample_rate = config.sample_rate.0;
         self.channels = config.channels;
+        self.full_audio_data.lock().unwrap().reset(
+            self.max_recording_secs as usize * self.device_sample_rate as usize * self.channels as usize,
+        );
 
         let target_sample_rate = utils::resolve_compression_sample_rate(
             self.device_sample_rate,
@@ -149,10 +338,23 @@ impl StreamingRecorder {
             self.device_sample_rate,
             self.channels,
             target_sample_rate,
-            CHUNK_SAMPLES
+            self.frame_samples
         );
 
-        let is_recording = Arc::clone(&self.is_recording);
+        self.monitor = if self.monitor_enabled {
+            match MonitorOutput::open(self.monitor_volume, self.device_sample_rate, self.channels) {
+                Ok(monitor) => Some(monitor),
+                Err(e) => {
+                    log_warn!("无法打开监听输出，继续录音: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let monitor_handle = self.monitor.as_ref().map(|m| m.handle());
+
+        let state = self.state.clone();
         let full_audio_data = Arc::clone(&self.full_audio_data);
         let level_callback = Arc::clone(&self.level_callback);
         let smoothed_level = Arc::clone(&self.smoothed_level);
@@ -162,144 +364,58 @@ impl StreamingRecorder {
         let last_emit_time = Arc::clone(&self.last_emit_time);
         let device_sample_rate = self.device_sample_rate;
         let channels = self.channels;
+        let shared_stream = self.stream.clone();
+
+        let pending_samples = Arc::new(Mutex::new(FrameBuffer::new(self.frame_samples)));
+        let resampler = Arc::new(Mutex::new(StreamingResampler::new(
+            device_sample_rate,
+            TARGET_SAMPLE_RATE,
+        )));
+
+        let device_lost = Arc::clone(&self.device_lost);
+        let err_fn = move |err| {
+            log_error!("录音流错误: {}", err);
+            *device_lost.lock().unwrap() = true;
+            invalidate_input_device_cache();
+        };
 
-        let pending_samples: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
-
-        let err_fn = |err| log_error!("录音流错误: {}", err);
-
-        let stream = match supported_config.sample_format() {
-            cpal::SampleFormat::F32 => {
-                let pending = Arc::clone(&pending_samples);
-                let chunk_tx = chunk_tx.clone();
-                let vad_hangover = Arc::clone(&vad_hangover);
-                let agc_gain = Arc::clone(&agc_gain);
-                let last_emit_time = Arc::clone(&last_emit_time);
-
-                device
-                    .build_input_stream(
-                        &config,
-                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                            Self::handle_streaming_callback(
-                                data,
-                                &is_recording,
-                                &full_audio_data,
-                                &pending,
-                                &chunk_tx,
-                                &level_callback,
-                                &smoothed_level,
-                                &start_time,
-                                &vad_hangover,
-                                &agc_gain,
-                                &last_emit_time,
-                                device_sample_rate,
-                                channels,
-                            );
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| RecordingError::DeviceError(e.to_string()))?
-            }
-            cpal::SampleFormat::I16 => {
-                let is_recording = Arc::clone(&is_recording);
-                let full_audio_data = Arc::clone(&full_audio_data);
-                let pending = Arc::clone(&pending_samples);
-                let level_callback = Arc::clone(&level_callback);
-                let smoothed_level = Arc::clone(&smoothed_level);
-                let start_time = Arc::clone(&start_time);
-                let chunk_tx = chunk_tx.clone();
-                let vad_hangover = Arc::clone(&vad_hangover);
-                let agc_gain = Arc::clone(&agc_gain);
-                let last_emit_time = Arc::clone(&last_emit_time);
-
-                device
-                    .build_input_stream(
-                        &config,
-                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                            let f32_data = convert_i16_to_f32(data);
-                            Self::handle_streaming_callback(
-                                &f32_data,
-                                &is_recording,
-                                &full_audio_data,
-                                &pending,
-                                &chunk_tx,
-                                &level_callback,
-                                &smoothed_level,
-                                &start_time,
-                                &vad_hangover,
-                                &agc_gain,
-                                &last_emit_time,
-                                device_sample_rate,
-                                channels,
-                            );
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| RecordingError::DeviceError(e.to_string()))?
-            }
-            cpal::SampleFormat::U16 => {
-                let is_recording = Arc::clone(&is_recording);
-                let full_audio_data = Arc::clone(&full_audio_data);
-                let pending = Arc::clone(&pending_samples);
-                let level_callback = Arc::clone(&level_callback);
-                let smoothed_level = Arc::clone(&smoothed_level);
-                let start_time = Arc::clone(&start_time);
-                let chunk_tx = chunk_tx.clone();
-                let vad_hangover = Arc::clone(&vad_hangover);
-                let agc_gain = Arc::clone(&agc_gain);
-                let last_emit_time = Arc::clone(&last_emit_time);
-
-                device
-                    .build_input_stream(
-                        &config,
-                        move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                            let f32_data = convert_u16_to_f32(data);
-                            Self::handle_streaming_callback(
-                                &f32_data,
-                                &is_recording,
-                                &full_audio_data,
-                                &pending,
-                                &chunk_tx,
-                                &level_callback,
-                                &smoothed_level,
-                                &start_time,
-                                &vad_hangover,
-                                &agc_gain,
-                                &last_emit_time,
-                                device_sample_rate,
-                                channels,
-                            );
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| RecordingError::DeviceError(e.to_string()))?
-            }
-            format => {
-                return Err(RecordingError::UnsupportedSampleFormat(format!(
-                    "{:?}",
-                    format
-                )));
-            }
+        let on_data = move |data: &[f32]| {
+            Self::handle_streaming_callback(
+                data,
+                &state,
+                &full_audio_data,
+                &shared_stream,
+                &pending_samples,
+                &resampler,
+                &chunk_tx,
+                &level_callback,
+                &smoothed_level,
+                &start_time,
+                &vad_hangover,
+                &agc_gain,
+                &last_emit_time,
+                monitor_handle.as_ref(),
+                channels,
+            );
         };
+        let stream = build_f32_input_stream(&device, &supported_config, on_data, err_fn)?;
 
         stream
             .play()
-            .map_err(|e| RecordingError::DeviceError(e.to_string()))?;
-
-        self.stream = Some(stream);
+            .map_err(stream_error)?;
 
-        log_info!("流式录音已启动");
+        self.stream.set(Some(stream));
         Ok(chunk_rx)
     }
 
     #[allow(clippy::too_many_arguments)]
     fn handle_streaming_callback(
         data: &[f32],
-        is_recording: &Arc<Mutex<bool>>,
-        full_audio_data: &Arc<Mutex<Vec<f32>>>,
-        pending_samples: &Arc<Mutex<Vec<f32>>>,
+        state: &CaptureStateMachine,
+        full_audio_data: &Arc<Mutex<CaptureBuffer>>,
+        stream: &SharedStream,
+        pending_samples: &Arc<Mutex<FrameBuffer>>,
+        resampler: &Arc<Mutex<StreamingResampler>>,
         chunk_tx: &mpsc::Sender<AudioChunkData>,
         level_callback: &Arc<Mutex<Option<StreamingLevelCallback>>>,
         smoothed_level: &Arc<Mutex<f32>>,
@@ -307,17 +423,29 @@ impl StreamingRecorder {
         vad_hangover: &Arc<Mutex<usize>>,
         agc_gain: &Arc<Mutex<f32>>,
         last_emit_time: &Arc<Mutex<Instant>>,
-        device_sample_rate: u32,
+        monitor: Option<&MonitorHandle>,
         channels: u16,
     ) {
-        if !*is_recording.lock().unwrap() {
+        if !state.is_capturing() {
             return;
         }
 
-        full_audio_data.lock().unwrap().extend_from_slice(data);
+        {
+            let mut buffer = full_audio_data.lock().unwrap();
+            if buffer.push_reached_limit(data) {
+                stream.close_in_background();
+            }
+            if buffer.is_overflowed() {
+                return;
+            }
+        }
+
+        if let Some(monitor) = monitor {
+            monitor.push(data);
+        }
 
         let mono = to_mono(data, channels);
-        let resampled = resample(&mono, device_sample_rate, TARGET_SAMPLE_RATE);
+        let resampled = resampler.lock().unwrap().process(&mono);
 
         {
             let mut last_emit = last_emit_time.lock().unwrap();
@@ -336,16 +464,17 @@ impl StreamingRecorder {
         }
 
         let mut pending = pending_samples.lock().unwrap();
-        pending.extend(resampled);
-
-        while pending.len() >= CHUNK_SAMPLES {
-            let mut chunk_f32: Vec<f32> = pending.drain(..CHUNK_SAMPLES).collect();
+        let frames = pending.push(&resampled);
+        // 拖尾时长保持与默认块大小一致 (约 0.6 秒)，不随帧长缩放
+        let hangover_frames = (VAD_HANGOVER_CHUNKS * CHUNK_SAMPLES).div_ceil(pending.frame_samples());
+        drop(pending);
 
+        for mut chunk_f32 in frames {
             let is_active = utils::is_voice_active(&chunk_f32);
             let mut hangover = vad_hangover.lock().unwrap();
 
             if is_active {
-                *hangover = VAD_HANGOVER_CHUNKS;
+                *hangover = hangover_frames;
             } else if *hangover > 0 {
                 *hangover -= 1;
             }
@@ -384,44 +513,52 @@ impl StreamingRecorder {
     }
 
     pub fn stop_streaming(&mut self) -> Result<AudioData, RecordingError> {
-        {
-            let is_recording = self.is_recording.lock().unwrap();
-            if !*is_recording {
-                return Err(RecordingError::NotRecording);
-            }
+        if !self.state.is_recording() {
+            return Err(RecordingError::NotRecording);
         }
 
         log_info!("停止流式录音...");
 
         std::thread::sleep(std::time::Duration::from_millis(200));
 
-        *self.is_recording.lock().unwrap() = false;
-        *self.recording_mode.lock().unwrap() = None;
+        self.state.begin_stop()?;
 
         std::thread::sleep(std::time::Duration::from_millis(100));
 
-        self.stream = None;
+        self.stream.set(None);
         self.chunk_sender = None;
+        self.monitor = None;
 
-        let raw_audio = self.full_audio_data.lock().unwrap().clone();
+        let result = self.take_audio();
+        self.state.finish();
+        result
+    }
+
+    /// 取出完整录音并转换为目标格式 (单声道、压缩采样率)
+    fn take_audio(&mut self) -> Result<AudioData, RecordingError> {
+        let raw_audio = {
+            let buffer = self.full_audio_data.lock().unwrap();
+            if buffer.is_overflowed() {
+                log_warn!("录音达到时长上限 ({} 秒)，使用上限内的音频", self.max_recording_secs);
+            }
+            buffer.samples().to_vec()
+        };
 
         if raw_audio.is_empty() {
             log_warn!("没有录制到音频数据");
             return Ok(AudioData::new(Vec::new(), TARGET_SAMPLE_RATE, 1));
         }
 
-        let mono_audio = to_mono(&raw_audio, self.channels);
         let target_sample_rate = utils::resolve_compression_sample_rate(
             self.device_sample_rate,
             self.compression_level,
         );
-        let resampled_audio = if target_sample_rate == self.device_sample_rate {
-            mono_audio
-        } else {
-            resample(&mono_audio, self.device_sample_rate, target_sample_rate)
-        };
-
-        let audio_data = AudioData::new(resampled_audio, target_sample_rate, 1);
+        let audio_data = process_capture(
+            &raw_audio,
+            self.channels,
+            self.device_sample_rate,
+            target_sample_rate,
+        );
         log_info!(
             "流式录音停止，完整音频时长: {}ms",
             audio_data.duration_ms
@@ -430,24 +567,125 @@ impl StreamingRecorder {
         Ok(audio_data)
     }
 
-    pub fn cancel(&mut self) {
+    /// 取消流式录音 (停止过程中不可取消)
+    pub fn cancel(&mut self) -> Result<(), RecordingError> {
+        self.state.cancel()?;
         log_info!("取消流式录音");
+        self.release();
+        Ok(())
+    }
+
+    /// 暂停流式录音，暂停期间不采集也不发送音频块
+    pub fn pause(&self) -> Result<(), RecordingError> {
+        self.state.pause()?;
+        log_info!("流式录音已暂停");
+        Ok(())
+    }
 
-        *self.is_recording.lock().unwrap() = false;
-        *self.recording_mode.lock().unwrap() = None;
-        self.stream = None;
+    /// 继续已暂停的流式录音
+    pub fn resume(&self) -> Result<(), RecordingError> {
+        self.state.resume()?;
+        log_info!("流式录音已继续");
+        Ok(())
+    }
+
+    /// 关闭音频流与音频块通道，清空完整录音缓冲 (不改变录音状态)
+    fn release(&mut self) {
+        self.stream.set(None);
         self.chunk_sender = None;
-        self.full_audio_data.lock().unwrap().clear();
+        self.monitor = None;
+        self.full_audio_data.lock().unwrap().reset(0);
     }
 
     pub fn is_recording(&self) -> bool {
-        *self.is_recording.lock().unwrap()
+        self.state.is_recording()
+    }
+
+    /// 当前录音状态
+    pub fn state(&self) -> CaptureState {
+        self.state.state()
+    }
+
+    /// 最近一次录音的结束原因 (录音流出错时为 `DeviceLost`，达到时长上限时为 `MaxDuration`，否则为 `Manual`)
+    pub fn stop_reason(&self) -> StopReason {
+        if *self.device_lost.lock().unwrap() {
+            StopReason::DeviceLost
+        } else if self.full_audio_data.lock().unwrap().is_overflowed() {
+            StopReason::MaxDuration
+        } else {
+            StopReason::Manual
+        }
     }
 
     pub fn recording_mode(&self) -> Option<RecordingMode> {
-        *self.recording_mode.lock().unwrap()
+        self.state.mode()
+    }
+
+    /// 录音中切换录音模式 (如将按住录音锁定为切换录音)，不重启音频流
+    pub fn set_mode(&self, mode: RecordingMode) -> Result<(), RecordingError> {
+        self.state.set_mode(mode)
     }
 }
 
 unsafe impl Send for StreamingRecorder {}
 unsafe impl Sync for StreamingRecorder {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_buffer_emits_fixed_frames() {
+        let mut buffer = FrameBuffer::new(4);
+
+        assert!(buffer.push(&[0.1, 0.2, 0.3]).is_empty());
+
+        let frames = buffer.push(&[0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0]);
+        assert_eq!(frames, vec![vec![0.1, 0.2, 0.3, 0.4], vec![0.5, 0.6, 0.7, 0.8]]);
+
+        let frames = buffer.push(&[0.0, 0.0]);
+        assert_eq!(frames, vec![vec![0.9, 1.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_frame_buffer_frame_ms() {
+        assert_eq!(FrameBuffer::with_frame_ms(20).frame_samples(), 320);
+        assert_eq!(FrameBuffer::with_frame_ms(100).frame_samples(), 1600);
+        assert_eq!(FrameBuffer::with_frame_ms(1).frame_samples(), 160);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_producer_resamples_into_fixed_frames() {
+        // 48kHz 立体声输入，20ms 帧 (320 样本 @ 16kHz)
+        let (producer, mut chunk_rx) = ChunkProducer::new(48000, 2);
+        let mut producer = producer.with_frame_ms(20);
+        assert_eq!(producer.frame_samples(), 320);
+
+        // 分多次推送 100ms 音频，推送块大小与帧长无关
+        let data: Vec<f32> = std::iter::repeat_n([0.5f32, 0.5], 4800).flatten().collect();
+        for part in data.chunks(1234) {
+            producer.push(part).await.unwrap();
+        }
+        producer.finish().await.unwrap();
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = chunk_rx.recv().await {
+            chunks.push(chunk);
+        }
+        assert!(chunks.len() >= 5);
+        assert!(chunks.iter().all(|c| c.samples.len() == 320));
+        let timestamps: Vec<u64> = chunks.iter().map(|c| c.timestamp_ms).collect();
+        assert_eq!(&timestamps[..5], &[0, 20, 40, 60, 80]);
+        assert!((chunks[0].samples[0] - i16::MAX / 2).abs() <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_producer_reports_closed_receiver() {
+        let (mut producer, chunk_rx) = ChunkProducer::new(TARGET_SAMPLE_RATE, 1);
+        drop(chunk_rx);
+        assert!(matches!(
+            producer.push_i16(&[0; CHUNK_SAMPLES]).await,
+            Err(RecordingError::NotRecording)
+        ));
+    }
+}
@@ -1,9 +1,10 @@
 // 音频反馈播放器模块
 // 使用 rodio 实现录音开始/结束提示音
 
-use rodio::{OutputStreamBuilder, Sink, Source};
+use cpal::traits::HostTrait;
+use rodio::{OutputStream, OutputStreamBuilder, Sink, Source};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 /// 日志宏
@@ -21,6 +22,32 @@ macro_rules! log_error {
     };
 }
 
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        eprintln!("[WARN] [beep] {}", format!($($arg)*));
+    };
+}
+
+/// 监听缓冲时长上限 (毫秒)，回放落后超过该时长时丢弃最旧的采样，延迟不随录音时长累积
+const MONITOR_BUFFER_MS: usize = 200;
+
+/// 监听输出每次从缓冲区取出的帧数
+const MONITOR_READ_FRAMES: usize = 128;
+
+/// 输出设备探测结果 (进程内只探测一次)
+static OUTPUT_AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+/// 探测是否存在可用的音频输出设备
+fn probe_output_device() -> bool {
+    *OUTPUT_AVAILABLE.get_or_init(|| {
+        let available = cpal::default_host().default_output_device().is_some();
+        if !available {
+            log_warn!("未检测到音频输出设备，提示音已禁用");
+        }
+        available
+    })
+}
+
 /// 提示音类型
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BeepType {
@@ -28,6 +55,8 @@ pub enum BeepType {
     RecordingStart,
     /// 录音结束提示音 (下降音调)
     RecordingStop,
+    /// 录音取消提示音 (两声短促低音)
+    RecordingCancel,
 }
 
 /// 音频反馈播放器
@@ -38,6 +67,8 @@ pub struct BeepPlayer {
     enabled: Arc<AtomicBool>,
     /// 音量 (0.0 - 1.0)
     volume: f32,
+    /// 是否存在可用的输出设备 (无设备时始终禁用，避免每次播放都失败)
+    output_available: bool,
 }
 
 impl Default for BeepPlayer {
@@ -49,23 +80,27 @@ impl Default for BeepPlayer {
 impl BeepPlayer {
     /// 创建新的播放器实例
     pub fn new() -> Self {
-        Self {
-            enabled: Arc::new(AtomicBool::new(true)),
-            volume: 0.3, // 默认音量 30%
-        }
+        Self::with_volume(0.3) // 默认音量 30%
     }
 
     /// 创建带自定义音量的播放器
     pub fn with_volume(volume: f32) -> Self {
+        let output_available = probe_output_device();
         Self {
-            enabled: Arc::new(AtomicBool::new(true)),
+            enabled: Arc::new(AtomicBool::new(output_available)),
             volume: volume.clamp(0.0, 1.0),
+            output_available,
         }
     }
 
-    /// 设置是否启用音频反馈
+    /// 设置是否启用音频反馈 (无输出设备时保持禁用)
     pub fn set_enabled(&self, enabled: bool) {
-        self.enabled.store(enabled, Ordering::SeqCst);
+        self.enabled.store(enabled && self.output_available, Ordering::SeqCst);
+    }
+
+    /// 是否存在可用的音频输出设备
+    pub fn is_output_available(&self) -> bool {
+        self.output_available
     }
 
     /// 检查是否启用音频反馈
@@ -93,6 +128,11 @@ impl BeepPlayer {
         self.play(BeepType::RecordingStop);
     }
 
+    /// 播放录音取消提示音 (非阻塞)
+    pub fn play_cancel(&self) {
+        self.play(BeepType::RecordingCancel);
+    }
+
     /// 播放指定类型的提示音 (非阻塞)
     pub fn play(&self, beep_type: BeepType) {
         if !self.is_enabled() {
@@ -111,28 +151,41 @@ impl BeepPlayer {
     }
 }
 
-/// 阻塞式播放提示音
-fn play_beep_blocking(beep_type: BeepType, volume: f32) -> Result<(), BeepError> {
+/// 打开默认音频输出设备并创建 Sink
+///
+/// 返回的 OutputStream 必须与 Sink 一起保持存活，否则播放会立即停止
+fn open_output_sink() -> Result<(OutputStream, Sink), BeepError> {
     // 获取音频输出流 (rodio 0.21 新 API)
     let stream = OutputStreamBuilder::open_default_stream()
         .map_err(|e| BeepError::OutputStreamError(e.to_string()))?;
     
     let mixer = stream.mixer();
     let sink = Sink::connect_new(&mixer);
+    
+    Ok((stream, sink))
+}
+
+/// 阻塞式播放提示音
+fn play_beep_blocking(beep_type: BeepType, volume: f32) -> Result<(), BeepError> {
+    let (_stream, sink) = open_output_sink()?;
 
     // 根据提示音类型生成不同的音调
-    let source = match beep_type {
+    match beep_type {
         BeepType::RecordingStart => {
             // 上升音调: 440Hz -> 880Hz (A4 -> A5)
-            create_sweep_tone(440.0, 880.0, 150, volume)
+            sink.append(create_sweep_tone(440.0, 880.0, 150, volume));
         }
         BeepType::RecordingStop => {
             // 下降音调: 880Hz -> 440Hz (A5 -> A4)
-            create_sweep_tone(880.0, 440.0, 150, volume)
+            sink.append(create_sweep_tone(880.0, 440.0, 150, volume));
         }
-    };
+        BeepType::RecordingCancel => {
+            // 两声短促低音: 330Hz (E4)
+            sink.append(create_sweep_tone(330.0, 330.0, 80, volume));
+            sink.append(create_sweep_tone(330.0, 330.0, 80, volume));
+        }
+    }
 
-    sink.append(source);
     sink.sleep_until_end();
 
     Ok(())
@@ -238,6 +291,176 @@ fn calculate_envelope(progress: f32) -> f32 {
     }
 }
 
+/// 监听输出 (录音时将采集到的音频回放到输出设备，用于自我监听)
+///
+/// 仅持有输出流和 Sink，不会修改录音缓冲区；采集数据经定长环形缓冲区交给输出端
+pub struct MonitorOutput {
+    /// 输出流 (需保持存活)
+    _stream: OutputStream,
+    /// 播放 Sink
+    sink: Sink,
+    /// 采集回调写入、输出端读取的环形缓冲区
+    ring: Arc<Mutex<SampleRing>>,
+}
+
+impl MonitorOutput {
+    /// 打开默认输出设备，按采集设备的采样率和声道创建监听输出
+    pub fn open(volume: f32, sample_rate: u32, channels: u16) -> Result<Self, BeepError> {
+        let (mut stream, sink) = open_output_sink()?;
+        stream.log_on_drop(false);
+        sink.set_volume(volume.clamp(0.0, 1.0));
+        
+        let sample_rate = sample_rate.max(1);
+        let channels = channels.max(1);
+        let frames = (sample_rate as usize * MONITOR_BUFFER_MS / 1000).max(1);
+        let ring = Arc::new(Mutex::new(SampleRing::new(frames * channels as usize)));
+        sink.append(MonitorSource::new(Arc::clone(&ring), sample_rate, channels));
+        
+        log_debug!("监听输出已打开，音量: {}", volume);
+        
+        Ok(Self {
+            _stream: stream,
+            sink,
+            ring,
+        })
+    }
+    
+    /// 获取可在音频回调中使用的句柄
+    pub fn handle(&self) -> MonitorHandle {
+        MonitorHandle {
+            ring: Arc::clone(&self.ring),
+        }
+    }
+}
+
+impl Drop for MonitorOutput {
+    fn drop(&mut self) {
+        self.sink.stop();
+    }
+}
+
+/// 监听输出句柄
+#[derive(Clone)]
+pub struct MonitorHandle {
+    ring: Arc<Mutex<SampleRing>>,
+}
+
+impl MonitorHandle {
+    /// 推送一帧采集到的音频 (打开监听时的采样率和声道)，缓冲区写满时丢弃最旧的采样
+    pub fn push(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        self.ring.lock().unwrap().write(samples);
+    }
+}
+
+/// 定长环形缓冲区 (创建时预分配，写满时覆盖最旧的采样)
+///
+/// 容量与每次写入的采样数均为声道数的整数倍，丢弃时按整帧对齐
+struct SampleRing {
+    samples: Box<[f32]>,
+    /// 最旧采样的位置
+    start: usize,
+    len: usize,
+}
+
+impl SampleRing {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: vec![0.0; capacity.max(1)].into_boxed_slice(),
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// 写入采样，超出容量时丢弃最旧的采样 (单次写入超过容量时只保留最新部分)
+    fn write(&mut self, data: &[f32]) {
+        let capacity = self.samples.len();
+        let data = &data[data.len().saturating_sub(capacity)..];
+        for &sample in data {
+            self.samples[(self.start + self.len) % capacity] = sample;
+            if self.len == capacity {
+                self.start = (self.start + 1) % capacity;
+            } else {
+                self.len += 1;
+            }
+        }
+    }
+
+    /// 按写入顺序读出至多 `out.len()` 个采样，返回读出的数量
+    fn read(&mut self, out: &mut [f32]) -> usize {
+        let capacity = self.samples.len();
+        let count = out.len().min(self.len);
+        for slot in &mut out[..count] {
+            *slot = self.samples[self.start];
+            self.start = (self.start + 1) % capacity;
+        }
+        self.len -= count;
+        count
+    }
+}
+
+/// 监听回放源：按块从环形缓冲区取样，缓冲区为空时输出静音，持续到 Sink 停止
+struct MonitorSource {
+    ring: Arc<Mutex<SampleRing>>,
+    /// 当前块 (预分配，长度为声道数的整数倍)
+    block: Box<[f32]>,
+    pos: usize,
+    filled: usize,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl MonitorSource {
+    fn new(ring: Arc<Mutex<SampleRing>>, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            ring,
+            block: vec![0.0; MONITOR_READ_FRAMES * channels as usize].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            sample_rate,
+            channels,
+        }
+    }
+}
+
+impl Iterator for MonitorSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.filled {
+            self.filled = self.ring.lock().unwrap().read(&mut self.block);
+            if self.filled == 0 {
+                self.block.fill(0.0);
+                self.filled = self.block.len();
+            }
+            self.pos = 0;
+        }
+        let sample = self.block[self.pos];
+        self.pos += 1;
+        Some(sample)
+    }
+}
+
+impl Source for MonitorSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
 /// 提示音播放错误
 #[derive(Debug, thiserror::Error)]
 pub enum BeepError {
@@ -255,7 +478,7 @@ mod tests {
     #[test]
     fn test_beep_player_creation() {
         let player = BeepPlayer::new();
-        assert!(player.is_enabled());
+        assert_eq!(player.is_enabled(), player.is_output_available());
         assert!((player.volume() - 0.3).abs() < 0.001);
     }
 
@@ -278,13 +501,32 @@ mod tests {
     fn test_beep_player_enable_disable() {
         let player = BeepPlayer::new();
         
-        assert!(player.is_enabled());
+        assert_eq!(player.is_enabled(), player.is_output_available());
         
         player.set_enabled(false);
         assert!(!player.is_enabled());
         
+        // 无输出设备时无法重新启用
         player.set_enabled(true);
-        assert!(player.is_enabled());
+        assert_eq!(player.is_enabled(), player.is_output_available());
+    }
+
+    #[test]
+    fn test_sample_ring_drops_oldest_frames() {
+        // 2 声道、3 帧容量
+        let mut ring = SampleRing::new(6);
+        ring.write(&[1.0, 1.0, 2.0, 2.0]);
+        ring.write(&[3.0, 3.0, 4.0, 4.0]);
+
+        let mut out = [0.0; 8];
+        assert_eq!(ring.read(&mut out), 6);
+        assert_eq!(&out[..6], &[2.0, 2.0, 3.0, 3.0, 4.0, 4.0]);
+        assert_eq!(ring.read(&mut out), 0);
+
+        // 单次写入超过容量时只保留最新部分
+        ring.write(&[5.0, 5.0, 6.0, 6.0, 7.0, 7.0, 8.0, 8.0]);
+        assert_eq!(ring.read(&mut out[..2]), 2);
+        assert_eq!(&out[..2], &[6.0, 6.0]);
     }
 
     #[test]
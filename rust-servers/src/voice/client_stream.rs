@@ -0,0 +1,236 @@
+// 客户端音频流模块
+// 客户端通过 WebSocket 二进制帧上传自己采集的音频，由服务端完成 ASR (瘦客户端 / 移动端)
+
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::asr::{
+    ASRError, PartialResultCallback, RealtimeTaskResult, RealtimeTranscriptionTask, SessionStatusCallback,
+};
+use super::audio::recorder::{CaptureBuffer, DEFAULT_MAX_RECORDING_SECS};
+use super::audio::streaming::{AudioChunkData, CHUNK_CHANNEL_BUFFER};
+use super::audio::{AudioData, StopReason, TARGET_SAMPLE_RATE};
+use super::config::{ASRConfig, ASRMode};
+use super::session::PendingTranscription;
+use crate::server::ServerStats;
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [client_stream] {}", format!($($arg)*));
+    };
+}
+
+/// 待转发到实时转录任务的音频块
+///
+/// 由 `push` 在持有连接状态时生成，调用方释放状态锁后再发送，音频通道满时不阻塞其他命令
+pub struct ChunkForward {
+    chunk_tx: mpsc::Sender<AudioChunkData>,
+    chunk: AudioChunkData,
+}
+
+impl ChunkForward {
+    /// 发送到实时任务 (任务已结束时忽略发送失败，结果在 finish 时上报)
+    pub async fn send(self) {
+        let _ = self.chunk_tx.send(self.chunk).await;
+    }
+}
+
+/// 一帧音频的接收结果
+#[derive(Default)]
+pub struct PushedFrame {
+    /// 需转发到实时任务的音频块 (HTTP 模式或无新音频时为空)
+    pub forward: Option<ChunkForward>,
+    /// 本帧首次超出时长上限 (上限内的部分已接收，之后的帧静默丢弃)
+    pub limit_reached: bool,
+}
+
+/// 实时转录任务及其音频通道
+struct RealtimeForward {
+    chunk_tx: mpsc::Sender<AudioChunkData>,
+    task: JoinHandle<RealtimeTaskResult>,
+}
+
+/// 客户端音频流
+///
+/// 二进制帧为 16kHz 单声道 16-bit 小端 PCM：
+/// - Realtime 模式：每帧立即送入实时转录任务的音频通道
+/// - HTTP 模式：累积到流结束后整段转录
+///
+/// 两种模式都保留完整音频，用于实时失败时的 HTTP 回退和录音存档；
+/// 音频时长受 `max_recording_secs` 限制，超出部分丢弃，结束时按上限内的音频转录
+pub struct ClientAudioStream {
+    asr_config: ASRConfig,
+    samples: CaptureBuffer,
+    max_secs: u32,
+    realtime: Option<RealtimeForward>,
+}
+
+impl ClientAudioStream {
+    /// 按配置开始接收音频 (Realtime 模式同时启动实时转录任务)
+    pub fn start(
+        asr_config: ASRConfig,
+        partial_callback: Option<PartialResultCallback>,
+        status_callback: Option<SessionStatusCallback>,
+        stats: Option<Arc<ServerStats>>,
+    ) -> Self {
+        log_info!(
+            "客户端音频流开始，供应商: {}, 模式: {:?}",
+            asr_config.primary.provider,
+            asr_config.primary.mode
+        );
+
+        let realtime = (asr_config.primary.mode == ASRMode::Realtime).then(|| {
+            let (chunk_tx, chunk_rx) = mpsc::channel::<AudioChunkData>(CHUNK_CHANNEL_BUFFER);
+            let (task, stop_signal) = RealtimeTranscriptionTask::new(
+                asr_config.primary.clone(),
+                chunk_rx,
+                partial_callback,
+            );
+            let task = task.with_status_callback(status_callback);
+
+            let task_guard = stats.as_ref().map(|stats| stats.track_realtime_task());
+            let task = tokio::spawn(async move {
+                let _task_guard = task_guard;
+                // 停止信号的发送端被丢弃会使任务立即收尾，需保持到任务结束；
+                // 流结束以音频通道关闭为准，保证已排队的音频全部发出
+                let _stop_signal = stop_signal;
+                task.run_with_details().await
+            });
+
+            RealtimeForward { chunk_tx, task }
+        });
+
+        let max_secs = asr_config.max_recording_secs.unwrap_or(DEFAULT_MAX_RECORDING_SECS).max(1);
+        Self {
+            asr_config,
+            samples: CaptureBuffer::for_duration(max_secs, TARGET_SAMPLE_RATE, 1),
+            max_secs,
+            realtime,
+        }
+    }
+
+    /// 接收一帧 PCM 数据，返回需转发到实时任务的音频块
+    ///
+    /// 帧格式无效时返回错误；时长上限不是错误，首次超出时在结果中标记
+    pub fn push(&mut self, frame: &[u8]) -> Result<PushedFrame, ASRError> {
+        let mut pcm = decode_pcm16le(frame)?;
+        if pcm.is_empty() || self.samples.is_overflowed() {
+            return Ok(PushedFrame::default());
+        }
+
+        let received = self.samples.samples().len();
+        let timestamp_ms = received as u64 * 1000 / TARGET_SAMPLE_RATE as u64;
+        let samples: Vec<f32> = pcm.iter().map(|&s| s as f32 / 32768.0).collect();
+        let accepted = self.samples.push(&samples);
+        // 超出上限时只转发上限内的部分
+        pcm.truncate(self.samples.samples().len() - received);
+
+        let forward = self.realtime.as_ref().filter(|_| !pcm.is_empty()).map(|realtime| ChunkForward {
+            chunk_tx: realtime.chunk_tx.clone(),
+            chunk: AudioChunkData { samples: pcm, timestamp_ms },
+        });
+
+        if !accepted {
+            log_info!("音频流时长达到上限 ({} 秒)，之后的音频将被丢弃", self.max_secs);
+        }
+        Ok(PushedFrame { forward, limit_reached: !accepted })
+    }
+
+    /// 音频时长上限 (秒)
+    pub fn max_secs(&self) -> u32 {
+        self.max_secs
+    }
+
+    /// 结束音频流，返回待完成的转录
+    ///
+    /// Realtime 模式关闭音频通道，实时任务发送完已排队的音频后收尾
+    pub fn finish(self) -> PendingTranscription {
+        let stop_reason = if self.samples.is_overflowed() {
+            StopReason::MaxDuration
+        } else {
+            StopReason::Manual
+        };
+        let audio_data = AudioData::new(self.samples.into_samples(), TARGET_SAMPLE_RATE, 1);
+        log_info!("客户端音频流结束，时长: {}ms", audio_data.duration_ms);
+
+        let realtime_task = self.realtime.map(|RealtimeForward { chunk_tx, task }| {
+            drop(chunk_tx);
+            task
+        });
+
+        PendingTranscription::new(audio_data, self.asr_config, realtime_task).with_stop_reason(stop_reason)
+    }
+
+    /// 中止音频流与实时转录任务
+    pub fn abort(self) {
+        if let Some(realtime) = self.realtime {
+            realtime.task.abort();
+        }
+    }
+}
+
+/// 解析 16-bit 小端 PCM 帧
+fn decode_pcm16le(frame: &[u8]) -> Result<Vec<i16>, ASRError> {
+    if !frame.len().is_multiple_of(2) {
+        return Err(ASRError::InvalidAudio(format!(
+            "PCM 帧长度 {} 不是 16-bit 样本的整数倍",
+            frame.len()
+        )));
+    }
+    Ok(frame
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::config::ASRProviderConfig;
+
+    #[test]
+    fn test_decode_pcm16le() {
+        assert_eq!(decode_pcm16le(&[0x01, 0x00, 0xff, 0x7f, 0x00, 0x80]).unwrap(), vec![1, 32767, -32768]);
+        assert!(matches!(decode_pcm16le(&[0x01, 0x00, 0x02]), Err(ASRError::InvalidAudio(_))));
+    }
+
+    #[tokio::test]
+    async fn test_http_stream_buffers_frames() {
+        let asr_config = ASRConfig::primary_only(ASRProviderConfig::qwen(
+            ASRMode::Http,
+            "test-key".to_string(),
+        ));
+
+        let mut stream = ClientAudioStream::start(asr_config, None, None, None);
+        let frame: Vec<u8> = std::iter::repeat_n(0x4000i16.to_le_bytes(), 1600).flatten().collect();
+        assert!(stream.push(&frame).unwrap().forward.is_none());
+        stream.push(&frame).unwrap();
+
+        let pending = stream.finish();
+        assert_eq!(pending.audio_data().duration_ms, 200);
+        assert_eq!(pending.audio_data().sample_rate, TARGET_SAMPLE_RATE);
+        assert!((pending.audio_data().samples[0] - 0.5).abs() < 1e-6);
+        assert_eq!(pending.stop_reason(), StopReason::Manual);
+    }
+
+    #[tokio::test]
+    async fn test_stream_stops_at_max_duration() {
+        let mut asr_config = ASRConfig::primary_only(ASRProviderConfig::qwen(
+            ASRMode::Http,
+            "test-key".to_string(),
+        ));
+        asr_config.max_recording_secs = Some(1);
+
+        let mut stream = ClientAudioStream::start(asr_config, None, None, None);
+        let frame = vec![0u8; 12000 * 2];
+        assert!(!stream.push(&frame).unwrap().limit_reached);
+        assert!(stream.push(&frame).unwrap().limit_reached);
+        // 超出上限后的帧静默丢弃
+        assert!(!stream.push(&frame).unwrap().limit_reached);
+
+        let pending = stream.finish();
+        assert_eq!(pending.audio_data().duration_ms, 1000);
+        assert_eq!(pending.stop_reason(), StopReason::MaxDuration);
+    }
+}
@@ -1,10 +1,14 @@
 // 配置管理模块
 // 定义 ASR 供应商配置和相关数据结构
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
+use crate::voice::asr::text::default_separator;
+use crate::voice::asr::ASRErrorKind;
 
 /// ASR 供应商类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ASRProvider {
     /// 阿里云 Qwen
@@ -60,8 +64,135 @@ impl Default for AudioCompressionLevel {
     }
 }
 
+/// 标点处理模式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PunctuationMode {
+    /// 保留原始标点
+    Keep,
+    /// 去除标点 (默认)
+    #[default]
+    Strip,
+    /// 统一为半角标点
+    NormalizeHalf,
+    /// 统一为全角标点
+    NormalizeFull,
+}
+
+/// 豆包实时识别接口类型
+/// 
+/// - `NoStream`: `bigmodel_nostream`，流式上传音频，结束后一次性返回结果 (准确率更高)
+/// - `Stream`: `bigmodel_async`，双向流式，识别结果变化时即返回增量结果 (部分结果更快)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DoubaoStreamMode {
+    /// 非流式返回 (默认)
+    #[default]
+    NoStream,
+    /// 双向流式返回
+    Stream,
+}
+
+/// 实时模式音频线上格式 (发送给供应商的 PCM 字节打包方式)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RealtimeAudioFormat {
+    /// 16 位有符号整数，小端序 (默认)
+    #[default]
+    Pcm16Le,
+    /// 16 位有符号整数，大端序
+    Pcm16Be,
+    /// 32 位浮点，小端序
+    PcmF32Le,
+}
+
+impl RealtimeAudioFormat {
+    /// 每个采样的位数
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            RealtimeAudioFormat::Pcm16Le | RealtimeAudioFormat::Pcm16Be => 16,
+            RealtimeAudioFormat::PcmF32Le => 32,
+        }
+    }
+}
+
+/// 实时模式部分结果推送粒度
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialGranularity {
+    /// 每次识别结果变化即推送 (默认)
+    #[default]
+    Delta,
+    /// 仅在一句话结束 (`is_final`) 时推送完整句子，减少界面频繁刷新
+    Utterance,
+}
+
+/// HTTP 模式音频超过时长上限 (`max_audio_secs`) 时的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizedAudio {
+    /// 在静音处切分后逐段转录再拼接 (默认)
+    #[default]
+    Chunk,
+    /// 只转录上限内的开头部分，超出部分丢弃
+    Truncate,
+    /// 直接返回错误，不发送请求
+    Reject,
+}
+
+/// 实时会话重连策略 (Qwen 与豆包共用)
+///
+/// 建立连接与会话初始化失败时按指数退避重试，等待时长带随机抖动，避免多个客户端同时重连；
+/// 只重试网络类错误 (网络、WebSocket、超时)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    /// 最多尝试次数 (含首次，至少 1 次)
+    pub max_attempts: u32,
+    /// 首次重试前的等待 (毫秒)，之后每次翻倍
+    pub base_delay_ms: u64,
+    /// 单次等待上限 (毫秒)
+    pub max_delay_ms: u64,
+    /// 抖动比例 (0.0 - 1.0)，等待时长在 ±jitter 范围内随机浮动
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay_ms: 300,
+            max_delay_ms: 3000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl std::fmt::Display for RealtimeAudioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RealtimeAudioFormat::Pcm16Le => write!(f, "pcm16_le"),
+            RealtimeAudioFormat::Pcm16Be => write!(f, "pcm16_be"),
+            RealtimeAudioFormat::PcmF32Le => write!(f, "pcm_f32_le"),
+        }
+    }
+}
+
+/// 默认启用逆文本规范化，书面笔记中数字、日期以阿拉伯数字呈现更易读
+pub const DEFAULT_ENABLE_ITN: bool = true;
+
+fn default_enable_itn() -> bool {
+    DEFAULT_ENABLE_ITN
+}
+
+/// Qwen HTTP 模式默认单次请求音频时长上限 (秒)
+pub const QWEN_MAX_AUDIO_SECS: u32 = 180;
+
+/// DashScope 默认服务地址 (华北2 北京)
+pub const DEFAULT_DASHSCOPE_BASE_URL: &str = "https://dashscope.aliyuncs.com";
+
 /// ASR 供应商配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ASRProviderConfig {
     /// 供应商类型
     pub provider: ASRProvider,
@@ -72,6 +203,18 @@ pub struct ASRProviderConfig {
     /// DashScope API Key (阿里云)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dashscope_api_key: Option<String>,
+    /// HTTP 模式下 gzip 压缩请求体 (Qwen，长音频在慢速网络下可明显缩短上传时间)
+    #[serde(default)]
+    pub qwen_gzip_request: bool,
+    /// HTTP 模式下以 SSE 流式接收结果 (Qwen)，转录过程中即可上报中间结果
+    #[serde(default)]
+    pub qwen_http_streaming: bool,
+    /// DashScope 服务地址 (按账号开通地域选择，如新加坡 https://dashscope-intl.aliyuncs.com)，空则使用默认地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qwen_base_url: Option<String>,
+    /// 识别上下文提示 (Qwen)，作为 system 消息 / 实时会话 corpus 传入，用于提升领域词汇的识别准确率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_prompt: Option<String>,
     
     // Doubao 特有配置
     /// 应用 ID (豆包)
@@ -80,11 +223,118 @@ pub struct ASRProviderConfig {
     /// 访问令牌 (豆包)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub access_token: Option<String>,
+    /// 实时识别接口类型 (豆包)
+    #[serde(default)]
+    pub doubao_stream_mode: DoubaoStreamMode,
     
     // SenseVoice 特有配置
     /// 硅基流动 API Key
     #[serde(skip_serializing_if = "Option::is_none")]
     pub siliconflow_api_key: Option<String>,
+    /// 是否启用逆文本规范化 (SenseVoice)，空则沿用通用配置 `enable_itn`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensevoice_use_itn: Option<bool>,
+    
+    // 通用配置
+    /// 识别语言 (如 "zh"、"en")，决定请求参数和标点处理方式，空则使用中文
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 标点处理模式
+    #[serde(default)]
+    pub punctuation_mode: PunctuationMode,
+    /// 是否启用逆文本规范化 (如 "二零二四年" -> "2024年")，默认开启
+    /// 
+    /// 对 Qwen HTTP、豆包 (HTTP / 实时) 和 SenseVoice 生效；Qwen 实时接口不提供该参数
+    #[serde(default = "default_enable_itn")]
+    pub enable_itn: bool,
+    /// 输出请求/响应调试日志 (音频截断、密钥脱敏)
+    #[serde(default)]
+    pub debug_logging: bool,
+    /// 实时模式空闲保活间隔 (毫秒)，超过该时长未发送音频时补发静音帧；空则按供应商默认值，0 关闭
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realtime_keepalive_ms: Option<u64>,
+    /// 实时会话最长持续时间 (秒)，超过后强制关闭会话并回退 HTTP 转录；空则为录音时长上限加 1 分钟，0 不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realtime_max_session_secs: Option<u64>,
+    /// 预连接会话的保活窗口 (秒)，超时未被录音取用则关闭连接；空则为 15 秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realtime_preconnect_keepalive_secs: Option<u64>,
+    /// 实时模式音频线上格式 (Qwen 与豆包目前仅支持 16 位小端 PCM，其余格式建立会话时报错)
+    #[serde(default)]
+    pub realtime_audio_format: RealtimeAudioFormat,
+    /// 实时模式建立会话失败时的重连策略 (Qwen / 豆包)
+    #[serde(default)]
+    pub realtime_reconnect: ReconnectPolicy,
+    /// 实时模式部分结果推送粒度
+    #[serde(default)]
+    pub partial_granularity: PartialGranularity,
+    /// HTTP 单次请求超时 (毫秒，空则使用默认 6 秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_timeout_ms: Option<u64>,
+    /// 实时会话结束后等待最终结果的超时 (毫秒，空则使用默认 30 秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_timeout_ms: Option<u64>,
+    /// HTTP 模式单次请求音频时长上限 (秒)，超出时在静音处切分后分段转录；空则按供应商默认值，0 不切分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_audio_secs: Option<u32>,
+    /// HTTP 模式音频超过时长上限时的处理方式 (切分、截断或拒绝)
+    #[serde(default)]
+    pub oversized_audio: OversizedAudio,
+    /// HTTP 模式按语句 (静音处) 切分后逐段转录，每段完成即推送中间结果，纯静音分段不发送
+    #[serde(default)]
+    pub utterance_segmentation: bool,
+    /// 该供应商同时进行的请求与实时会话数上限 (同一进程内共享，超出时排队)；空则不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<u32>,
+    /// HTTP 模式按该供应商最近请求耗时 (p95 加余量) 调整请求超时，request_timeout_ms 作为下限
+    #[serde(default)]
+    pub adaptive_timeout: bool,
+    /// 自适应请求超时的上限 (毫秒，空则使用默认 30 秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive_timeout_max_ms: Option<u64>,
+    /// HTTP 模式附加到每个请求的自定义请求头 (如 API 网关要求的 `X-Gateway-Key`)，同名时覆盖引擎默认请求头；
+    /// 名称或值不合法的请求头会被跳过并记录警告
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra_headers: HashMap<String, String>,
+}
+
+/// 默认配置：Qwen HTTP 模式，不含任何凭据，其余选项均取默认值
+impl Default for ASRProviderConfig {
+    fn default() -> Self {
+        Self {
+            provider: ASRProvider::Qwen,
+            mode: ASRMode::Http,
+            dashscope_api_key: None,
+            qwen_gzip_request: false,
+            utterance_segmentation: false,
+            extra_headers: HashMap::new(),
+            oversized_audio: OversizedAudio::default(),
+            context_prompt: None,
+            realtime_reconnect: ReconnectPolicy::default(),
+            qwen_http_streaming: false,
+            qwen_base_url: None,
+            app_id: None,
+            access_token: None,
+            doubao_stream_mode: DoubaoStreamMode::default(),
+            siliconflow_api_key: None,
+            sensevoice_use_itn: None,
+            language: None,
+            punctuation_mode: PunctuationMode::default(),
+            enable_itn: DEFAULT_ENABLE_ITN,
+            debug_logging: false,
+            realtime_keepalive_ms: None,
+            realtime_max_session_secs: None,
+            realtime_preconnect_keepalive_secs: None,
+            realtime_audio_format: RealtimeAudioFormat::default(),
+            partial_granularity: PartialGranularity::default(),
+            request_timeout_ms: None,
+            session_timeout_ms: None,
+            max_audio_secs: None,
+            max_concurrency: None,
+            adaptive_timeout: false,
+            adaptive_timeout_max_ms: None,
+        }
+    }
 }
 
 impl ASRProviderConfig {
@@ -94,9 +344,7 @@ impl ASRProviderConfig {
             provider: ASRProvider::Qwen,
             mode,
             dashscope_api_key: Some(api_key),
-            app_id: None,
-            access_token: None,
-            siliconflow_api_key: None,
+            ..Self::default()
         }
     }
     
@@ -105,10 +353,9 @@ impl ASRProviderConfig {
         Self {
             provider: ASRProvider::Doubao,
             mode,
-            dashscope_api_key: None,
             app_id: Some(app_id),
             access_token: Some(access_token),
-            siliconflow_api_key: None,
+            ..Self::default()
         }
     }
     
@@ -117,48 +364,141 @@ impl ASRProviderConfig {
         Self {
             provider: ASRProvider::SenseVoice,
             mode: ASRMode::Http, // SenseVoice 仅支持 HTTP
-            dashscope_api_key: None,
-            app_id: None,
-            access_token: None,
             siliconflow_api_key: Some(api_key),
+            ..Self::default()
         }
     }
     
-    /// 验证配置是否完整
+    /// 设置识别语言
+    pub fn with_language(mut self, language: String) -> Self {
+        self.language = Some(language);
+        self
+    }
+    
+    /// HTTP 模式单次请求音频时长上限 (毫秒)，None 表示不切分
+    pub fn max_audio_ms(&self) -> Option<u64> {
+        let secs = match self.max_audio_secs {
+            Some(secs) => secs,
+            None => match self.provider {
+                // qwen3-asr-flash 单次请求音频不超过 3 分钟
+                ASRProvider::Qwen => QWEN_MAX_AUDIO_SECS,
+                ASRProvider::Doubao | ASRProvider::SenseVoice => 0,
+            },
+        };
+        (secs > 0).then(|| secs as u64 * 1000)
+    }
+    
+    /// DashScope 服务地址 (未配置时为默认地址)
+    pub fn dashscope_base_url(&self) -> &str {
+        self.qwen_base_url.as_deref().unwrap_or(DEFAULT_DASHSCOPE_BASE_URL)
+    }
+    
+    /// 验证配置是否完整，返回首个问题
     pub fn validate(&self) -> Result<(), ConfigError> {
-        match self.provider {
-            ASRProvider::Qwen => {
-                if self.dashscope_api_key.as_ref().map_or(true, |k| k.is_empty()) {
-                    return Err(ConfigError::MissingApiKey("dashscope_api_key".to_string()));
-                }
+        match self.validate_all().into_iter().next() {
+            Some(issue) => Err(issue.error),
+            None => Ok(()),
+        }
+    }
+    
+    /// 收集配置中的全部问题
+    /// 
+    /// 不在首个错误处返回，供设置界面一次性标出所有有问题的字段
+    pub fn validate_all(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut require = |field: &str, value: &Option<String>| {
+            if value.as_ref().is_none_or(|v| v.is_empty()) {
+                issues.push(ConfigIssue::new(field, ConfigError::MissingApiKey(field.to_string())));
             }
+        };
+        
+        match self.provider {
+            ASRProvider::Qwen => require("dashscope_api_key", &self.dashscope_api_key),
             ASRProvider::Doubao => {
-                if self.app_id.as_ref().map_or(true, |k| k.is_empty()) {
-                    return Err(ConfigError::MissingApiKey("app_id".to_string()));
-                }
-                if self.access_token.as_ref().map_or(true, |k| k.is_empty()) {
-                    return Err(ConfigError::MissingApiKey("access_token".to_string()));
-                }
+                require("app_id", &self.app_id);
+                require("access_token", &self.access_token);
             }
-            ASRProvider::SenseVoice => {
-                if self.siliconflow_api_key.as_ref().map_or(true, |k| k.is_empty()) {
-                    return Err(ConfigError::MissingApiKey("siliconflow_api_key".to_string()));
-                }
-                // SenseVoice 仅支持 HTTP 模式
-                if self.mode != ASRMode::Http {
-                    return Err(ConfigError::UnsupportedMode {
-                        provider: self.provider.to_string(),
-                        mode: self.mode.to_string(),
-                    });
-                }
+            ASRProvider::SenseVoice => require("siliconflow_api_key", &self.siliconflow_api_key),
+        }
+        
+        if let (ASRProvider::Qwen, Some(base_url)) = (&self.provider, &self.qwen_base_url) {
+            if let Err(e) = validate_base_url(base_url) {
+                issues.push(ConfigIssue::new("qwen_base_url", e));
             }
         }
-        Ok(())
+        
+        if self.provider == ASRProvider::SenseVoice && self.mode != ASRMode::Http {
+            issues.push(ConfigIssue::new(
+                "mode",
+                ConfigError::UnsupportedMode {
+                    provider: self.provider.to_string(),
+                    mode: self.mode.to_string(),
+                },
+            ));
+        }
+        
+        issues
+    }
+    
+    /// 隐藏 API Key、访问令牌与自定义请求头值后的配置 (用于诊断输出，只保留长度信息)
+    pub fn redacted(&self) -> Self {
+        let redact_value = |value: &String| format!("<redacted {} chars>", value.chars().count());
+        let redact = |secret: &Option<String>| secret.as_ref().map(redact_value);
+        Self {
+            dashscope_api_key: redact(&self.dashscope_api_key),
+            access_token: redact(&self.access_token),
+            siliconflow_api_key: redact(&self.siliconflow_api_key),
+            extra_headers: self.extra_headers.iter()
+                .map(|(name, value)| (name.clone(), redact_value(value)))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// 校验服务地址：需为 http(s) 绝对地址，且不带查询参数和片段
+fn validate_base_url(base_url: &str) -> Result<(), ConfigError> {
+    let invalid = |reason: &str| {
+        ConfigError::InvalidConfig(format!("qwen_base_url {} {}", base_url, reason))
+    };
+    
+    let url = reqwest::Url::parse(base_url).map_err(|e| invalid(&e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid("须使用 http 或 https 协议"));
+    }
+    if url.host_str().is_none_or(|host| host.is_empty()) {
+        return Err(invalid("缺少主机名"));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("不能包含查询参数或片段"));
+    }
+    Ok(())
+}
+
+/// 配置问题 (用于设置界面一次性标出所有有问题的字段)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    /// 字段路径 (如 "primary.dashscope_api_key")
+    pub field: String,
+    /// 问题描述
+    pub message: String,
+    /// 对应的配置错误
+    #[serde(skip)]
+    error: ConfigError,
+}
+
+impl ConfigIssue {
+    fn new(field: impl Into<String>, error: ConfigError) -> Self {
+        Self {
+            field: field.into(),
+            message: error.to_string(),
+            error,
+        }
     }
 }
 
 /// 完整 ASR 配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ASRConfig {
     /// 主 ASR 引擎配置
     pub primary: ASRProviderConfig,
@@ -167,15 +507,66 @@ pub struct ASRConfig {
     pub fallbacks: Vec<ASRProviderConfig>,
     /// 是否启用自动兜底
     pub enable_fallback: bool,
+    /// 触发兜底的主引擎错误类型 (空则任意错误都触发)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_on: Vec<ASRErrorKind>,
+    /// 多选一模式：主引擎与全部备用引擎并行转录，按词元一致性选出结果 (以成本换准确率，仅 HTTP 模式生效)
+    #[serde(default)]
+    pub best_of: bool,
     /// 是否启用音频反馈（提示音）
     #[serde(default = "default_enable_audio_feedback")]
     pub enable_audio_feedback: bool,
     /// 录音设备名称（空则使用系统默认设备）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recording_device: Option<String>,
+    /// 录音中系统默认输入设备切换时在新设备上继续录音 (仅 HTTP 模式且未指定录音设备时生效)
+    #[serde(default)]
+    pub follow_default_device: bool,
+    /// 第二路录音设备名称 (空则不启用)，与主设备同时录音并混合，仅 HTTP 模式生效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_recording_device: Option<String>,
+    /// 第二路录音混合增益 (相对主设备)
+    #[serde(default = "default_secondary_gain")]
+    pub secondary_gain: f32,
     /// 音频压缩等级
     #[serde(default)]
     pub audio_compression: AudioCompressionLevel,
+    /// 是否在录音时回放采集到的音频（自我监听，默认关闭以避免啸叫）
+    #[serde(default)]
+    pub monitor: bool,
+    /// 监听音量 (0.0 - 1.0)
+    #[serde(default = "default_monitor_volume")]
+    pub monitor_volume: f32,
+    /// 录音时长上限 (秒，空则使用默认 30 分钟)，超出后自动停止采集
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_recording_secs: Option<u32>,
+    /// 最短音频时长 (毫秒)，更短的录音不发起转录
+    #[serde(default = "default_min_duration_ms")]
+    pub min_duration_ms: u64,
+    /// 静音窗口占比达到该值 (0.0 - 1.0) 的录音视为无语音，不发起转录 (空则不检查)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub silence_skip_ratio: Option<f32>,
+    /// HTTP 转录前在音频开头补充的静音时长 (毫秒，0 不补充)，改善首字识别
+    #[serde(default)]
+    pub pad_start_ms: u32,
+    /// HTTP 转录前在音频结尾补充的静音时长 (毫秒，0 不补充)
+    #[serde(default)]
+    pub pad_end_ms: u32,
+    /// 单次转录的总时长上限 (毫秒，空则不限制)，含主引擎重试与兜底引擎；超出后放弃剩余尝试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overall_timeout_ms: Option<u64>,
+    /// 实时模式音频帧时长 (毫秒，空则使用默认 200ms)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_frame_ms: Option<u32>,
+    /// 连续听写时的转录拼接分隔符 (空则按主引擎语言决定：中日韩不加空格，其余为空格)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcription_separator: Option<String>,
+    /// 录音存档目录 (空则不存档)，每次录音保存 WAV 与转录结果 JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recording_archive_dir: Option<String>,
+    /// 录音存档最多保留的录音数 (0 不限制)，超出时删除最旧的录音
+    #[serde(default = "default_recording_archive_max")]
+    pub recording_archive_max: usize,
 }
 
 /// 默认启用音频反馈
@@ -183,6 +574,30 @@ fn default_enable_audio_feedback() -> bool {
     true
 }
 
+/// 默认监听音量
+fn default_monitor_volume() -> f32 {
+    0.5
+}
+
+/// 默认第二路录音增益 (与主设备等量混合)
+fn default_secondary_gain() -> f32 {
+    1.0
+}
+
+/// 默认最短音频时长 (毫秒)，过滤误触按键产生的极短录音
+pub const DEFAULT_MIN_DURATION_MS: u64 = 300;
+
+fn default_min_duration_ms() -> u64 {
+    DEFAULT_MIN_DURATION_MS
+}
+
+/// 默认录音存档保留数
+pub const DEFAULT_RECORDING_ARCHIVE_MAX: usize = 100;
+
+fn default_recording_archive_max() -> usize {
+    DEFAULT_RECORDING_ARCHIVE_MAX
+}
+
 impl ASRConfig {
     /// 创建仅主引擎的配置
     pub fn primary_only(primary: ASRProviderConfig) -> Self {
@@ -190,9 +605,26 @@ impl ASRConfig {
             primary,
             fallbacks: Vec::new(),
             enable_fallback: false,
+            fallback_on: Vec::new(),
+            best_of: false,
             enable_audio_feedback: true,
             recording_device: None,
+            secondary_recording_device: None,
+            secondary_gain: default_secondary_gain(),
             audio_compression: AudioCompressionLevel::default(),
+            monitor: false,
+            monitor_volume: default_monitor_volume(),
+            max_recording_secs: None,
+            min_duration_ms: DEFAULT_MIN_DURATION_MS,
+            silence_skip_ratio: None,
+            pad_start_ms: 0,
+            pad_end_ms: 0,
+            overall_timeout_ms: None,
+            stream_frame_ms: None,
+            transcription_separator: None,
+            recording_archive_dir: None,
+            recording_archive_max: DEFAULT_RECORDING_ARCHIVE_MAX,
+            follow_default_device: false,
         }
     }
     
@@ -203,12 +635,36 @@ impl ASRConfig {
             primary,
             fallbacks,
             enable_fallback,
+            fallback_on: Vec::new(),
+            best_of: false,
             enable_audio_feedback: true,
             recording_device: None,
+            secondary_recording_device: None,
+            secondary_gain: default_secondary_gain(),
             audio_compression: AudioCompressionLevel::default(),
+            monitor: false,
+            monitor_volume: default_monitor_volume(),
+            max_recording_secs: None,
+            min_duration_ms: DEFAULT_MIN_DURATION_MS,
+            silence_skip_ratio: None,
+            pad_start_ms: 0,
+            pad_end_ms: 0,
+            overall_timeout_ms: None,
+            stream_frame_ms: None,
+            transcription_separator: None,
+            recording_archive_dir: None,
+            recording_archive_max: DEFAULT_RECORDING_ARCHIVE_MAX,
+            follow_default_device: false,
         }
     }
     
+    /// 获取转录拼接分隔符
+    pub fn transcription_separator(&self) -> &str {
+        self.transcription_separator
+            .as_deref()
+            .unwrap_or_else(|| default_separator(self.primary.language.as_deref()))
+    }
+
     /// 验证配置
     pub fn validate(&self) -> Result<(), ConfigError> {
         self.primary.validate()?;
@@ -217,10 +673,36 @@ impl ASRConfig {
         }
         Ok(())
     }
+    
+    /// 收集主引擎与全部备用引擎的配置问题，字段名带 "primary." / "fallbacks[i]." 前缀
+    pub fn validate_all(&self) -> Vec<ConfigIssue> {
+        let prefixed = |prefix: String, issues: Vec<ConfigIssue>| {
+            issues.into_iter().map(move |issue| ConfigIssue {
+                field: format!("{}.{}", prefix, issue.field),
+                ..issue
+            })
+        };
+        
+        let mut issues: Vec<ConfigIssue> =
+            prefixed("primary".to_string(), self.primary.validate_all()).collect();
+        for (index, fallback) in self.fallbacks.iter().enumerate() {
+            issues.extend(prefixed(format!("fallbacks[{}]", index), fallback.validate_all()));
+        }
+        issues
+    }
+    
+    /// 主引擎与全部备用引擎均隐藏密钥后的配置
+    pub fn redacted(&self) -> Self {
+        Self {
+            primary: self.primary.redacted(),
+            fallbacks: self.fallbacks.iter().map(ASRProviderConfig::redacted).collect(),
+            ..self.clone()
+        }
+    }
 }
 
 /// 配置错误
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum ConfigError {
     #[error("缺少必需的 API Key: {0}")]
     MissingApiKey(String),
@@ -248,9 +730,7 @@ mod tests {
             provider: ASRProvider::Qwen,
             mode: ASRMode::Realtime,
             dashscope_api_key: None,
-            app_id: None,
-            access_token: None,
-            siliconflow_api_key: None,
+            ..ASRProviderConfig::default()
         };
         assert!(invalid_config.validate().is_err());
     }
@@ -268,12 +748,16 @@ mod tests {
         let invalid_config = ASRProviderConfig {
             provider: ASRProvider::Doubao,
             mode: ASRMode::Realtime,
-            dashscope_api_key: None,
             app_id: None,
             access_token: Some("token".to_string()),
-            siliconflow_api_key: None,
+            ..ASRProviderConfig::default()
         };
         assert!(invalid_config.validate().is_err());
+        
+        // 返回 validate_all 的首个问题
+        let missing_both = ASRProviderConfig { access_token: None, ..invalid_config };
+        assert_eq!(missing_both.validate_all().len(), 2);
+        assert!(matches!(missing_both.validate(), Err(ConfigError::MissingApiKey(ref field)) if field == "app_id"));
     }
 
     #[test]
@@ -287,6 +771,22 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_qwen_base_url_validation() {
+        let mut config = ASRProviderConfig::qwen(ASRMode::Http, "test-key".to_string());
+        assert_eq!(config.dashscope_base_url(), DEFAULT_DASHSCOPE_BASE_URL);
+        
+        config.qwen_base_url = Some("https://dashscope-intl.aliyuncs.com".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.dashscope_base_url(), "https://dashscope-intl.aliyuncs.com");
+        
+        for invalid in ["dashscope-intl.aliyuncs.com", "ftp://dashscope.aliyuncs.com", "https://dashscope.aliyuncs.com?x=1"] {
+            config.qwen_base_url = Some(invalid.to_string());
+            assert!(matches!(config.validate(), Err(ConfigError::InvalidConfig(_))), "{}", invalid);
+            assert_eq!(config.validate_all()[0].field, "qwen_base_url");
+        }
+    }
+
     #[test]
     fn test_asr_config_serialization() {
         let config = ASRConfig::with_fallbacks(
@@ -324,6 +824,8 @@ mod tests {
         assert_eq!(config.primary.provider, ASRProvider::Qwen);
         assert_eq!(config.primary.mode, ASRMode::Realtime);
         assert_eq!(config.primary.dashscope_api_key, Some("sk-xxx".to_string()));
+        assert_eq!(config.primary.doubao_stream_mode, DoubaoStreamMode::NoStream);
+        assert!(config.primary.enable_itn);
         
         let fallback = config.fallbacks.into_iter().next().unwrap();
         assert_eq!(fallback.provider, ASRProvider::SenseVoice);
@@ -331,6 +833,9 @@ mod tests {
         assert_eq!(fallback.siliconflow_api_key, Some("sf-xxx".to_string()));
         
         assert!(config.enable_fallback);
+        assert!(!config.monitor);
+        assert!((config.monitor_volume - 0.5).abs() < 0.001);
+        assert_eq!(config.min_duration_ms, DEFAULT_MIN_DURATION_MS);
     }
 
     #[test]
@@ -343,4 +848,47 @@ mod tests {
         assert!(!config.enable_fallback);
         assert!(config.validate().is_ok());
     }
+
+    #[test]
+    fn test_validate_all_reports_every_issue() {
+        let mut primary = ASRProviderConfig::sensevoice(String::new());
+        primary.mode = ASRMode::Realtime;
+        let fallback = ASRProviderConfig::doubao(ASRMode::Http, String::new(), String::new());
+        let config = ASRConfig::with_fallbacks(primary, vec![fallback]);
+
+        let fields: Vec<String> = config.validate_all().into_iter().map(|issue| issue.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "primary.siliconflow_api_key",
+                "primary.mode",
+                "fallbacks[0].app_id",
+                "fallbacks[0].access_token",
+            ]
+        );
+        assert!(config.validate().is_err());
+
+        let valid = ASRConfig::primary_only(ASRProviderConfig::qwen(ASRMode::Http, "key".to_string()));
+        assert!(valid.validate_all().is_empty());
+    }
+    
+    #[test]
+    fn test_redacted_hides_secrets() {
+        let mut primary = ASRProviderConfig::qwen(ASRMode::Realtime, "sk-secret".to_string());
+        primary.extra_headers.insert("X-Gateway-Key".to_string(), "gw-secret".to_string());
+        let config = ASRConfig::with_fallbacks(
+            primary,
+            vec![ASRProviderConfig::doubao(ASRMode::Http, "app".to_string(), "token".to_string())],
+        );
+        
+        let json = serde_json::to_string(&config.redacted()).unwrap();
+        assert!(!json.contains("sk-secret") && !json.contains("\"token\""), "{}", json);
+        assert!(!json.contains("gw-secret") && json.contains("X-Gateway-Key"), "{}", json);
+        
+        let redacted = config.redacted();
+        assert_eq!(redacted.primary.dashscope_api_key.as_deref(), Some("<redacted 9 chars>"));
+        assert_eq!(redacted.fallbacks[0].access_token.as_deref(), Some("<redacted 5 chars>"));
+        assert_eq!(redacted.fallbacks[0].app_id.as_deref(), Some("app"));
+        assert_eq!(redacted.primary.mode, ASRMode::Realtime);
+    }
 }
@@ -0,0 +1,153 @@
+// 自检模块
+// 录制一小段音频并走完整转录流程，返回设备、电平、引擎和耗时等信息，便于排查问题
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cpal::traits::DeviceTrait;
+use serde::Serialize;
+
+use crate::voice::asr::{transcribe_stream, transcribe_with, ASRError, FallbackStrategy};
+use crate::voice::audio::recorder::{convert_f32_to_i16, resample, to_mono};
+use crate::voice::audio::{
+    select_input_device, AudioData, AudioRecorder, AudioSummary, RecordingError, RecordingMode,
+    TARGET_SAMPLE_RATE,
+};
+use crate::voice::config::{ASRConfig, ASRMode};
+use tokio_util::sync::CancellationToken;
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [diagnostics] {}", format!($($arg)*));
+    };
+}
+
+/// 默认自检录音时长 (毫秒)
+pub const DEFAULT_SELF_TEST_DURATION_MS: u64 = 1000;
+
+/// 自检录音时长上限 (毫秒)
+const MAX_SELF_TEST_DURATION_MS: u64 = 10_000;
+
+/// 实时模式自检时每块发送的采样数 (100ms @ 16kHz)
+const REALTIME_CHUNK_SAMPLES: usize = 1600;
+
+/// 自检报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// 录音设备名称
+    pub device: String,
+    /// 设备采样率
+    pub device_sample_rate: u32,
+    /// 设备声道数
+    pub device_channels: u16,
+    /// 送入转录的音频采样率
+    pub sample_rate: u32,
+    /// 录音摘要 (时长、峰值、电平)
+    pub audio: AudioSummary,
+    /// 主引擎供应商
+    pub provider: String,
+    /// 主引擎模式
+    pub mode: ASRMode,
+    /// 实际完成转录的引擎 (失败时为空)
+    pub engine: Option<String>,
+    /// 是否使用了兜底引擎
+    pub used_fallback: bool,
+    /// 录音停止到得到结果的耗时 (毫秒)
+    pub latency_ms: u64,
+    /// 转录文本 (失败时为空)
+    pub text: Option<String>,
+    /// 转录错误 (成功时为空)
+    pub error: Option<String>,
+}
+
+/// 运行自检：录音 `duration_ms` 毫秒后按当前配置转录
+///
+/// HTTP 模式使用调用方转录服务构建的策略；录音失败直接返回错误，转录失败记录在报告的 `error` 字段中
+pub async fn run_self_test(
+    asr_config: &ASRConfig,
+    strategy: Result<Arc<FallbackStrategy>, ASRError>,
+    duration_ms: u64,
+) -> Result<SelfTestReport, RecordingError> {
+    let duration_ms = duration_ms.clamp(1, MAX_SELF_TEST_DURATION_MS);
+    let device_name = asr_config.recording_device.as_deref();
+    let device = select_input_device(device_name)?
+        .name()
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    log_info!("开始自检，设备: {}, 录音 {}ms", device, duration_ms);
+
+    let mut recorder = AudioRecorder::new()?;
+    recorder.start(RecordingMode::Press, device_name, asr_config.audio_compression)?;
+    tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+    let audio = recorder.stop()?;
+
+    let started = Instant::now();
+    let result = match asr_config.primary.mode {
+        ASRMode::Realtime => {
+            let chunks = realtime_chunks(&audio);
+            transcribe_stream(
+                asr_config.primary.clone(),
+                futures_util::stream::iter(chunks),
+                None,
+            )
+            .await
+        }
+        ASRMode::Http => match strategy {
+            Ok(strategy) => transcribe_with(&strategy, &audio, &CancellationToken::new()).await,
+            Err(e) => Err(e),
+        },
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    log_info!("自检完成，耗时 {}ms, 结果: {:?}", latency_ms, result);
+
+    let (engine, used_fallback, text, error) = match result {
+        Ok(result) => (Some(result.engine), result.used_fallback, Some(result.text), None),
+        Err(e) => (None, false, None, Some(e.to_string())),
+    };
+
+    Ok(SelfTestReport {
+        device,
+        device_sample_rate: recorder.device_sample_rate(),
+        device_channels: recorder.device_channels(),
+        sample_rate: audio.sample_rate,
+        audio: audio.summary(),
+        provider: asr_config.primary.provider.to_string(),
+        mode: asr_config.primary.mode.clone(),
+        engine,
+        used_fallback,
+        latency_ms,
+        text,
+        error,
+    })
+}
+
+/// 将录音转为实时会话所需的 16kHz 单声道 PCM 分块
+fn realtime_chunks(audio: &AudioData) -> Vec<Vec<i16>> {
+    let mono = to_mono(&audio.samples, audio.channels);
+    let samples = if audio.sample_rate == TARGET_SAMPLE_RATE {
+        mono
+    } else {
+        resample(&mono, audio.sample_rate, TARGET_SAMPLE_RATE)
+    };
+    convert_f32_to_i16(&samples)
+        .chunks(REALTIME_CHUNK_SAMPLES)
+        .map(|chunk| chunk.to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_realtime_chunks_resample_to_16k_mono() {
+        // 0.5 秒 48kHz 立体声
+        let audio = AudioData::new(vec![0.5f32; 48000], 48000, 2);
+        let chunks = realtime_chunks(&audio);
+
+        assert_eq!(chunks.iter().map(Vec::len).sum::<usize>(), 8000);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= REALTIME_CHUNK_SAMPLES));
+        assert!(chunks[0].iter().all(|&s| (s - 16384).abs() <= 1));
+    }
+}
@@ -1,28 +1,38 @@
 // Voice 模块
 // 提供语音录制和 ASR 转录功能
 
+pub mod archive;
 pub mod audio;
 pub mod asr;
 pub mod beep;
+pub mod client_stream;
 pub mod config;
+pub mod diagnostics;
+pub mod session;
 
 use crate::router::{ModuleHandler, ModuleMessage, ModuleType, RouterError, ServerResponse};
-use crate::server::WsSender;
+use crate::server::{ServerStats, WsSender};
 use futures_util::SinkExt;
-use std::time::Instant;
-use tokio::sync::{mpsc, oneshot, Mutex as TokioMutex};
-use tokio::task::JoinHandle;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex as TokioMutex};
+use tokio_util::sync::CancellationToken;
 
 use audio::{
-    AudioRecorder,
     RecordingMode as AudioRecordingMode,
-    StreamingRecorder,
-    AudioData,
-    list_input_devices,
+    invalidate_input_device_cache, list_input_devices, prewarm_input_device,
+    TARGET_SAMPLE_RATE,
 };
-use asr::{FallbackStrategy, TranscriptionResult, ASRError, RealtimeTaskResult, RealtimeTranscriptionTask};
-use beep::BeepPlayer;
-use config::{ASRConfig, ASRMode};
+use asr::{
+    ASRError, FallbackStrategy, PartialResultCallback, PartialTranscription, PreconnectedSession,
+    SessionStatus, SessionStatusCallback,
+    RealtimeTranscriptionTask, TranscriptionService,
+};
+use config::{ASRConfig, ASRMode, ASRProvider};
+use client_stream::ClientAudioStream;
+use session::{PendingTranscription, VoiceSession};
 
 /// 日志宏
 macro_rules! log_info {
@@ -50,9 +60,10 @@ macro_rules! log_debug {
 // ============================================================================
 
 /// 录音模式
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum RecordingMode {
+    #[default]
     Press,  // 按住录音
     Toggle, // 切换录音
 }
@@ -66,6 +77,97 @@ impl From<RecordingMode> for AudioRecordingMode {
     }
 }
 
+/// 部分结果输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialFormat {
+    /// JSON 包装的 `partial` 消息 (默认)
+    #[default]
+    Json,
+    /// 纯文本帧，内容为截至当前的完整识别文本，便于极简客户端直接显示
+    Raw,
+}
+
+// ============================================================================
+// 请求消息
+// ============================================================================
+
+/// start_recording 请求
+#[derive(Debug, serde::Deserialize)]
+struct StartRecordingRequest {
+    /// 录音模式 (空则使用连接的默认模式)
+    #[serde(default)]
+    mode: Option<RecordingMode>,
+    asr_config: ASRConfig,
+}
+
+/// set_recording_mode 请求
+#[derive(Debug, serde::Deserialize)]
+struct SetRecordingModeRequest {
+    mode: RecordingMode,
+}
+
+/// update_config / preconnect 请求
+#[derive(Debug, serde::Deserialize)]
+struct ConfigRequest {
+    asr_config: ASRConfig,
+}
+
+/// validate_config 请求
+#[derive(Debug, serde::Deserialize)]
+struct ValidateConfigRequest {
+    asr_config: ASRConfig,
+    #[serde(default)]
+    request_id: Option<String>,
+}
+
+/// switch_provider 请求
+#[derive(Debug, serde::Deserialize)]
+struct SwitchProviderRequest {
+    provider: ASRProvider,
+}
+
+/// switch_mode 请求
+#[derive(Debug, serde::Deserialize)]
+struct SwitchModeRequest {
+    mode: ASRMode,
+}
+
+/// set_audio_level_stream 请求
+#[derive(Debug, serde::Deserialize)]
+struct SetAudioLevelStreamRequest {
+    enabled: bool,
+    /// 推送频率上限 (Hz，空则保持当前设置)
+    #[serde(default)]
+    max_rate_hz: Option<u32>,
+}
+
+/// list_input_devices 请求
+#[derive(Debug, serde::Deserialize)]
+struct ListInputDevicesRequest {
+    #[serde(default)]
+    request_id: Option<String>,
+}
+
+/// init 请求 (之后的二进制帧作为该配置下的音频流)
+#[derive(Debug, serde::Deserialize)]
+struct InitRequest {
+    asr_config: ASRConfig,
+    #[serde(default)]
+    partial_format: PartialFormat,
+}
+
+/// self_test 请求
+#[derive(Debug, serde::Deserialize)]
+struct SelfTestRequest {
+    asr_config: ASRConfig,
+    /// 录音时长 (毫秒，空则使用默认时长)
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    #[serde(default)]
+    request_id: Option<String>,
+}
+
 // ============================================================================
 // 录音状态
 // ============================================================================
@@ -75,6 +177,8 @@ impl From<RecordingMode> for AudioRecordingMode {
 #[serde(rename_all = "snake_case")]
 pub enum RecordingState {
     Started,
+    Paused,
+    Resumed,
     Stopped,
     Cancelled,
 }
@@ -83,6 +187,12 @@ pub enum RecordingState {
 // 音频级别数据
 // ============================================================================
 
+/// 音频级别默认推送频率上限 (Hz)
+const DEFAULT_AUDIO_LEVEL_RATE_HZ: u32 = 20;
+
+/// 音频级别推送频率上限的允许范围 (Hz)
+const AUDIO_LEVEL_RATE_RANGE_HZ: std::ops::RangeInclusive<u32> = 1..=60;
+
 /// 音频级别数据 (用于通过 channel 传递)
 #[derive(Debug, Clone)]
 struct AudioLevelData {
@@ -90,49 +200,84 @@ struct AudioLevelData {
     waveform: Vec<f32>,
 }
 
+/// 自检占用守卫
+///
+/// Drop 时清除自检标记，保证自检任务 panic 时也能恢复录音
+struct SelfTestGuard {
+    running: Arc<AtomicBool>,
+}
+
+impl Drop for SelfTestGuard {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
 // ============================================================================
 // 连接状态
 // ============================================================================
 
 /// 连接状态
 struct ConnectionState {
-    /// 当前 ASR 配置
-    asr_config: Option<ASRConfig>,
-    /// 是否正在录音
-    is_recording: bool,
-    /// 录音模式
-    recording_mode: Option<RecordingMode>,
-    /// 录音开始时间
-    recording_start_time: Option<Instant>,
-    /// 音频录制器 (HTTP 模式)
-    recorder: Option<AudioRecorder>,
-    /// 流式录制器 (Realtime 模式)
-    streaming_recorder: Option<StreamingRecorder>,
-    /// 实时转录任务句柄
-    realtime_task: Option<JoinHandle<RealtimeTaskResult>>,
-    /// 停止信号发送器 (用于停止实时转录任务)
-    stop_signal: Option<oneshot::Sender<()>>,
-    /// 提示音播放器
-    beep_player: BeepPlayer,
+    /// 按当前 ASR 配置构建的转录服务 (跨录音复用，保留引擎的自适应超时统计)
+    service: Option<TranscriptionService>,
+    /// 进行中的语音会话 (录音期间存在)
+    session: Option<VoiceSession>,
+    /// 默认录音模式 (start_recording 未指定 mode 时使用)
+    default_mode: RecordingMode,
     /// 音频级别发送器
     audio_level_tx: Option<mpsc::UnboundedSender<AudioLevelData>>,
+    /// 客户端上传的音频流 (init 之后、end_stream 之前存在)
+    client_stream: Option<ClientAudioStream>,
+    /// HTTP 流式中间结果的输出格式 (由最近一次 init / start_recording 决定)
+    partial_format: Arc<Mutex<PartialFormat>>,
 }
 
 impl ConnectionState {
     fn new() -> Self {
         Self {
-            asr_config: None,
-            is_recording: false,
-            recording_mode: None,
-            recording_start_time: None,
-            recorder: None,
-            streaming_recorder: None,
-            realtime_task: None,
-            stop_signal: None,
-            beep_player: BeepPlayer::new(),
+            service: None,
+            session: None,
+            default_mode: RecordingMode::default(),
             audio_level_tx: None,
+            client_stream: None,
+            partial_format: Arc::new(Mutex::new(PartialFormat::default())),
         }
     }
+
+    /// 当前 ASR 配置
+    fn asr_config(&self) -> Option<&ASRConfig> {
+        self.service.as_ref().map(TranscriptionService::config)
+    }
+
+    /// 更新当前 ASR 配置 (配置不变时沿用已构建的转录策略)
+    ///
+    /// 首次下发配置时创建转录服务，HTTP 流式中间结果推送给客户端
+    fn set_asr_config(&mut self, asr_config: ASRConfig, ws_sender: Option<WsSender>) {
+        match self.service {
+            Some(ref mut service) => service.set_config(asr_config),
+            None => {
+                let partial_callback = switchable_partial_forwarder(ws_sender, Arc::clone(&self.partial_format))
+                    .map(asr::shared_partial_callback);
+                self.service = Some(
+                    TranscriptionService::new(asr_config).with_partial_callback(partial_callback),
+                );
+            }
+        }
+    }
+
+    /// 设置之后 HTTP 流式中间结果的输出格式 (转录服务跨录音复用，无需重建)
+    fn set_partial_format(&self, format: PartialFormat) {
+        *self.partial_format.lock().unwrap() = format;
+    }
+
+    /// 按当前配置获取转录策略
+    fn strategy(&mut self) -> Result<Arc<FallbackStrategy>, ASRError> {
+        self.service
+            .as_mut()
+            .ok_or(ASRError::NotInitialized)
+            .and_then(TranscriptionService::strategy)
+    }
 }
 
 // ============================================================================
@@ -147,14 +292,37 @@ pub struct VoiceHandler {
     state: TokioMutex<ConnectionState>,
     /// WebSocket 发送器
     ws_sender: TokioMutex<Option<WsSender>>,
+    /// 是否向客户端推送音频级别
+    audio_level_stream: Arc<AtomicBool>,
+    /// 音频级别推送频率上限 (Hz)
+    audio_level_rate_hz: AtomicU32,
+    /// 服务器运行状态 (统计实时转录任务数)
+    stats: Arc<ServerStats>,
+    /// 进行中转录的取消令牌
+    transcription_cancel: TokioMutex<Option<CancellationToken>>,
+    /// 预连接的实时会话
+    preconnected: Arc<TokioMutex<Option<PreconnectedSession>>>,
+    /// 是否正在执行自检 (自检期间占用录音设备，拒绝开始录音)
+    self_test_running: Arc<AtomicBool>,
 }
 
 impl VoiceHandler {
     /// 创建新的 Voice 处理器
     pub fn new() -> Self {
+        Self::with_stats(Arc::new(ServerStats::new()))
+    }
+    
+    /// 使用共享的服务器状态创建 Voice 处理器
+    pub fn with_stats(stats: Arc<ServerStats>) -> Self {
         Self {
             state: TokioMutex::new(ConnectionState::new()),
             ws_sender: TokioMutex::new(None),
+            audio_level_stream: Arc::new(AtomicBool::new(true)),
+            audio_level_rate_hz: AtomicU32::new(DEFAULT_AUDIO_LEVEL_RATE_HZ),
+            stats,
+            transcription_cancel: TokioMutex::new(None),
+            preconnected: Arc::new(TokioMutex::new(None)),
+            self_test_running: Arc::new(AtomicBool::new(false)),
         }
     }
     
@@ -166,383 +334,134 @@ impl VoiceHandler {
     
     /// 发送消息给客户端
     async fn send_message(&self, msg_type: &str, payload: serde_json::Value) -> Result<(), RouterError> {
-        let ws_sender = self.ws_sender.lock().await;
-        if let Some(ref sender) = *ws_sender {
-            let response = serde_json::json!({
-                "module": "voice",
-                "type": msg_type,
-            });
-            
-            // 合并 payload 到 response
-            let mut response = response.as_object().unwrap().clone();
-            if let serde_json::Value::Object(payload_obj) = payload {
-                for (k, v) in payload_obj {
-                    response.insert(k, v);
-                }
-            }
-            
-            let json = serde_json::to_string(&response)
-                .map_err(|e| RouterError::ModuleError(format!("JSON 序列化失败: {}", e)))?;
-            
-            let mut sender = sender.lock().await;
-            sender.send(tokio_tungstenite::tungstenite::Message::Text(json.into())).await
-                .map_err(|e| RouterError::ModuleError(format!("发送消息失败: {}", e)))?;
-        }
-        Ok(())
+        let ws_sender = self.ws_sender.lock().await.clone();
+        send_voice_message(&ws_sender, msg_type, payload).await
     }
 
     /// 处理开始录音命令
     async fn handle_start_recording(
         &self,
-        mode: RecordingMode,
+        mode: Option<RecordingMode>,
         asr_config: ASRConfig,
     ) -> Result<Option<ServerResponse>, RouterError> {
-        log_info!("收到开始录音命令，模式: {:?}", mode);
-        
         let mut state = self.state.lock().await;
-        let recording_device = asr_config.recording_device.clone();
-        let compression_level = asr_config.audio_compression;
+        let mode = mode.unwrap_or(state.default_mode);
+        log_info!("收到开始录音命令，模式: {:?}", mode);
         
-        // 检查是否已在录音
-        if state.is_recording {
+        // 检查是否已在录音 (含客户端上传的音频流)
+        if state.session.is_some() || state.client_stream.is_some() {
             return Err(RouterError::ModuleError("已在录音中".to_string()));
         }
+        if self.self_test_running.load(Ordering::SeqCst) {
+            return Err(RouterError::ModuleError("正在自检，无法开始录音".to_string()));
+        }
         
         // 创建音频级别 channel
-        let (audio_level_tx, mut audio_level_rx) = mpsc::unbounded_channel::<AudioLevelData>();
+        let (audio_level_tx, audio_level_rx) = mpsc::unbounded_channel::<AudioLevelData>();
         
-        // 根据 ASR 模式选择录音器
-        let is_realtime_mode = asr_config.primary.mode == ASRMode::Realtime;
+        let mut session = VoiceSession::new(asr_config.clone())
+            .with_stats(Arc::clone(&self.stats));
         
-        if is_realtime_mode {
-            log_info!("使用 Realtime 模式，启动流式录音器");
-            
-            // 创建流式录音器
-            let mut streaming_recorder = StreamingRecorder::new()
-                .map_err(|e| RouterError::ModuleError(format!("创建流式录音器失败: {}", e)))?;
-            
-            // 设置音频级别回调
-            let tx = audio_level_tx.clone();
-            streaming_recorder.set_level_callback(move |level, waveform| {
-                let _ = tx.send(AudioLevelData { level, waveform });
-            });
-            
-            // 启动流式录音，获取音频块接收通道
-            let chunk_rx = streaming_recorder.start_streaming(
-                mode.clone().into(),
-                recording_device.as_deref(),
-                compression_level,
-            )
-                .map_err(|e| RouterError::ModuleError(format!("启动流式录音失败: {}", e)))?;
-            
-            // 创建实时转录任务
-            let primary_config = asr_config.primary.clone();
-            let ws_sender = self.ws_sender.lock().await.clone();
-            
-            // 创建部分结果回调
-            let partial_callback: Option<Box<dyn Fn(&str) + Send + 'static>> = if let Some(sender) = ws_sender.clone() {
-                Some(Box::new(move |text: &str| {
-                    let text_owned = text.to_string();
-                    let sender = sender.clone();
-                    tokio::spawn(async move {
-                        let msg = serde_json::json!({
-                            "module": "voice",
-                            "type": "transcription_progress",
-                            "partial_text": text_owned,
-                        });
-                        let json = serde_json::to_string(&msg).unwrap();
-                        let mut s = sender.lock().await;
-                        let _ = s.send(tokio_tungstenite::tungstenite::Message::Text(json.into())).await;
-                    });
-                }))
-            } else {
-                None
-            };
-            
-            // 创建实时转录任务
-            let (task, stop_tx) = RealtimeTranscriptionTask::new(
-                primary_config,
-                chunk_rx,
-                partial_callback,
-            );
-            
-            // 启动实时转录任务
-            let task_handle = tokio::spawn(async move {
-                task.run_with_details().await
-            });
-            
-            state.asr_config = Some(asr_config.clone());
-            state.is_recording = true;
-            state.recording_mode = Some(mode.clone());
-            state.recording_start_time = Some(Instant::now());
-            state.audio_level_tx = Some(audio_level_tx.clone());
-            state.streaming_recorder = Some(streaming_recorder);
-            state.realtime_task = Some(task_handle);
-            state.stop_signal = Some(stop_tx);
-            
-        } else {
-            log_info!("使用 HTTP 模式，启动普通录音器");
-            
-            // 创建普通录音器
-            let mut recorder = AudioRecorder::new()
-                .map_err(|e| RouterError::ModuleError(format!("创建录音器失败: {}", e)))?;
-            
-            // 设置音频级别回调
-            let tx = audio_level_tx.clone();
-            recorder.set_level_callback(move |level, waveform| {
-                let _ = tx.send(AudioLevelData { level, waveform });
-            });
-            
-            // 启动录音
-            recorder.start(
-                mode.clone().into(),
-                recording_device.as_deref(),
-                compression_level,
-            )
-                .map_err(|e| RouterError::ModuleError(format!("启动录音失败: {}", e)))?;
+        // 设置音频级别回调
+        let tx = audio_level_tx.clone();
+        session.set_level_callback(move |level, waveform| {
+            let _ = tx.send(AudioLevelData { level, waveform });
+        });
+        
+        let ws_sender = self.ws_sender.lock().await.clone();
+        
+        if asr_config.primary.mode == ASRMode::Realtime {
+            session.set_partial_callback(partial_forwarder(ws_sender.clone(), PartialFormat::Json));
+            session.set_status_callback(status_forwarder(ws_sender.clone()));
             
-            state.asr_config = Some(asr_config.clone());
-            state.is_recording = true;
-            state.recording_mode = Some(mode.clone());
-            state.recording_start_time = Some(Instant::now());
-            state.audio_level_tx = Some(audio_level_tx.clone());
-            state.recorder = Some(recorder);
+            // 优先使用预连接的会话
+            session.set_preconnected(self.preconnected.lock().await.take());
         }
         
-        // 根据配置设置音频反馈
-        state.beep_player.set_enabled(asr_config.enable_audio_feedback);
-        
-        // 播放开始提示音
-        state.beep_player.play_start();
+        // 开始录音 (播放开始提示音)
+        session.start(mode)
+            .map_err(|e| RouterError::ModuleError(format!("启动录音失败: {}", e)))?;
+        let audio_feedback_available = session.is_audio_feedback_available();
         
+        state.set_asr_config(asr_config, ws_sender.clone());
+        state.set_partial_format(PartialFormat::Json);
+        state.audio_level_tx = Some(audio_level_tx);
+        state.session = Some(session);
         drop(state);
         
-        // 启动音频级别转发任务
-        let ws_sender = self.ws_sender.lock().await.clone();
+        // 启动音频级别转发任务 (合并到推送频率上限，只发送最新值)
         if let Some(sender) = ws_sender {
-            tokio::spawn(async move {
-                while let Some(data) = audio_level_rx.recv().await {
-                    let msg = serde_json::json!({
-                        "module": "voice",
-                        "type": "audio_level",
+            let stream_enabled = Arc::clone(&self.audio_level_stream);
+            let interval = Duration::from_secs(1) / self.audio_level_rate_hz.load(Ordering::SeqCst);
+            tokio::spawn(forward_audio_levels(audio_level_rx, interval, stream_enabled, move |data| {
+                let sender = sender.clone();
+                async move {
+                    let response = ServerResponse::new(ModuleType::Voice, "audio_level", serde_json::json!({
                         "level": data.level,
                         "waveform": data.waveform,
-                    });
-                    let json = serde_json::to_string(&msg).unwrap();
-                    let mut s = sender.lock().await;
-                    if s.send(tokio_tungstenite::tungstenite::Message::Text(json.into())).await.is_err() {
-                        break;
-                    }
+                    }));
+                    crate::server::send_response(&sender, &response).await.is_ok()
                 }
-            });
+            }));
         }
         
         // 发送录音开始状态
         self.send_message("recording_state", serde_json::json!({
-            "state": "started"
+            "state": "started",
+            "audio_feedback_available": audio_feedback_available,
         })).await?;
         
         Ok(None)
     }
 
     /// 处理停止录音命令
+    /// 
+    /// 录音停止后转录在后台任务中执行，避免阻塞连接的消息循环，
+    /// 使转录过程中仍能接收 `cancel_recording` 命令
     async fn handle_stop_recording(&self) -> Result<Option<ServerResponse>, RouterError> {
         log_info!("收到停止录音命令");
         
         let mut state = self.state.lock().await;
         
         // 检查是否在录音
-        if !state.is_recording {
-            return Err(RouterError::ModuleError("未在录音中".to_string()));
-        }
-        
-        // 播放结束提示音
-        state.beep_player.play_stop();
+        let mut session = state.session.take()
+            .ok_or_else(|| RouterError::ModuleError("未在录音中".to_string()))?;
         
         // 关闭音频级别 channel
         state.audio_level_tx = None;
+        let strategy = state.strategy();
+        drop(state);
         
-        // 获取 ASR 配置
-        let asr_config = state.asr_config.clone()
-            .ok_or_else(|| RouterError::ModuleError("ASR 配置未设置".to_string()))?;
+        // 停止录音 (播放结束提示音)，Realtime 模式同时通知实时转录任务收尾
+        // 超出时长上限的录音返回上限内的音频，结束原因为 MaxDuration
+        let pending = session.stop()
+            .map_err(|e| RouterError::ModuleError(format!("停止录音失败: {}", e)))?
+            .with_strategy(strategy);
         
-        // 检查是否是 realtime 模式
-        let is_realtime_mode = state.streaming_recorder.is_some();
+        let audio_summary = pending.audio_data().summary();
+        log_info!("录音摘要: {:?}, 结束原因: {:?}", audio_summary, pending.stop_reason());
         
-        if is_realtime_mode {
-            // Realtime 模式：停止流式录音，等待实时转录任务完成
-            log_info!("停止 Realtime 模式录音");
-            
-            // 发送停止信号给实时转录任务
-            if let Some(stop_tx) = state.stop_signal.take() {
-                let _ = stop_tx.send(());
-            }
-            
-            // 停止流式录音并获取完整音频数据 (用于回退)
-            let audio_data = if let Some(ref mut streaming_recorder) = state.streaming_recorder {
-                streaming_recorder.stop_streaming()
-                    .map_err(|e| RouterError::ModuleError(format!("停止流式录音失败: {}", e)))?
-            } else {
-                return Err(RouterError::ModuleError("流式录音器未初始化".to_string()));
-            };
-            
-            // 获取实时转录任务句柄
-            let realtime_task = state.realtime_task.take();
-            
-            // 更新状态
-            state.is_recording = false;
-            state.recording_mode = None;
-            state.streaming_recorder = None;
-            drop(state);
-            
-            // 发送录音停止状态
-            self.send_message("recording_state", serde_json::json!({
-                "state": "stopped"
-            })).await?;
-            
-            // 等待实时转录任务完成
-            let realtime_result = if let Some(task_handle) = realtime_task {
-                log_info!("等待实时转录任务完成...");
-                match task_handle.await {
-                    Ok(result) => Some(result),
-                    Err(e) => {
-                        log_error!("实时转录任务 panic: {}", e);
-                        None
-                    }
-                }
-            } else {
-                log_error!("实时转录任务句柄不存在");
-                None
-            };
-            
-            // 处理实时转录结果
-            match realtime_result {
-                Some(RealtimeTaskResult::Success(result)) => {
-                    log_info!(
-                        "实时转录成功: engine={}, duration={}ms, text={}",
-                        result.engine,
-                        result.duration_ms,
-                        &result.text
-                    );
-                    
-                    self.send_message("transcription_complete", serde_json::json!({
-                        "text": result.text,
-                        "engine": result.engine,
-                        "used_fallback": false,
-                        "duration_ms": result.duration_ms,
-                    })).await?;
-                }
-                Some(RealtimeTaskResult::Failed { error, engine_name, .. }) => {
-                    log_error!("实时转录失败 ({}): {}，尝试回退到 HTTP 模式", engine_name, error);
-                    
-                    // 回退到 HTTP 模式
-                    let fallback_result = perform_fallback_transcription(&audio_data, &asr_config).await;
-                    
-                    match fallback_result {
-                        Ok(result) => {
-                            log_info!(
-                                "HTTP 回退转录成功: engine={}, duration={}ms, text={}",
-                                result.engine,
-                                result.duration_ms,
-                                &result.text
-                            );
-                            
-                            self.send_message("transcription_complete", serde_json::json!({
-                                "text": result.text,
-                                "engine": result.engine,
-                                "used_fallback": true,
-                                "duration_ms": result.duration_ms,
-                            })).await?;
-                        }
-                        Err(fallback_error) => {
-                            log_error!("HTTP 回退也失败: {}", fallback_error);
-                            
-                            self.send_message("error", serde_json::json!({
-                                "code": "TRANSCRIPTION_FAILED",
-                                "message": format!(
-                                    "实时转录失败: {}; HTTP 回退也失败: {}",
-                                    error, fallback_error
-                                ),
-                            })).await?;
-                        }
-                    }
-                }
-                None => {
-                    log_error!("实时转录任务异常，尝试回退到 HTTP 模式");
-                    
-                    // 回退到 HTTP 模式
-                    let fallback_result = perform_fallback_transcription(&audio_data, &asr_config).await;
-                    
-                    match fallback_result {
-                        Ok(result) => {
-                            log_info!(
-                                "HTTP 回退转录成功: engine={}, duration={}ms, text={}",
-                                result.engine,
-                                result.duration_ms,
-                                &result.text
-                            );
-                            
-                            self.send_message("transcription_complete", serde_json::json!({
-                                "text": result.text,
-                                "engine": result.engine,
-                                "used_fallback": true,
-                                "duration_ms": result.duration_ms,
-                            })).await?;
-                        }
-                        Err(fallback_error) => {
-                            log_error!("HTTP 回退也失败: {}", fallback_error);
-                            
-                            self.send_message("error", serde_json::json!({
-                                "code": "TRANSCRIPTION_FAILED",
-                                "message": format!(
-                                    "实时转录任务异常; HTTP 回退也失败: {}",
-                                    fallback_error
-                                ),
-                            })).await?;
-                        }
-                    }
-                }
-            }
-        } else {
-            // HTTP 模式：停止普通录音，执行 HTTP 转录
-            log_info!("停止 HTTP 模式录音");
-            
-            // 停止录音并获取音频数据
-            let audio_data = if let Some(ref mut recorder) = state.recorder {
-                recorder.stop().map_err(|e| RouterError::ModuleError(format!("停止录音失败: {}", e)))?
-            } else {
-                return Err(RouterError::ModuleError("录音器未初始化".to_string()));
-            };
-            
-            // 更新状态
-            state.is_recording = false;
-            state.recording_mode = None;
-            state.recorder = None;
-            drop(state);
-            
-            // 发送录音停止状态
-            self.send_message("recording_state", serde_json::json!({
-                "state": "stopped"
-            })).await?;
-            
-            // 检查音频数据是否为空
-            if audio_data.is_empty() {
-                log_info!("录音数据为空，跳过转录");
-                self.send_message("transcription_complete", serde_json::json!({
-                    "text": "",
-                    "engine": "none",
-                    "used_fallback": false,
-                    "duration_ms": 0,
-                })).await?;
-                return Ok(None);
-            }
-            
-            log_info!("开始 ASR 转录，音频时长: {}ms", audio_data.duration_ms);
-            
-            // 执行 ASR 转录
-            let transcription_result = perform_transcription(&audio_data, &asr_config).await;
-            
-            match transcription_result {
+        // 发送录音停止状态
+        self.send_message("recording_state", serde_json::json!({
+            "state": "stopped",
+            "reason": pending.stop_reason(),
+            "audio_summary": audio_summary,
+        })).await?;
+        
+        self.spawn_transcription(pending).await;
+        
+        Ok(None)
+    }
+    
+    /// 在后台任务中完成转录并上报结果
+    /// 
+    /// 转录不阻塞连接的消息循环，过程中仍能接收 `cancel_recording` 命令
+    async fn spawn_transcription(&self, pending: PendingTranscription) {
+        let cancel_token = self.begin_transcription().await;
+        let ws_sender = self.ws_sender.lock().await.clone();
+        
+        tokio::spawn(async move {
+            match pending.transcribe(&cancel_token).await {
                 Ok(result) => {
                     log_info!(
                         "转录成功: engine={}, used_fallback={}, duration={}ms, text={}",
@@ -552,25 +471,50 @@ impl VoiceHandler {
                         &result.text
                     );
                     
-                    self.send_message("transcription_complete", serde_json::json!({
+                    let mut payload = serde_json::json!({
                         "text": result.text,
                         "engine": result.engine,
                         "used_fallback": result.used_fallback,
                         "duration_ms": result.duration_ms,
-                    })).await?;
+                        "retried": result.retried,
+                    });
+                    // 与 TranscriptionResult 的序列化一致，未测量任何阶段时省略
+                    if !result.timings.is_empty() {
+                        payload["timings"] = serde_json::json!(result.timings);
+                    }
+                    let _ = send_voice_message(&ws_sender, "transcription_complete", payload).await;
+                }
+                Err(ASRError::Cancelled) => {
+                    log_info!("转录已取消");
+                }
+                Err(ASRError::NoSpeechDetected) => {
+                    log_info!("未检测到语音");
+                    
+                    let _ = send_voice_message(&ws_sender, "error", serde_json::json!({
+                        "code": "NO_SPEECH_DETECTED",
+                        "message": ASRError::NoSpeechDetected.to_string(),
+                    })).await;
                 }
                 Err(e) => {
                     log_error!("转录失败: {}", e);
                     
-                    self.send_message("error", serde_json::json!({
+                    let _ = send_voice_message(&ws_sender, "error", serde_json::json!({
                         "code": "TRANSCRIPTION_FAILED",
                         "message": e.to_string(),
-                    })).await?;
+                    })).await;
                 }
             }
-        }
-        
-        Ok(None)
+            // 转录结束后令牌失效，之后的取消命令不再视为转录进行中
+            cancel_token.cancel();
+        });
+    }
+
+    /// 登记新的进行中转录，返回其取消令牌
+    async fn begin_transcription(&self) -> CancellationToken {
+        let cancel_token = CancellationToken::new();
+        let mut slot = self.transcription_cancel.lock().await;
+        *slot = Some(cancel_token.clone());
+        cancel_token
     }
 
     /// 处理取消录音命令
@@ -579,50 +523,42 @@ impl VoiceHandler {
         
         let mut state = self.state.lock().await;
         
-        // 检查是否在录音
-        if !state.is_recording {
-            return Err(RouterError::ModuleError("未在录音中".to_string()));
+        // 客户端音频流未结束时直接中止
+        if let Some(stream) = state.client_stream.take() {
+            drop(state);
+            stream.abort();
+            log_info!("已取消客户端音频流");
+            self.send_message("recording_state", serde_json::json!({
+                "state": "cancelled"
+            })).await?;
+            return Ok(None);
         }
         
+        // 未在录音时，尝试取消进行中的转录
+        let Some(mut session) = state.session.take() else {
+            drop(state);
+            let cancel_token = self.transcription_cancel.lock().await.take();
+            return match cancel_token {
+                Some(token) if !token.is_cancelled() => {
+                    token.cancel();
+                    log_info!("已取消进行中的转录");
+                    self.send_message("recording_state", serde_json::json!({
+                        "state": "cancelled"
+                    })).await?;
+                    Ok(None)
+                }
+                _ => Err(RouterError::ModuleError("未在录音中".to_string())),
+            };
+        };
+        
         // 关闭音频级别 channel
         state.audio_level_tx = None;
-        
-        // 检查是否是 realtime 模式
-        let is_realtime_mode = state.streaming_recorder.is_some();
-        
-        if is_realtime_mode {
-            // 发送停止信号给实时转录任务
-            if let Some(stop_tx) = state.stop_signal.take() {
-                let _ = stop_tx.send(());
-            }
-            
-            // 取消流式录音
-            if let Some(ref mut streaming_recorder) = state.streaming_recorder {
-                streaming_recorder.cancel();
-            }
-            
-            // 中止实时转录任务
-            if let Some(task_handle) = state.realtime_task.take() {
-                task_handle.abort();
-            }
-            
-            // 更新状态
-            state.streaming_recorder = None;
-            state.realtime_task = None;
-            state.stop_signal = None;
-        } else {
-            // 取消普通录音
-            if let Some(ref mut recorder) = state.recorder {
-                recorder.cancel();
-            }
-            state.recorder = None;
-        }
-        
-        // 更新状态
-        state.is_recording = false;
-        state.recording_mode = None;
         drop(state);
         
+        // 取消录音与实时转录 (播放取消提示音)
+        session.cancel()
+            .map_err(|e| RouterError::ModuleError(format!("取消录音失败: {}", e)))?;
+        
         // 发送录音取消状态
         self.send_message("recording_state", serde_json::json!({
             "state": "cancelled"
@@ -635,19 +571,203 @@ impl VoiceHandler {
     async fn handle_update_config(&self, asr_config: ASRConfig) -> Result<Option<ServerResponse>, RouterError> {
         log_info!("收到更新配置命令");
         
+        let ws_sender = self.ws_sender.lock().await.clone();
         let mut state = self.state.lock().await;
-        state.asr_config = Some(asr_config);
+        if let Some(ref mut session) = state.session {
+            session.set_asr_config(asr_config.clone());
+        }
+        let device_name = asr_config.recording_device.clone();
+        state.set_asr_config(asr_config, ws_sender);
         
         log_debug!("ASR 配置已更新");
         
+        // 后台预先解析录音设备，缩短下一次录音的启动延迟
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = prewarm_input_device(device_name.as_deref()) {
+                log_debug!("预解析录音设备失败: {}", e);
+            }
+        });
+        
+        Ok(None)
+    }
+    /// 处理录音模式设置命令
+    /// 
+    /// 录音中直接切换当前会话的模式 (如按住后锁定为切换录音)，否则设置默认录音模式
+    async fn handle_set_recording_mode(&self, mode: RecordingMode) -> Result<Option<ServerResponse>, RouterError> {
+        let mut state = self.state.lock().await;
+        let recording = match state.session {
+            Some(ref session) => {
+                session.set_mode(mode)
+                    .map_err(|e| RouterError::ModuleError(format!("切换录音模式失败: {}", e)))?;
+                log_info!("录音中切换模式: {:?}", mode);
+                true
+            }
+            None => {
+                state.default_mode = mode;
+                log_info!("默认录音模式: {:?}", mode);
+                false
+            }
+        };
+        
+        Ok(Some(ServerResponse::new(
+            ModuleType::Voice,
+            "recording_mode",
+            serde_json::json!({ "mode": mode, "recording": recording }),
+        )))
+    }
+    
+    /// 处理暂停 / 继续录音命令
+    /// 
+    /// 暂停期间音频流与实时会话保持，采集到的音频被丢弃
+    async fn handle_pause_recording(&self, paused: bool) -> Result<Option<ServerResponse>, RouterError> {
+        let state = self.state.lock().await;
+        let session = state.session.as_ref()
+            .ok_or_else(|| RouterError::ModuleError("未在录音中".to_string()))?;
+        
+        let (result, recording_state) = if paused {
+            (session.pause(), "paused")
+        } else {
+            (session.resume(), "resumed")
+        };
+        result.map_err(|e| RouterError::ModuleError(format!("切换录音暂停状态失败: {}", e)))?;
+        drop(state);
+        
+        self.send_message("recording_state", serde_json::json!({
+            "state": recording_state
+        })).await?;
+        
+        Ok(None)
+    }
+    
+    /// 处理配置校验命令
+    /// 
+    /// 返回全部配置问题 (而非首个错误)，便于设置界面同时标出多个字段
+    fn handle_validate_config(
+        &self,
+        asr_config: ASRConfig,
+        request_id: Option<String>,
+    ) -> Result<Option<ServerResponse>, RouterError> {
+        let issues = asr_config.validate_all();
+        
+        Ok(Some(ServerResponse::new(
+            ModuleType::Voice,
+            "config_validation",
+            serde_json::json!({
+                "valid": issues.is_empty(),
+                "issues": issues,
+                "request_id": request_id,
+            }),
+        )))
+    }
+    
+    /// 处理运行时切换供应商 / 模式命令
+    /// 
+    /// 目标供应商缺少凭证时返回错误，当前配置保持不变；切换成功后下次录音即生效
+    async fn handle_switch_engine(
+        &self,
+        provider: Option<ASRProvider>,
+        mode: Option<ASRMode>,
+    ) -> Result<Option<ServerResponse>, RouterError> {
+        let mut state = self.state.lock().await;
+        let service = state.service.as_mut()
+            .ok_or_else(|| RouterError::ModuleError("尚未收到 ASR 配置，无法切换引擎".to_string()))?;
+        
+        // 任一步失败时恢复切换前的配置
+        let previous = service.config().clone();
+        let switched = provider
+            .map_or(Ok(()), |provider| service.switch_provider(provider))
+            .and_then(|_| mode.map_or(Ok(()), |mode| service.switch_mode(mode)));
+        if let Err(e) = switched {
+            service.set_config(previous);
+            return Err(RouterError::ModuleError(e.to_string()));
+        }
+        
+        let available_providers = service.available_providers();
+        let asr_config = service.config().clone();
+        let payload = serde_json::json!({
+            "provider": asr_config.primary.provider,
+            "mode": asr_config.primary.mode,
+            "available_providers": available_providers,
+        });
+        
+        if let Some(ref mut session) = state.session {
+            session.set_asr_config(asr_config);
+        }
+        
+        Ok(Some(ServerResponse::new(ModuleType::Voice, "engine_switched", payload)))
+    }
+    
+    /// 处理预连接命令
+    /// 
+    /// 在后台建立实时会话，保活窗口内的下一次录音直接复用，过期后关闭连接
+    async fn handle_preconnect(&self, asr_config: ASRConfig) -> Result<Option<ServerResponse>, RouterError> {
+        if asr_config.primary.mode != ASRMode::Realtime {
+            log_debug!("主引擎非 Realtime 模式，跳过预连接");
+            return Ok(None);
+        }
+        
+        let primary_config = asr_config.primary;
+        let slot = Arc::clone(&self.preconnected);
+        
+        tokio::spawn(async move {
+            let keepalive = match RealtimeTranscriptionTask::preconnect(&primary_config).await {
+                Ok(session) => {
+                    log_info!("预连接完成: {}", session.engine_name());
+                    let keepalive = session.remaining();
+                    *slot.lock().await = Some(session);
+                    keepalive
+                }
+                Err(e) => {
+                    log_error!("预连接失败: {}", e);
+                    return;
+                }
+            };
+            
+            tokio::time::sleep(keepalive).await;
+            
+            // 保活窗口结束仍未被使用，丢弃会话以关闭连接
+            let mut slot = slot.lock().await;
+            if slot.as_ref().is_some_and(|session| session.is_expired()) {
+                log_info!("预连接会话已过期，关闭连接");
+                *slot = None;
+            }
+        });
+        
         Ok(None)
     }
 
+    /// 处理音频级别推送开关命令
+    /// 
+    /// 推送频率上限在下一次开始录音时生效
+    fn handle_set_audio_level_stream(
+        &self,
+        enabled: bool,
+        max_rate_hz: Option<u32>,
+    ) -> Result<Option<ServerResponse>, RouterError> {
+        log_info!("音频级别推送: {}", if enabled { "开启" } else { "关闭" });
+        self.audio_level_stream.store(enabled, Ordering::SeqCst);
+        if let Some(rate) = max_rate_hz {
+            let rate = rate.clamp(*AUDIO_LEVEL_RATE_RANGE_HZ.start(), *AUDIO_LEVEL_RATE_RANGE_HZ.end());
+            self.audio_level_rate_hz.store(rate, Ordering::SeqCst);
+        }
+        
+        Ok(Some(ServerResponse::new(
+            ModuleType::Voice,
+            "audio_level_stream",
+            serde_json::json!({
+                "enabled": enabled,
+                "max_rate_hz": self.audio_level_rate_hz.load(Ordering::SeqCst),
+            }),
+        )))
+    }
+
     /// 获取输入设备列表
     async fn handle_list_input_devices(
         &self,
         request_id: Option<String>,
     ) -> Result<Option<ServerResponse>, RouterError> {
+        // 设备列表可能已变化，下次录音重新解析设备
+        invalidate_input_device_cache();
         let devices = list_input_devices()
             .map_err(|e| RouterError::ModuleError(format!("获取录音设备失败: {}", e)))?;
 
@@ -658,41 +778,200 @@ impl VoiceHandler {
 
         Ok(Some(ServerResponse::new(ModuleType::Voice, "input_devices", payload)))
     }
+
+    /// 处理客户端音频流初始化命令
+    /// 
+    /// 之后收到的二进制帧作为 16kHz 单声道 16-bit PCM 送入转录，`end_stream` 结束；
+    /// 已有未结束的音频流时将其中止
+    async fn handle_init(
+        &self,
+        asr_config: ASRConfig,
+        partial_format: PartialFormat,
+    ) -> Result<Option<ServerResponse>, RouterError> {
+        asr_config.validate()
+            .map_err(|e| RouterError::ModuleError(format!("ASR 配置无效: {}", e)))?;
+
+        let mut state = self.state.lock().await;
+        if state.session.is_some() {
+            return Err(RouterError::ModuleError("正在录音，无法接收客户端音频".to_string()));
+        }
+        if let Some(stream) = state.client_stream.take() {
+            log_info!("重新初始化，中止未结束的客户端音频流");
+            stream.abort();
+        }
+
+        let ws_sender = self.ws_sender.lock().await.clone();
+        let (partial_callback, status_callback) = match asr_config.primary.mode {
+            ASRMode::Realtime => (
+                partial_forwarder(ws_sender.clone(), partial_format),
+                status_forwarder(ws_sender.clone()),
+            ),
+            ASRMode::Http => (None, None),
+        };
+
+        let payload = serde_json::json!({
+            "provider": asr_config.primary.provider,
+            "mode": asr_config.primary.mode,
+            "sample_rate": TARGET_SAMPLE_RATE,
+            "partial_format": partial_format,
+        });
+        state.client_stream = Some(ClientAudioStream::start(
+            asr_config.clone(),
+            partial_callback,
+            status_callback,
+            Some(Arc::clone(&self.stats)),
+        ));
+        state.set_asr_config(asr_config, ws_sender);
+        state.set_partial_format(partial_format);
+
+        Ok(Some(ServerResponse::new(ModuleType::Voice, "stream_ready", payload)))
+    }
+
+    /// 处理客户端上传的二进制音频帧
+    /// 
+    /// 连接状态锁只用于缓存音频，等待实时任务的音频通道前先释放
+    pub async fn handle_binary(&self, frame: &[u8]) -> Result<(), RouterError> {
+        let (pushed, max_secs) = {
+            let mut state = self.state.lock().await;
+            let stream = state.client_stream.as_mut()
+                .ok_or_else(|| RouterError::ModuleError("音频流未初始化，请先发送 init".to_string()))?;
+            let pushed = stream.push(frame)
+                .map_err(|e| RouterError::ModuleError(format!("无效的音频帧: {}", e)))?;
+            (pushed, stream.max_secs())
+        };
+
+        if let Some(forward) = pushed.forward {
+            forward.send().await;
+        }
+        // 达到时长上限不是帧错误：通知客户端停止上传，已接收的音频在 end_stream 时照常转录
+        if pushed.limit_reached {
+            self.send_message("stream_limit_reached", serde_json::json!({
+                "max_recording_secs": max_secs,
+            })).await?;
+        }
+        Ok(())
+    }
+
+    /// 处理客户端音频流结束命令，转录在后台任务中执行
+    async fn handle_end_stream(&self) -> Result<Option<ServerResponse>, RouterError> {
+        let mut state = self.state.lock().await;
+        let stream = state.client_stream.take()
+            .ok_or_else(|| RouterError::ModuleError("音频流未初始化".to_string()))?;
+        let strategy = state.strategy();
+        drop(state);
+
+        let pending = stream.finish().with_strategy(strategy);
+        self.send_message("recording_state", serde_json::json!({
+            "state": "stopped",
+            "reason": pending.stop_reason(),
+            "audio_summary": pending.audio_data().summary(),
+        })).await?;
+
+        self.spawn_transcription(pending).await;
+
+        Ok(None)
+    }
+
+    /// 处理自检命令
+    /// 
+    /// 录制一小段音频并按当前配置转录，完成后推送设备、电平、引擎与耗时报告；
+    /// 录音进行中或已有自检时拒绝执行。自检在后台任务中运行，期间拒绝开始录音
+    async fn handle_self_test(
+        &self,
+        asr_config: ASRConfig,
+        duration_ms: Option<u64>,
+        request_id: Option<String>,
+    ) -> Result<Option<ServerResponse>, RouterError> {
+        let ws_sender = self.ws_sender.lock().await.clone();
+        let (strategy, guard) = {
+            // 在状态锁内检查并占用，与开始录音互斥
+            let mut state = self.state.lock().await;
+            if state.session.is_some() || state.client_stream.is_some() {
+                return Err(RouterError::ModuleError("正在录音，无法执行自检".to_string()));
+            }
+            if self.self_test_running.swap(true, Ordering::SeqCst) {
+                return Err(RouterError::ModuleError("自检进行中".to_string()));
+            }
+            let guard = SelfTestGuard { running: Arc::clone(&self.self_test_running) };
+            state.set_asr_config(asr_config.clone(), ws_sender.clone());
+            state.set_partial_format(PartialFormat::Json);
+            (state.strategy(), guard)
+        };
+
+        let duration_ms = duration_ms.unwrap_or(diagnostics::DEFAULT_SELF_TEST_DURATION_MS);
+        tokio::spawn(async move {
+            let _guard = guard;
+            match diagnostics::run_self_test(&asr_config, strategy, duration_ms).await {
+                Ok(report) => {
+                    let _ = send_voice_message(&ws_sender, "self_test_report", serde_json::json!({
+                        "report": report,
+                        "request_id": request_id,
+                    })).await;
+                }
+                Err(e) => {
+                    log_error!("自检录音失败: {}", e);
+
+                    let _ = send_voice_message(&ws_sender, "error", serde_json::json!({
+                        "code": "SELF_TEST_FAILED",
+                        "message": format!("自检录音失败: {}", e),
+                        "request_id": request_id,
+                    })).await;
+                }
+            }
+        });
+
+        Ok(None)
+    }
+    
+    /// 当前生效的 ASR 配置 (密钥已隐藏) 与由主引擎配置推导的重试参数，未下发配置时为空
+    pub async fn effective_config(&self) -> serde_json::Value {
+        let state = self.state.lock().await;
+        let Some(asr_config) = state.asr_config() else {
+            return serde_json::json!({ "asr": null, "retry": null });
+        };
+        
+        let retry = asr::RetryConfig::from_provider_config(&asr_config.primary);
+        serde_json::json!({
+            "asr": asr_config.redacted(),
+            "retry": {
+                "max_retries": retry.max_retries,
+                "base_delay_ms": retry.base_delay_ms,
+                "request_timeout_ms": retry.request_timeout_ms,
+                "session_timeout_ms": retry.session_timeout_ms,
+                "adaptive_timeout_max_ms": retry.adaptive_timeout.map(|adaptive| adaptive.max_timeout_ms),
+            },
+        })
+    }
     
     /// 检查是否正在录音
     pub async fn is_recording(&self) -> bool {
         let state = self.state.lock().await;
-        state.is_recording
+        state.session.is_some()
     }
     
     /// 清理资源
     pub async fn cleanup(&self) {
+        // 取消进行中的转录
+        if let Some(token) = self.transcription_cancel.lock().await.take() {
+            token.cancel();
+        }
+        
+        // 关闭预连接会话
+        *self.preconnected.lock().await = None;
+        
         let mut state = self.state.lock().await;
         
-        if state.is_recording {
-            state.is_recording = false;
-            state.recording_mode = None;
+        // 中止录音与实时转录任务
+        if let Some(mut session) = state.session.take() {
             log_info!("连接关闭，取消录音");
+            session.abort();
         }
         
-        // 取消实时转录任务
-        if let Some(stop_tx) = state.stop_signal.take() {
-            let _ = stop_tx.send(());
-        }
-        if let Some(task_handle) = state.realtime_task.take() {
-            task_handle.abort();
+        if let Some(stream) = state.client_stream.take() {
+            log_info!("连接关闭，中止客户端音频流");
+            stream.abort();
         }
         
-        // 取消录音
-        if let Some(ref mut streaming_recorder) = state.streaming_recorder {
-            streaming_recorder.cancel();
-        }
-        if let Some(ref mut recorder) = state.recorder {
-            recorder.cancel();
-        }
-        
-        state.streaming_recorder = None;
-        state.recorder = None;
         state.audio_level_tx = None;
     }
 }
@@ -714,12 +993,18 @@ impl ModuleHandler for VoiceHandler {
         
         match msg.msg_type.as_str() {
             "start_recording" => {
-                let mode: RecordingMode = msg.get_field("mode")
-                    .ok_or_else(|| RouterError::ModuleError("缺少 mode 字段".to_string()))?;
-                let asr_config: ASRConfig = msg.get_field("asr_config")
-                    .ok_or_else(|| RouterError::ModuleError("缺少 asr_config 字段".to_string()))?;
-                
-                self.handle_start_recording(mode, asr_config).await
+                let request: StartRecordingRequest = msg.parse()?;
+                self.handle_start_recording(request.mode, request.asr_config).await
+            }
+            "set_recording_mode" => {
+                let request: SetRecordingModeRequest = msg.parse()?;
+                self.handle_set_recording_mode(request.mode).await
+            }
+            "pause_recording" => {
+                self.handle_pause_recording(true).await
+            }
+            "resume_recording" => {
+                self.handle_pause_recording(false).await
             }
             "stop_recording" => {
                 self.handle_stop_recording().await
@@ -728,14 +1013,43 @@ impl ModuleHandler for VoiceHandler {
                 self.handle_cancel_recording().await
             }
             "update_config" => {
-                let asr_config: ASRConfig = msg.get_field("asr_config")
-                    .ok_or_else(|| RouterError::ModuleError("缺少 asr_config 字段".to_string()))?;
-                
-                self.handle_update_config(asr_config).await
+                let request: ConfigRequest = msg.parse()?;
+                self.handle_update_config(request.asr_config).await
+            }
+            "validate_config" => {
+                let request: ValidateConfigRequest = msg.parse()?;
+                self.handle_validate_config(request.asr_config, request.request_id)
+            }
+            "switch_provider" => {
+                let request: SwitchProviderRequest = msg.parse()?;
+                self.handle_switch_engine(Some(request.provider), None).await
+            }
+            "switch_mode" => {
+                let request: SwitchModeRequest = msg.parse()?;
+                self.handle_switch_engine(None, Some(request.mode)).await
+            }
+            "preconnect" => {
+                let request: ConfigRequest = msg.parse()?;
+                self.handle_preconnect(request.asr_config).await
+            }
+            "set_audio_level_stream" => {
+                let request: SetAudioLevelStreamRequest = msg.parse()?;
+                self.handle_set_audio_level_stream(request.enabled, request.max_rate_hz)
             }
             "list_input_devices" => {
-                let request_id: Option<String> = msg.get_field("request_id");
-                self.handle_list_input_devices(request_id).await
+                let request: ListInputDevicesRequest = msg.parse()?;
+                self.handle_list_input_devices(request.request_id).await
+            }
+            "init" => {
+                let request: InitRequest = msg.parse()?;
+                self.handle_init(request.asr_config, request.partial_format).await
+            }
+            "end_stream" => {
+                self.handle_end_stream().await
+            }
+            "self_test" => {
+                let request: SelfTestRequest = msg.parse()?;
+                self.handle_self_test(request.asr_config, request.duration_ms, request.request_id).await
             }
             _ => {
                 log_debug!("未知的 Voice 消息类型: {}", msg.msg_type);
@@ -749,104 +1063,161 @@ impl ModuleHandler for VoiceHandler {
 // 辅助函数
 // ============================================================================
 
-/// 执行 ASR 转录
-async fn perform_transcription(
-    audio_data: &AudioData,
-    asr_config: &ASRConfig,
-) -> Result<TranscriptionResult, ASRError> {
-    // 验证配置
-    asr_config.validate()
-        .map_err(|e| ASRError::ConfigError(e.to_string()))?;
-    
-    // 创建顺序故障转移策略
-    let strategy = FallbackStrategy::from_config(asr_config)?;
-    let fallback_providers: Vec<String> = asr_config
-        .fallbacks
-        .iter()
-        .map(|config| config.provider.to_string())
-        .collect();
-    
-    log_info!(
-        "使用 ASR 引擎: primary={}, fallbacks={:?}, enable_fallback={}",
-        strategy.primary_provider(),
-        fallback_providers,
-        strategy.is_fallback_enabled()
-    );
-    
-    // 执行转录
-    strategy.transcribe(audio_data).await
-}
-
-/// 执行回退 ASR 转录
-async fn perform_fallback_transcription(
-    audio_data: &AudioData,
-    asr_config: &ASRConfig,
-) -> Result<TranscriptionResult, ASRError> {
-    // 检查音频数据是否为空
-    if audio_data.is_empty() {
-        log_info!("回退转录：音频数据为空");
-        return Ok(TranscriptionResult::new(
-            String::new(),
-            "none".to_string(),
-            true,
-            0,
-        ));
-    }
-    
-    log_info!("执行回退转录，音频时长: {}ms", audio_data.duration_ms);
+/// 创建部分结果回调：按到达顺序转发为 partial 消息 (或纯文本帧)
+fn partial_forwarder(ws_sender: Option<WsSender>, format: PartialFormat) -> Option<PartialResultCallback> {
+    switchable_partial_forwarder(ws_sender, Arc::new(Mutex::new(format)))
+}
+
+/// 创建输出格式可切换的部分结果回调：每条结果按转发时的格式发送
+fn switchable_partial_forwarder(
+    ws_sender: Option<WsSender>,
+    format: Arc<Mutex<PartialFormat>>,
+) -> Option<PartialResultCallback> {
+    let sender = ws_sender?;
+    let (partial_tx, mut partial_rx) = mpsc::unbounded_channel::<PartialTranscription>();
+    tokio::spawn(async move {
+        while let Some(partial) = partial_rx.recv().await {
+            let format = *format.lock().unwrap();
+            let sent = match format {
+                PartialFormat::Json => {
+                    let response = ServerResponse::new(ModuleType::Voice, "partial", serde_json::json!(partial));
+                    crate::server::send_response(&sender, &response).await
+                }
+                PartialFormat::Raw => crate::server::send_text(&sender, &partial.text).await,
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+    Some(Box::new(move |partial: &PartialTranscription| {
+        let _ = partial_tx.send(partial.clone());
+    }))
+}
+
+/// 创建会话状态回调：按到达顺序转发为 session_status 消息
+fn status_forwarder(ws_sender: Option<WsSender>) -> Option<SessionStatusCallback> {
+    let sender = ws_sender?;
+    let (status_tx, mut status_rx) = mpsc::unbounded_channel::<SessionStatus>();
+    tokio::spawn(async move {
+        while let Some(status) = status_rx.recv().await {
+            let response = ServerResponse::new(ModuleType::Voice, "session_status", serde_json::json!(status));
+            if crate::server::send_response(&sender, &response).await.is_err() {
+                break;
+            }
+        }
+    });
+    Some(Box::new(move |status: &SessionStatus| {
+        let _ = status_tx.send(status.clone());
+    }))
+}
+
+/// 转发音频级别：每个间隔最多发送一次，期间到达的多个值只保留最新一个
+/// 
+/// 级别回调的频率随音频块大小变化，直接转发会在响亮段落产生大量小帧占满连接；
+/// 推送关闭时丢弃收到的值，`send` 返回 false (连接已断开) 时结束
+async fn forward_audio_levels<F, Fut>(
+    mut level_rx: mpsc::UnboundedReceiver<AudioLevelData>,
+    interval: Duration,
+    stream_enabled: Arc<AtomicBool>,
+    mut send: F,
+) where
+    F: FnMut(AudioLevelData) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut latest: Option<AudioLevelData> = None;
+    let mut next_send = tokio::time::Instant::now();
     
-    // 如果配置了 fallback 引擎且启用了 fallback，按顺序依次尝试
-    if asr_config.enable_fallback {
-        if !asr_config.fallbacks.is_empty() {
-            let mut fallback_errors: Vec<String> = Vec::new();
-            for fallback_config in &asr_config.fallbacks {
-                log_info!("使用配置的 fallback 引擎: {}", fallback_config.provider);
-
-                let engine = asr::create_engine(fallback_config)?;
-
-                let start_time = std::time::Instant::now();
-                match engine.transcribe(audio_data).await {
-                    Ok(text) => {
-                        let duration_ms = start_time.elapsed().as_millis() as u64;
-
-                        return Ok(TranscriptionResult::new(
-                            text,
-                            engine.name().to_string(),
-                            true,
-                            duration_ms,
-                        ));
-                    }
-                    Err(error) => {
-                        fallback_errors.push(format!("{}: {}", engine.name(), error));
+    loop {
+        tokio::select! {
+            data = level_rx.recv() => match data {
+                Some(data) if stream_enabled.load(Ordering::SeqCst) => latest = Some(data),
+                Some(_) => {}
+                None => break,
+            },
+            _ = tokio::time::sleep_until(next_send), if latest.is_some() => {
+                if let Some(data) = latest.take() {
+                    if !send(data).await {
+                        break;
                     }
                 }
+                next_send = tokio::time::Instant::now() + interval;
             }
+        }
+    }
+}
 
-            return Err(ASRError::AllEnginesFailed {
-                primary_error: "实时模式失败".to_string(),
-                fallback_error: Some(fallback_errors.join("; ")),
-            });
+/// 发送 Voice 模块消息给客户端
+async fn send_voice_message(
+    ws_sender: &Option<WsSender>,
+    msg_type: &str,
+    payload: serde_json::Value,
+) -> Result<(), RouterError> {
+    if let Some(ref sender) = *ws_sender {
+        let response = serde_json::json!({
+            "module": "voice",
+            "type": msg_type,
+        });
+        
+        // 合并 payload 到 response
+        let mut response = response.as_object().unwrap().clone();
+        if let serde_json::Value::Object(payload_obj) = payload {
+            for (k, v) in payload_obj {
+                response.insert(k, v);
+            }
         }
+        
+        let json = serde_json::to_string(&response)
+            .map_err(|e| RouterError::ModuleError(format!("JSON 序列化失败: {}", e)))?;
+        
+        let mut sender = sender.lock().await;
+        sender.send(tokio_tungstenite::tungstenite::Message::Text(json.into())).await
+            .map_err(|e| RouterError::ModuleError(format!("发送消息失败: {}", e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_forward_audio_levels_coalesces_to_latest() {
+        let (level_tx, level_rx) = mpsc::unbounded_channel::<AudioLevelData>();
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sent_clone = Arc::clone(&sent);
+        let forward = tokio::spawn(forward_audio_levels(
+            level_rx,
+            Duration::from_millis(500),
+            Arc::new(AtomicBool::new(true)),
+            move |data| {
+                sent_clone.lock().unwrap().push(data.level);
+                async { true }
+            },
+        ));
+
+        // 第一个值立即发送，间隔内的后续值合并为最新一个 (间隔远大于发送耗时，避免负载下偶发失败)
+        for i in 1..=5 {
+            level_tx.send(AudioLevelData { level: i as f32, waveform: Vec::new() }).unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        drop(level_tx);
+        forward.await.unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), vec![1.0, 5.0]);
+    }
+
+    #[tokio::test]
+    async fn test_self_test_guard_clears_flag_on_panic() {
+        let running = Arc::new(AtomicBool::new(true));
+        let guard = SelfTestGuard { running: Arc::clone(&running) };
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            panic!("自检任务异常");
+        });
+
+        assert!(task.await.is_err());
+        assert!(!running.load(Ordering::SeqCst));
     }
-    
-    // 没有配置 fallback 引擎，使用 primary 引擎的 HTTP 模式
-    log_info!("使用 primary 引擎的 HTTP 模式进行回退");
-    
-    // 创建 HTTP 模式的配置
-    let mut http_config = asr_config.primary.clone();
-    http_config.mode = ASRMode::Http;
-    
-    // 创建 HTTP 引擎
-    let engine = asr::create_engine(&http_config)?;
-    
-    let start_time = std::time::Instant::now();
-    let text = engine.transcribe(audio_data).await?;
-    let duration_ms = start_time.elapsed().as_millis() as u64;
-    
-    Ok(TranscriptionResult::new(
-        text,
-        format!("{}-http", engine.name()),
-        true,
-        duration_ms,
-    ))
 }
@@ -0,0 +1,630 @@
+// 语音会话模块
+// 将录音器、提示音和转录策略组合为 start / stop / cancel 三个操作
+
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::asr::{
+    self, realtime_task, transcribe_with, ASRError, ASRErrorKind, FallbackStrategy, PartialResultCallback,
+    PreconnectedSession, RealtimeTaskResult, SessionStatusCallback,
+    RealtimeTranscriptionTask, TranscriptionResult,
+};
+use super::archive::RecordingArchive;
+use super::asr::fallback::{check_audio, pad_silence, Deadline};
+use super::audio::{AudioData, AudioRecorder, RecordingError, StopReason, StreamingRecorder};
+use super::beep::BeepPlayer;
+use super::config::{ASRConfig, ASRMode};
+use super::RecordingMode;
+use crate::server::ServerStats;
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        eprintln!("[INFO] [session] {}", format!($($arg)*));
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        eprintln!("[WARN] [session] {}", format!($($arg)*));
+    };
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        eprintln!("[ERROR] [session] {}", format!($($arg)*))
+    }};
+}
+
+/// 音频级别回调类型
+pub type SessionLevelCallback = Box<dyn Fn(f32, Vec<f32>) + Send + 'static>;
+
+/// 采集状态 (按 ASR 模式区分)
+enum SessionCapture {
+    /// HTTP 模式：录音结束后整段转录
+    Http(AudioRecorder),
+    /// Realtime 模式：边录边转录
+    Realtime {
+        recorder: StreamingRecorder,
+        task: JoinHandle<RealtimeTaskResult>,
+        stop_signal: oneshot::Sender<()>,
+    },
+}
+
+/// 语音会话
+///
+/// 持有录音器、提示音播放器以及按配置选择的转录方式 (Realtime / HTTP)：
+/// - `start` 播放开始提示音并开始录音 (Realtime 模式同时启动实时转录任务)
+/// - `stop` 播放结束提示音并停止录音，返回待完成的转录
+/// - `cancel` 播放取消提示音并中止录音与转录
+pub struct VoiceSession {
+    asr_config: ASRConfig,
+    beep_player: BeepPlayer,
+    capture: Option<SessionCapture>,
+    level_callback: Option<SessionLevelCallback>,
+    partial_callback: Option<PartialResultCallback>,
+    status_callback: Option<SessionStatusCallback>,
+    preconnected: Option<PreconnectedSession>,
+    stats: Option<Arc<ServerStats>>,
+}
+
+impl VoiceSession {
+    pub fn new(asr_config: ASRConfig) -> Self {
+        let beep_player = BeepPlayer::new();
+        beep_player.set_enabled(asr_config.enable_audio_feedback);
+
+        Self {
+            asr_config,
+            beep_player,
+            capture: None,
+            level_callback: None,
+            partial_callback: None,
+            status_callback: None,
+            preconnected: None,
+            stats: None,
+        }
+    }
+
+    /// 使用共享的服务器状态统计实时转录任务数
+    pub fn with_stats(mut self, stats: Arc<ServerStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// 设置音频级别回调 (下次 start 时生效)
+    pub fn set_level_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(f32, Vec<f32>) + Send + 'static,
+    {
+        self.level_callback = Some(Box::new(callback));
+    }
+
+    /// 设置部分转录结果回调 (仅 Realtime 模式，HTTP 流式结果由转录服务上报)
+    pub fn set_partial_callback(&mut self, callback: Option<PartialResultCallback>) {
+        self.partial_callback = callback;
+    }
+
+    /// 设置实时会话连接状态回调 (仅 Realtime 模式)
+    pub fn set_status_callback(&mut self, callback: Option<SessionStatusCallback>) {
+        self.status_callback = callback;
+    }
+
+    /// 设置预连接的实时会话 (配置不匹配或已过期时忽略)
+    pub fn set_preconnected(&mut self, session: Option<PreconnectedSession>) {
+        self.preconnected = session;
+    }
+
+    /// 更新 ASR 配置 (已启动的实时转录任务不受影响)
+    pub fn set_asr_config(&mut self, asr_config: ASRConfig) {
+        self.beep_player.set_enabled(asr_config.enable_audio_feedback);
+        self.asr_config = asr_config;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// 录音中切换录音模式，音频流与转录任务不受影响
+    pub fn set_mode(&self, mode: RecordingMode) -> Result<(), RecordingError> {
+        match self.capture {
+            Some(SessionCapture::Http(ref recorder)) => recorder.set_mode(mode.into()),
+            Some(SessionCapture::Realtime { ref recorder, .. }) => recorder.set_mode(mode.into()),
+            None => Err(RecordingError::NotRecording),
+        }
+    }
+
+    /// 暂停录音，音频流与转录任务保持，暂停期间的音频被丢弃
+    pub fn pause(&self) -> Result<(), RecordingError> {
+        match self.capture {
+            Some(SessionCapture::Http(ref recorder)) => recorder.pause(),
+            Some(SessionCapture::Realtime { ref recorder, .. }) => recorder.pause(),
+            None => Err(RecordingError::NotRecording),
+        }
+    }
+
+    /// 继续已暂停的录音
+    pub fn resume(&self) -> Result<(), RecordingError> {
+        match self.capture {
+            Some(SessionCapture::Http(ref recorder)) => recorder.resume(),
+            Some(SessionCapture::Realtime { ref recorder, .. }) => recorder.resume(),
+            None => Err(RecordingError::NotRecording),
+        }
+    }
+
+    /// 是否存在可用的提示音输出设备
+    pub fn is_audio_feedback_available(&self) -> bool {
+        self.beep_player.is_output_available()
+    }
+
+    /// 开始录音并播放开始提示音
+    pub fn start(&mut self, mode: RecordingMode) -> Result<(), RecordingError> {
+        if self.capture.is_some() {
+            return Err(RecordingError::AlreadyRecording);
+        }
+
+        let capture = if self.asr_config.primary.mode == ASRMode::Realtime {
+            log_info!("使用 Realtime 模式，启动流式录音器");
+            self.start_realtime(mode)?
+        } else {
+            log_info!("使用 HTTP 模式，启动普通录音器");
+            self.start_http(mode)?
+        };
+        self.capture = Some(capture);
+
+        self.beep_player.play_start();
+        Ok(())
+    }
+
+    fn start_http(&mut self, mode: RecordingMode) -> Result<SessionCapture, RecordingError> {
+        let mut recorder = AudioRecorder::new()?;
+
+        if let Some(callback) = self.level_callback.take() {
+            recorder.set_level_callback(callback);
+        }
+        recorder.set_monitor(self.asr_config.monitor, self.asr_config.monitor_volume);
+        recorder.set_follow_default_device(self.asr_config.follow_default_device);
+        recorder.set_secondary_device(
+            self.asr_config.secondary_recording_device.clone(),
+            self.asr_config.secondary_gain,
+        );
+        if let Some(max_secs) = self.asr_config.max_recording_secs {
+            recorder.set_max_recording_secs(max_secs);
+        }
+
+        recorder.start(
+            mode.into(),
+            self.asr_config.recording_device.as_deref(),
+            self.asr_config.audio_compression,
+        )?;
+
+        Ok(SessionCapture::Http(recorder))
+    }
+
+    fn start_realtime(&mut self, mode: RecordingMode) -> Result<SessionCapture, RecordingError> {
+        let mut recorder = StreamingRecorder::new()?;
+
+        if let Some(callback) = self.level_callback.take() {
+            recorder.set_level_callback(callback);
+        }
+        recorder.set_monitor(self.asr_config.monitor, self.asr_config.monitor_volume);
+        if let Some(max_secs) = self.asr_config.max_recording_secs {
+            recorder.set_max_recording_secs(max_secs);
+        }
+        if let Some(frame_ms) = self.asr_config.stream_frame_ms {
+            recorder.set_frame_ms(frame_ms);
+        }
+        if let Some(ref device) = self.asr_config.secondary_recording_device {
+            log_warn!("Realtime 模式不支持第二路录音，忽略设备: {}", device);
+        }
+        if self.asr_config.follow_default_device {
+            log_warn!("Realtime 模式不支持跟随默认设备切换，忽略该设置");
+        }
+
+        // 启动流式录音，获取音频块接收通道
+        let chunk_rx = recorder.start_streaming(
+            mode.into(),
+            self.asr_config.recording_device.as_deref(),
+            self.asr_config.audio_compression,
+        )?;
+
+        // 创建实时转录任务 (优先使用预连接的会话)
+        let (task, stop_signal) = RealtimeTranscriptionTask::new(
+            self.asr_config.primary.clone(),
+            chunk_rx,
+            self.partial_callback.take(),
+        );
+        let task = task
+            .with_preconnected(self.preconnected.take())
+            .with_status_callback(self.status_callback.take());
+
+        let task_guard = self.stats.as_ref().map(|stats| stats.track_realtime_task());
+        let task = tokio::spawn(async move {
+            let _task_guard = task_guard;
+            task.run_with_details().await
+        });
+
+        Ok(SessionCapture::Realtime {
+            recorder,
+            task,
+            stop_signal,
+        })
+    }
+
+    /// 停止录音并播放结束提示音
+    ///
+    /// 返回待完成的转录，调用方可先上报录音停止状态，再等待转录结果
+    pub fn stop(&mut self) -> Result<PendingTranscription, RecordingError> {
+        let capture = self.capture.take().ok_or(RecordingError::NotRecording)?;
+
+        self.beep_player.play_stop();
+
+        let (audio_data, realtime_task, stop_reason) = match capture {
+            SessionCapture::Http(mut recorder) => {
+                log_info!("停止 HTTP 模式录音");
+                let stop_reason = recorder.stop_reason();
+                (recorder.stop()?, None, stop_reason)
+            }
+            SessionCapture::Realtime { mut recorder, task, stop_signal } => {
+                log_info!("停止 Realtime 模式录音");
+                let _ = stop_signal.send(());
+                let stop_reason = recorder.stop_reason();
+                (recorder.stop_streaming()?, Some(task), stop_reason)
+            }
+        };
+
+        Ok(PendingTranscription {
+            audio_data,
+            asr_config: self.asr_config.clone(),
+            realtime_task,
+            strategy: Err(ASRError::NotInitialized),
+            stop_reason,
+        })
+    }
+
+    /// 取消录音并播放取消提示音
+    pub fn cancel(&mut self) -> Result<(), RecordingError> {
+        if self.capture.is_none() {
+            return Err(RecordingError::NotRecording);
+        }
+
+        self.beep_player.play_cancel();
+        self.abort();
+        Ok(())
+    }
+
+    /// 静默中止录音与实时转录 (不播放提示音，用于连接关闭等清理场景)
+    pub fn abort(&mut self) {
+        match self.capture.take() {
+            Some(SessionCapture::Http(mut recorder)) => {
+                if let Err(e) = recorder.cancel() {
+                    log_warn!("取消录音失败: {}", e);
+                }
+            }
+            Some(SessionCapture::Realtime { mut recorder, task, stop_signal }) => {
+                let _ = stop_signal.send(());
+                if let Err(e) = recorder.cancel() {
+                    log_warn!("取消流式录音失败: {}", e);
+                }
+                task.abort();
+            }
+            None => {}
+        }
+    }
+}
+
+impl Drop for VoiceSession {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+/// 录音已停止、尚未完成的转录
+pub struct PendingTranscription {
+    audio_data: AudioData,
+    asr_config: ASRConfig,
+    realtime_task: Option<JoinHandle<RealtimeTaskResult>>,
+    /// HTTP 模式使用的转录策略 (由持有转录服务的调用方设置)
+    strategy: Result<Arc<FallbackStrategy>, ASRError>,
+    /// 录音结束原因
+    stop_reason: StopReason,
+}
+
+impl PendingTranscription {
+    /// 由外部采集的音频创建 (如客户端上传的音频流)
+    pub fn new(
+        audio_data: AudioData,
+        asr_config: ASRConfig,
+        realtime_task: Option<JoinHandle<RealtimeTaskResult>>,
+    ) -> Self {
+        Self {
+            audio_data,
+            asr_config,
+            realtime_task,
+            strategy: Err(ASRError::NotInitialized),
+            stop_reason: StopReason::Manual,
+        }
+    }
+
+    /// 设置 HTTP 模式使用的转录策略 (跨录音复用，保留引擎的自适应超时统计)
+    pub fn with_strategy(mut self, strategy: Result<Arc<FallbackStrategy>, ASRError>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// 设置录音结束原因
+    pub fn with_stop_reason(mut self, stop_reason: StopReason) -> Self {
+        self.stop_reason = stop_reason;
+        self
+    }
+
+    /// 本次录音的完整音频
+    pub fn audio_data(&self) -> &AudioData {
+        &self.audio_data
+    }
+
+    /// 录音结束原因
+    pub fn stop_reason(&self) -> StopReason {
+        self.stop_reason
+    }
+
+    /// 等待转录结果
+    ///
+    /// Realtime 模式等待实时转录任务结束，失败时回退到 HTTP 模式；
+    /// HTTP 模式按故障转移策略转录。令牌取消时返回 `ASRError::Cancelled`
+    pub async fn transcribe(
+        self,
+        cancel_token: &CancellationToken,
+    ) -> Result<TranscriptionResult, ASRError> {
+        let PendingTranscription { audio_data, asr_config, realtime_task, strategy, .. } = self;
+
+        let result = transcribe_audio(&audio_data, &asr_config, realtime_task, strategy, cancel_token).await;
+
+        // 存档失败不影响转录结果；已取消或无音频的录音不存档
+        if let Some(archive) = RecordingArchive::from_config(&asr_config) {
+            if !audio_data.is_empty() && !matches!(result, Err(ASRError::Cancelled)) {
+                let outcome = result.clone().map_err(|e| e.to_string());
+                match tokio::task::spawn_blocking(move || archive.save(&audio_data, &outcome)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => log_error!("录音存档失败: {}", e),
+                    Err(e) => log_error!("录音存档任务异常: {}", e),
+                }
+            }
+        }
+
+        result
+    }
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// 按采集方式转录：有实时任务时等待其结束，否则整段转录
+async fn transcribe_audio(
+    audio_data: &AudioData,
+    asr_config: &ASRConfig,
+    realtime_task: Option<JoinHandle<RealtimeTaskResult>>,
+    strategy: Result<Arc<FallbackStrategy>, ASRError>,
+    cancel_token: &CancellationToken,
+) -> Result<TranscriptionResult, ASRError> {
+    match realtime_task {
+        Some(task) => {
+            let abort_handle = task.abort_handle();
+            tokio::select! {
+                _ = cancel_token.cancelled() => {
+                    abort_handle.abort();
+                    Err(ASRError::Cancelled)
+                }
+                result = finish_realtime_transcription(task, audio_data, asr_config) => result,
+            }
+        }
+        None => {
+            if audio_data.is_empty() {
+                log_info!("录音数据为空，跳过转录");
+                return Err(ASRError::NoSpeechDetected);
+            }
+
+            log_info!("开始 ASR 转录，音频时长: {}ms", audio_data.duration_ms);
+            transcribe_with(&*strategy?, audio_data, cancel_token).await
+        }
+    }
+}
+
+/// 等待实时转录任务结束，失败时回退到 HTTP 模式
+async fn finish_realtime_transcription(
+    task: JoinHandle<RealtimeTaskResult>,
+    audio_data: &AudioData,
+    asr_config: &ASRConfig,
+) -> Result<TranscriptionResult, ASRError> {
+    log_info!("等待实时转录任务完成...");
+
+    let (primary_error, error_kind) = match task.await {
+        Ok(RealtimeTaskResult::Success(result)) => {
+            log_info!(
+                "实时转录成功: engine={}, duration={}ms",
+                result.engine,
+                result.duration_ms
+            );
+            return Ok(result);
+        }
+        // 静音不是引擎故障，HTTP 回退也只会得到空结果
+        Ok(RealtimeTaskResult::Failed { error: ASRError::NoSpeechDetected, .. }) => {
+            log_info!("实时转录未检测到语音");
+            return Err(ASRError::NoSpeechDetected);
+        }
+        // 会话关闭时未收到结果：用录音器保存的完整音频重试一次实时会话
+        Ok(RealtimeTaskResult::Failed { error, chunks_sent, .. })
+            if chunks_sent > 0 && realtime_task::is_transient_empty_result(&error) =>
+        {
+            log_warn!("会话关闭时未收到转录结果 (已发送 {} 个音频块)，重试一次", chunks_sent);
+            match realtime_task::retry_session(&asr_config.primary, audio_data).await {
+                Ok(result) => return Ok(result),
+                Err(ASRError::NoSpeechDetected) => return Err(ASRError::NoSpeechDetected),
+                Err(e) => {
+                    log_error!("重试实时会话失败: {}，尝试回退到 HTTP 模式", e);
+                    (format!("实时转录失败: {}", e), Some(e.kind()))
+                }
+            }
+        }
+        Ok(RealtimeTaskResult::Failed { error, engine_name, .. }) => {
+            log_error!("实时转录失败 ({}): {}，尝试回退到 HTTP 模式", engine_name, error);
+            (format!("实时转录失败: {}", error), Some(error.kind()))
+        }
+        Err(e) => {
+            log_error!("实时转录任务异常: {}，尝试回退到 HTTP 模式", e);
+            ("实时转录任务异常".to_string(), None)
+        }
+    };
+
+    match perform_fallback_transcription(audio_data, asr_config, error_kind).await {
+        Ok(result) => {
+            log_info!(
+                "HTTP 回退转录成功: engine={}, duration={}ms",
+                result.engine,
+                result.duration_ms
+            );
+            Ok(result)
+        }
+        Err(fallback_error) => {
+            log_error!("HTTP 回退也失败: {}", fallback_error);
+            Err(ASRError::AllEnginesFailed {
+                primary_error,
+                fallback_error: Some(fallback_error.to_string()),
+            })
+        }
+    }
+}
+
+/// 执行回退 ASR 转录
+/// 
+/// 与 HTTP 模式的兜底策略一致：按最短时长与静音比例检查音频，按 `fallback_on` 决定是否使用
+/// 备用引擎 (`error_kind` 为实时模式的错误类型，任务异常时为空)，并受转录总时长上限约束
+async fn perform_fallback_transcription(
+    audio_data: &AudioData,
+    asr_config: &ASRConfig,
+    error_kind: Option<ASRErrorKind>,
+) -> Result<TranscriptionResult, ASRError> {
+    // 检查音频数据是否为空
+    if audio_data.is_empty() {
+        log_info!("回退转录：音频数据为空");
+        return Err(ASRError::NoSpeechDetected);
+    }
+    check_audio(audio_data, asr_config.min_duration_ms, asr_config.silence_skip_ratio)?;
+
+    log_info!("执行回退转录，音频时长: {}ms", audio_data.duration_ms);
+
+    let audio_data = pad_silence(audio_data, asr_config.pad_start_ms, asr_config.pad_end_ms);
+    let audio_data = audio_data.as_ref();
+    let deadline = Deadline::new(std::time::Instant::now(), asr_config.overall_timeout_ms);
+
+    // 如果配置了 fallback 引擎且启用了 fallback，错误类型在兜底条件中时按顺序依次尝试
+    let mut use_fallbacks = asr_config.enable_fallback && !asr_config.fallbacks.is_empty();
+    if let Some(kind) = error_kind.filter(|_| use_fallbacks) {
+        if !asr_config.fallback_on.is_empty() && !asr_config.fallback_on.contains(&kind) {
+            log_info!("实时模式错误类型 {:?} 不在兜底条件中，跳过 fallback 引擎", kind);
+            use_fallbacks = false;
+        }
+    }
+    if use_fallbacks {
+        let mut fallback_errors: Vec<String> = Vec::new();
+        for fallback_config in &asr_config.fallbacks {
+            if deadline.is_expired() {
+                fallback_errors.push(deadline.error().to_string());
+                break;
+            }
+            log_info!("使用配置的 fallback 引擎: {}", fallback_config.provider);
+
+            let engine = asr::create_engine_with_separator(fallback_config, asr_config.transcription_separator())?;
+
+            let start_time = std::time::Instant::now();
+            match deadline.run(engine.transcribe(audio_data)).await {
+                Ok(text) => {
+                    let duration_ms = start_time.elapsed().as_millis() as u64;
+
+                    return TranscriptionResult::new(
+                        text,
+                        engine.name().to_string(),
+                        true,
+                        duration_ms,
+                    )
+                    .require_speech();
+                }
+                Err(error) => {
+                    fallback_errors.push(format!("{}: {}", engine.name(), error));
+                }
+            }
+        }
+
+        return Err(ASRError::AllEnginesFailed {
+            primary_error: "实时模式失败".to_string(),
+            fallback_error: Some(fallback_errors.join("; ")),
+        });
+    }
+
+    // 没有配置 fallback 引擎，使用 primary 引擎的 HTTP 模式
+    log_info!("使用 primary 引擎的 HTTP 模式进行回退");
+
+    // 创建 HTTP 模式的配置
+    let mut http_config = asr_config.primary.clone();
+    http_config.mode = ASRMode::Http;
+
+    // 创建 HTTP 引擎
+    let engine = asr::create_engine_with_separator(&http_config, asr_config.transcription_separator())?;
+
+    let start_time = std::time::Instant::now();
+    let text = deadline.run(engine.transcribe(audio_data)).await?;
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+
+    TranscriptionResult::new(
+        text,
+        format!("{}-http", engine.name()),
+        true,
+        duration_ms,
+    )
+    .require_speech()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::config::ASRProviderConfig;
+
+    fn test_session() -> VoiceSession {
+        VoiceSession::new(ASRConfig::primary_only(ASRProviderConfig::qwen(
+            ASRMode::Http,
+            "test-key".to_string(),
+        )))
+    }
+
+    #[test]
+    fn test_stop_and_cancel_require_recording() {
+        let mut session = test_session();
+
+        assert!(!session.is_recording());
+        assert!(matches!(session.stop(), Err(RecordingError::NotRecording)));
+        assert!(matches!(session.cancel(), Err(RecordingError::NotRecording)));
+    }
+
+    #[tokio::test]
+    async fn test_empty_audio_skips_transcription() {
+        let pending = PendingTranscription {
+            audio_data: AudioData::new(Vec::new(), 16000, 1),
+            asr_config: test_session().asr_config.clone(),
+            realtime_task: None,
+            strategy: Err(ASRError::NotInitialized),
+            stop_reason: StopReason::Manual,
+        };
+
+        let result = pending.transcribe(&CancellationToken::new()).await;
+        assert!(matches!(result, Err(ASRError::NoSpeechDetected)));
+    }
+
+    #[tokio::test]
+    async fn test_realtime_fallback_rejects_short_audio() {
+        // 50ms @ 16kHz，短于默认最短时长，不发起 HTTP 请求
+        let audio = AudioData::new(vec![0.1; 800], 16000, 1);
+        let result = perform_fallback_transcription(&audio, &test_session().asr_config, None).await;
+        assert!(matches!(result, Err(ASRError::InvalidAudio(_))));
+    }
+}
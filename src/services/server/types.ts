@@ -4,6 +4,8 @@
  * 定义 ServerManager 和各模块客户端使用的类型
  */
 
+import type { ASRMode, ASRProvider, RecordingMode } from '../voice/types';
+
 // ============================================================================
 // 模块类型
 // ============================================================================
@@ -12,7 +14,7 @@
  * 模块类型
  * 与 Rust 端 ModuleType 保持一致
  */
-export type ModuleType = 'pty' | 'voice' | 'llm' | 'utils';
+export type ModuleType = 'pty' | 'voice' | 'llm' | 'utils' | 'system';
 
 // ============================================================================
 // 服务器信息
@@ -40,6 +42,10 @@ export interface ClientMessage {
   module: ModuleType;
   /** 消息类型 */
   type: string;
+  /** 客户端消息 ID (用于确认回执关联) */
+  msg_id?: string;
+  /** 处理成功后是否回复确认 (需同时提供 msg_id) */
+  ack?: boolean;
   /** 其他字段 */
   [key: string]: unknown;
 }
@@ -56,6 +62,15 @@ export interface ServerMessage {
   [key: string]: unknown;
 }
 
+/**
+ * 消息确认回执 (客户端消息带 `ack: true` 与 `msg_id` 且处理成功时发送)
+ */
+export interface AckMessage extends ServerMessage {
+  type: 'ack';
+  /** 对应的客户端消息 ID */
+  ref_id: string;
+}
+
 // ============================================================================
 // 错误类型
 // ============================================================================
@@ -124,14 +139,19 @@ export interface ServerEvents {
 // 从 voice/types.ts 导入
 export type { 
   ASRConfig, 
+  ASRMode,
+  ASRProvider,
   ASRProviderConfig, 
   AudioCompressionLevel,
+  ConfigIssue,
   InputDeviceInfo,
   InputDevicesMessage,
+  PartialFormat,
   RecordingMode,
   RecordingStateMessage,
+  SelfTestReport,
   AudioLevelMessage,
-  TranscriptionProgressMessage,
+  PartialTranscriptionMessage,
   TranscriptionCompleteMessage,
 } from '../voice/types';
 
@@ -140,13 +160,21 @@ export type {
  */
 export interface VoiceEvents {
   /** 录音状态变化 */
-  'recording-state': (state: 'started' | 'stopped' | 'cancelled') => void;
+  'recording-state': (state: 'started' | 'paused' | 'resumed' | 'stopped' | 'cancelled') => void;
+  /** 录音模式变化 (recording 为 true 表示切换的是进行中的录音) */
+  'recording-mode': (mode: RecordingMode, recording: boolean) => void;
   /** 音频级别 */
   'audio-level': (level: number, waveform: number[]) => void;
-  /** 转录进度 */
-  'transcription-progress': (text: string) => void;
+  /** 实时会话连接状态 (connecting 正在连接供应商，connected 可以开始说话) */
+  'session-status': (status: 'connecting' | 'connected', engine: string | undefined) => void;
+  /** 转录进度 (实时模式部分结果) */
+  'transcription-progress': (text: string, isFinal: boolean) => void;
   /** 转录完成 */
   'transcription-complete': (text: string, engine: string, usedFallback: boolean, durationMs: number) => void;
+  /** 客户端音频流达到时长上限 (之后的音频被丢弃，应调用 endStream) */
+  'stream-limit-reached': (maxRecordingSecs: number) => void;
+  /** ASR 引擎已切换 */
+  'engine-switched': (provider: ASRProvider, mode: ASRMode, availableProviders: ASRProvider[]) => void;
   /** 错误 */
   'error': (code: string, message: string) => void;
 }
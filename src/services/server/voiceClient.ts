@@ -5,7 +5,7 @@
  */
 
 import { ModuleClient } from './moduleClient';
-import type { VoiceEvents, ServerMessage, ASRConfig, RecordingMode, InputDeviceInfo } from './types';
+import type { VoiceEvents, ServerMessage, ASRConfig, ASRMode, ASRProvider, ConfigIssue, PartialFormat, RecordingMode, InputDeviceInfo, SelfTestReport } from './types';
 import { debugLog } from '../../utils/logger';
 
 /**
@@ -19,6 +19,16 @@ export class VoiceClient extends ModuleClient {
     reject: (error: Error) => void;
     timeoutId: number;
   }> = new Map();
+  private pendingValidationRequests: Map<string, {
+    resolve: (issues: ConfigIssue[]) => void;
+    reject: (error: Error) => void;
+    timeoutId: number;
+  }> = new Map();
+  private pendingSelfTestRequests: Map<string, {
+    resolve: (report: SelfTestReport) => void;
+    reject: (error: Error) => void;
+    timeoutId: number;
+  }> = new Map();
 
   constructor() {
     super('voice');
@@ -37,6 +47,31 @@ export class VoiceClient extends ModuleClient {
     });
   }
 
+  /**
+   * 设置录音模式
+   * 
+   * 录音中调用时切换当前录音的模式 (如按住后锁定为松手模式)，否则设置默认录音模式
+   * 
+   * @param mode 录音模式
+   */
+  setRecordingMode(mode: RecordingMode): void {
+    this.send('set_recording_mode', { mode });
+  }
+
+  /**
+   * 暂停录音 (音频流保持，暂停期间的音频被丢弃)
+   */
+  pauseRecording(): void {
+    this.send('pause_recording');
+  }
+
+  /**
+   * 继续已暂停的录音
+   */
+  resumeRecording(): void {
+    this.send('resume_recording');
+  }
+
   /**
    * 停止录音
    */
@@ -51,6 +86,40 @@ export class VoiceClient extends ModuleClient {
     this.send('cancel_recording');
   }
 
+  /**
+   * 开始上传客户端音频流 (由服务端转录，适用于无本地录音能力的客户端)
+   * 
+   * 之后通过 sendAudioFrame 发送 16kHz 单声道 16-bit 小端 PCM，endStream 结束，cancelRecording 中止
+   * 
+   * @param asrConfig ASR 配置
+   * @param partialFormat 中间结果输出格式 (实时模式与 HTTP 流式均适用)；本客户端只解析 json
+   */
+  initStream(asrConfig: ASRConfig, partialFormat: PartialFormat = 'json'): void {
+    this.send('init', {
+      asr_config: asrConfig,
+      partial_format: partialFormat,
+    });
+  }
+
+  /**
+   * 发送一帧 PCM 音频 (需先调用 initStream)
+   * 
+   * @param pcm 16kHz 单声道 16-bit 小端 PCM
+   */
+  sendAudioFrame(pcm: Int16Array<ArrayBuffer> | ArrayBuffer): void {
+    const data = pcm instanceof ArrayBuffer
+      ? pcm
+      : new Uint8Array(pcm.buffer, pcm.byteOffset, pcm.byteLength);
+    this.sendBinary(data);
+  }
+
+  /**
+   * 结束客户端音频流，结果通过 transcription-complete / error 事件返回
+   */
+  endStream(): void {
+    this.send('end_stream');
+  }
+
   /**
    * 更新 ASR 配置
    * 
@@ -62,6 +131,71 @@ export class VoiceClient extends ModuleClient {
     });
   }
 
+  /**
+   * 校验 ASR 配置，返回全部问题 (空数组表示配置有效)
+   * 
+   * @param asrConfig ASR 配置
+   */
+  validateConfig(asrConfig: ASRConfig, timeoutMs = 5000): Promise<ConfigIssue[]> {
+    if (!this.isConnected()) {
+      return Promise.reject(new Error('Voice WebSocket 未连接'));
+    }
+
+    const requestId = `validate_config_${Date.now()}_${Math.random().toString(16).slice(2, 8)}`;
+
+    return new Promise((resolve, reject) => {
+      const timeoutId = window.setTimeout(() => {
+        this.pendingValidationRequests.delete(requestId);
+        reject(new Error('校验 ASR 配置超时'));
+      }, timeoutMs);
+
+      this.pendingValidationRequests.set(requestId, { resolve, reject, timeoutId });
+      this.send('validate_config', { asr_config: asrConfig, request_id: requestId });
+    });
+  }
+
+  /**
+   * 运行自检：录制一小段音频并按配置转录，返回设备、电平、引擎与耗时报告
+   * 
+   * @param asrConfig ASR 配置
+   * @param durationMs 录音时长 (毫秒)
+   */
+  selfTest(asrConfig: ASRConfig, durationMs = 1000, timeoutMs = 30000): Promise<SelfTestReport> {
+    if (!this.isConnected()) {
+      return Promise.reject(new Error('Voice WebSocket 未连接'));
+    }
+
+    const requestId = `self_test_${Date.now()}_${Math.random().toString(16).slice(2, 8)}`;
+
+    return new Promise((resolve, reject) => {
+      const timeoutId = window.setTimeout(() => {
+        this.pendingSelfTestRequests.delete(requestId);
+        reject(new Error('语音自检超时'));
+      }, timeoutMs);
+
+      this.pendingSelfTestRequests.set(requestId, { resolve, reject, timeoutId });
+      this.send('self_test', { asr_config: asrConfig, duration_ms: durationMs, request_id: requestId });
+    });
+  }
+
+  /**
+   * 运行时切换 ASR 供应商 (缺少凭证时服务端返回错误)
+   * 
+   * @param provider 目标供应商
+   */
+  switchProvider(provider: ASRProvider): void {
+    this.send('switch_provider', { provider });
+  }
+
+  /**
+   * 运行时切换 ASR 模式
+   * 
+   * @param mode 目标模式
+   */
+  switchMode(mode: ASRMode): void {
+    this.send('switch_mode', { mode });
+  }
+
   /**
    * 获取录音输入设备列表
    */
@@ -90,6 +224,13 @@ export class VoiceClient extends ModuleClient {
     return this.on('recording-state', handler);
   }
 
+  /**
+   * 注册录音模式变化处理器
+   */
+  onRecordingMode(handler: VoiceEvents['recording-mode']): () => void {
+    return this.on('recording-mode', handler);
+  }
+
   /**
    * 注册音频级别处理器
    */
@@ -111,6 +252,13 @@ export class VoiceClient extends ModuleClient {
     return this.on('transcription-complete', handler);
   }
 
+  /**
+   * 注册引擎切换处理器
+   */
+  onEngineSwitched(handler: VoiceEvents['engine-switched']): () => void {
+    return this.on('engine-switched', handler);
+  }
+
   /**
    * 注册错误处理器
    */
@@ -173,16 +321,45 @@ export class VoiceClient extends ModuleClient {
         }
         break;
       }
+      case 'config_validation': {
+        const requestId = (msg as { request_id?: string }).request_id;
+        const issues = (msg as { issues?: ConfigIssue[] }).issues || [];
+        const pending = requestId ? this.pendingValidationRequests.get(requestId) : undefined;
+        if (requestId && pending) {
+          window.clearTimeout(pending.timeoutId);
+          pending.resolve(issues);
+          this.pendingValidationRequests.delete(requestId);
+        }
+        break;
+      }
+      case 'self_test_report': {
+        const requestId = (msg as { request_id?: string }).request_id;
+        const pending = requestId ? this.pendingSelfTestRequests.get(requestId) : undefined;
+        if (requestId && pending) {
+          window.clearTimeout(pending.timeoutId);
+          pending.resolve((msg as { report: SelfTestReport }).report);
+          this.pendingSelfTestRequests.delete(requestId);
+        }
+        break;
+      }
       case 'recording_state':
-        this.emit('recording-state', msg.state as 'started' | 'stopped' | 'cancelled');
+        this.emit('recording-state', msg.state as 'started' | 'paused' | 'resumed' | 'stopped' | 'cancelled');
+        break;
+        
+      case 'recording_mode':
+        this.emit('recording-mode', msg.mode as RecordingMode, msg.recording as boolean);
         break;
         
       case 'audio_level':
         this.emit('audio-level', msg.level as number, msg.waveform as number[]);
         break;
         
-      case 'transcription_progress':
-        this.emit('transcription-progress', msg.partial_text as string);
+      case 'session_status':
+        this.emit('session-status', msg.status as 'connecting' | 'connected', msg.engine as string | undefined);
+        break;
+        
+      case 'partial':
+        this.emit('transcription-progress', msg.text as string, msg.is_final as boolean);
         break;
         
       case 'transcription_complete':
@@ -195,9 +372,32 @@ export class VoiceClient extends ModuleClient {
         );
         break;
         
-      case 'error':
+      case 'stream_limit_reached':
+        this.emit('stream-limit-reached', msg.max_recording_secs as number);
+        break;
+        
+      case 'engine_switched':
+        this.emit(
+          'engine-switched',
+          msg.provider as ASRProvider,
+          msg.mode as ASRMode,
+          (msg.available_providers as ASRProvider[]) || []
+        );
+        break;
+        
+      case 'error': {
+        // 自检在服务端后台执行，失败时携带 request_id 以结束对应的等待
+        const requestId = (msg as { request_id?: string }).request_id;
+        const pending = requestId ? this.pendingSelfTestRequests.get(requestId) : undefined;
+        if (requestId && pending) {
+          window.clearTimeout(pending.timeoutId);
+          pending.reject(new Error(msg.message as string));
+          this.pendingSelfTestRequests.delete(requestId);
+          break;
+        }
         this.emit('error', msg.code as string, msg.message as string);
         break;
+      }
     }
   }
 
@@ -210,6 +410,16 @@ export class VoiceClient extends ModuleClient {
       reject(new Error('VoiceClient 已销毁'));
     });
     this.pendingDeviceRequests.clear();
+    this.pendingValidationRequests.forEach(({ timeoutId, reject }) => {
+      window.clearTimeout(timeoutId);
+      reject(new Error('VoiceClient 已销毁'));
+    });
+    this.pendingValidationRequests.clear();
+    this.pendingSelfTestRequests.forEach(({ timeoutId, reject }) => {
+      window.clearTimeout(timeoutId);
+      reject(new Error('VoiceClient 已销毁'));
+    });
+    this.pendingSelfTestRequests.clear();
     this.eventListeners.clear();
     super.destroy();
   }
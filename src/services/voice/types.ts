@@ -24,6 +24,13 @@ export type ASRMode = 'realtime' | 'http';
  */
 export type RecordingMode = 'press' | 'toggle';
 
+/**
+ * 中间结果输出格式
+ * - json: 以 partial 消息推送 (默认)
+ * - raw: 纯文本帧，内容为截至当前的完整识别文本，供无法解析 JSON 的极简客户端使用
+ */
+export type PartialFormat = 'json' | 'raw';
+
 /**
  * 音频压缩等级
  * - original: 原始
@@ -140,7 +147,9 @@ export type ClientMessage =
  */
 export interface RecordingStateMessage {
   type: 'recording_state';
-  state: 'started' | 'stopped' | 'cancelled';
+  state: 'started' | 'paused' | 'resumed' | 'stopped' | 'cancelled';
+  /** 录音结束原因 (仅 stopped) */
+  reason?: 'manual' | 'max_duration' | 'device_lost';
 }
 
 /**
@@ -164,11 +173,14 @@ export interface AudioLevelMessage {
 }
 
 /**
- * 转录进度消息 (实时模式)
+ * 部分转录结果消息 (实时模式)
  */
-export interface TranscriptionProgressMessage {
-  type: 'transcription_progress';
-  partial_text: string;
+export interface PartialTranscriptionMessage {
+  type: 'partial';
+  /** 截至当前的完整识别文本 (非增量，各供应商一致) */
+  text: string;
+  /** 是否为该段语音的最终结果 */
+  is_final: boolean;
 }
 
 /**
@@ -180,6 +192,22 @@ export interface TranscriptionCompleteMessage {
   engine: string;
   used_fallback: boolean;
   duration_ms: number;
+  /** 是否经过自动重试 (实时会话首次未收到结果) */
+  retried?: boolean;
+  /** 耗时分解 (毫秒)，无法单独测量的阶段省略 */
+  timings?: TranscriptionTimings;
+}
+
+/**
+ * 转录耗时分解
+ */
+export interface TranscriptionTimings {
+  /** 建立连接 (实时会话) */
+  connect_ms?: number;
+  /** 上传音频 (实时会话为推流时长) */
+  upload_ms?: number;
+  /** 上传完成到收到最终结果 */
+  inference_ms?: number;
 }
 
 /**
@@ -198,10 +226,20 @@ export type ServerMessage =
   | RecordingStateMessage 
   | InputDevicesMessage
   | AudioLevelMessage 
-  | TranscriptionProgressMessage 
+  | PartialTranscriptionMessage 
   | TranscriptionCompleteMessage 
   | VoiceErrorMessage;
 
+/**
+ * 配置问题 (validate_config 返回)
+ */
+export interface ConfigIssue {
+  /** 字段路径，如 primary.dashscope_api_key、fallbacks[0].app_id */
+  field: string;
+  /** 问题描述 */
+  message: string;
+}
+
 /**
  * 输入设备信息
  */
@@ -210,6 +248,42 @@ export interface InputDeviceInfo {
   is_default: boolean;
 }
 
+/**
+ * 自检报告 (self_test 返回)
+ */
+export interface SelfTestReport {
+  /** 录音设备名称 */
+  device: string;
+  /** 设备采样率 */
+  device_sample_rate: number;
+  /** 设备声道数 */
+  device_channels: number;
+  /** 送入转录的音频采样率 */
+  sample_rate: number;
+  /** 录音摘要 */
+  audio: {
+    duration_ms: number;
+    /** 峰值 (0-1) */
+    peak: number;
+    /** RMS 电平 (dBFS) */
+    rms_dbfs: number;
+    clipped_samples: number;
+  };
+  /** 主引擎供应商 */
+  provider: ASRProvider;
+  /** 主引擎模式 */
+  mode: ASRMode;
+  /** 实际完成转录的引擎 (失败时为空) */
+  engine: string | null;
+  used_fallback: boolean;
+  /** 录音停止到得到结果的耗时 (毫秒) */
+  latency_ms: number;
+  /** 转录文本 (失败时为空) */
+  text: string | null;
+  /** 转录错误 (成功时为空) */
+  error: string | null;
+}
+
 // ============================================================================
 // 悬浮窗状态类型
 // ============================================================================
@@ -283,6 +357,8 @@ export enum VoiceErrorCode {
   ASR_INVALID_AUDIO = 'ASR_INVALID_AUDIO',
   ASR_TIMEOUT = 'ASR_TIMEOUT',
   ASR_ALL_FAILED = 'ASR_ALL_FAILED',
+  /** 识别结果为空 (静音或噪声)，应提示用户而非插入空文本 */
+  NO_SPEECH_DETECTED = 'NO_SPEECH_DETECTED',
   
   // 服务器错误
   SERVER_NOT_RUNNING = 'SERVER_NOT_RUNNING',
@@ -855,7 +855,7 @@ export class VoiceInputService implements IVoiceInputService {
   /**
    * 处理录音状态变化
    */
-  private handleRecordingState(state: 'started' | 'stopped' | 'cancelled'): void {
+  private handleRecordingState(state: 'started' | 'paused' | 'resumed' | 'stopped' | 'cancelled'): void {
     switch (state) {
       case 'started':
         this.emit('recording-start');